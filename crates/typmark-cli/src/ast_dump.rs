@@ -0,0 +1,157 @@
+use serde_json::{Map, Value};
+use typmark_core::Document;
+
+/// Prints the serialized document as an indented s-expression tree.
+///
+/// Nodes print as `(Kind start..end key=value ...)`, with child sequences
+/// listed under their field name. Empty fields are left out.
+pub fn document_to_sexpr(document: &Document) -> String {
+    let value = serde_json::to_value(document).expect("document serializes to JSON");
+    let mut lines = Vec::new();
+    if let Value::Object(map) = &value {
+        write_node(&mut lines, "Document", Node::from_fields(map), 0);
+    }
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+/// One printed node: the fields of a struct plus those of its `kind` variant.
+#[derive(Default)]
+struct Node<'a> {
+    span: Option<String>,
+    fields: Vec<(&'a str, &'a Value)>,
+    children: &'a [Value],
+}
+
+impl<'a> Node<'a> {
+    fn from_fields(map: &'a Map<String, Value>) -> Self {
+        let mut node = Node {
+            span: map.get("span").and_then(span_text),
+            ..Node::default()
+        };
+        for (key, value) in map {
+            if key != "span" && key != "kind" {
+                node.fields.push((key, value));
+            }
+        }
+        node
+    }
+
+    /// Adds a variant payload: struct fields merge in, sequences become children,
+    /// and scalars print next to the node name.
+    fn with_payload(mut self, payload: &'a Value) -> Self {
+        match payload {
+            Value::Object(map) => self.fields.extend(map.iter().map(|(k, v)| (k.as_str(), v))),
+            Value::Array(items) => self.children = items,
+            _ => self.fields.insert(0, ("", payload)),
+        }
+        self
+    }
+}
+
+fn write_node(lines: &mut Vec<String>, name: &str, node: Node, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let mut parts: Vec<String> = Vec::new();
+    if !name.is_empty() {
+        parts.push(name.to_string());
+    }
+    parts.extend(node.span);
+    let mut nested = Vec::new();
+    for (key, value) in node.fields {
+        if is_empty(value) {
+            continue;
+        }
+        match (is_scalar(value), span_text(value)) {
+            (true, _) if key.is_empty() => parts.push(value.to_string()),
+            (true, _) => parts.push(format!("{}={}", key, value)),
+            (false, Some(span)) => parts.push(format!("{}={}", key, span)),
+            (false, None) => nested.push((key, value)),
+        }
+    }
+    lines.push(format!("{}({}", indent, parts.join(" ")));
+
+    for child in node.children {
+        write_value(lines, child, depth + 1);
+    }
+    for (key, value) in nested {
+        match value {
+            Value::Array(items) => {
+                lines.push(format!("{}  {}:", indent, key));
+                for item in items {
+                    write_value(lines, item, depth + 2);
+                }
+            }
+            Value::Object(map) => match tagged_variant(map) {
+                Some((variant, inner)) => write_node(
+                    lines,
+                    &format!("{} {}", key, variant),
+                    Node::default().with_payload(inner),
+                    depth + 1,
+                ),
+                None => write_node(lines, key, Node::from_fields(map), depth + 1),
+            },
+            _ => {}
+        }
+    }
+    if let Some(last) = lines.last_mut() {
+        last.push(')');
+    }
+}
+
+fn write_value(lines: &mut Vec<String>, value: &Value, depth: usize) {
+    let Value::Object(map) = value else {
+        lines.push(format!("{}{}", "  ".repeat(depth), value));
+        return;
+    };
+    match map.get("kind") {
+        // Blocks and inlines: `{ span, kind: "Variant" | { "Variant": payload }, ... }`.
+        Some(Value::String(kind)) => write_node(lines, kind, Node::from_fields(map), depth),
+        Some(Value::Object(kind)) if tagged_variant(kind).is_some() => {
+            let (variant, payload) = tagged_variant(kind).expect("checked above");
+            write_node(
+                lines,
+                variant,
+                Node::from_fields(map).with_payload(payload),
+                depth,
+            );
+        }
+        _ => match tagged_variant(map) {
+            Some((variant, payload)) => {
+                write_node(lines, variant, Node::default().with_payload(payload), depth)
+            }
+            None => write_node(lines, "", Node::from_fields(map), depth),
+        },
+    }
+}
+
+/// Splits an externally tagged enum value such as `{"Paragraph": {...}}`.
+fn tagged_variant(map: &Map<String, Value>) -> Option<(&str, &Value)> {
+    let mut entries = map.iter();
+    let (variant, payload) = entries.next()?;
+    let is_variant = entries.next().is_none() && variant.starts_with(char::is_uppercase);
+    is_variant.then_some((variant.as_str(), payload))
+}
+
+fn span_text(value: &Value) -> Option<String> {
+    let map = value.as_object()?;
+    if map.len() != 2 {
+        return None;
+    }
+    let start = map.get("start")?.as_u64()?;
+    let end = map.get("end")?.as_u64()?;
+    Some(format!("{}..{}", start, end))
+}
+
+fn is_scalar(value: &Value) -> bool {
+    matches!(value, Value::Bool(_) | Value::Number(_) | Value::String(_))
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.values().all(is_empty),
+        _ => false,
+    }
+}
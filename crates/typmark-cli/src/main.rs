@@ -1,28 +1,53 @@
 use std::env;
 use std::fs;
-use std::io::{self, Read};
-use std::path::{Path, PathBuf};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
 use std::process;
 
 use typmark_core::{
-    AttrList, Diagnostic, DiagnosticSeverity, HtmlEmitOptions, ParseResult,
-    emit_html_document_sanitized_with_options,
-    emit_html_document_sanitized_with_options_and_source_map, emit_html_document_with_options,
-    emit_html_document_with_options_and_source_map, parse, resolve,
+    AttrList, Diagnostic, DiagnosticSeverity, FeatureSet, HtmlEmitOptions, IncludeExpansion,
+    IncludeResolver, IncludedSource, ParseOptions, ParseResult, ResolveResult, SanitizeConfig,
+    SourceMap, directive_diagnostics, emit_html_document_sanitized_with_config,
+    emit_html_document_sanitized_with_config_and_source_map, emit_html_document_to_writer,
+    emit_html_document_to_writer_with_source_map, emit_html_document_with_options,
+    emit_html_document_with_options_and_source_map, expand_includes, is_text_dir,
+    parse_with_options, resolve,
 };
-use typmark_renderer::{PdfBackend, PdfMargin, PdfOptions, Renderer, Theme};
+mod ast_dump;
+
+use typmark_renderer::{
+    PdfBackend, PdfMargin, PdfOptions, Renderer, Theme, TomlEntry, TomlError, TomlValue,
+    inline_local_assets, parse_toml_subset,
+};
+
+/// Local images up to this size are embedded as data URIs in `--pdf` output.
+const PDF_INLINE_IMAGE_MAX_BYTES: u64 = 512 * 1024;
 
 fn main() {
     let mut input: Option<String> = None;
-    let mut sanitized = false;
-    let mut simple_code_blocks = false;
+    let mut extra_inputs: Vec<String> = Vec::new();
+    let mut concat = false;
+    let mut stdin_filename: Option<String> = None;
+    let mut config_path: Option<String> = None;
+    let mut sanitized: Option<bool> = None;
+    let mut simple_code_blocks: Option<bool> = None;
     let mut emit_source_map = false;
-    let mut wrap_sections = true;
+    let mut wrap_sections: Option<bool> = None;
+    let mut number_sections: Option<bool> = None;
     let mut diagnostics_mode: Option<DiagnosticsMode> = None;
+    let mut min_severity = DiagnosticSeverity::Hint;
+    let mut fail_on = FailOn::Error;
+    let mut quiet = false;
+    let mut includes = false;
+    let mut strict = false;
     let mut render = true;
-    let mut render_js = true;
-    let mut theme = Theme::Dark;
+    let mut render_js: Option<bool> = None;
+    let mut theme: Option<Theme> = None;
     let mut pdf_output: Option<String> = None;
+    let mut output: Option<String> = None;
+    let mut assets_dir: Option<String> = None;
+    let mut standalone = false;
+    let mut ast_mode: Option<AstMode> = None;
 
     let mut args = env::args().skip(1);
     while let Some(arg) = args.next() {
@@ -35,16 +60,47 @@ fn main() {
                 println!("{}", env!("CARGO_PKG_VERSION"));
                 return;
             }
-            "--sanitized" => sanitized = true,
-            "--simple-code" => simple_code_blocks = true,
+            "--sanitized" => sanitized = Some(true),
+            "--simple-code" => simple_code_blocks = Some(true),
             "--source-map" => emit_source_map = true,
-            "--no-section-wrap" => wrap_sections = false,
+            "--no-section-wrap" => wrap_sections = Some(false),
+            "--number-sections" => number_sections = Some(true),
+            "--quiet" => quiet = true,
+            "--includes" => includes = true,
+            "--strict" => strict = true,
+            "--concat" => concat = true,
             "--render" => render = true,
             "--render-js" => {
                 render = true;
-                render_js = true;
+                render_js = Some(true);
+            }
+            "--config" => {
+                let value = match args.next() {
+                    Some(value) => value,
+                    None => {
+                        eprintln!("--config expects a file path");
+                        print_usage();
+                        process::exit(2);
+                    }
+                };
+                config_path = Some(value);
+            }
+            "--stdin-filename" => {
+                let value = match args.next() {
+                    Some(value) => value,
+                    None => {
+                        eprintln!("--stdin-filename expects a file path");
+                        print_usage();
+                        process::exit(2);
+                    }
+                };
+                stdin_filename = Some(value);
             }
             "--raw" => render = false,
+            "--standalone" => {
+                render = true;
+                standalone = true;
+            }
             "--pdf" => {
                 let value = match args.next() {
                     Some(value) => value,
@@ -56,23 +112,55 @@ fn main() {
                 };
                 pdf_output = Some(value);
             }
+            "--output" => {
+                let value = match args.next() {
+                    Some(value) => value,
+                    None => {
+                        eprintln!("--output expects an output file path");
+                        print_usage();
+                        process::exit(2);
+                    }
+                };
+                output = Some(value);
+            }
+            "--assets-dir" => {
+                let value = match args.next() {
+                    Some(value) => value,
+                    None => {
+                        eprintln!("--assets-dir expects a directory path");
+                        print_usage();
+                        process::exit(2);
+                    }
+                };
+                assets_dir = Some(value);
+            }
             "--theme" => {
                 let value = args.next().unwrap_or_else(|| {
                     eprintln!("--theme expects: auto | light | dark");
                     print_usage();
                     process::exit(2);
                 });
-                theme = match value.as_str() {
-                    "auto" => Theme::Auto,
-                    "light" => Theme::Light,
-                    "dark" => Theme::Dark,
-                    _ => {
+                theme = match parse_theme(&value) {
+                    Ok(theme) => Some(theme),
+                    Err(_) => {
                         eprintln!("--theme expects: auto | light | dark");
                         print_usage();
                         process::exit(2);
                     }
                 };
             }
+            "--ast" => {
+                let mode = match args.next().as_deref() {
+                    Some("json") => AstMode::Json,
+                    Some("pretty") => AstMode::Pretty,
+                    _ => {
+                        eprintln!("--ast expects: json | pretty");
+                        print_usage();
+                        process::exit(2);
+                    }
+                };
+                ast_mode = Some(mode);
+            }
             "--diagnostics" => {
                 let mode = match args.next().as_deref() {
                     Some("json") => DiagnosticsMode::Json,
@@ -85,19 +173,344 @@ fn main() {
                 };
                 diagnostics_mode = Some(mode);
             }
+            "--min-severity" => {
+                min_severity = match args.next().as_deref() {
+                    Some("error") => DiagnosticSeverity::Error,
+                    Some("warning") => DiagnosticSeverity::Warning,
+                    Some("info") => DiagnosticSeverity::Info,
+                    Some("hint") => DiagnosticSeverity::Hint,
+                    _ => {
+                        eprintln!("--min-severity expects: error | warning | info | hint");
+                        print_usage();
+                        process::exit(2);
+                    }
+                };
+            }
+            "--fail-on" => {
+                fail_on = match args.next().as_deref() {
+                    Some("error") => FailOn::Error,
+                    Some("warning") => FailOn::Warning,
+                    Some("none") => FailOn::None,
+                    _ => {
+                        eprintln!("--fail-on expects: error | warning | none");
+                        print_usage();
+                        process::exit(2);
+                    }
+                };
+            }
             _ => {
                 if input.is_none() {
                     input = Some(arg);
                 } else {
-                    eprintln!("unexpected argument: {}", arg);
-                    print_usage();
-                    process::exit(2);
+                    extra_inputs.push(arg);
+                }
+            }
+        }
+    }
+
+    if let Some(arg) = extra_inputs.first()
+        && !concat
+    {
+        eprintln!("unexpected argument: {}", arg);
+        print_usage();
+        process::exit(2);
+    }
+    if concat && (pdf_output.is_none() || input.is_none()) {
+        eprintln!("--concat expects --pdf and at least one input file");
+        print_usage();
+        process::exit(2);
+    }
+    if ast_mode.is_some() && pdf_output.is_some() {
+        eprintln!("--ast cannot be used with --pdf");
+        print_usage();
+        process::exit(2);
+    }
+    if standalone && (assets_dir.is_some() || pdf_output.is_some()) {
+        eprintln!("--standalone cannot be used with --assets-dir or --pdf");
+        print_usage();
+        process::exit(2);
+    }
+    if input.is_some() && stdin_filename.is_some() {
+        eprintln!("--stdin-filename cannot be used with an input file");
+        print_usage();
+        process::exit(2);
+    }
+    // The path relative assets resolve against; stdin input borrows it from --stdin-filename.
+    let logical_input = input.as_deref().or(stdin_filename.as_deref());
+
+    let config = load_cli_config(config_path.as_deref(), logical_input);
+    let sanitized = sanitized.or(config.sanitized).unwrap_or(false);
+    // --strict emits the plain markup a CommonMark renderer would.
+    let simple_code_blocks = strict
+        || simple_code_blocks
+            .or(config.simple_code_blocks)
+            .unwrap_or(false);
+    let wrap_sections = !strict && wrap_sections.or(config.wrap_sections).unwrap_or(true);
+    let number_sections = !strict && number_sections.or(config.number_sections).unwrap_or(false);
+    let mut parse_options = ParseOptions {
+        strict_commonmark: strict,
+        ..Default::default()
+    };
+    if includes {
+        parse_options.features.insert(FeatureSet::INCLUDES);
+    }
+    let render_js = render_js.or(config.render_js).unwrap_or(true);
+    let theme = theme.or(config.theme).unwrap_or(Theme::Dark);
+
+    let options = HtmlEmitOptions {
+        simple_code_blocks,
+        wrap_sections,
+        number_sections,
+        ..Default::default()
+    };
+
+    // --quiet only makes sense with diagnostics, so it falls back to pretty output.
+    if quiet && diagnostics_mode.is_none() {
+        diagnostics_mode = Some(DiagnosticsMode::Pretty);
+    }
+
+    if concat {
+        let pdf_path = pdf_output.as_deref().expect("checked with --concat");
+        let paths: Vec<&str> = input
+            .iter()
+            .chain(&extra_inputs)
+            .map(String::as_str)
+            .collect();
+        let mut parts = Vec::new();
+        let mut shown = Vec::new();
+        let mut failed = false;
+        let mut first_settings = None;
+        for (idx, path) in paths.iter().enumerate() {
+            let loaded = load_document(Some(path), Some(path), includes, &parse_options);
+            // Each file gets its own id namespace so labels cannot collide.
+            let options = HtmlEmitOptions {
+                id_namespace: Some(format!("part{}", idx + 1)),
+                ..options.clone()
+            };
+            shown.extend(
+                loaded
+                    .shown_diagnostics(&options, min_severity)
+                    .into_iter()
+                    .map(|mut diag| {
+                        diag.file.get_or_insert_with(|| path.to_string());
+                        diag
+                    }),
+            );
+            failed |= loaded.fails(&options, fail_on);
+            let document = &loaded.resolved.document;
+            let html = if sanitized {
+                emit_html_document_sanitized_with_config(document, &options, &config.sanitize)
+            } else {
+                emit_html_document_with_options(document, &options)
+            };
+            parts.push((html, Some(Path::new(*path))));
+            if idx == 0 {
+                first_settings = document.settings.clone();
+            }
+        }
+        if let Some(mode) = diagnostics_mode {
+            emit_diagnostics(&shown, mode, quiet);
+        }
+        export_pdf(&parts, first_settings.as_ref(), config.pdf, pdf_path);
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+
+    let loaded = load_document(input.as_deref(), logical_input, includes, &parse_options);
+    let resolved = &loaded.resolved;
+    let source_map = &loaded.source_map;
+    let shown = loaded.shown_diagnostics(&options, min_severity);
+    if let Some(mode) = diagnostics_mode {
+        emit_diagnostics(&shown, mode, quiet);
+    }
+
+    // AST dumps are a debugging aid, so they exit 0 whatever the diagnostics say.
+    if let Some(mode) = ast_mode {
+        let dump = match mode {
+            AstMode::Json => {
+                let diagnostics: serde_json::Value =
+                    serde_json::from_str(&diagnostics_to_json(&shown))
+                        .expect("diagnostics JSON is valid");
+                let value = serde_json::json!({
+                    "document": resolved.document,
+                    "diagnostics": diagnostics,
+                });
+                let mut json =
+                    serde_json::to_string_pretty(&value).expect("document serializes to JSON");
+                json.push('\n');
+                json
+            }
+            AstMode::Pretty => ast_dump::document_to_sexpr(&resolved.document),
+        };
+        write_output(output.as_deref(), &dump);
+        return;
+    }
+
+    let emit_document = || {
+        if emit_source_map {
+            if sanitized {
+                emit_html_document_sanitized_with_config_and_source_map(
+                    &resolved.document,
+                    &options,
+                    source_map,
+                    &config.sanitize,
+                )
+            } else {
+                emit_html_document_with_options_and_source_map(
+                    &resolved.document,
+                    &options,
+                    source_map,
+                )
+            }
+        } else if sanitized {
+            emit_html_document_sanitized_with_config(&resolved.document, &options, &config.sanitize)
+        } else {
+            emit_html_document_with_options(&resolved.document, &options)
+        }
+    };
+
+    if let Some(pdf_path) = pdf_output {
+        export_pdf(
+            &[(emit_document(), logical_input.map(Path::new))],
+            resolved.document.settings.as_ref(),
+            config.pdf,
+            &pdf_path,
+        );
+    } else if render {
+        let mut renderer =
+            apply_renderer_settings(Renderer::new(theme), resolved.document.settings.as_ref());
+        if let Some(assets_dir) = assets_dir.as_deref().map(Path::new) {
+            if let Err(err) = renderer.generate_files(assets_dir) {
+                eprintln!(
+                    "failed to write assets to {}: {}",
+                    assets_dir.display(),
+                    err
+                );
+                process::exit(1);
+            }
+            let output_dir = match output.as_deref().map(Path::new).and_then(Path::parent) {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            match relative_dir_href(output_dir, assets_dir) {
+                Ok(href) => renderer = renderer.with_linked_assets(href),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            }
+        }
+        let wrapped = if standalone {
+            let base_dir = match default_pdf_base_dir(logical_input.map(Path::new)) {
+                Ok(dir) => dir.unwrap_or_default(),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
                 }
+            };
+            let (html, warnings) =
+                renderer.render_standalone_with_warnings(&emit_document(), &base_dir);
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
             }
+            html
+        } else {
+            let highlighted = renderer.highlight_html(&emit_document());
+            renderer.embed_html(&highlighted, true, render_js)
+        };
+        write_output(output.as_deref(), &wrapped);
+    } else if sanitized || output.is_some() {
+        write_output(output.as_deref(), &emit_document());
+    } else {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let result = if emit_source_map {
+            emit_html_document_to_writer_with_source_map(
+                &resolved.document,
+                &options,
+                source_map,
+                &mut out,
+            )
+        } else {
+            emit_html_document_to_writer(&resolved.document, &options, &mut out)
+        };
+        if let Err(err) = result.and_then(|_| out.flush()) {
+            eprintln!("failed to write output: {}", err);
+            process::exit(1);
         }
     }
 
-    let source = match input.as_deref() {
+    if loaded.fails(&options, fail_on) {
+        process::exit(1);
+    }
+}
+
+/// An input file after parsing, include expansion and resolving.
+struct LoadedDocument {
+    resolved: ResolveResult,
+    source_map: SourceMap,
+    expansion: Option<IncludeExpansion>,
+}
+
+impl LoadedDocument {
+    /// Resolver and directive diagnostics at or above `min_severity`, with
+    /// diagnostics from included files pointing back at those files.
+    fn shown_diagnostics(
+        &self,
+        options: &HtmlEmitOptions,
+        min_severity: DiagnosticSeverity,
+    ) -> Vec<Diagnostic> {
+        self.resolved
+            .diagnostics
+            .iter()
+            .cloned()
+            .chain(directive_diagnostics(
+                &self.resolved.document,
+                options,
+                &self.source_map,
+            ))
+            .filter(|diag| diag.severity <= min_severity)
+            .map(|mut diag| {
+                if let Some(expansion) = &self.expansion {
+                    expansion.relocate(&mut diag);
+                }
+                diag
+            })
+            .collect()
+    }
+
+    /// Whether the diagnostics call for exit code 1. Diagnostics hidden by
+    /// `--min-severity` still count.
+    fn fails(&self, options: &HtmlEmitOptions, fail_on: FailOn) -> bool {
+        let threshold = match fail_on {
+            FailOn::Error => DiagnosticSeverity::Error,
+            FailOn::Warning => DiagnosticSeverity::Warning,
+            FailOn::None => return false,
+        };
+        self.resolved
+            .diagnostics
+            .iter()
+            .cloned()
+            .chain(directive_diagnostics(
+                &self.resolved.document,
+                options,
+                &self.source_map,
+            ))
+            .any(|diag| diag.severity <= threshold)
+    }
+}
+
+/// Reads `path`, or stdin when it is `None`, and resolves it. `logical_input`
+/// is the path includes are relative to.
+fn load_document(
+    path: Option<&str>,
+    logical_input: Option<&str>,
+    includes: bool,
+    parse_options: &ParseOptions,
+) -> LoadedDocument {
+    let source = match path {
         Some(path) => fs::read_to_string(path).unwrap_or_else(|err| {
             eprintln!("failed to read {}: {}", path, err);
             process::exit(1);
@@ -114,144 +527,276 @@ fn main() {
         }
     };
 
+    let mut parsed = parse_with_options(&source, parse_options);
+    let expansion = includes.then(|| {
+        let resolver = FsIncludeResolver::new(logical_input.map(Path::new));
+        expand_includes(
+            &mut parsed,
+            &source,
+            resolver.root_name().as_deref(),
+            &resolver,
+            parse_options,
+        )
+    });
+    let source = expansion
+        .as_ref()
+        .map_or(source.as_str(), |expansion| expansion.source.as_str());
     let ParseResult {
         document,
         diagnostics,
         source_map,
         link_defs,
-    } = parse(&source);
-    let resolved = resolve(document, &source, &source_map, diagnostics, &link_defs);
-
-    let options = HtmlEmitOptions {
-        simple_code_blocks,
-        wrap_sections,
-    };
-
-    if let Some(mode) = diagnostics_mode {
-        emit_diagnostics(&resolved.diagnostics, mode);
+    } = parsed;
+    let resolved = resolve(document, source, &source_map, diagnostics, &link_defs);
+    LoadedDocument {
+        resolved,
+        source_map,
+        expansion,
     }
+}
 
-    let html = if emit_source_map {
-        if sanitized {
-            emit_html_document_sanitized_with_options_and_source_map(
-                &resolved.document,
-                &options,
-                &source_map,
-            )
-        } else {
-            emit_html_document_with_options_and_source_map(
-                &resolved.document,
-                &options,
-                &source_map,
-            )
+/// Exports one PDF from HTML parts, separated by page breaks. Each part is
+/// paired with its input file, if any, which its relative images resolve against.
+/// `settings` are the document settings that pick the page, margin and styles.
+fn export_pdf(
+    parts: &[(String, Option<&Path>)],
+    settings: Option<&AttrList>,
+    defaults: PdfSettings,
+    pdf_path: &str,
+) {
+    let input_path = parts.first().and_then(|(_, path)| *path);
+    let pdf_settings = match parse_pdf_settings(defaults, settings) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("pdf settings error: {}", err);
+            process::exit(1);
         }
-    } else if sanitized {
-        emit_html_document_sanitized_with_options(&resolved.document, &options)
-    } else {
-        emit_html_document_with_options(&resolved.document, &options)
     };
-
-    if let Some(pdf_path) = pdf_output {
-        let input_path = input.as_deref().map(Path::new);
-        let output_path = Path::new(&pdf_path);
-        let pdf_settings = match parse_pdf_settings(resolved.document.settings.as_ref()) {
-            Ok(settings) => settings,
-            Err(err) => {
-                eprintln!("pdf settings error: {}", err);
-                process::exit(1);
+    let base_url = match resolve_pdf_base_url(&pdf_settings, input_path) {
+        Ok(base_url) => base_url,
+        Err(err) => {
+            eprintln!("pdf settings error: {}", err);
+            process::exit(1);
+        }
+    };
+    let mut pages = Vec::new();
+    for (html, path) in parts {
+        match resolve_pdf_asset_dir(&pdf_settings, *path) {
+            Ok(Some(asset_dir)) => {
+                let (inlined, warnings) =
+                    inline_local_assets(html, &asset_dir, PDF_INLINE_IMAGE_MAX_BYTES);
+                for warning in warnings {
+                    eprintln!("warning: {}", warning);
+                }
+                pages.push(inlined);
             }
-        };
-        let base_url = match resolve_pdf_base_url(&pdf_settings, input_path) {
-            Ok(base_url) => base_url,
+            Ok(None) => pages.push(html.clone()),
             Err(err) => {
-                eprintln!("pdf settings error: {}", err);
-                process::exit(1);
+                eprintln!("warning: {}", err);
+                pages.push(html.clone());
             }
-        };
-        let renderer = apply_renderer_settings(
-            Renderer::new(Theme::Light),
-            resolved.document.settings.as_ref(),
-        );
-        let mut options = PdfOptions::new(pdf_settings.backend);
-        if let Some(page) = pdf_settings.page {
-            options = options.with_page(page);
-        }
-        if let Some(margin) = pdf_settings.margin {
-            options = options.with_margin(margin);
         }
-        if let Some(scale) = pdf_settings.scale {
-            options = options.with_scale(scale);
+    }
+    let renderer = apply_renderer_settings(Renderer::new(Theme::Light), settings);
+    let mut options = PdfOptions::new(pdf_settings.backend);
+    if let Some(page) = pdf_settings.page {
+        options = options.with_page(page);
+    }
+    if let Some(margin) = pdf_settings.margin {
+        options = options.with_margin(margin);
+    }
+    if let Some(scale) = pdf_settings.scale {
+        options = options.with_scale(scale);
+    }
+    if let Some(base_url) = base_url {
+        options = options.with_base_url(base_url);
+    }
+    let html = pages.join("\n<div style=\"break-before:page\"></div>\n");
+    if pdf_settings.backend == PdfBackend::Native {
+        let html_path = Path::new(pdf_path).with_extension("html");
+        if let Err(err) = renderer.export_print_html(&html, &options, &html_path) {
+            eprintln!("pdf export failed: {}", err);
+            process::exit(1);
         }
-        if let Some(base_url) = base_url {
-            options = options.with_base_url(base_url);
+        eprintln!(
+            "note: native backend wrote print-ready HTML to {} instead of a PDF; print it to PDF from a browser",
+            html_path.display()
+        );
+        return;
+    }
+    match renderer.export_pdf_with_warnings(&html, &options, Path::new(pdf_path)) {
+        Ok(warnings) => {
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
+            }
         }
-        if let Err(err) = renderer.export_pdf(&html, &options, output_path) {
+        Err(err) => {
             eprintln!("pdf export failed: {}", err);
             process::exit(1);
         }
-    } else if render {
-        let renderer =
-            apply_renderer_settings(Renderer::new(theme), resolved.document.settings.as_ref());
-        let highlighted = renderer.highlight_html(&html);
-        let wrapped = renderer.embed_html(&highlighted, true, render_js);
-        print!("{}", wrapped);
-    } else {
-        print!("{}", html);
-    }
-
-    if resolved
-        .diagnostics
-        .iter()
-        .any(|diag| diag.severity == DiagnosticSeverity::Error)
-    {
-        process::exit(1);
     }
 }
 
 fn print_usage() {
     eprintln!(
-        "Usage: typmark-cli [--version] [--sanitized] [--simple-code] [--source-map] [--no-section-wrap] [--render|--render-js|--raw] [--pdf output.pdf] [--theme auto|light|dark] [--diagnostics json|pretty] [input]"
+        "Usage: typmark-cli [--version] [--config typmark.toml] [--stdin-filename path] [--sanitized] [--simple-code] [--source-map] [--no-section-wrap] [--number-sections] [--render|--render-js|--raw] [--standalone] [--output file] [--assets-dir dir] [--pdf output.pdf] [--ast json|pretty] [--theme auto|light|dark] [--diagnostics json|pretty] [--min-severity error|warning|info|hint] [--fail-on error|warning|none] [--quiet] [--includes] [--strict] [--concat] [input...]"
+    );
+    eprintln!(
+        "--strict parses plain CommonMark: TypMark syntax such as ::: boxes, $math$, @refs and {{...}} lines stays text."
+    );
+    eprintln!(
+        "--fail-on picks the diagnostics that make the exit code 1 (default error). It counts diagnostics hidden by --min-severity, and output or PDF failures exit 1 regardless."
     );
 }
 
+/// Lowest severity that makes the CLI exit with code 1.
+#[derive(Clone, Copy)]
+enum FailOn {
+    Error,
+    Warning,
+    None,
+}
+
 #[derive(Clone, Copy)]
 enum DiagnosticsMode {
     Json,
     Pretty,
 }
 
-fn emit_diagnostics(diagnostics: &[Diagnostic], mode: DiagnosticsMode) {
-    if diagnostics.is_empty() {
-        if let DiagnosticsMode::Json = mode {
-            eprintln!("[]");
-        }
+#[derive(Clone, Copy)]
+enum AstMode {
+    Json,
+    Pretty,
+}
+
+/// Prints the diagnostics followed by per-severity totals. In quiet mode only the
+/// totals are printed, and nothing at all when there are no diagnostics.
+fn emit_diagnostics(diagnostics: &[Diagnostic], mode: DiagnosticsMode, quiet: bool) {
+    if quiet && diagnostics.is_empty() {
         return;
     }
+    let counts = severity_counts(diagnostics);
     match mode {
         DiagnosticsMode::Json => {
-            eprintln!("{}", diagnostics_to_json(diagnostics));
+            let mut out = String::from("{\n");
+            if !quiet {
+                out.push_str("  \"diagnostics\": ");
+                out.push_str(&diagnostics_to_json(diagnostics).replace('\n', "\n  "));
+                out.push_str(",\n");
+            }
+            out.push_str(&format!("  \"summary\": {}\n}}", summary_to_json(&counts)));
+            eprintln!("{}", out);
         }
         DiagnosticsMode::Pretty => {
-            for diagnostic in diagnostics {
-                eprintln!("{}", diagnostic_to_pretty(diagnostic));
+            if !quiet {
+                for diagnostic in diagnostics {
+                    eprintln!("{}", diagnostic_to_pretty(diagnostic));
+                }
             }
+            eprintln!("{}", summary_to_pretty(&counts));
+        }
+    }
+}
+
+const SEVERITIES: [DiagnosticSeverity; 4] = [
+    DiagnosticSeverity::Error,
+    DiagnosticSeverity::Warning,
+    DiagnosticSeverity::Info,
+    DiagnosticSeverity::Hint,
+];
+
+fn severity_counts(diagnostics: &[Diagnostic]) -> [usize; 4] {
+    SEVERITIES.map(|severity| {
+        diagnostics
+            .iter()
+            .filter(|diag| diag.severity == severity)
+            .count()
+    })
+}
+
+/// `3 errors, 5 warnings`; info and hint counts are appended only when non-zero.
+fn summary_to_pretty(counts: &[usize; 4]) -> String {
+    let mut parts = Vec::new();
+    for (idx, severity) in SEVERITIES.iter().enumerate() {
+        let count = counts[idx];
+        if idx >= 2 && count == 0 {
+            continue;
         }
+        let label = severity_label(*severity);
+        let suffix = if count == 1 || *severity == DiagnosticSeverity::Info {
+            ""
+        } else {
+            "s"
+        };
+        parts.push(format!("{} {}{}", count, label, suffix));
     }
+    parts.join(", ")
+}
+
+fn summary_to_json(counts: &[usize; 4]) -> String {
+    let fields: Vec<String> = SEVERITIES
+        .iter()
+        .zip(counts)
+        .map(|(severity, count)| format!("\"{}\": {}", severity_label(*severity), count))
+        .collect();
+    format!("{{ {} }}", fields.join(", "))
 }
 
 fn diagnostic_to_pretty(diagnostic: &Diagnostic) -> String {
-    let severity = match diagnostic.severity {
-        DiagnosticSeverity::Error => "error",
-        DiagnosticSeverity::Warning => "warning",
-    };
+    let severity = severity_label(diagnostic.severity);
     let start_line = diagnostic.range.start.line + 1;
     let start_col = diagnostic.range.start.character + 1;
+    let file = diagnostic
+        .file
+        .as_deref()
+        .map(|file| format!("{}:", file))
+        .unwrap_or_default();
     format!(
-        "{}:{}:{} {} {}",
-        start_line, start_col, severity, diagnostic.code, diagnostic.message
+        "{}{}:{}:{} {} {}",
+        file, start_line, start_col, severity, diagnostic.code, diagnostic.message
     )
 }
 
+/// Loads `@include` targets relative to the directory of the including file.
+struct FsIncludeResolver {
+    root_dir: PathBuf,
+    root: Option<PathBuf>,
+}
+
+impl FsIncludeResolver {
+    fn new(input: Option<&Path>) -> Self {
+        let root_dir = input
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        Self {
+            root_dir,
+            root: input.map(Path::to_path_buf),
+        }
+    }
+
+    fn root_name(&self) -> Option<String> {
+        let root = self.root.as_ref()?;
+        let path = fs::canonicalize(root).unwrap_or_else(|_| root.clone());
+        Some(path.display().to_string())
+    }
+}
+
+impl IncludeResolver for FsIncludeResolver {
+    fn load(&self, from: Option<&str>, path: &str) -> Result<IncludedSource, String> {
+        let base = match from.and_then(|from| Path::new(from).parent()) {
+            Some(dir) => dir.to_path_buf(),
+            None => self.root_dir.clone(),
+        };
+        let full = fs::canonicalize(base.join(path)).map_err(|err| err.to_string())?;
+        let text = fs::read_to_string(&full).map_err(|err| err.to_string())?;
+        Ok(IncludedSource {
+            name: full.display().to_string(),
+            text,
+        })
+    }
+}
+
 fn apply_renderer_settings(renderer: Renderer, settings: Option<&AttrList>) -> Renderer {
     let mut renderer = renderer;
     let Some(settings) = settings else {
@@ -269,17 +814,257 @@ fn apply_renderer_settings(renderer: Renderer, settings: Option<&AttrList>) -> R
             "code-font" => renderer = renderer.with_var("--typmark-code-font", value),
             "code-size" => renderer = renderer.with_var("--typmark-code-size", value),
             "paragraph-gap" => renderer = renderer.with_var("--typmark-paragraph-gap", value),
-            "page-width" => {
+            "page-width" | "max-content-width" => {
                 let normalized = if value == "auto" { "none" } else { value };
                 renderer = renderer.with_var("--typmark-page-width", normalized);
             }
             "image-max-width" => renderer = renderer.with_var("--typmark-image-max-width", value),
+            "content-align" => match value {
+                "left" => renderer = renderer.with_var("--typmark-content-margin", "0 auto 0 0"),
+                "center" => renderer = renderer.with_var("--typmark-content-margin", "0 auto"),
+                _ => eprintln!(
+                    "warning: ignoring content-align: {} (expected left|center)",
+                    value
+                ),
+            },
+            "page-padding" => {
+                if is_css_length(value) {
+                    renderer = renderer.with_var("--typmark-page-padding", value);
+                } else {
+                    eprintln!(
+                        "warning: ignoring page-padding: {} (expected a length such as 1.5rem)",
+                        value
+                    );
+                }
+            }
+            "lang" => renderer = renderer.with_lang(value),
+            "dir" if is_text_dir(value) => renderer = renderer.with_dir(value),
+            "dir" => eprintln!("warning: ignoring dir: {} (expected rtl|ltr|auto)", value),
             _ => {}
         }
     }
     renderer
 }
 
+/// Accepts `0` or a non-negative number followed by a CSS length unit.
+fn is_css_length(value: &str) -> bool {
+    const UNITS: &[&str] = &[
+        "px", "em", "rem", "%", "pt", "pc", "cm", "mm", "in", "ch", "ex", "vw", "vh", "vmin",
+        "vmax",
+    ];
+    if value == "0" {
+        return true;
+    }
+    let split = value
+        .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    number.parse::<f64>().is_ok() && UNITS.contains(&unit)
+}
+
+fn parse_theme(value: &str) -> Result<Theme, String> {
+    match value {
+        "auto" => Ok(Theme::Auto),
+        "light" => Ok(Theme::Light),
+        "dark" => Ok(Theme::Dark),
+        _ => Err(format!(
+            "unsupported theme: {} (expected auto|light|dark)",
+            value
+        )),
+    }
+}
+
+/// Defaults read from `typmark.toml`; command line flags take precedence.
+#[derive(Default)]
+struct CliConfig {
+    theme: Option<Theme>,
+    sanitized: Option<bool>,
+    simple_code_blocks: Option<bool>,
+    wrap_sections: Option<bool>,
+    number_sections: Option<bool>,
+    render_js: Option<bool>,
+    pdf: PdfSettings,
+    sanitize: SanitizeConfig,
+}
+
+fn load_cli_config(explicit: Option<&str>, input: Option<&str>) -> CliConfig {
+    let path = match explicit {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let Some(dir) = input.and_then(|input| Path::new(input).parent()) else {
+                return CliConfig::default();
+            };
+            let candidate = dir.join("typmark.toml");
+            if !candidate.is_file() {
+                return CliConfig::default();
+            }
+            candidate
+        }
+    };
+    let text = fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("failed to read {}: {}", path.display(), err);
+        process::exit(1);
+    });
+    let mut warnings = Vec::new();
+    let config = parse_cli_config(&text, &mut warnings).unwrap_or_else(|err| {
+        eprintln!("{}:{}: {}", path.display(), err.line, err.message);
+        process::exit(1);
+    });
+    for warning in warnings {
+        eprintln!(
+            "{}:{}: warning: {}",
+            path.display(),
+            warning.line,
+            warning.message
+        );
+    }
+    config
+}
+
+// Reads top-level keys and the `[pdf]` and `[sanitize]` tables; see
+// `parse_toml_subset` for the syntax.
+// Unknown tables and keys are collected in `warnings`.
+fn parse_cli_config(text: &str, warnings: &mut Vec<TomlError>) -> Result<CliConfig, TomlError> {
+    let mut config = CliConfig::default();
+    let document = parse_toml_subset(text)?;
+    for (line, table) in &document.tables {
+        if table != "pdf" && table != "sanitize" {
+            warnings.push(TomlError {
+                line: *line,
+                message: format!("unknown table [{}]", table),
+            });
+        }
+    }
+    for entry in &document.entries {
+        let known = apply_config_entry(&mut config, entry).map_err(|message| TomlError {
+            line: entry.line,
+            message,
+        })?;
+        if !known {
+            let name = if entry.table.is_empty() {
+                entry.key.clone()
+            } else {
+                format!("{}.{}", entry.table, entry.key)
+            };
+            warnings.push(TomlError {
+                line: entry.line,
+                message: format!("unknown key {}", name),
+            });
+        }
+    }
+    Ok(config)
+}
+
+/// Returns false for a key the config does not know. Keys under an unknown
+/// table count as known, since the table itself is reported.
+fn apply_config_entry(config: &mut CliConfig, entry: &TomlEntry) -> Result<bool, String> {
+    let (key, value) = (entry.key.as_str(), &entry.value);
+    Ok(match (entry.table.as_str(), key) {
+        ("", "theme") => {
+            config.theme = Some(parse_theme(&value.as_string())?);
+            true
+        }
+        ("", "sanitized") => {
+            config.sanitized = Some(value.as_bool(key)?);
+            true
+        }
+        ("", "simple_code_blocks") => {
+            config.simple_code_blocks = Some(value.as_bool(key)?);
+            true
+        }
+        ("", "wrap_sections") => {
+            config.wrap_sections = Some(value.as_bool(key)?);
+            true
+        }
+        ("", "number_sections") => {
+            config.number_sections = Some(value.as_bool(key)?);
+            true
+        }
+        ("", "render_js") => {
+            config.render_js = Some(value.as_bool(key)?);
+            true
+        }
+        ("pdf", "page") => {
+            config.pdf.page = Some(value.as_string());
+            true
+        }
+        ("pdf", "margin") => {
+            config.pdf.margin = Some(parse_pdf_margin(&value.as_string())?);
+            true
+        }
+        ("pdf", "scale") => {
+            let scale = value.as_string();
+            parse_pdf_scale(&scale)?;
+            config.pdf.scale = Some(scale);
+            true
+        }
+        ("pdf", "base") => {
+            config.pdf.base = Some(value.as_string());
+            true
+        }
+        ("pdf", "backend") => {
+            config.pdf.backend = parse_pdf_backend(&value.as_string())?;
+            true
+        }
+        ("sanitize", "add_tags") => {
+            config.sanitize.add_tags = config_list(value);
+            true
+        }
+        ("sanitize", "remove_tags") => {
+            config.sanitize.remove_tags = config_list(value);
+            true
+        }
+        ("sanitize", "add_attributes") => {
+            config.sanitize.add_attributes = parse_tag_attributes(value)?;
+            true
+        }
+        ("sanitize", "remove_attributes") => {
+            config.sanitize.remove_attributes = parse_tag_attributes(value)?;
+            true
+        }
+        ("sanitize", "add_url_schemes") => {
+            config.sanitize.add_url_schemes = config_list(value);
+            true
+        }
+        ("sanitize", "remove_url_schemes") => {
+            config.sanitize.remove_url_schemes = config_list(value);
+            true
+        }
+        ("sanitize", "allow_media") => {
+            config.sanitize.allow_media = value.as_bool(key)?;
+            true
+        }
+        ("" | "pdf" | "sanitize", _) => false,
+        _ => true,
+    })
+}
+
+/// Splits a whitespace- or comma-separated list.
+fn config_list(value: &TomlValue) -> Vec<String> {
+    value
+        .as_string()
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reads a list of `tag:attribute` pairs; `*:attribute` applies to every tag.
+fn parse_tag_attributes(value: &TomlValue) -> Result<Vec<(String, String)>, String> {
+    config_list(value)
+        .into_iter()
+        .map(|item| match item.split_once(':') {
+            Some((tag, attr)) if !tag.is_empty() && !attr.is_empty() => {
+                Ok((tag.to_string(), attr.to_string()))
+            }
+            _ => Err(format!(
+                "invalid attribute: {} (expected tag:attribute)",
+                item
+            )),
+        })
+        .collect()
+}
+
 struct PdfSettings {
     page: Option<String>,
     margin: Option<PdfMargin>,
@@ -288,14 +1073,23 @@ struct PdfSettings {
     backend: PdfBackend,
 }
 
-fn parse_pdf_settings(settings: Option<&AttrList>) -> Result<PdfSettings, String> {
-    let mut pdf = PdfSettings {
-        page: None,
-        margin: Some(PdfMargin::new("1.5rem", "1.5rem", "1.5rem", "1.5rem")),
-        scale: None,
-        base: None,
-        backend: PdfBackend::Auto,
-    };
+impl Default for PdfSettings {
+    fn default() -> Self {
+        Self {
+            page: None,
+            margin: Some(PdfMargin::new("1.5rem", "1.5rem", "1.5rem", "1.5rem")),
+            scale: None,
+            base: None,
+            backend: PdfBackend::Auto,
+        }
+    }
+}
+
+fn parse_pdf_settings(
+    defaults: PdfSettings,
+    settings: Option<&AttrList>,
+) -> Result<PdfSettings, String> {
+    let mut pdf = defaults;
     let Some(settings) = settings else {
         return Ok(pdf);
     };
@@ -330,8 +1124,9 @@ fn parse_pdf_backend(value: &str) -> Result<PdfBackend, String> {
         "auto" => Ok(PdfBackend::Auto),
         "chromium" | "chrome" => Ok(PdfBackend::Chromium),
         "wkhtmltopdf" | "wkhtml" => Ok(PdfBackend::Wkhtmltopdf),
+        "native" => Ok(PdfBackend::Native),
         _ => Err(format!(
-            "unsupported pdf-backend: {} (expected auto|chromium|wkhtmltopdf)",
+            "unsupported pdf-backend: {} (expected auto|chromium|wkhtmltopdf|native)",
             value
         )),
     }
@@ -397,6 +1192,19 @@ fn resolve_pdf_base_url(
     Ok(Some(path_to_file_url_dir(&default_dir)?))
 }
 
+/// Local directory that relative image paths in the PDF resolve against, if any.
+fn resolve_pdf_asset_dir(
+    settings: &PdfSettings,
+    input_path: Option<&Path>,
+) -> Result<Option<PathBuf>, String> {
+    match settings.base.as_deref().map(str::trim) {
+        Some("") => Ok(None),
+        Some(base) if base.contains("://") => Ok(None),
+        Some(base) => resolve_pdf_base_dir(Path::new(base), input_path).map(Some),
+        None => default_pdf_base_dir(input_path),
+    }
+}
+
 fn resolve_pdf_base_dir(path: &Path, input_path: Option<&Path>) -> Result<PathBuf, String> {
     if path.is_absolute() {
         return Ok(path.to_path_buf());
@@ -439,7 +1247,11 @@ fn path_to_file_url(path: &Path) -> Result<String, String> {
         value = format!("/{}", value);
     }
 
-    let mut out = String::from("file://");
+    Ok(format!("file://{}", percent_encode_path(&value)))
+}
+
+fn percent_encode_path(value: &str) -> String {
+    let mut out = String::new();
     for byte in value.as_bytes() {
         let ch = *byte as char;
         if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | '~' | '/') {
@@ -448,9 +1260,71 @@ fn path_to_file_url(path: &Path) -> Result<String, String> {
             out.push_str(&format!("%{:02X}", byte));
         }
     }
+    out
+}
+
+/// Returns a URL prefix that reaches `to` from a document in `from`, such as
+/// `../assets/`, or an empty string when both are the same directory. Falls back
+/// to a `file://` URL when the paths share no root.
+fn relative_dir_href(from: &Path, to: &Path) -> Result<String, String> {
+    let from = normalize_path(from)?;
+    let to = normalize_path(to)?;
+    let from_parts: Vec<_> = from.components().collect();
+    let to_parts: Vec<_> = to.components().collect();
+    let common = from_parts
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return path_to_file_url_dir(&to);
+    }
+
+    let mut href = String::new();
+    for _ in common..from_parts.len() {
+        href.push_str("../");
+    }
+    for part in &to_parts[common..] {
+        href.push_str(&percent_encode_path(&part.as_os_str().to_string_lossy()));
+        href.push('/');
+    }
+    Ok(href)
+}
+
+/// Makes `path` absolute and removes `.` and `..` components without touching the filesystem.
+fn normalize_path(path: &Path) -> Result<PathBuf, String> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()
+            .map_err(|err| format!("failed to resolve current directory: {}", err))?
+            .join(path)
+    };
+    let mut out = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
     Ok(out)
 }
 
+fn write_output(path: Option<&str>, html: &str) {
+    match path {
+        Some(path) => {
+            if let Err(err) = fs::write(path, html) {
+                eprintln!("failed to write {}: {}", path, err);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", html),
+    }
+}
+
 fn path_to_file_url_dir(path: &Path) -> Result<String, String> {
     let mut url = path_to_file_url(path)?;
     if !url.ends_with('/') {
@@ -469,6 +1343,9 @@ fn diagnostics_to_json(diagnostics: &[Diagnostic]) -> String {
     for (idx, diag) in diagnostics.iter().enumerate() {
         out.push_str("  {\n");
         out.push_str(&format!("    \"code\": \"{}\",\n", diag.code));
+        if let Some(file) = &diag.file {
+            out.push_str(&format!("    \"file\": \"{}\",\n", escape_json(file)));
+        }
         out.push_str(&format!(
             "    \"severity\": \"{}\",\n",
             severity_label(diag.severity)
@@ -488,12 +1365,13 @@ fn diagnostics_to_json(diagnostics: &[Diagnostic]) -> String {
         ));
         out.push_str("    }");
 
-        if diag.related.is_empty() {
-            out.push_str("\n  }");
-        } else {
+        if !diag.related.is_empty() {
             out.push_str(",\n    \"related\": [\n");
             for (rel_idx, related) in diag.related.iter().enumerate() {
                 out.push_str("      {\n");
+                if let Some(file) = &related.file {
+                    out.push_str(&format!("        \"file\": \"{}\",\n", escape_json(file)));
+                }
                 out.push_str("        \"range\": {\n");
                 out.push_str(&format!(
                     "          \"start\": {{ \"line\": {}, \"character\": {} }},\n",
@@ -519,8 +1397,41 @@ fn diagnostics_to_json(diagnostics: &[Diagnostic]) -> String {
                     out.push('\n');
                 }
             }
-            out.push_str("    ]\n  }");
+            out.push_str("    ]");
+        }
+
+        if !diag.fixes.is_empty() {
+            out.push_str(",\n    \"fixes\": [\n");
+            for (fix_idx, fix) in diag.fixes.iter().enumerate() {
+                out.push_str("      {\n");
+                out.push_str(&format!(
+                    "        \"title\": \"{}\",\n",
+                    escape_json(&fix.title)
+                ));
+                out.push_str("        \"range\": {\n");
+                out.push_str(&format!(
+                    "          \"start\": {{ \"line\": {}, \"character\": {} }},\n",
+                    fix.range.start.line, fix.range.start.character
+                ));
+                out.push_str(&format!(
+                    "          \"end\": {{ \"line\": {}, \"character\": {} }}\n",
+                    fix.range.end.line, fix.range.end.character
+                ));
+                out.push_str("        },\n");
+                out.push_str(&format!(
+                    "        \"replacement\": \"{}\"\n",
+                    escape_json(&fix.replacement)
+                ));
+                out.push_str("      }");
+                if fix_idx + 1 < diag.fixes.len() {
+                    out.push_str(",\n");
+                } else {
+                    out.push('\n');
+                }
+            }
+            out.push_str("    ]");
         }
+        out.push_str("\n  }");
 
         if idx + 1 < diagnostics.len() {
             out.push_str(",\n");
@@ -536,6 +1447,8 @@ fn severity_label(severity: DiagnosticSeverity) -> &'static str {
     match severity {
         DiagnosticSeverity::Error => "error",
         DiagnosticSeverity::Warning => "warning",
+        DiagnosticSeverity::Info => "info",
+        DiagnosticSeverity::Hint => "hint",
     }
 }
 
@@ -71,6 +71,144 @@ fn diagnostics_json_reports_warning_and_exit_code() {
     );
 }
 
+#[test]
+fn diagnostics_json_includes_fixes() {
+    let input = temp_file("attr_fix", "Intro.\n\n{#box width:10}\nText.\n");
+    let output = Command::new(bin_path())
+        .args(["--diagnostics", "json", input.to_str().expect("path")])
+        .output()
+        .expect("run");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("\"code\": \"E_ATTR_SYNTAX\""),
+        "expected E_ATTR_SYNTAX in stderr: {stderr}"
+    );
+    assert!(
+        stderr.contains(concat!(
+            "      \"fixes\": [\n",
+            "        {\n",
+            "          \"title\": \"Use `=`\",\n",
+            "          \"range\": {\n",
+            "            \"start\": { \"line\": 2, \"character\": 6 },\n",
+            "            \"end\": { \"line\": 2, \"character\": 14 }\n",
+            "          },\n",
+            "          \"replacement\": \"width=10\"\n",
+            "        }\n",
+            "      ]\n",
+        )),
+        "expected the fix in stderr: {stderr}"
+    );
+}
+
+#[test]
+fn min_severity_filters_printed_diagnostics() {
+    let input = temp_file("min_severity", "@missing[link]\n");
+    let output = Command::new(bin_path())
+        .args([
+            "--diagnostics",
+            "pretty",
+            "--min-severity",
+            "error",
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+
+    assert!(output.status.success(), "expected success exit code");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !stderr.contains("W_REF_MISSING"),
+        "expected warning to be filtered out"
+    );
+}
+
+#[test]
+fn fail_on_controls_exit_code() {
+    let warning = temp_file("fail_on_warning", "@missing[link]\n");
+    let error = temp_file("fail_on_error", "{#p}\nParagraph.\n\n@p\n");
+    let run = |args: &[&str], input: &PathBuf| {
+        Command::new(bin_path())
+            .args(args)
+            .arg(input)
+            .output()
+            .expect("run")
+            .status
+            .success()
+    };
+
+    assert!(run(&[], &warning));
+    assert!(!run(&["--fail-on", "warning"], &warning));
+    // Warnings hidden from the output still fail the run.
+    assert!(!run(
+        &["--fail-on", "warning", "--min-severity", "error"],
+        &warning
+    ));
+    assert!(!run(&["--fail-on", "error"], &error));
+    assert!(run(&["--fail-on", "none"], &error));
+
+    let output = Command::new(bin_path())
+        .args(["--fail-on", "info"])
+        .output()
+        .expect("run");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn strict_renders_plain_commonmark() {
+    let input = temp_file(
+        "strict",
+        "{#intro}\n# Intro\n\nSee @intro and $x$.\n\n```rs {#code}\nx\n```\n",
+    );
+    let output = Command::new(bin_path())
+        .args(["--raw", "--strict", input.to_str().expect("path")])
+        .output()
+        .expect("run");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+        "<p>{#intro}</p>\n<h1>Intro</h1>\n<p>See @intro and $x$.</p>\n<pre><code class=\"language-rs\">x\n</code></pre>"
+    );
+}
+
+#[test]
+fn quiet_prints_only_the_summary() {
+    let input = temp_file("quiet", "{#p}\nParagraph.\n\n@p\n\n@missing[link]\n");
+    let output = Command::new(bin_path())
+        .args(["--quiet", input.to_str().expect("path")])
+        .output()
+        .expect("run");
+
+    assert!(!output.status.success(), "expected error exit code");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr, "1 error, 1 warning\n");
+
+    let output = Command::new(bin_path())
+        .args([
+            "--quiet",
+            "--diagnostics",
+            "json",
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("\"diagnostics\""), "{stderr}");
+    assert!(
+        stderr.contains("\"summary\": { \"error\": 1, \"warning\": 1, \"info\": 0, \"hint\": 0 }"),
+        "{stderr}"
+    );
+
+    let clean = temp_file("quiet_clean", "Paragraph.\n");
+    let output = Command::new(bin_path())
+        .args(["--quiet", clean.to_str().expect("path")])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    assert!(output.stderr.is_empty(), "expected no summary");
+}
+
 #[test]
 fn render_wraps_html_with_assets() {
     let input = temp_file("render", "Paragraph.\n");
@@ -138,3 +276,515 @@ fn version_reports_cli_version() {
     let version = env!("CARGO_PKG_VERSION");
     assert_eq!(stdout.trim(), version, "expected version output");
 }
+
+#[test]
+fn config_file_sets_defaults_and_flags_override() {
+    let mut dir = env::temp_dir();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("time");
+    dir.push(format!(
+        "typmark_cli_config_{}_{}",
+        now.as_secs(),
+        now.subsec_nanos()
+    ));
+    fs::create_dir_all(&dir).expect("create temp dir");
+    fs::write(
+        dir.join("typmark.toml"),
+        "render_js = false # no script\nwrap_sections = false\nunknown = \"x\"\n\n[pdf]\nmargin = \"1cm 2cm\"\ncolour = \"red\"\n\n[extra]\nkey = 1\n",
+    )
+    .expect("write config");
+    let input = dir.join("doc.tmd");
+    fs::write(&input, "# Title\n\nBody.\n").expect("write input");
+
+    let output = Command::new(bin_path())
+        .args([input.to_str().expect("path")])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stdout.contains("<script>"), "config disables inline JS");
+    assert!(!stdout.contains("<section"), "config disables section wrap");
+    assert!(
+        stderr.contains("typmark.toml:3: warning: unknown key unknown"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("typmark.toml:7: warning: unknown key pdf.colour"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("typmark.toml:9: warning: unknown table [extra]"),
+        "{stderr}"
+    );
+
+    let output = Command::new(bin_path())
+        .args(["--render-js", input.to_str().expect("path")])
+        .output()
+        .expect("run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("<script>"), "flag overrides config");
+
+    let explicit = dir.join("other.toml");
+    fs::write(&explicit, "[pdf]\n# renderer\nbackend = \"bogus\"\n").expect("write config");
+    let output = Command::new(bin_path())
+        .args([
+            "--config",
+            explicit.to_str().expect("path"),
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert!(!output.status.success(), "invalid config value fails");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("other.toml:3: unsupported pdf-backend"),
+        "{stderr}"
+    );
+
+    fs::write(&explicit, "sanitized = true\nrender_js = \"no\"\n").expect("write config");
+    let output = Command::new(bin_path())
+        .args([
+            "--config",
+            explicit.to_str().expect("path"),
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert!(!output.status.success(), "invalid config type fails");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("other.toml:2: render_js expects true or false"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn config_sanitize_table_changes_the_allow_list() {
+    let input = temp_file(
+        "sanitize_config",
+        "Some <mark>marked</mark> and <em>more</em><script>alert(1)</script>.\n",
+    );
+    let config = input.with_extension("toml");
+    fs::write(
+        &config,
+        "[sanitize]\nadd_tags = \"mark\"\nremove_tags = \"em\"\nadd_attributes = \"mark:title\"\n",
+    )
+    .expect("write config");
+    let output = Command::new(bin_path())
+        .args([
+            "--sanitized",
+            "--config",
+            config.to_str().expect("path"),
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("<mark>marked</mark>"), "{stdout}");
+    assert!(stdout.contains(" and more."), "{stdout}");
+    assert!(!stdout.contains("alert(1)"), "{stdout}");
+
+    fs::write(&config, "[sanitize]\nadd_attributes = \"title\"\n").expect("write config");
+    let output = Command::new(bin_path())
+        .args([
+            "--config",
+            config.to_str().expect("path"),
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert!(!output.status.success(), "invalid attribute pair fails");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(":2: invalid attribute: title (expected tag:attribute)"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn stdin_filename_locates_config_and_rejects_input_file() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut dir = env::temp_dir();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("time");
+    dir.push(format!(
+        "typmark_cli_stdin_{}_{}",
+        now.as_secs(),
+        now.subsec_nanos()
+    ));
+    fs::create_dir_all(&dir).expect("create temp dir");
+    fs::write(dir.join("typmark.toml"), "wrap_sections = false\n").expect("write config");
+    let logical = dir.join("piped.tmd");
+
+    let mut child = Command::new(bin_path())
+        .args(["--raw", "--stdin-filename", logical.to_str().expect("path")])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("run");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(b"# Title\n\nBody.\n")
+        .expect("write stdin");
+    let output = child.wait_with_output().expect("wait");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("<h1"), "expected stdin content");
+    assert!(
+        !stdout.contains("<section"),
+        "config found via stdin filename"
+    );
+
+    let input = temp_file("stdin_conflict", "Body.\n");
+    let output = Command::new(bin_path())
+        .args([
+            "--stdin-filename",
+            logical.to_str().expect("path"),
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert_eq!(output.status.code(), Some(2), "expected usage error");
+}
+
+#[test]
+fn includes_read_files_relative_to_the_including_file() {
+    let mut dir = env::temp_dir();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("time");
+    dir.push(format!(
+        "typmark_cli_includes_{}_{}",
+        now.as_secs(),
+        now.subsec_nanos()
+    ));
+    fs::create_dir_all(dir.join("parts")).expect("create temp dir");
+    fs::write(dir.join("main.tmd"), "Intro.\n\n@include(parts/a.md)\n").expect("write main");
+    fs::write(
+        dir.join("parts/a.md"),
+        "Part @missing[x].\n\n@include(b.md)\n",
+    )
+    .expect("write a");
+    fs::write(dir.join("parts/b.md"), "Nested.\n").expect("write b");
+    let main = dir.join("main.tmd");
+
+    let output = Command::new(bin_path())
+        .args([
+            "--raw",
+            "--includes",
+            "--diagnostics",
+            "pretty",
+            main.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("<p>Nested.</p>"), "{stdout}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("a.md:1:6:warning W_REF_MISSING"),
+        "{stderr}"
+    );
+
+    let output = Command::new(bin_path())
+        .args(["--raw", main.to_str().expect("path")])
+        .output()
+        .expect("run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Without --includes the line is ordinary text and no file is read.
+    assert!(stdout.contains("(parts/a.md)</p>"), "{stdout}");
+    assert!(!stdout.contains("Nested."), "{stdout}");
+}
+
+#[test]
+fn assets_dir_links_assets_relative_to_output() {
+    let mut dir = env::temp_dir();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("time");
+    dir.push(format!(
+        "typmark_cli_assets_{}_{}",
+        now.as_secs(),
+        now.subsec_nanos()
+    ));
+    fs::create_dir_all(dir.join("site/docs")).expect("create temp dir");
+    let input = temp_file("assets_dir", "Hello.\n");
+    let html_path = dir.join("site/docs/index.html");
+
+    let output = Command::new(bin_path())
+        .args([
+            "--render-js",
+            "--output",
+            html_path.to_str().expect("path"),
+            "--assets-dir",
+            dir.join("site/static files").to_str().expect("path"),
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    assert!(output.stdout.is_empty());
+    assert!(dir.join("site/static files/typmark.css").exists());
+    assert!(dir.join("site/static files/typmark.js").exists());
+    let html = fs::read_to_string(&html_path).expect("read output");
+    assert!(
+        html.contains("<link rel=\"stylesheet\" href=\"../static%20files/typmark.css\" />"),
+        "{html}"
+    );
+    assert!(
+        html.contains("<script src=\"../static%20files/typmark.js\"></script>"),
+        "{html}"
+    );
+    assert!(!html.contains("<style>"), "{html}");
+
+    let output = Command::new(bin_path())
+        .args([
+            "--output",
+            html_path.to_str().expect("path"),
+            "--assets-dir",
+            dir.join("site/docs").to_str().expect("path"),
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let html = fs::read_to_string(&html_path).expect("read output");
+    assert!(
+        html.contains("<link rel=\"stylesheet\" href=\"typmark.css\" />"),
+        "{html}"
+    );
+    assert!(html.contains("<script src=\"typmark.js\">"), "{html}");
+}
+
+#[test]
+fn standalone_embeds_local_images() {
+    let mut dir = env::temp_dir();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("time");
+    dir.push(format!(
+        "typmark_cli_standalone_{}_{}",
+        now.as_secs(),
+        now.subsec_nanos()
+    ));
+    fs::create_dir_all(dir.join("img")).expect("create temp dir");
+    fs::write(dir.join("img/logo.png"), b"png").expect("write image");
+    let input = dir.join("doc.tmd");
+    fs::write(
+        &input,
+        "![logo](img/logo.png) ![gone](missing.png)\n\n```rust\nfn main() {}\n```\n",
+    )
+    .expect("write input");
+
+    let output = Command::new(bin_path())
+        .args(["--standalone", input.to_str().expect("path")])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("src=\"data:image/png;base64,cG5n\""),
+        "{stdout}"
+    );
+    assert!(stdout.contains("<style>") && stdout.contains("<script>"));
+    assert!(stdout.contains("data-lang-label=\"Rust\""), "{stdout}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing.png"), "{stderr}");
+
+    let output = Command::new(bin_path())
+        .args([
+            "--standalone",
+            "--assets-dir",
+            "out",
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn dir_setting_and_block_dir_render() {
+    let input = temp_file("dir", "{ dir=rtl }\n\nمرحبا\n\n{dir=ltr}\nEnglish.\n");
+    let output = Command::new(bin_path())
+        .arg(input.to_str().expect("path"))
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("<html lang=\"en\" dir=\"rtl\">"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("<p dir=\"ltr\">English.</p>"), "{stdout}");
+    assert!(output.stderr.is_empty());
+
+    let input = temp_file("dir_invalid", "{ dir=right }\n\nText.\n");
+    let output = Command::new(bin_path())
+        .arg(input.to_str().expect("path"))
+        .output()
+        .expect("run");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("<html lang=\"en\">"), "{stdout}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("warning: ignoring dir: right"), "{stderr}");
+}
+
+#[test]
+fn layout_settings_become_css_variables() {
+    let input = temp_file(
+        "layout_settings",
+        "{ content-align=left page-padding=2rem max-content-width=40rem }\n\nText.\n",
+    );
+    let output = Command::new(bin_path())
+        .arg(input.to_str().expect("path"))
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("--typmark-content-margin: 0 auto 0 0;"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("--typmark-page-padding: 2rem;"), "{stdout}");
+    assert!(stdout.contains("--typmark-page-width: 40rem;"), "{stdout}");
+    assert!(output.stderr.is_empty());
+
+    let input = temp_file(
+        "layout_settings_invalid",
+        "{ content-align=right page-padding=\"1px;color:red\" }\n\nText.\n",
+    );
+    let output = Command::new(bin_path())
+        .arg(input.to_str().expect("path"))
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("--typmark-content-margin:"), "{stdout}");
+    assert!(!stdout.contains("color:red"), "{stdout}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("warning: ignoring content-align: right"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("warning: ignoring page-padding"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn ast_dump_prints_tree_and_exits_zero() {
+    let input = temp_file("ast_dump", "{#p}\nParagraph.\n\nSee @p and @missing[x].\n");
+    let output = Command::new(bin_path())
+        .args(["--ast", "json", input.to_str().expect("path")])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let dump: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON");
+    let first = &dump["document"]["blocks"][0];
+    assert_eq!(first["span"], serde_json::json!({ "start": 5, "end": 15 }));
+    assert_eq!(first["attrs"]["label"]["name"], "p");
+    assert_eq!(
+        first["kind"]["Paragraph"]["content"][0]["kind"]["Text"],
+        "Paragraph."
+    );
+    let codes: Vec<&str> = dump["diagnostics"]
+        .as_array()
+        .expect("diagnostics array")
+        .iter()
+        .map(|diag| diag["code"].as_str().expect("code"))
+        .collect();
+    assert_eq!(codes, ["E_REF_OMIT", "W_REF_MISSING"]);
+    assert!(!stdout.contains("<p"), "{stdout}");
+
+    let output = Command::new(bin_path())
+        .args(["--ast", "pretty", input.to_str().expect("path")])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("(Document 0..41\n"), "{stdout}");
+    assert!(
+        stdout.contains("(Paragraph 5..15\n") && stdout.contains("(Text 5..15 \"Paragraph.\")"),
+        "{stdout}"
+    );
+
+    let output = Command::new(bin_path())
+        .args([
+            "--ast",
+            "json",
+            "--pdf",
+            "out.pdf",
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert_eq!(output.status.code(), Some(2), "expected usage error");
+}
+
+#[test]
+fn concat_uses_first_settings_and_names_files_in_diagnostics() {
+    let first = temp_file("concat_first", "{ pdf-backend=bogus }\n\n# One\n");
+    let second = temp_file("concat_second", "# Two\n\nSee @missing.\n");
+    let output = Command::new(bin_path())
+        .args([
+            "--concat",
+            "--pdf",
+            "out.pdf",
+            "--diagnostics",
+            "pretty",
+            first.to_str().expect("path"),
+            second.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let expected = format!("{}:3:5:warning W_REF_MISSING", second.display());
+    assert!(stderr.contains(&expected), "{stderr}");
+    assert!(stderr.contains("unsupported pdf-backend"), "{stderr}");
+
+    let output = Command::new(bin_path())
+        .args([
+            "--raw",
+            first.to_str().expect("path"),
+            second.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert_eq!(
+        output.status.code(),
+        Some(2),
+        "several inputs need --concat"
+    );
+
+    let output = Command::new(bin_path())
+        .args(["--concat", first.to_str().expect("path")])
+        .output()
+        .expect("run");
+    assert_eq!(output.status.code(), Some(2), "--concat needs --pdf");
+}
+
+#[test]
+fn native_pdf_backend_writes_print_html_next_to_output() {
+    let input = temp_file("native_pdf", "{ pdf-backend=native }\n\n# Title\n");
+    let pdf = input.with_extension("pdf");
+    let output = Command::new(bin_path())
+        .args([
+            "--pdf",
+            pdf.to_str().expect("path"),
+            input.to_str().expect("path"),
+        ])
+        .output()
+        .expect("run");
+    assert!(output.status.success(), "expected success exit code");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("note: native backend wrote print-ready HTML"),
+        "{stderr}"
+    );
+    let page = fs::read_to_string(input.with_extension("html")).expect("print html");
+    assert!(page.contains("Title</h1>"), "{page}");
+    assert!(!pdf.exists());
+}
@@ -0,0 +1,64 @@
+//! Measures parsing and resolving a large generated document, and prints its
+//! node count and depth so timings can be compared against AST size.
+//!
+//! Run with `cargo bench -p typmark-core --bench parse_large`.
+
+use std::time::{Duration, Instant};
+use typmark_core::{parse, resolve};
+
+const RUNS: u32 = 5;
+const SECTIONS: usize = 2_000;
+
+fn section(idx: usize) -> String {
+    format!(
+        concat!(
+            "## Section {idx} {{#sec-{idx}}}\n\n",
+            "Some *emphasis*, **strong [link](https://example.com/{idx})**, `code`, ",
+            "and a reference to @sec-{idx}.\n\n",
+            "- item one\n",
+            "  - nested *item* with [a link](#sec-{idx})\n",
+            "- item two\n\n",
+            ":::box\n",
+            "> A quote with `inline code` and more *text*.\n",
+            ":::\n\n",
+            "```rust\n",
+            "fn section_{idx}() {{}}\n",
+            "```\n\n",
+        ),
+        idx = idx
+    )
+}
+
+fn measure<T>(mut run: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        std::hint::black_box(run());
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let source: String = (0..SECTIONS).map(section).collect();
+
+    let parse_time = measure(|| parse(std::hint::black_box(&source)));
+    let resolve_time = measure(|| {
+        let parsed = parse(std::hint::black_box(&source));
+        resolve(
+            parsed.document,
+            &source,
+            &parsed.source_map,
+            parsed.diagnostics,
+            &parsed.link_defs,
+        )
+    });
+
+    let document = parse(&source).document;
+    println!(
+        "{} bytes, {} nodes, depth {}",
+        source.len(),
+        document.node_count(),
+        document.max_depth()
+    );
+    println!("parse:           {:>10.3?} per document", parse_time);
+    println!("parse + resolve: {:>10.3?} per document", resolve_time);
+}
@@ -3,16 +3,31 @@ use crate::span::Span;
 pub type InlineSeq = Vec<Inline>;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NodeId(pub u32);
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Document {
     pub span: Span,
     pub settings: Option<AttrList>,
     pub blocks: Vec<Block>,
 }
 
+impl Document {
+    /// Bibliography entry for a citation key, taken from the `bib-<key>` setting.
+    pub fn bibliography_entry(&self, key: &str) -> Option<&str> {
+        self.settings
+            .as_ref()?
+            .items
+            .iter()
+            .find(|item| item.key.strip_prefix("bib-") == Some(key))
+            .map(|item| item.value.raw.as_str())
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Block {
     pub span: Span,
     pub attrs: AttrList,
@@ -20,6 +35,7 @@ pub struct Block {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum BlockKind {
     Paragraph {
         content: InlineSeq,
@@ -33,13 +49,23 @@ pub enum BlockKind {
         title: InlineSeq,
         label: Option<Label>,
         children: Vec<Block>,
+        /// Place in the document outline, `[1, 2]` for 1.2. Empty for
+        /// `{.unnumbered}` sections and sections inside other blocks.
+        number: Vec<u32>,
     },
     List(List),
     BlockQuote {
         blocks: Vec<Block>,
+        kind: Option<AlertKind>,
     },
     CodeBlock(CodeBlock),
     Box(BoxBlock),
+    /// `:::name [title]` fenced container other than the built-in `box`.
+    Container {
+        name: String,
+        title: Option<InlineSeq>,
+        blocks: Vec<Block>,
+    },
     MathBlock {
         typst_src: String,
     },
@@ -48,9 +74,58 @@ pub enum BlockKind {
         raw: String,
     },
     Table(Table),
+    /// `@include(path)` line, replaced by the included blocks when includes are expanded.
+    Include {
+        path: String,
+    },
+}
+
+/// GitHub-style alert marker on a block quote (`> [!NOTE]`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AlertKind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl AlertKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "note" => Some(Self::Note),
+            "tip" => Some(Self::Tip),
+            "important" => Some(Self::Important),
+            "warning" => Some(Self::Warning),
+            "caution" => Some(Self::Caution),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Note => "note",
+            Self::Tip => "tip",
+            Self::Important => "important",
+            Self::Warning => "warning",
+            Self::Caution => "caution",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Note => "Note",
+            Self::Tip => "Tip",
+            Self::Important => "Important",
+            Self::Warning => "Warning",
+            Self::Caution => "Caution",
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct List {
     pub ordered: bool,
     pub start: Option<u64>,
@@ -59,6 +134,7 @@ pub struct List {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ListItem {
     pub span: Span,
     pub blocks: Vec<Block>,
@@ -66,6 +142,7 @@ pub struct ListItem {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Table {
     pub headers: Vec<InlineSeq>,
     pub aligns: Vec<TableAlign>,
@@ -73,6 +150,7 @@ pub struct Table {
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TableAlign {
     None,
     Left,
@@ -81,21 +159,38 @@ pub enum TableAlign {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CodeBlock {
     pub kind: CodeBlockKind,
     pub lang: Option<String>,
     pub info_attrs: AttrList,
     pub meta: CodeMeta,
     pub text: String,
+    /// Set by a `nohighlight` info token or `{.no-highlight}`; the renderer skips tokenizing.
+    pub no_highlight: bool,
+    /// Set by `wrap=soft`; long lines wrap instead of scrolling.
+    pub soft_wrap: bool,
+    /// The code block named by an `output=label` attribute. The resolver moves
+    /// it here so both blocks render in one figure.
+    pub output: Option<Box<Block>>,
+}
+
+impl CodeBlock {
+    /// Highlight, diff, and label state of a 1-based line, as the emitter marks it.
+    pub fn line_status(&self, line: u32) -> LineStatus<'_> {
+        self.meta.line_status(line)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum CodeBlockKind {
     Fenced,
     Indented,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CodeMeta {
     // Line numbers are 1-based and include blank lines.
     pub hl: Vec<LineRange>,
@@ -104,37 +199,91 @@ pub struct CodeMeta {
     pub line_labels: Vec<LineLabel>,
 }
 
+impl CodeMeta {
+    /// Highlight, diff, and label state of a 1-based line. A line in both
+    /// `diff_add` and `diff_del` counts as added.
+    pub fn line_status(&self, line: u32) -> LineStatus<'_> {
+        let in_any = |ranges: &[LineRange]| ranges.iter().any(|range| range.contains(line));
+        let diff = if in_any(&self.diff_add) {
+            Some(LineDiff::Add)
+        } else if in_any(&self.diff_del) {
+            Some(LineDiff::Del)
+        } else {
+            None
+        };
+        LineStatus {
+            highlighted: in_any(&self.hl),
+            diff,
+            label: self
+                .line_labels
+                .iter()
+                .find(|label| label.line == line)
+                .map(|label| &label.label),
+        }
+    }
+}
+
+/// Lines `start..=end`, counted from 1. Open-ended ranges such as `5-` are
+/// already clamped to the block by the parser.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LineRange {
     pub start: u32,
     pub end: u32,
 }
 
+impl LineRange {
+    pub fn contains(&self, line: u32) -> bool {
+        self.start <= line && line <= self.end
+    }
+}
+
+/// Result of [`CodeBlock::line_status`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineStatus<'a> {
+    pub highlighted: bool,
+    pub diff: Option<LineDiff>,
+    /// Label attached to the line by `hl="2:name"`.
+    pub label: Option<&'a Label>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineDiff {
+    Add,
+    Del,
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LineLabel {
     pub line: u32,
     pub label: Label,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BoxBlock {
     pub title: Option<InlineSeq>,
     pub blocks: Vec<Block>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Inline {
     pub span: Span,
     pub kind: InlineKind,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum InlineKind {
     Text(String),
     Emph(InlineSeq),
     Strong(InlineSeq),
     Strikethrough(InlineSeq),
     CodeSpan(String),
+    /// Literal text from `\!{...}!`, emitted escaped without inline parsing.
+    Raw(String),
     SoftBreak,
     HardBreak,
     Link {
@@ -146,6 +295,17 @@ pub enum InlineKind {
         url: String,
         title: Option<String>,
         alt: InlineSeq,
+        /// Attribute list right after an inline image (`![alt](src){srcset="..."}`).
+        attrs: AttrList,
+    },
+    /// `[[Page Name]]` or `[[Page Name|display]]`, parsed when
+    /// `ParseOptions::wikilink_resolver` is set.
+    WikiLink {
+        target: String,
+        /// Text after the `|`, or the target when there is none.
+        display: String,
+        /// Destination returned by the resolver for `target`.
+        href: String,
     },
     LinkRef {
         label: String,
@@ -160,23 +320,61 @@ pub enum InlineKind {
     Ref {
         label: Label,
         bracket: Option<InlineSeq>,
+        /// Category prefix of an auto-numbered reference (`@fig:label`).
+        numbered: Option<RefCategory>,
         resolved: Option<ResolvedRef>,
     },
+    /// Bracketed citation group (`[@smith2020; @doe2021]`).
+    Citation {
+        keys: Vec<Label>,
+        /// Position of each key in the generated references list, filled in by the
+        /// resolver; `None` for keys without a bibliography entry.
+        numbers: Vec<Option<usize>>,
+    },
     MathInline {
         typst_src: String,
     },
     HtmlSpan {
         raw: String,
     },
+    /// Bracketed text with an attribute list (`[text]{.class #id}`).
+    Span {
+        children: InlineSeq,
+        attrs: AttrList,
+    },
+    /// Custom inline directive (`::name[label]{attrs}`), rendered by a registered handler.
+    Directive(Directive),
+    /// Known emoji shortcode (`:smile:`); emitted as the shortcode unless enabled.
+    Emoji {
+        name: String,
+        emoji: String,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Directive {
+    pub name: String,
+    pub label: InlineSeq,
+    pub attrs: AttrList,
+    /// Destination written as `(url)` after the label, as in `::video[Demo](demo.mp4)`.
+    /// Only `video` and `audio` directives take one.
+    pub url: Option<String>,
+    /// Source text of the directive, emitted verbatim when no handler is registered.
+    pub raw: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LinkDefinition {
     pub url: String,
     pub title: Option<String>,
+    /// The definition's lines, from the opening `[` to the end of its last line.
+    pub span: Span,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LinkRefMeta {
     pub opener_span: Span,
     pub closer_span: Span,
@@ -186,17 +384,68 @@ pub struct LinkRefMeta {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ResolvedRef {
     Block {
         label: String,
         display: Option<InlineSeq>,
+        number: Option<RefNumber>,
     },
     CodeLine {
         label: String,
     },
 }
 
+/// Numbering category of a labelled target.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum RefCategory {
+    Figure,
+    Table,
+    Section,
+    Equation,
+}
+
+impl RefCategory {
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "fig" => Some(Self::Figure),
+            "tbl" => Some(Self::Table),
+            "sec" => Some(Self::Section),
+            "eq" => Some(Self::Equation),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Figure => "figure",
+            Self::Table => "table",
+            Self::Section => "section",
+            Self::Equation => "equation",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Figure => "Figure",
+            Self::Table => "Table",
+            Self::Section => "Section",
+            Self::Equation => "Equation",
+        }
+    }
+}
+
+/// 1-based position of a target among the labelled targets of its category.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RefNumber {
+    pub category: RefCategory,
+    pub ordinal: usize,
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AttrList {
     pub span: Option<Span>,
     pub label: Option<Label>,
@@ -220,12 +469,14 @@ impl Default for AttrList {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AttrItem {
     pub key: String,
     pub value: AttrValue,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AttrValue {
     pub raw: String,
     pub span: Span,
@@ -233,6 +484,7 @@ pub struct AttrValue {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Label {
     pub name: String,
     pub span: Span,
@@ -0,0 +1,196 @@
+//! Constructors for building a document in code instead of parsing it.
+//!
+//! Every node gets an empty span at offset 0. Emitting without a source map
+//! never looks at spans, so a hand-built document renders like a parsed one.
+//!
+//! ```
+//! use typmark_core::{Block, Document, Inline, emit_html};
+//!
+//! let document = Document::new(vec![
+//!     Block::heading(1, vec![Inline::text("Hello")]).with_label("hello"),
+//!     Block::paragraph(vec![
+//!         Inline::text("See "),
+//!         Inline::link("https://example.com", vec![Inline::strong(vec![Inline::text("this")])]),
+//!         Inline::text("."),
+//!     ]),
+//! ]);
+//! assert_eq!(
+//!     emit_html(&document.blocks),
+//!     "<h1 id=\"hello\">Hello</h1>\n<p>See <a href=\"https://example.com\"><strong>this</strong></a>.</p>"
+//! );
+//! ```
+
+use crate::ast::{
+    AttrItem, AttrList, AttrValue, Block, BlockKind, CodeBlock, CodeBlockKind, CodeMeta, Document,
+    Inline, InlineKind, InlineSeq, Label, List, ListItem,
+};
+use crate::span::Span;
+
+const NO_SPAN: Span = Span { start: 0, end: 0 };
+
+impl Document {
+    /// A document without settings.
+    pub fn new(blocks: Vec<Block>) -> Self {
+        Self {
+            span: NO_SPAN,
+            settings: None,
+            blocks,
+        }
+    }
+}
+
+impl Block {
+    pub fn new(kind: BlockKind) -> Self {
+        Self {
+            span: NO_SPAN,
+            attrs: AttrList::empty(),
+            kind,
+        }
+    }
+
+    pub fn paragraph(content: InlineSeq) -> Self {
+        Self::new(BlockKind::Paragraph { content })
+    }
+
+    /// A bare heading. `resolve` groups headings into sections; a hand-built
+    /// heading is emitted as `<hN>` on its own.
+    pub fn heading(level: u8, title: InlineSeq) -> Self {
+        Self::new(BlockKind::Heading { level, title })
+    }
+
+    pub fn block_quote(blocks: Vec<Block>) -> Self {
+        Self::new(BlockKind::BlockQuote { blocks, kind: None })
+    }
+
+    /// A fenced code block; `text` should end with a newline.
+    pub fn code_block(lang: Option<&str>, text: impl Into<String>) -> Self {
+        Self::new(BlockKind::CodeBlock(CodeBlock {
+            kind: CodeBlockKind::Fenced,
+            lang: lang.map(str::to_string),
+            info_attrs: AttrList::empty(),
+            meta: CodeMeta {
+                hl: Vec::new(),
+                diff_add: Vec::new(),
+                diff_del: Vec::new(),
+                line_labels: Vec::new(),
+            },
+            text: text.into(),
+            no_highlight: false,
+            soft_wrap: false,
+            output: None,
+        }))
+    }
+
+    /// A tight list with one item per entry of `items`.
+    pub fn list(ordered: bool, items: Vec<Vec<Block>>) -> Self {
+        Self::new(BlockKind::List(List {
+            ordered,
+            start: None,
+            tight: true,
+            items: items
+                .into_iter()
+                .map(|blocks| ListItem {
+                    span: NO_SPAN,
+                    blocks,
+                    task: None,
+                })
+                .collect(),
+        }))
+    }
+
+    pub fn math_block(typst_src: impl Into<String>) -> Self {
+        Self::new(BlockKind::MathBlock {
+            typst_src: typst_src.into(),
+        })
+    }
+
+    pub fn thematic_break() -> Self {
+        Self::new(BlockKind::ThematicBreak)
+    }
+
+    pub fn html_block(raw: impl Into<String>) -> Self {
+        Self::new(BlockKind::HtmlBlock { raw: raw.into() })
+    }
+
+    /// Sets the label, which becomes the `id` and a reference target.
+    pub fn with_label(mut self, name: impl Into<String>) -> Self {
+        self.attrs.label = Some(Label {
+            name: name.into(),
+            span: NO_SPAN,
+        });
+        self
+    }
+
+    /// Adds an attribute, as if written on a target line.
+    pub fn with_attr(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attrs.items.push(AttrItem {
+            key: key.into(),
+            value: AttrValue {
+                raw: value.into(),
+                span: NO_SPAN,
+                quoted: false,
+            },
+        });
+        self
+    }
+}
+
+impl Inline {
+    pub fn new(kind: InlineKind) -> Self {
+        Self {
+            span: NO_SPAN,
+            kind,
+        }
+    }
+
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::new(InlineKind::Text(text.into()))
+    }
+
+    pub fn emph(children: InlineSeq) -> Self {
+        Self::new(InlineKind::Emph(children))
+    }
+
+    pub fn strong(children: InlineSeq) -> Self {
+        Self::new(InlineKind::Strong(children))
+    }
+
+    pub fn strikethrough(children: InlineSeq) -> Self {
+        Self::new(InlineKind::Strikethrough(children))
+    }
+
+    pub fn code(code: impl Into<String>) -> Self {
+        Self::new(InlineKind::CodeSpan(code.into()))
+    }
+
+    pub fn link(url: impl Into<String>, children: InlineSeq) -> Self {
+        Self::new(InlineKind::Link {
+            url: url.into(),
+            title: None,
+            children,
+        })
+    }
+
+    pub fn image(url: impl Into<String>, alt: InlineSeq) -> Self {
+        Self::new(InlineKind::Image {
+            url: url.into(),
+            title: None,
+            alt,
+            attrs: AttrList::empty(),
+        })
+    }
+
+    pub fn math(typst_src: impl Into<String>) -> Self {
+        Self::new(InlineKind::MathInline {
+            typst_src: typst_src.into(),
+        })
+    }
+
+    pub fn soft_break() -> Self {
+        Self::new(InlineKind::SoftBreak)
+    }
+
+    pub fn hard_break() -> Self {
+        Self::new(InlineKind::HardBreak)
+    }
+}
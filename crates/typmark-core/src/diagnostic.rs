@@ -8,11 +8,29 @@ pub const E_REF_BRACKET_NL: &str = "E_REF_BRACKET_NL";
 pub const E_REF_SELF_TITLE: &str = "E_REF_SELF_TITLE";
 pub const E_REF_DEPTH: &str = "E_REF_DEPTH";
 pub const E_MATH_INLINE_NL: &str = "E_MATH_INLINE_NL";
+pub const E_MATH_PREAMBLE: &str = "E_MATH_PREAMBLE";
+pub const E_MATH_COMPILE: &str = "E_MATH_COMPILE";
 pub const E_CODE_CONFLICT: &str = "E_CODE_CONFLICT";
+pub const E_INCLUDE: &str = "E_INCLUDE";
 
 pub const W_REF_MISSING: &str = "W_REF_MISSING";
 pub const W_CODE_RANGE_OOB: &str = "W_CODE_RANGE_OOB";
+pub const W_CODE_WRAP_INVALID: &str = "W_CODE_WRAP_INVALID";
 pub const W_BOX_STYLE_INVALID: &str = "W_BOX_STYLE_INVALID";
+pub const W_ALERT_UNKNOWN: &str = "W_ALERT_UNKNOWN";
+pub const W_REF_CATEGORY: &str = "W_REF_CATEGORY";
+pub const W_DIRECTIVE_UNKNOWN: &str = "W_DIRECTIVE_UNKNOWN";
+pub const W_TABLE_RAGGED: &str = "W_TABLE_RAGGED";
+pub const W_CITE_MISSING: &str = "W_CITE_MISSING";
+pub const W_LINK_DEF_DUP: &str = "W_LINK_DEF_DUP";
+pub const W_LINK_DEF_UNUSED: &str = "W_LINK_DEF_UNUSED";
+pub const W_HEADING_SKIP: &str = "W_HEADING_SKIP";
+pub const W_DIR_INVALID: &str = "W_DIR_INVALID";
+pub const W_IMAGE_SRCSET_INVALID: &str = "W_IMAGE_SRCSET_INVALID";
+pub const W_UNCLOSED_FENCE: &str = "W_UNCLOSED_FENCE";
+pub const W_UNCLOSED_BOX: &str = "W_UNCLOSED_BOX";
+pub const W_UNCLOSED_MATH: &str = "W_UNCLOSED_MATH";
+pub const W_ATTR_UNKNOWN: &str = "W_ATTR_UNKNOWN";
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Diagnostic {
@@ -21,6 +39,10 @@ pub struct Diagnostic {
     pub code: &'static str,
     pub message: String,
     pub related: Vec<RelatedDiagnostic>,
+    /// Included file the range belongs to; `None` for the root document.
+    pub file: Option<String>,
+    /// Edits that resolve the diagnostic, for editors to offer as quick fixes.
+    pub fixes: Vec<Fix>,
 }
 
 impl Diagnostic {
@@ -36,24 +58,58 @@ impl Diagnostic {
             code,
             message: message.into(),
             related: Vec::new(),
+            file: None,
+            fixes: Vec::new(),
         }
     }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fixes.push(fix);
+        self
+    }
+}
+
+/// Replaces the source in `range` with `replacement`; an empty replacement
+/// deletes it. `title` describes the edit, such as "Remove the self-reference".
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fix {
+    pub range: Range,
+    pub replacement: String,
+    pub title: String,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+impl Fix {
+    pub fn new(range: Range, replacement: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            range,
+            replacement: replacement.into(),
+            title: title.into(),
+        }
+    }
+}
+
+/// Severity levels, ordered from most to least severe (LSP numbering).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub enum DiagnosticSeverity {
     Error,
     Warning,
+    Info,
+    Hint,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct RelatedDiagnostic {
     pub range: Range,
     pub message: Option<String>,
+    pub file: Option<String>,
 }
 
 impl RelatedDiagnostic {
     pub fn new(range: Range, message: Option<String>) -> Self {
-        Self { range, message }
+        Self {
+            range,
+            message,
+            file: None,
+        }
     }
 }
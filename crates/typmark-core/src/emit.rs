@@ -1,12 +1,23 @@
 use crate::ast::{
-    AttrItem, AttrList, Block, BlockKind, BoxBlock, CodeBlock, CodeBlockKind, CodeMeta, Inline,
-    InlineKind, Label, LineRange, List, ResolvedRef, Table, TableAlign,
+    AttrItem, AttrList, AttrValue, Block, BlockKind, BoxBlock, CodeBlock, CodeBlockKind, CodeMeta,
+    Directive, Document, Inline, InlineKind, Label, LineDiff, List, RefCategory, ResolvedRef,
+    Table, TableAlign,
 };
-use crate::math::{MathSettings, prefix_svg_ids, render_math};
+use crate::diagnostic::{Diagnostic, DiagnosticSeverity, W_DIRECTIVE_UNKNOWN};
+use crate::math::{
+    MathOutput, MathRenderer, MathSettings, math_settings_from_attrs, prefix_svg_ids, render_math,
+};
+use crate::normalize::crlf_line_endings;
 use crate::source_map::SourceMap;
 use crate::span::Span;
+use crate::text::{namespaced_id, render_inlines_text, shifted_heading_level};
+use crate::visit::{Visitor, walk_document, walk_inline};
 use ammonia::Builder;
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::sync::Arc;
 
 const SVG_ALLOWED_TAGS: &[&str] = &["svg", "g", "defs", "path", "symbol", "use"];
 
@@ -33,6 +44,83 @@ const SVG_ALLOWED_ATTRS: &[(&str, &[&str])] = &[
     ("use", &["href", "x", "y", "fill", "fill-rule"]),
 ];
 
+const MATHML_ALLOWED_TAGS: &[&str] = &[
+    "math",
+    "semantics",
+    "annotation",
+    "mrow",
+    "mi",
+    "mn",
+    "mo",
+    "ms",
+    "mtext",
+    "mspace",
+    "msub",
+    "msup",
+    "msubsup",
+    "munder",
+    "mover",
+    "munderover",
+    "mfrac",
+    "msqrt",
+    "mroot",
+    "mstyle",
+    "mpadded",
+    "mphantom",
+    "menclose",
+    "mtable",
+    "mtr",
+    "mtd",
+];
+
+const MATHML_ALLOWED_ATTRS: &[(&str, &[&str])] = &[
+    ("math", &["display", "xmlns"]),
+    ("annotation", &["encoding"]),
+    ("mi", &["mathvariant"]),
+    (
+        "mo",
+        &["fence", "stretchy", "separator", "lspace", "rspace"],
+    ),
+    ("mspace", &["width"]),
+    ("mfrac", &["linethickness"]),
+    ("mstyle", &["displaystyle", "scriptlevel"]),
+    ("menclose", &["notation"]),
+    ("mtd", &["columnspan", "rowspan"]),
+];
+
+/// How raw HTML blocks and inline HTML spans are emitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RawHtmlMode {
+    /// Pass raw HTML through unchanged.
+    #[default]
+    Keep,
+    /// Drop raw HTML from the output.
+    Strip,
+    /// Emit raw HTML as escaped text.
+    Escape,
+}
+
+/// Where `HtmlEmitOptions.heading_anchors` puts a heading's permalink.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeadingAnchorPosition {
+    /// A `#` link before the title.
+    Before,
+    /// A `#` link after the title.
+    #[default]
+    After,
+    /// The whole title is the link.
+    Wrap,
+}
+
+/// Line terminator between the lines `emit_html` writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    /// `\r\n`, for files checked in with Windows line endings.
+    Crlf,
+}
+
 /// Options for HTML emission.
 #[derive(Debug, Clone)]
 pub struct HtmlEmitOptions {
@@ -42,6 +130,62 @@ pub struct HtmlEmitOptions {
     /// Whether to use simple code block output (just `<pre><code>`).
     /// If false, uses TypMark's enhanced structure with line spans and figure wrapper.
     pub simple_code_blocks: bool,
+    /// Markup emitted for math nodes. Defaults to Typst-rendered SVG.
+    pub math_output: MathOutput,
+    /// Optional MathML backend used when `math_output` is `MathML`.
+    pub math_renderer: Option<Arc<dyn MathRenderer>>,
+    /// Handling of raw HTML blocks and spans. TypMark's own markup is unaffected.
+    pub raw_html: RawHtmlMode,
+    /// Drops HTML blocks made only of `<!-- -->` comments and inline comments,
+    /// keeping other raw HTML. Conditional comments (`<!--[if IE]>`) are kept.
+    pub strip_comments: bool,
+    /// Shifts every heading level by this amount, clamped to `h1`..`h6`.
+    pub heading_offset: i8,
+    /// Prefix for the class names of TypMark's own markup (`TypMark-codeblock`, ...).
+    pub class_prefix: String,
+    /// Renderers for `::name[label]{attrs}` directives, keyed by name.
+    /// Directives without a handler are emitted as escaped source text.
+    pub directive_handlers: HashMap<String, Arc<dyn DirectiveHandler>>,
+    /// Prefixes every generated `id` (labels, line labels, math SVG ids) and the
+    /// matching reference links with `{namespace}-`, so several documents can
+    /// share one page.
+    pub id_namespace: Option<String>,
+    /// Renders `:shortcode:` emoji as `<span class="emoji">`; off keeps the shortcode text.
+    pub emoji: bool,
+    /// Emits soft line breaks as `<br />` like hard breaks, the `breaks: true`
+    /// behaviour of chat and comment renderers. Off keeps them as newlines.
+    pub soft_breaks_as_br: bool,
+    /// Replaces straight quotes with curly ones and `--`, `---`, `...` with an
+    /// en dash, em dash, and ellipsis in text. Code, math, and raw text are left alone.
+    pub smart_punctuation: bool,
+    /// Wraps `YYYY-MM-DD` and `YYYY-MM-DDThh:mm` dates in text in
+    /// `<time datetime="...">`. Dates in code, math, and link text are left alone.
+    pub wrap_dates: bool,
+    /// Adds a permalink `<a class="anchor">` to every heading that has an id.
+    pub heading_anchors: bool,
+    /// Where the permalink goes when `heading_anchors` is on.
+    pub heading_anchor_position: HeadingAnchorPosition,
+    /// Adds a `<button class="TypMark-copy">` to each fenced code block figure.
+    /// Its `data-copy-target` names the id given to the block's `<pre>`, which
+    /// the bundled script copies from.
+    pub code_copy_button: bool,
+    /// Prefixes each section heading with its outline number, as
+    /// `<span class="section-number">1.2</span>`. Sections marked
+    /// `{.unnumbered}` are skipped.
+    pub number_sections: bool,
+    /// Renders `::kbd[Ctrl+C]` as `<kbd><kbd>Ctrl</kbd>+<kbd>C</kbd></kbd>`,
+    /// one nested `<kbd>` per `+`-separated key, unless a handler is
+    /// registered under `kbd`.
+    pub kbd_directive: bool,
+    /// Line terminator of the output. The content of `<pre>`, `<script>`,
+    /// `<style>` and `<textarea>` elements keeps `\n`.
+    pub line_ending: LineEnding,
+}
+
+/// Hook for rendering a custom inline directive.
+pub trait DirectiveHandler: fmt::Debug + Send + Sync {
+    /// Returns the HTML for `directive`; `label_html` is its already rendered label.
+    fn render_directive(&self, directive: &Directive, label_html: &str) -> String;
 }
 
 impl Default for HtmlEmitOptions {
@@ -49,6 +193,24 @@ impl Default for HtmlEmitOptions {
         Self {
             wrap_sections: true,
             simple_code_blocks: false,
+            math_output: MathOutput::default(),
+            math_renderer: None,
+            raw_html: RawHtmlMode::Keep,
+            strip_comments: false,
+            heading_offset: 0,
+            class_prefix: "TypMark-".to_string(),
+            directive_handlers: HashMap::new(),
+            id_namespace: None,
+            emoji: false,
+            soft_breaks_as_br: false,
+            smart_punctuation: false,
+            wrap_dates: false,
+            heading_anchors: false,
+            heading_anchor_position: HeadingAnchorPosition::After,
+            code_copy_button: false,
+            number_sections: false,
+            kbd_directive: false,
+            line_ending: LineEnding::Lf,
         }
     }
 }
@@ -60,7 +222,8 @@ pub fn emit_html(blocks: &[Block]) -> String {
 
 /// Emits raw, un-sanitized HTML from a slice of blocks with custom options.
 pub fn emit_html_with_options(blocks: &[Block], options: &HtmlEmitOptions) -> String {
-    // Deterministic formatting: 2-space indentation and LF newlines.
+    // Deterministic formatting: 2-space indentation and LF newlines unless
+    // `line_ending` asks for CRLF.
     let mut writer = HtmlWriter::new(options.clone(), MathSettings::default());
     for block in blocks {
         emit_block(&mut writer, block);
@@ -78,6 +241,7 @@ pub fn emit_html_document_with_options(
     for block in &document.blocks {
         emit_block(&mut writer, block);
     }
+    emit_references(&mut writer, document);
     writer.finish()
 }
 
@@ -92,28 +256,101 @@ pub fn emit_html_document_with_options_and_source_map(
     for block in &document.blocks {
         emit_block(&mut writer, block);
     }
+    emit_references(&mut writer, document);
     writer.finish()
 }
 
+/// Emits raw, un-sanitized HTML from a slice of blocks into an `io::Write`.
+/// Output is flushed after each top-level block and matches `emit_html_with_options` byte for byte.
+pub fn emit_html_to_writer<W: io::Write>(
+    blocks: &[Block],
+    options: &HtmlEmitOptions,
+    w: &mut W,
+) -> io::Result<()> {
+    let mut writer = HtmlWriter::new(options.clone(), MathSettings::default());
+    stream_blocks(&mut writer, blocks, w)?;
+    writer.finish_to(w)
+}
+
+/// Emits raw, un-sanitized HTML from a document into an `io::Write`.
+pub fn emit_html_document_to_writer<W: io::Write>(
+    document: &crate::ast::Document,
+    options: &HtmlEmitOptions,
+    w: &mut W,
+) -> io::Result<()> {
+    let math_settings = math_settings_from_attrs(document.settings.as_ref());
+    let mut writer = HtmlWriter::new(options.clone(), math_settings);
+    stream_blocks(&mut writer, &document.blocks, w)?;
+    emit_references(&mut writer, document);
+    writer.finish_to(w)
+}
+
+/// Emits raw, un-sanitized HTML with source map attributes from a document into an `io::Write`.
+pub fn emit_html_document_to_writer_with_source_map<W: io::Write>(
+    document: &crate::ast::Document,
+    options: &HtmlEmitOptions,
+    source_map: &SourceMap,
+    w: &mut W,
+) -> io::Result<()> {
+    let math_settings = math_settings_from_attrs(document.settings.as_ref());
+    let mut writer = HtmlWriter::new_with_source_map(options.clone(), math_settings, source_map);
+    stream_blocks(&mut writer, &document.blocks, w)?;
+    emit_references(&mut writer, document);
+    writer.finish_to(w)
+}
+
+fn stream_blocks<W: io::Write>(
+    writer: &mut HtmlWriter,
+    blocks: &[Block],
+    w: &mut W,
+) -> io::Result<()> {
+    for block in blocks {
+        emit_block(writer, block);
+        writer.flush_to(w)?;
+    }
+    Ok(())
+}
+
 /// Emits HTML from a slice of blocks and sanitizes it according to a safe allow-list.
 pub fn emit_html_sanitized(blocks: &[Block]) -> String {
     let raw_html = emit_html(blocks);
-    sanitize_html(&raw_html)
+    sanitize_html(&raw_html, &SanitizeConfig::default())
 }
 
 /// Emits HTML from a slice of blocks with custom options and sanitizes it.
 pub fn emit_html_sanitized_with_options(blocks: &[Block], options: &HtmlEmitOptions) -> String {
+    emit_html_sanitized_with_config(blocks, options, &SanitizeConfig::default())
+}
+
+/// Emits HTML from a slice of blocks with custom options and sanitizes it with
+/// the default allow-list changed by `config`.
+pub fn emit_html_sanitized_with_config(
+    blocks: &[Block],
+    options: &HtmlEmitOptions,
+    config: &SanitizeConfig,
+) -> String {
     let raw_html = emit_html_with_options(blocks, options);
-    sanitize_html(&raw_html)
+    // The HTML parser behind the sanitizer reads `\r\n` as `\n`.
+    with_line_ending(sanitize_html(&raw_html, config), options.line_ending)
 }
 
 /// Emits HTML from a document with custom options and sanitizes it.
 pub fn emit_html_document_sanitized_with_options(
     document: &crate::ast::Document,
     options: &HtmlEmitOptions,
+) -> String {
+    emit_html_document_sanitized_with_config(document, options, &SanitizeConfig::default())
+}
+
+/// Emits HTML from a document with custom options and sanitizes it with the
+/// default allow-list changed by `config`.
+pub fn emit_html_document_sanitized_with_config(
+    document: &crate::ast::Document,
+    options: &HtmlEmitOptions,
+    config: &SanitizeConfig,
 ) -> String {
     let raw_html = emit_html_document_with_options(document, options);
-    sanitize_html(&raw_html)
+    with_line_ending(sanitize_html(&raw_html, config), options.line_ending)
 }
 
 /// Emits HTML from a document with source map attributes and sanitizes it.
@@ -121,13 +358,53 @@ pub fn emit_html_document_sanitized_with_options_and_source_map(
     document: &crate::ast::Document,
     options: &HtmlEmitOptions,
     source_map: &SourceMap,
+) -> String {
+    emit_html_document_sanitized_with_config_and_source_map(
+        document,
+        options,
+        source_map,
+        &SanitizeConfig::default(),
+    )
+}
+
+/// Emits HTML from a document with source map attributes and sanitizes it with
+/// the default allow-list changed by `config`.
+pub fn emit_html_document_sanitized_with_config_and_source_map(
+    document: &crate::ast::Document,
+    options: &HtmlEmitOptions,
+    source_map: &SourceMap,
+    config: &SanitizeConfig,
 ) -> String {
     let raw_html = emit_html_document_with_options_and_source_map(document, options, source_map);
-    sanitize_html(&raw_html)
+    with_line_ending(sanitize_html(&raw_html, config), options.line_ending)
 }
 
-fn sanitize_html(raw_html: &str) -> String {
-    let mut tags: HashSet<&'static str> = [
+/// Changes merged into the sanitizer's default allow-list.
+///
+/// Attributes are `(tag, attribute)` pairs; the tag `"*"` means every tag.
+/// Removals win over additions.
+#[derive(Clone, Debug, Default)]
+pub struct SanitizeConfig {
+    /// Tags kept in addition to the defaults.
+    pub add_tags: Vec<String>,
+    /// Default tags to strip. Their content is kept.
+    pub remove_tags: Vec<String>,
+    /// Attributes kept in addition to the defaults. Allowing `rel` on `a`
+    /// drops the default `rel="noopener noreferrer"`.
+    pub add_attributes: Vec<(String, String)>,
+    /// Default attributes to strip.
+    pub remove_attributes: Vec<(String, String)>,
+    /// URL schemes allowed in `href` and `src` in addition to the defaults.
+    pub add_url_schemes: Vec<String>,
+    /// Default URL schemes to reject.
+    pub remove_url_schemes: Vec<String>,
+    /// Keeps the `<video>`, `<audio>` and `<source>` elements written by the
+    /// media directives. Off by default, since media autoloads remote content.
+    pub allow_media: bool,
+}
+
+fn sanitize_html(raw_html: &str, config: &SanitizeConfig) -> String {
+    let mut tags: HashSet<&str> = [
         // Standard tags
         "a",
         "abbr",
@@ -171,23 +448,47 @@ fn sanitize_html(raw_html: &str) -> String {
         "td",
         "input",
         "figure",
+        "figcaption",
+        "caption",
         "span",
+        "time",
+        "button",
     ]
     .iter()
     .copied()
     .collect();
 
-    let mut generic_attributes: HashSet<&'static str> = HashSet::new();
+    let mut generic_attributes: HashSet<&str> = HashSet::new();
     generic_attributes.insert("class");
     generic_attributes.insert("id");
+    generic_attributes.insert("lang");
+    generic_attributes.insert("dir");
 
-    let mut tag_attributes = HashMap::new();
+    let mut tag_attributes: HashMap<&str, HashSet<&str>> = HashMap::new();
 
     // Standard attributes
-    tag_attributes.insert("a", ["href", "title"].iter().copied().collect());
+    tag_attributes.insert(
+        "a",
+        ["href", "title", "aria-label"].iter().copied().collect(),
+    );
     tag_attributes.insert("abbr", ["title"].iter().copied().collect());
-    tag_attributes.insert("img", ["alt", "src", "title"].iter().copied().collect());
-    tag_attributes.insert("ol", ["start"].iter().copied().collect());
+    tag_attributes.insert(
+        "blockquote",
+        ["role", "aria-label"].iter().copied().collect(),
+    );
+    tag_attributes.insert(
+        "img",
+        ["alt", "src", "srcset", "sizes", "title", "aria-hidden"]
+            .iter()
+            .copied()
+            .collect(),
+    );
+    tag_attributes.insert(
+        "ol",
+        ["start", "type", "reversed"].iter().copied().collect(),
+    );
+    tag_attributes.insert("time", ["datetime"].iter().copied().collect());
+    tag_attributes.insert("button", ["type", "aria-label"].iter().copied().collect());
     tag_attributes.insert("th", ["align"].iter().copied().collect());
     tag_attributes.insert("td", ["align"].iter().copied().collect());
     tag_attributes.insert(
@@ -205,6 +506,9 @@ fn sanitize_html(raw_html: &str) -> String {
             "data-diff",
             "data-line-label",
             "id",
+            "role",
+            "aria-label",
+            "aria-hidden",
         ]
         .iter()
         .copied()
@@ -225,6 +529,18 @@ fn sanitize_html(raw_html: &str) -> String {
     for (tag, attrs) in SVG_ALLOWED_ATTRS {
         tag_attributes.insert(*tag, attrs.iter().copied().collect());
     }
+    // Accessibility attributes added by `label_math_svg`, not by Typst.
+    if let Some(attrs) = tag_attributes.get_mut("svg") {
+        attrs.extend(["role", "aria-label"]);
+    }
+
+    // MathML produced by a user-supplied MathRenderer
+    for tag in MATHML_ALLOWED_TAGS {
+        tags.insert(*tag);
+    }
+    for (tag, attrs) in MATHML_ALLOWED_ATTRS {
+        tag_attributes.insert(*tag, attrs.iter().copied().collect());
+    }
 
     // Box attributes (data-bg, data-border-style, etc.)
     tag_attributes.insert(
@@ -244,16 +560,100 @@ fn sanitize_html(raw_html: &str) -> String {
         .collect(),
     );
 
+    // Collapsible boxes
+    tag_attributes.insert("details", ["open"].iter().copied().collect());
+
+    // Media directives, only on request
+    if config.allow_media {
+        tags.extend(["video", "audio", "source"]);
+        tag_attributes.insert(
+            "video",
+            ["controls", "poster", "width", "height", "aria-label"]
+                .iter()
+                .copied()
+                .collect(),
+        );
+        tag_attributes.insert(
+            "audio",
+            ["controls", "aria-label"].iter().copied().collect(),
+        );
+        tag_attributes.insert("source", ["src", "type"].iter().copied().collect());
+    }
+
     let mut generic_attribute_prefixes = HashSet::new();
     generic_attribute_prefixes.insert("data-");
 
-    Builder::new()
+    tags.extend(config.add_tags.iter().map(String::as_str));
+    for (tag, attr) in &config.add_attributes {
+        match tag.as_str() {
+            "*" => generic_attributes.insert(attr),
+            tag => tag_attributes.entry(tag).or_default().insert(attr),
+        };
+    }
+    for tag in &config.remove_tags {
+        tags.remove(tag.as_str());
+    }
+    for (tag, attr) in &config.remove_attributes {
+        match tag.as_str() {
+            "*" => {
+                generic_attributes.remove(attr.as_str());
+                for attrs in tag_attributes.values_mut() {
+                    attrs.remove(attr.as_str());
+                }
+            }
+            tag => {
+                if let Some(attrs) = tag_attributes.get_mut(tag) {
+                    attrs.remove(attr.as_str());
+                }
+            }
+        }
+    }
+    let allows_link_rel = tag_attributes
+        .get("a")
+        .is_some_and(|attrs| attrs.contains("rel"));
+
+    let mut builder = Builder::new();
+    builder
         .tags(tags)
         .generic_attributes(generic_attributes)
         .tag_attributes(tag_attributes)
         .generic_attribute_prefixes(generic_attribute_prefixes)
-        .clean(raw_html)
-        .to_string()
+        // ammonia refuses a tag that is both allowed and dropped with its content.
+        .rm_clean_content_tags(&config.add_tags)
+        .add_url_schemes(config.add_url_schemes.iter().map(String::as_str))
+        .rm_url_schemes(&config.remove_url_schemes);
+    if allows_link_rel {
+        builder.link_rel(None);
+    }
+    let cleaned = builder.clean(raw_html).to_string();
+    restore_pre_leading_newlines(&cleaned)
+}
+
+/// HTML parsing drops a newline right after `<pre>`, and ammonia serializes
+/// without putting it back. A `<pre>` whose content starts with a newline
+/// therefore gets one extra, so the block renders the same as before sanitizing.
+fn restore_pre_leading_newlines(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(idx) = rest.find("<pre") {
+        // Serialized attribute values are double-quoted and may contain `>`.
+        let mut in_quotes = false;
+        let Some(tag_len) = rest[idx..].find(|ch| {
+            in_quotes ^= ch == '"';
+            ch == '>' && !in_quotes
+        }) else {
+            break;
+        };
+        let tag_end = idx + tag_len + 1;
+        let is_pre = matches!(rest.as_bytes()[idx + 4], b'>' | b' ');
+        out.push_str(&rest[..tag_end]);
+        if is_pre && rest[tag_end..].starts_with('\n') {
+            out.push('\n');
+        }
+        rest = &rest[tag_end..];
+    }
+    out.push_str(rest);
+    out
 }
 
 struct HtmlWriter {
@@ -261,6 +661,8 @@ struct HtmlWriter {
     indent: usize,
     options: HtmlEmitOptions,
     math_counter: usize,
+    /// Code blocks given a copy button so far; numbers the `<pre>` ids.
+    copy_counter: usize,
     math_settings: MathSettings,
     source_map: Option<SourceMap>,
 }
@@ -273,12 +675,15 @@ enum RenderContext {
 }
 
 impl HtmlWriter {
-    fn new(options: HtmlEmitOptions, math_settings: MathSettings) -> Self {
+    fn new(options: HtmlEmitOptions, mut math_settings: MathSettings) -> Self {
+        math_settings.output = options.math_output;
+        math_settings.renderer = options.math_renderer.clone();
         Self {
             out: String::new(),
             indent: 0,
             options,
             math_counter: 0,
+            copy_counter: 0,
             math_settings,
             source_map: None,
         }
@@ -306,24 +711,27 @@ impl HtmlWriter {
         if self.out.ends_with('\n') {
             self.out.pop();
         }
-        self.out
+        with_line_ending(self.out, self.options.line_ending)
+    }
+
+    /// Writes buffered output, holding back a trailing newline so `finish_to` can drop it.
+    fn flush_to<W: io::Write>(&mut self, w: &mut W) -> io::Result<()> {
+        let keep = usize::from(self.out.ends_with('\n'));
+        let split = self.out.len() - keep;
+        let chunk: String = self.out.drain(..split).collect();
+        w.write_all(with_line_ending(chunk, self.options.line_ending).as_bytes())
+    }
+
+    fn finish_to<W: io::Write>(self, w: &mut W) -> io::Result<()> {
+        w.write_all(self.finish().as_bytes())
     }
 }
 
-fn math_settings_from_attrs(settings: Option<&AttrList>) -> MathSettings {
-    let mut out = MathSettings::default();
-    let Some(settings) = settings else {
-        return out;
-    };
-    for item in &settings.items {
-        match item.key.as_str() {
-            "math-inline-size" => out.inline_size = Some(item.value.raw.clone()),
-            "math-block-size" => out.block_size = Some(item.value.raw.clone()),
-            "math-font" => out.font = Some(item.value.raw.clone()),
-            _ => {}
-        }
+fn with_line_ending(html: String, line_ending: LineEnding) -> String {
+    match line_ending {
+        LineEnding::Lf => html,
+        LineEnding::Crlf => crlf_line_endings(&html),
     }
-    out
 }
 
 fn emit_block(writer: &mut HtmlWriter, block: &Block) {
@@ -333,13 +741,15 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
             title,
             label,
             children,
+            number,
         } => {
             if writer.options.wrap_sections {
-                let attrs = compose_block_attrs_with_span(
+                let attrs = compose_wrapper_attrs_with_span(
                     label.as_ref(),
                     &block.attrs.items,
                     block.span,
                     writer.source_map.as_ref(),
+                    writer.options.id_namespace.as_deref(),
                 );
                 writer.line(&format!("<section{}>", attrs));
                 writer.indent += 1;
@@ -349,8 +759,10 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
                     &mut writer.math_counter,
                     &writer.math_settings,
                     writer.source_map.as_ref(),
+                    &writer.options,
                 );
-                let heading = format!("<h{}>{}</h{}>", level, title_html, level);
+                let title_html = numbered_title(&writer.options, number, title_html);
+                let heading = heading_html(writer, *level, "", title, &title_html, label.as_ref());
                 writer.line(&heading);
                 for child in children {
                     emit_block(writer, child);
@@ -359,11 +771,12 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
                 writer.line("</section>");
             } else {
                 // CommonMark-compatible: just emit heading without wrapper
-                let attrs = compose_block_attrs_with_span(
+                let attrs = compose_wrapper_attrs_with_span(
                     label.as_ref(),
                     &block.attrs.items,
                     block.span,
                     writer.source_map.as_ref(),
+                    writer.options.id_namespace.as_deref(),
                 );
                 let title_html = render_inlines_with_context(
                     title,
@@ -371,8 +784,12 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
                     &mut writer.math_counter,
                     &writer.math_settings,
                     writer.source_map.as_ref(),
+                    &writer.options,
                 );
-                writer.line(&format!("<h{}{}>{}</h{}>", level, attrs, title_html, level));
+                let title_html = numbered_title(&writer.options, number, title_html);
+                let heading =
+                    heading_html(writer, *level, &attrs, title, &title_html, label.as_ref());
+                writer.line(&heading);
                 for child in children {
                     emit_block(writer, child);
                 }
@@ -384,6 +801,7 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
                 &block.attrs.items,
                 block.span,
                 writer.source_map.as_ref(),
+                writer.options.id_namespace.as_deref(),
             );
             let title_html = render_inlines_with_context(
                 title,
@@ -391,15 +809,29 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
                 &mut writer.math_counter,
                 &writer.math_settings,
                 writer.source_map.as_ref(),
+                &writer.options,
+            );
+            let heading = heading_html(
+                writer,
+                *level,
+                &attrs,
+                title,
+                &title_html,
+                block.attrs.label.as_ref(),
             );
-            writer.line(&format!("<h{}{}>{}</h{}>", level, attrs, title_html, level));
+            writer.line(&heading);
         }
         BlockKind::Paragraph { content } => {
+            if let Some(figure) = captioned_image_html(writer, block, content) {
+                writer.line(&figure);
+                return;
+            }
             let attrs = compose_block_attrs_with_span(
                 block.attrs.label.as_ref(),
                 &block.attrs.items,
                 block.span,
                 writer.source_map.as_ref(),
+                writer.options.id_namespace.as_deref(),
             );
             let inline_html = render_inlines_with_context(
                 content,
@@ -407,18 +839,37 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
                 &mut writer.math_counter,
                 &writer.math_settings,
                 writer.source_map.as_ref(),
+                &writer.options,
             );
             writer.line(&format!("<p{}>{}</p>", attrs, inline_html));
         }
-        BlockKind::BlockQuote { blocks } => {
+        BlockKind::BlockQuote { blocks, kind } => {
             let attrs = compose_block_attrs_with_span(
                 block.attrs.label.as_ref(),
                 &block.attrs.items,
                 block.span,
                 writer.source_map.as_ref(),
+                writer.options.id_namespace.as_deref(),
             );
-            writer.line(&format!("<blockquote{}>", attrs));
-            writer.indent += 1;
+            if let Some(kind) = kind {
+                writer.line(&format!(
+                    "<blockquote class=\"{}alert alert-{}\" data-alert=\"{}\" role=\"note\" aria-label=\"{}\"{}>",
+                    writer.options.class_prefix,
+                    kind.as_str(),
+                    kind.as_str(),
+                    kind.title(),
+                    attrs
+                ));
+                writer.indent += 1;
+                writer.line(&format!(
+                    "<p class=\"{}alert-title\">{}</p>",
+                    writer.options.class_prefix,
+                    kind.title()
+                ));
+            } else {
+                writer.line(&format!("<blockquote{}>", attrs));
+                writer.indent += 1;
+            }
             for child in blocks {
                 emit_block(writer, child);
             }
@@ -433,13 +884,27 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
             ..
         }) => {
             let tag = if *ordered { "ol" } else { "ul" };
+            let (numbering_attrs, data_items) = if *ordered {
+                ordered_list_attrs(&block.attrs.items)
+            } else {
+                // Numbering styles mean nothing to a bullet list.
+                let items = block
+                    .attrs
+                    .items
+                    .iter()
+                    .filter(|item| !matches!(item.key.as_str(), "type" | "reversed"))
+                    .cloned()
+                    .collect();
+                (String::new(), items)
+            };
             let attrs = compose_block_attrs_with_span(
                 block.attrs.label.as_ref(),
-                &block.attrs.items,
+                &data_items,
                 block.span,
                 writer.source_map.as_ref(),
+                writer.options.id_namespace.as_deref(),
             );
-            let start_attr = if *ordered {
+            let mut start_attr = if *ordered {
                 start
                     .filter(|&value| value != 1) // Omit start="1" (default value)
                     .map(|value| format!(" start=\"{}\"", value))
@@ -447,6 +912,7 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
             } else {
                 String::new()
             };
+            start_attr.push_str(&numbering_attrs);
             let has_tasks = items.iter().any(|item| item.task.is_some());
             let list_class = if has_tasks {
                 " class=\"task-list\""
@@ -477,6 +943,7 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
                             &mut writer.math_counter,
                             &writer.math_settings,
                             writer.source_map.as_ref(),
+                            &writer.options,
                         );
                         writer.out.push_str(&"  ".repeat(writer.indent));
                         writer.out.push_str("<li");
@@ -554,43 +1021,67 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
             writer.line(&format!("</{}>", tag));
         }
         BlockKind::Table(table) => {
-            let attrs = compose_block_attrs_with_span(
+            let (classes, items): (Vec<AttrItem>, Vec<AttrItem>) = block
+                .attrs
+                .items
+                .iter()
+                .cloned()
+                .partition(|item| item.key == "class");
+            let mut attrs = table_class_attr(&classes);
+            attrs.push_str(&compose_block_attrs_with_span(
                 block.attrs.label.as_ref(),
-                &block.attrs.items,
+                &items,
                 block.span,
                 writer.source_map.as_ref(),
-            );
-            emit_table(writer, table, &attrs);
+                writer.options.id_namespace.as_deref(),
+            ));
+            let caption = attr_value(&block.attrs.items, "caption");
+            emit_table(writer, table, &attrs, caption);
         }
         BlockKind::Box(BoxBlock { title, blocks }) => {
-            let mut attrs = "class=\"TypMark-box\" data-typmark=\"box\"".to_string();
+            let (open, items) = collapsible_box_attrs(&block.attrs.items);
+            let tag = if open.is_some() { "details" } else { "div" };
+            let mut attrs = format!(
+                "class=\"{}box\" data-typmark=\"box\"",
+                writer.options.class_prefix
+            );
             attrs.push_str(&span_attr(block.span, writer.source_map.as_ref()));
-            if let Some(label) = block.attrs.label.as_ref() {
-                attrs.push_str(&format!(" id=\"{}\"", escape_attr(&label.name)));
-            }
-            for item in &block.attrs.items {
-                attrs.push_str(&format!(
-                    " data-{}=\"{}\"",
-                    escape_attr(&item.key),
-                    escape_attr(&item.value.raw)
-                ));
+            attrs.push_str(&id_attr(
+                block.attrs.label.as_ref(),
+                writer.options.id_namespace.as_deref(),
+            ));
+            attrs.push_str(&wrapper_data_attrs(&items));
+            if open == Some(true) {
+                attrs.push_str(" open");
             }
-            writer.line(&format!("<div {}>", attrs));
+            writer.line(&format!("<{} {}>", tag, attrs));
             writer.indent += 1;
-            if let Some(title) = title {
-                let title_html = render_inlines_with_context(
+            let title_html = title.as_ref().map(|title| {
+                render_inlines_with_context(
                     title,
                     RenderContext::Title,
                     &mut writer.math_counter,
                     &writer.math_settings,
                     writer.source_map.as_ref(),
-                );
+                    &writer.options,
+                )
+            });
+            if open.is_some() {
+                writer.line(&format!(
+                    "<summary class=\"{}box-title\">{}</summary>",
+                    writer.options.class_prefix,
+                    title_html.as_deref().unwrap_or("Details")
+                ));
+            } else if let Some(title_html) = title_html {
                 writer.line(&format!(
-                    "<div class=\"TypMark-box-title\">{}</div>",
-                    title_html
+                    "<div class=\"{}box-title\">{}</div>",
+                    writer.options.class_prefix, title_html
                 ));
             }
-            writer.line("<div class=\"TypMark-box-body\">");
+            writer.line(&format!(
+                "<div class=\"{}box-body\">",
+                writer.options.class_prefix
+            ));
             writer.indent += 1;
             for child in blocks {
                 emit_block(writer, child);
@@ -598,7 +1089,71 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
             writer.indent -= 1;
             writer.line("</div>");
             writer.indent -= 1;
-            writer.line("</div>");
+            writer.line(&format!("</{}>", tag));
+        }
+        BlockKind::Container {
+            name,
+            title,
+            blocks,
+        } => {
+            // `details` renders natively with its title as the summary; every
+            // other name is a div classed after the name.
+            let details = name == "details";
+            let mut open = false;
+            let mut items = block.attrs.items.clone();
+            if details {
+                items.retain(|item| {
+                    let value = item.value.raw.trim();
+                    if item.key == "open" && matches!(value, "true" | "false") {
+                        open = value == "true";
+                        return false;
+                    }
+                    true
+                });
+            }
+            let tag = if details { "details" } else { "div" };
+            let mut attrs = format!(
+                "class=\"{}{}\" data-typmark=\"{}\"",
+                writer.options.class_prefix, name, name
+            );
+            attrs.push_str(&span_attr(block.span, writer.source_map.as_ref()));
+            attrs.push_str(&id_attr(
+                block.attrs.label.as_ref(),
+                writer.options.id_namespace.as_deref(),
+            ));
+            attrs.push_str(&wrapper_data_attrs(&items));
+            if open {
+                attrs.push_str(" open");
+            }
+            writer.line(&format!("<{} {}>", tag, attrs));
+            writer.indent += 1;
+            let title_html = title.as_ref().map(|title| {
+                render_inlines_with_context(
+                    title,
+                    RenderContext::Title,
+                    &mut writer.math_counter,
+                    &writer.math_settings,
+                    writer.source_map.as_ref(),
+                    &writer.options,
+                )
+            });
+            if details {
+                writer.line(&format!(
+                    "<summary class=\"{}details-title\">{}</summary>",
+                    writer.options.class_prefix,
+                    title_html.as_deref().unwrap_or("Details")
+                ));
+            } else if let Some(title_html) = title_html {
+                writer.line(&format!(
+                    "<div class=\"{}{}-title\">{}</div>",
+                    writer.options.class_prefix, name, title_html
+                ));
+            }
+            for child in blocks {
+                emit_block(writer, child);
+            }
+            writer.indent -= 1;
+            writer.line(&format!("</{}>", tag));
         }
         BlockKind::MathBlock { typst_src } => {
             let attrs = compose_block_attrs_with_span(
@@ -606,19 +1161,23 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
                 &block.attrs.items,
                 block.span,
                 writer.source_map.as_ref(),
+                writer.options.id_namespace.as_deref(),
             );
             match render_math_with_prefix(
                 typst_src,
                 true,
                 &mut writer.math_counter,
                 &writer.math_settings,
+                writer.options.id_namespace.as_deref(),
+                &writer.options.class_prefix,
             ) {
                 Ok(svg) => writer.line(&format!(
-                    "<div class=\"TypMark-math-block\"{}>{}</div>",
-                    attrs, svg
+                    "<div class=\"{}math-block\"{}>{}</div>",
+                    writer.options.class_prefix, attrs, svg
                 )),
                 Err(source) => writer.line(&format!(
-                    "<div class=\"TypMark-math-block--error\"{}>{}</div>",
+                    "<div class=\"{}math-block--error\"{}>{}</div>",
+                    writer.options.class_prefix,
                     attrs,
                     escape_text(&source)
                 )),
@@ -630,6 +1189,7 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
                 &block.attrs.items,
                 block.span,
                 writer.source_map.as_ref(),
+                writer.options.id_namespace.as_deref(),
             );
             writer.line(&format!("<hr{} />", attrs));
         }
@@ -639,36 +1199,70 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
             info_attrs,
             meta,
             text,
+            no_highlight,
+            soft_wrap,
+            output,
         }) => {
             let attrs = compose_block_attrs_with_span(
                 block.attrs.label.as_ref(),
                 &block.attrs.items,
                 block.span,
                 writer.source_map.as_ref(),
+                writer.options.id_namespace.as_deref(),
             );
             let data = CodeBlockRender {
                 attrs,
                 kind: *kind,
                 lang: lang.as_deref(),
                 info_items: &info_attrs.items,
+                caption: attr_value(&block.attrs.items, "caption")
+                    .or_else(|| attr_value(&info_attrs.items, "caption")),
+                file: attr_value(&info_attrs.items, "file"),
+                title: attr_value(&info_attrs.items, "title"),
                 meta,
                 text,
+                no_highlight: *no_highlight,
+                soft_wrap: *soft_wrap,
+                output: output.as_deref(),
             };
             emit_code_block(writer, data);
         }
+        BlockKind::Include { path } => {
+            // Only reached when includes were not expanded.
+            let attrs = compose_block_attrs_with_span(
+                block.attrs.label.as_ref(),
+                &block.attrs.items,
+                block.span,
+                writer.source_map.as_ref(),
+                writer.options.id_namespace.as_deref(),
+            );
+            writer.line(&format!("<p{}>@include({})</p>", attrs, escape_text(path)));
+        }
         BlockKind::HtmlBlock { raw } => {
+            if writer.options.strip_comments && is_comment_only(raw) {
+                return;
+            }
             let attrs = compose_block_attrs_with_span(
                 block.attrs.label.as_ref(),
                 &block.attrs.items,
                 block.span,
                 writer.source_map.as_ref(),
+                writer.options.id_namespace.as_deref(),
             );
+            match writer.options.raw_html {
+                RawHtmlMode::Keep => {}
+                RawHtmlMode::Strip => return,
+                RawHtmlMode::Escape => {
+                    writer.line(&format!("<p{}>{}</p>", attrs, escape_text(raw)));
+                    return;
+                }
+            }
             if attrs.is_empty() {
                 writer.line(raw);
             } else {
                 writer.line(&format!(
-                    "<div class=\"TypMark-html\" data-typmark=\"html\"{}>",
-                    attrs
+                    "<div class=\"{}html\" data-typmark=\"html\"{}>",
+                    writer.options.class_prefix, attrs
                 ));
                 writer.indent += 1;
                 writer.line(raw);
@@ -679,15 +1273,93 @@ fn emit_block(writer: &mut HtmlWriter, block: &Block) {
     }
 }
 
+/// Whether `raw` holds nothing but HTML comments and whitespace. A comment
+/// left open runs to the end of `raw`. Conditional comments carry markup for
+/// old browsers, so they do not count.
+fn is_comment_only(raw: &str) -> bool {
+    let mut rest = raw.trim_start();
+    if rest.is_empty() {
+        return false;
+    }
+    while !rest.is_empty() {
+        let Some(body) = rest.strip_prefix("<!--") else {
+            return false;
+        };
+        if body.starts_with("[if") {
+            return false;
+        }
+        // `<!-->` and `<!--->` are complete, empty comments.
+        let end = if body.starts_with('>') {
+            1
+        } else if body.starts_with("->") {
+            2
+        } else {
+            body.find("-->").map_or(body.len(), |idx| idx + 3)
+        };
+        rest = body[end..].trim_start();
+    }
+    true
+}
+
+/// Prefixes the section number when `number_sections` is on.
+fn numbered_title(options: &HtmlEmitOptions, number: &[u32], title_html: String) -> String {
+    if !options.number_sections || number.is_empty() {
+        return title_html;
+    }
+    let number: Vec<String> = number.iter().map(u32::to_string).collect();
+    format!(
+        "<span class=\"section-number\">{}</span> {}",
+        number.join("."),
+        title_html
+    )
+}
+
+/// `<hN attrs>title</hN>`, with a permalink to `label` when heading anchors are on.
+fn heading_html(
+    writer: &HtmlWriter,
+    level: u8,
+    attrs: &str,
+    title: &[Inline],
+    title_html: &str,
+    label: Option<&Label>,
+) -> String {
+    let level = shifted_heading_level(level, writer.options.heading_offset);
+    let content = match label.filter(|_| writer.options.heading_anchors) {
+        Some(label) => {
+            let anchor = format!(
+                "<a class=\"anchor\" href=\"#{}\" aria-label=\"Permalink to {}\">",
+                escape_attr(&namespaced_id(
+                    writer.options.id_namespace.as_deref(),
+                    &label.name
+                )),
+                escape_attr(&render_inlines_text(title))
+            );
+            match writer.options.heading_anchor_position {
+                HeadingAnchorPosition::Before => format!("{}#</a> {}", anchor, title_html),
+                HeadingAnchorPosition::After => format!("{} {}#</a>", title_html, anchor),
+                HeadingAnchorPosition::Wrap => format!("{}{}</a>", anchor, title_html),
+            }
+        }
+        None => title_html.to_string(),
+    };
+    format!("<h{}{}>{}</h{}>", level, attrs, content, level)
+}
+
 fn emit_block_tight(writer: &mut HtmlWriter, block: &Block) -> bool {
     match &block.kind {
         BlockKind::Paragraph { content } => {
+            if let Some(figure) = captioned_image_html(writer, block, content) {
+                writer.out.push_str(&"  ".repeat(writer.indent));
+                writer.out.push_str(&figure);
+                return false;
+            }
             let inline = render_inlines_with_context(
                 content,
                 RenderContext::Normal,
                 &mut writer.math_counter,
                 &writer.math_settings,
                 writer.source_map.as_ref(),
+                &writer.options,
             );
             writer.out.push_str(&"  ".repeat(writer.indent));
             writer.out.push_str(&inline);
@@ -698,13 +1370,15 @@ fn emit_block_tight(writer: &mut HtmlWriter, block: &Block) -> bool {
             title,
             label,
             children,
+            number,
         } => {
             if writer.options.wrap_sections {
-                let attrs = compose_block_attrs_with_span(
+                let attrs = compose_wrapper_attrs_with_span(
                     label.as_ref(),
                     &block.attrs.items,
                     block.span,
                     writer.source_map.as_ref(),
+                    writer.options.id_namespace.as_deref(),
                 );
                 writer.line(&format!("<section{}>", attrs));
                 writer.indent += 1;
@@ -714,8 +1388,10 @@ fn emit_block_tight(writer: &mut HtmlWriter, block: &Block) -> bool {
                     &mut writer.math_counter,
                     &writer.math_settings,
                     writer.source_map.as_ref(),
+                    &writer.options,
                 );
-                let heading = format!("<h{}>{}</h{}>", level, title_html, level);
+                let title_html = numbered_title(&writer.options, number, title_html);
+                let heading = heading_html(writer, *level, "", title, &title_html, label.as_ref());
                 writer.line(&heading);
                 for (idx, child) in children.iter().enumerate() {
                     let ended = emit_block_tight(writer, child);
@@ -727,11 +1403,12 @@ fn emit_block_tight(writer: &mut HtmlWriter, block: &Block) -> bool {
                 writer.line("</section>");
                 true
             } else {
-                let attrs = compose_block_attrs_with_span(
+                let attrs = compose_wrapper_attrs_with_span(
                     label.as_ref(),
                     &block.attrs.items,
                     block.span,
                     writer.source_map.as_ref(),
+                    writer.options.id_namespace.as_deref(),
                 );
                 let title_html = render_inlines_with_context(
                     title,
@@ -739,8 +1416,12 @@ fn emit_block_tight(writer: &mut HtmlWriter, block: &Block) -> bool {
                     &mut writer.math_counter,
                     &writer.math_settings,
                     writer.source_map.as_ref(),
+                    &writer.options,
                 );
-                writer.line(&format!("<h{}{}>{}</h{}>", level, attrs, title_html, level));
+                let title_html = numbered_title(&writer.options, number, title_html);
+                let heading =
+                    heading_html(writer, *level, &attrs, title, &title_html, label.as_ref());
+                writer.line(&heading);
                 let mut last_ended = true;
                 for (idx, child) in children.iter().enumerate() {
                     let ended = emit_block_tight(writer, child);
@@ -764,13 +1445,39 @@ struct CodeBlockRender<'a> {
     kind: CodeBlockKind,
     lang: Option<&'a str>,
     info_items: &'a [AttrItem],
+    caption: Option<&'a str>,
+    file: Option<&'a str>,
+    title: Option<&'a str>,
     meta: &'a CodeMeta,
     text: &'a str,
+    no_highlight: bool,
+    soft_wrap: bool,
+    output: Option<&'a Block>,
 }
 
 fn emit_code_block(writer: &mut HtmlWriter, data: CodeBlockRender<'_>) {
+    let figure = !writer.options.simple_code_blocks && data.kind != CodeBlockKind::Indented;
+    let output = data.output;
+    emit_code_block_inner(writer, data);
+    // Without a figure to share, an output block simply follows its input.
+    if !figure && let Some(output) = output {
+        emit_block(writer, output);
+    }
+}
+
+fn emit_code_block_inner(writer: &mut HtmlWriter, data: CodeBlockRender<'_>) {
     let mut attrs = data.attrs;
-    attrs.push_str(&data_attrs(data.info_items));
+    // `file` and `title` are rendered as the figcaption, or dropped in simple output.
+    let info_items: Vec<AttrItem> = data
+        .info_items
+        .iter()
+        .filter(|item| item.key != "file" && item.key != "title")
+        .cloned()
+        .collect();
+    attrs.push_str(&data_attrs(&info_items));
+    if data.no_highlight {
+        attrs.push_str(" data-no-highlight");
+    }
     if writer.options.simple_code_blocks {
         // CommonMark-compatible simple output
         // Use code-specific escaping for code contents.
@@ -806,101 +1513,285 @@ fn emit_code_block(writer: &mut HtmlWriter, data: CodeBlockRender<'_>) {
             .lang
             .map(|value| format!(" data-lang=\"{}\"", escape_attr(value)))
             .unwrap_or_default();
+        let prefix = &writer.options.class_prefix;
+        let wrap_class = if data.soft_wrap {
+            format!(" {}codeblock--wrap", prefix)
+        } else {
+            String::new()
+        };
         writer.line(&format!(
-            "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\"{}{}>",
-            attrs, lang_attr
+            "<figure class=\"{}codeblock{}\" data-typmark=\"codeblock\"{}{}>",
+            prefix, wrap_class, attrs, lang_attr
         ));
         writer.indent += 1;
-        let code_class = data
-            .lang
-            .map(|value| format!("language-{}", escape_attr(value)))
-            .unwrap_or_else(|| "language-".to_string());
-        writer.out.push_str(&"  ".repeat(writer.indent));
-        writer.out.push_str(&format!(
-            "<pre class=\"TypMark-pre\"><code class=\"{}\">",
-            code_class
-        ));
-
-        let lines = split_lines_preserve(data.text);
-        let mut display_line_no = 1u32;
-        for (idx, line) in lines.iter().enumerate() {
-            let line_no = (idx + 1) as u32;
-            let highlighted = line_in_ranges(line_no, &data.meta.hl);
-            let diff = if line_in_ranges(line_no, &data.meta.diff_add) {
-                Some("add")
-            } else if line_in_ranges(line_no, &data.meta.diff_del) {
-                Some("del")
-            } else {
-                None
-            };
-            let line_label = data
-                .meta
-                .line_labels
-                .iter()
-                .find(|label| label.line == line_no);
-
-            let mut class = String::from("line");
-            if highlighted {
-                class.push_str(" highlighted");
-            }
-            if let Some(diff_kind) = diff {
-                class.push_str(" diff ");
-                class.push_str(diff_kind);
-            }
-            let mut attrs = format!("class=\"{}\"", class);
-            if diff != Some("del") {
-                attrs.push_str(&format!(" data-line=\"{}\"", display_line_no));
-                display_line_no += 1;
-            }
-            if highlighted {
-                attrs.push_str(" data-highlighted-line");
-            }
-            if let Some(diff_kind) = diff {
-                attrs.push_str(&format!(" data-diff=\"{}\"", diff_kind));
-            }
-            if let Some(label) = line_label {
-                attrs.push_str(&format!(
-                    " id=\"{}\" data-line-label=\"{}\"",
-                    escape_attr(&label.label.name),
-                    escape_attr(&label.label.name)
+        // A figure takes one figcaption, so a caption joins the title's.
+        match (data.title.or(data.file), data.caption) {
+            (Some(title), caption) => {
+                let file_attr = data
+                    .file
+                    .map(|file| format!(" data-file=\"{}\"", escape_attr(file)))
+                    .unwrap_or_default();
+                let caption_html = caption
+                    .map(|caption| {
+                        format!(
+                            " <span class=\"{}code-caption\">{}</span>",
+                            writer.options.class_prefix,
+                            escape_text(caption)
+                        )
+                    })
+                    .unwrap_or_default();
+                writer.line(&format!(
+                    "<figcaption class=\"{}code-title\"{}>{}{}</figcaption>",
+                    writer.options.class_prefix,
+                    file_attr,
+                    escape_text(title),
+                    caption_html
                 ));
             }
-            writer.out.push_str(&format!(
-                "<span {}>{}</span>",
-                attrs,
-                escape_html_code(line)
+            (None, Some(caption)) => writer.line(&format!(
+                "<figcaption>{}</figcaption>",
+                escape_text(caption)
+            )),
+            (None, None) => {}
+        }
+        let copy_target = writer.options.code_copy_button.then(|| {
+            writer.copy_counter += 1;
+            let id = namespaced_id(
+                writer.options.id_namespace.as_deref(),
+                &format!("{}code-{}", writer.options.class_prefix, writer.copy_counter),
+            );
+            writer.line(&format!(
+                "<button type=\"button\" class=\"{}copy\" data-copy-target=\"{}\" aria-label=\"Copy code\">Copy</button>",
+                writer.options.class_prefix,
+                escape_attr(&id)
             ));
+            id
+        });
+        match data.output {
+            Some(output) => {
+                writer.line(&format!(
+                    "<div class=\"{}code-input\" data-role=\"input\">",
+                    writer.options.class_prefix
+                ));
+                writer.indent += 1;
+                emit_code_lines(
+                    writer,
+                    data.lang,
+                    data.meta,
+                    data.text,
+                    copy_target.as_deref(),
+                );
+                writer.indent -= 1;
+                writer.line("</div>");
+                emit_code_output(writer, output);
+            }
+            None => emit_code_lines(
+                writer,
+                data.lang,
+                data.meta,
+                data.text,
+                copy_target.as_deref(),
+            ),
         }
-
-        writer.out.push_str("</code></pre>\n");
         writer.indent -= 1;
         writer.line("</figure>");
     }
 }
 
+/// The output half of an `output=label` pair, without a figure of its own.
+fn emit_code_output(writer: &mut HtmlWriter, block: &Block) {
+    let BlockKind::CodeBlock(code_block) = &block.kind else {
+        return;
+    };
+    let mut attrs = compose_block_attrs_with_span(
+        block.attrs.label.as_ref(),
+        &block.attrs.items,
+        block.span,
+        writer.source_map.as_ref(),
+        writer.options.id_namespace.as_deref(),
+    );
+    attrs.push_str(&data_attrs(&code_block.info_attrs.items));
+    if let Some(lang) = &code_block.lang {
+        attrs.push_str(&format!(" data-lang=\"{}\"", escape_attr(lang)));
+    }
+    writer.line(&format!(
+        "<div class=\"{}code-output\" data-role=\"output\"{}>",
+        writer.options.class_prefix, attrs
+    ));
+    writer.indent += 1;
+    emit_code_lines(
+        writer,
+        code_block.lang.as_deref(),
+        &code_block.meta,
+        &code_block.text,
+        None,
+    );
+    writer.indent -= 1;
+    writer.line("</div>");
+}
+
+/// Writes `<pre><code>` with one span per line, carrying highlight, diff, and
+/// line label markers. `pre_id` is the target of a copy button.
+fn emit_code_lines(
+    writer: &mut HtmlWriter,
+    lang: Option<&str>,
+    meta: &CodeMeta,
+    text: &str,
+    pre_id: Option<&str>,
+) {
+    let code_class = lang
+        .map(|value| format!("language-{}", escape_attr(value)))
+        .unwrap_or_else(|| "language-".to_string());
+    let id_attr = pre_id
+        .map(|id| format!(" id=\"{}\"", escape_attr(id)))
+        .unwrap_or_default();
+    writer.out.push_str(&"  ".repeat(writer.indent));
+    writer.out.push_str(&format!(
+        "<pre class=\"{}pre\"{}><code class=\"{}\">",
+        writer.options.class_prefix, id_attr, code_class
+    ));
+
+    let lines = split_lines_preserve(text);
+    let mut display_line_no = 1u32;
+    for (idx, line) in lines.iter().enumerate() {
+        let line_no = (idx + 1) as u32;
+        let status = meta.line_status(line_no);
+        let highlighted = status.highlighted;
+        let diff = status.diff.map(|diff| match diff {
+            LineDiff::Add => "add",
+            LineDiff::Del => "del",
+        });
+
+        let mut class = String::from("line");
+        if highlighted {
+            class.push_str(" highlighted");
+        }
+        if let Some(diff_kind) = diff {
+            class.push_str(" diff ");
+            class.push_str(diff_kind);
+        }
+        let mut attrs = format!("class=\"{}\"", class);
+        if diff != Some("del") {
+            attrs.push_str(&format!(" data-line=\"{}\"", display_line_no));
+            display_line_no += 1;
+        }
+        if highlighted {
+            attrs.push_str(" data-highlighted-line");
+        }
+        if let Some(diff_kind) = diff {
+            attrs.push_str(&format!(" data-diff=\"{}\"", diff_kind));
+        }
+        if let Some(label) = status.label {
+            attrs.push_str(&format!(
+                " id=\"{}\" data-line-label=\"{}\"",
+                escape_attr(&namespaced_id(
+                    writer.options.id_namespace.as_deref(),
+                    &label.name
+                )),
+                escape_attr(&label.name)
+            ));
+        }
+        writer.out.push_str(&format!(
+            "<span {}>{}</span>",
+            attrs,
+            escape_html_code(line)
+        ));
+    }
+
+    writer.out.push_str("</code></pre>\n");
+}
+
 fn render_math_with_prefix(
     typst_src: &str,
     display: bool,
     math_counter: &mut usize,
     math_settings: &MathSettings,
+    id_namespace: Option<&str>,
+    class_prefix: &str,
 ) -> Result<String, String> {
     *math_counter += 1;
-    let prefix = format!("tm-m{}", *math_counter);
-    render_math(typst_src, display, math_settings).map(|svg| prefix_svg_ids(&svg, &prefix))
-}
-
+    match math_settings.output {
+        MathOutput::Raw => {
+            return Ok(format!(
+                "<span class=\"{}math\" data-typst=\"{}\" data-math-mode=\"{}\"></span>",
+                class_prefix,
+                escape_attr(typst_src),
+                if display { "display" } else { "inline" }
+            ));
+        }
+        MathOutput::MathML => {
+            if let Some(mathml) = math_settings
+                .renderer
+                .as_ref()
+                .and_then(|renderer| renderer.render_mathml(typst_src, display))
+            {
+                return Ok(mathml);
+            }
+        }
+        MathOutput::Svg | MathOutput::SvgWithMathMLAnnotation => {}
+    }
+    let prefix = namespaced_id(id_namespace, &format!("tm-m{}", *math_counter));
+    let svg =
+        render_math(typst_src, display, math_settings).map(|svg| prefix_svg_ids(&svg, &prefix))?;
+    if math_settings.output == MathOutput::Svg {
+        return Ok(label_math_svg(&svg, typst_src));
+    }
+    Ok(format!(
+        "<span role=\"math\" aria-label=\"{}\">{}</span>",
+        escape_attr(typst_src.trim()),
+        svg
+    ))
+}
+
+/// Exposes a bare math SVG to assistive technology as an image named by its source.
+fn label_math_svg(svg: &str, typst_src: &str) -> String {
+    match svg.strip_prefix("<svg") {
+        Some(rest) => format!(
+            "<svg role=\"img\" aria-label=\"{}\"{}",
+            escape_attr(typst_src.trim()),
+            rest
+        ),
+        None => svg.to_string(),
+    }
+}
+
 fn render_inlines_with_context(
     inlines: &[Inline],
     context: RenderContext,
     math_counter: &mut usize,
     math_settings: &MathSettings,
     source_map: Option<&SourceMap>,
+    options: &HtmlEmitOptions,
 ) -> String {
     let mut out = String::new();
-    for inline in inlines {
+    for (idx, inline) in inlines.iter().enumerate() {
         let span_attr = span_attr(inline.span, source_map);
         match &inline.kind {
-            InlineKind::Text(text) => {
+            InlineKind::Text(text) if options.smart_punctuation || options.wrap_dates => {
+                let text = if options.smart_punctuation {
+                    let prev = idx
+                        .checked_sub(1)
+                        .and_then(|prev| render_inlines_text(&inlines[prev..idx]).chars().last());
+                    Cow::Owned(smarten(text, prev))
+                } else {
+                    Cow::Borrowed(text.as_str())
+                };
+                let html = if options.wrap_dates {
+                    escape_text_wrapping_dates(&text)
+                } else {
+                    escape_text(&text)
+                };
+                if span_attr.is_empty() {
+                    out.push_str(&html);
+                } else {
+                    out.push_str("<span");
+                    out.push_str(&span_attr);
+                    out.push('>');
+                    out.push_str(&html);
+                    out.push_str("</span>");
+                }
+            }
+            InlineKind::Text(text) | InlineKind::Raw(text) => {
                 if span_attr.is_empty() {
                     out.push_str(&escape_text(text));
                 } else {
@@ -911,6 +1802,28 @@ fn render_inlines_with_context(
                     out.push_str("</span>");
                 }
             }
+            InlineKind::Emoji { name, emoji } => {
+                if options.emoji {
+                    out.push_str("<span class=\"emoji\" role=\"img\" aria-label=\"");
+                    out.push_str(&escape_attr(name));
+                    out.push('"');
+                    out.push_str(&span_attr);
+                    out.push('>');
+                    out.push_str(emoji);
+                    out.push_str("</span>");
+                } else {
+                    let text = escape_text(&format!(":{}:", name));
+                    if span_attr.is_empty() {
+                        out.push_str(&text);
+                    } else {
+                        out.push_str("<span");
+                        out.push_str(&span_attr);
+                        out.push('>');
+                        out.push_str(&text);
+                        out.push_str("</span>");
+                    }
+                }
+            }
             InlineKind::CodeSpan(text) => {
                 if span_attr.is_empty() {
                     out.push_str("<code>");
@@ -923,36 +1836,34 @@ fn render_inlines_with_context(
                 out.push_str("</code>");
             }
             InlineKind::MathInline { typst_src } => {
-                match render_math_with_prefix(typst_src, false, math_counter, math_settings) {
+                match render_math_with_prefix(
+                    typst_src,
+                    false,
+                    math_counter,
+                    math_settings,
+                    options.id_namespace.as_deref(),
+                    &options.class_prefix,
+                ) {
                     Ok(svg) => {
-                        if span_attr.is_empty() {
-                            out.push_str("<span class=\"TypMark-math-inline\">");
-                        } else {
-                            out.push_str("<span class=\"TypMark-math-inline\"");
-                            out.push_str(&span_attr);
-                            out.push('>');
-                        }
-                        out.push_str(
-                            "<span class=\"TypMark-math-inline-strut\" aria-hidden=\"true\"></span>",
-                        );
+                        let prefix = &options.class_prefix;
+                        out.push_str(&format!(
+                            "<span class=\"{prefix}math-inline\"{span_attr}><span class=\"{prefix}math-inline-strut\" aria-hidden=\"true\"></span>"
+                        ));
                         out.push_str(&svg);
                         out.push_str("</span>");
                     }
                     Err(source) => {
-                        if span_attr.is_empty() {
-                            out.push_str("<span class=\"TypMark-math-inline--error\">");
-                        } else {
-                            out.push_str("<span class=\"TypMark-math-inline--error\"");
-                            out.push_str(&span_attr);
-                            out.push('>');
-                        }
+                        out.push_str(&format!(
+                            "<span class=\"{}math-inline--error\"{}>",
+                            options.class_prefix, span_attr
+                        ));
                         out.push_str(&escape_text(&source));
                         out.push_str("</span>");
                     }
                 }
             }
-            InlineKind::SoftBreak => out.push('\n'),
-            InlineKind::HardBreak => {
+            InlineKind::SoftBreak if !options.soft_breaks_as_br => out.push('\n'),
+            InlineKind::SoftBreak | InlineKind::HardBreak => {
                 if span_attr.is_empty() {
                     out.push_str("<br />\n");
                 } else {
@@ -964,17 +1875,25 @@ fn render_inlines_with_context(
             InlineKind::Ref {
                 label,
                 bracket,
+                numbered,
                 resolved,
             } => {
                 out.push_str(&render_ref(
                     label,
                     bracket.as_deref(),
+                    *numbered,
                     resolved.as_ref(),
                     context,
                     math_counter,
                     math_settings,
                     source_map,
                     inline.span,
+                    &link_text_options(options),
+                ));
+            }
+            InlineKind::Citation { keys, numbers } => {
+                out.push_str(&render_citation(
+                    keys, numbers, context, &span_attr, options,
                 ));
             }
             InlineKind::Emph(children) => {
@@ -985,12 +1904,13 @@ fn render_inlines_with_context(
                     out.push_str(&span_attr);
                     out.push('>');
                 }
-                out.push_str(&render_inlines_with_context(
+                out.push_str(&render_inline_children(
                     children,
                     context,
                     math_counter,
                     math_settings,
                     source_map,
+                    options,
                 ));
                 out.push_str("</em>");
             }
@@ -1002,12 +1922,13 @@ fn render_inlines_with_context(
                     out.push_str(&span_attr);
                     out.push('>');
                 }
-                out.push_str(&render_inlines_with_context(
+                out.push_str(&render_inline_children(
                     children,
                     context,
                     math_counter,
                     math_settings,
                     source_map,
+                    options,
                 ));
                 out.push_str("</strong>");
             }
@@ -1019,15 +1940,60 @@ fn render_inlines_with_context(
                     out.push_str(&span_attr);
                     out.push('>');
                 }
-                out.push_str(&render_inlines_with_context(
+                out.push_str(&render_inline_children(
                     children,
                     context,
                     math_counter,
                     math_settings,
                     source_map,
+                    options,
                 ));
                 out.push_str("</del>");
             }
+            InlineKind::Span { children, attrs } => {
+                out.push_str("<span");
+                out.push_str(&inline_span_attrs(attrs, options.id_namespace.as_deref()));
+                out.push_str(&span_attr);
+                out.push('>');
+                out.push_str(&render_inline_children(
+                    children,
+                    context,
+                    math_counter,
+                    math_settings,
+                    source_map,
+                    options,
+                ));
+                out.push_str("</span>");
+            }
+            InlineKind::Directive(directive) => {
+                match options.directive_handlers.get(&directive.name) {
+                    Some(handler) => {
+                        let label_html = render_inline_children(
+                            &directive.label,
+                            context,
+                            math_counter,
+                            math_settings,
+                            source_map,
+                            options,
+                        );
+                        out.push_str(&handler.render_directive(directive, &label_html));
+                    }
+                    None if is_media_directive(directive) => {
+                        out.push_str(&media_html(directive, &span_attr, options));
+                    }
+                    None if is_kbd_directive(directive, options) => {
+                        out.push_str(&kbd_html(directive, &span_attr, options));
+                    }
+                    None if span_attr.is_empty() => out.push_str(&escape_text(&directive.raw)),
+                    None => {
+                        out.push_str("<span");
+                        out.push_str(&span_attr);
+                        out.push('>');
+                        out.push_str(&escape_text(&directive.raw));
+                        out.push_str("</span>");
+                    }
+                }
+            }
             InlineKind::Link {
                 url,
                 title,
@@ -1044,33 +2010,51 @@ fn render_inlines_with_context(
                     }
                     out.push_str(&span_attr);
                     out.push('>');
-                    out.push_str(&render_inlines_with_context(
+                    out.push_str(&render_inline_children(
                         children,
                         context,
                         math_counter,
                         math_settings,
                         source_map,
+                        &link_text_options(options),
                     ));
                     out.push_str("</a>");
                 }
                 RenderContext::ReferenceText => {
-                    if span_attr.is_empty() {
-                        out.push_str("<span class=\"TypMark-delink\">");
-                    } else {
-                        out.push_str("<span class=\"TypMark-delink\"");
-                        out.push_str(&span_attr);
-                        out.push('>');
-                    }
+                    out.push_str(&format!(
+                        "<span class=\"{}delink\"{}>",
+                        options.class_prefix, span_attr
+                    ));
                     out.push_str(&render_inlines_with_context(
                         children,
                         RenderContext::ReferenceText,
                         math_counter,
                         math_settings,
                         source_map,
+                        options,
                     ));
                     out.push_str("</span>");
                 }
             },
+            InlineKind::WikiLink { display, href, .. } => match context {
+                RenderContext::Normal | RenderContext::Title => {
+                    out.push_str("<a href=\"");
+                    out.push_str(&escape_url_attr(href));
+                    out.push('"');
+                    out.push_str(&span_attr);
+                    out.push('>');
+                    out.push_str(&escape_text(display));
+                    out.push_str("</a>");
+                }
+                RenderContext::ReferenceText => {
+                    out.push_str(&format!(
+                        "<span class=\"{}delink\"{}>",
+                        options.class_prefix, span_attr
+                    ));
+                    out.push_str(&escape_text(display));
+                    out.push_str("</span>");
+                }
+            },
             InlineKind::LinkRef {
                 label,
                 children,
@@ -1084,6 +2068,7 @@ fn render_inlines_with_context(
                         math_counter,
                         math_settings,
                         source_map,
+                        options,
                     ));
                     out.push(']');
                     if meta.label_open_span.is_some() {
@@ -1102,6 +2087,7 @@ fn render_inlines_with_context(
                         math_counter,
                         math_settings,
                         source_map,
+                        options,
                     ));
                     out.push(']');
                     if meta.label_open_span.is_some() {
@@ -1112,7 +2098,12 @@ fn render_inlines_with_context(
                     out.push_str("</span>");
                 }
             }
-            InlineKind::Image { url, title, alt } => match context {
+            InlineKind::Image {
+                url,
+                title,
+                alt,
+                attrs,
+            } => match context {
                 RenderContext::ReferenceText => {
                     out.push_str(&render_inlines_with_context(
                         alt,
@@ -1120,22 +2111,18 @@ fn render_inlines_with_context(
                         math_counter,
                         math_settings,
                         source_map,
+                        options,
                     ));
                 }
-                _ => {
-                    out.push_str("<img src=\"");
-                    out.push_str(&escape_url_attr(url));
-                    out.push_str("\" alt=\"");
-                    out.push_str(&escape_attr(&render_inlines_text(alt)));
-                    out.push('"');
-                    if let Some(title) = title {
-                        out.push_str(" title=\"");
-                        out.push_str(&escape_attr(title));
-                        out.push('"');
-                    }
-                    out.push_str(&span_attr);
-                    out.push_str(" />");
-                }
+                _ => out.push_str(&render_image(
+                    url,
+                    title.as_deref(),
+                    alt,
+                    attrs,
+                    true,
+                    &span_attr,
+                    options.id_namespace.as_deref(),
+                )),
             },
             InlineKind::ImageRef { label, alt, meta } => match context {
                 RenderContext::ReferenceText => {
@@ -1145,6 +2132,7 @@ fn render_inlines_with_context(
                         math_counter,
                         math_settings,
                         source_map,
+                        options,
                     ));
                 }
                 _ => {
@@ -1156,6 +2144,7 @@ fn render_inlines_with_context(
                             math_counter,
                             math_settings,
                             source_map,
+                            options,
                         ));
                         out.push(']');
                         if meta.label_open_span.is_some() {
@@ -1174,6 +2163,7 @@ fn render_inlines_with_context(
                             math_counter,
                             math_settings,
                             source_map,
+                            options,
                         ));
                         out.push(']');
                         if meta.label_open_span.is_some() {
@@ -1186,13 +2176,21 @@ fn render_inlines_with_context(
                 }
             },
             InlineKind::HtmlSpan { raw } => {
+                if options.strip_comments && is_comment_only(raw) {
+                    continue;
+                }
+                let raw = match options.raw_html {
+                    RawHtmlMode::Keep => raw.clone(),
+                    RawHtmlMode::Strip => continue,
+                    RawHtmlMode::Escape => escape_text(raw),
+                };
                 if span_attr.is_empty() {
-                    out.push_str(raw);
+                    out.push_str(&raw);
                 } else {
                     out.push_str("<span");
                     out.push_str(&span_attr);
                     out.push('>');
-                    out.push_str(raw);
+                    out.push_str(&raw);
                     out.push_str("</span>");
                 }
             }
@@ -1201,16 +2199,49 @@ fn render_inlines_with_context(
     out
 }
 
+// The parent element already carries the source range, so a lone text child
+// is written bare instead of getting its own wrapper span.
+fn render_inline_children(
+    children: &[Inline],
+    context: RenderContext,
+    math_counter: &mut usize,
+    math_settings: &MathSettings,
+    source_map: Option<&SourceMap>,
+    options: &HtmlEmitOptions,
+) -> String {
+    if let [
+        Inline {
+            kind: InlineKind::Text(text),
+            ..
+        },
+    ] = children
+        && !options.smart_punctuation
+        && !options.wrap_dates
+    {
+        return escape_text(text);
+    }
+    render_inlines_with_context(
+        children,
+        context,
+        math_counter,
+        math_settings,
+        source_map,
+        options,
+    )
+}
+
 #[allow(clippy::too_many_arguments)]
 fn render_ref(
     label: &Label,
     bracket: Option<&[Inline]>,
+    numbered: Option<RefCategory>,
     resolved: Option<&ResolvedRef>,
     context: RenderContext,
     math_counter: &mut usize,
     math_settings: &MathSettings,
     source_map: Option<&SourceMap>,
     span: Span,
+    options: &HtmlEmitOptions,
 ) -> String {
     let span_attr = span_attr(span, source_map);
     let display = if let Some(bracket) = bracket {
@@ -1220,7 +2251,17 @@ fn render_ref(
             math_counter,
             math_settings,
             source_map,
+            options,
         )
+    } else if let (
+        Some(_),
+        Some(ResolvedRef::Block {
+            number: Some(number),
+            ..
+        }),
+    ) = (numbered, resolved)
+    {
+        format!("{} {}", number.category.title(), number.ordinal)
     } else if let Some(ResolvedRef::Block {
         display: Some(text),
         ..
@@ -1232,6 +2273,7 @@ fn render_ref(
             math_counter,
             math_settings,
             source_map,
+            options,
         )
     } else {
         escape_text(&label.name)
@@ -1241,14 +2283,16 @@ fn render_ref(
         RenderContext::Normal | RenderContext::Title => {
             if resolved.is_some() {
                 format!(
-                    "<a class=\"TypMark-ref\"{} href=\"#{}\">{}</a>",
+                    "<a class=\"{}ref\"{} href=\"#{}\">{}</a>",
+                    options.class_prefix,
                     span_attr,
-                    escape_attr(&label.name),
+                    escape_attr(&namespaced_id(options.id_namespace.as_deref(), &label.name)),
                     display
                 )
             } else {
                 format!(
-                    "<span class=\"TypMark-ref ref-unresolved\"{} data-ref-label=\"{}\">{}</span>",
+                    "<span class=\"{}ref ref-unresolved\"{} data-ref-label=\"{}\">{}</span>",
+                    options.class_prefix,
                     span_attr,
                     escape_attr(&label.name),
                     display
@@ -1258,12 +2302,13 @@ fn render_ref(
         RenderContext::ReferenceText => {
             if resolved.is_some() {
                 format!(
-                    "<span class=\"TypMark-delink\"{}>{}</span>",
-                    span_attr, display
+                    "<span class=\"{}delink\"{}>{}</span>",
+                    options.class_prefix, span_attr, display
                 )
             } else {
                 format!(
-                    "<span class=\"TypMark-delink ref-unresolved\"{} data-ref-label=\"{}\">{}</span>",
+                    "<span class=\"{}delink ref-unresolved\"{} data-ref-label=\"{}\">{}</span>",
+                    options.class_prefix,
                     span_attr,
                     escape_attr(&label.name),
                     display
@@ -1273,6 +2318,141 @@ fn render_ref(
     }
 }
 
+/// Renders a citation group as `[1, 2]`. Resolved keys link to their entry in the
+/// references list; unresolved keys keep their `@key` source text.
+fn render_citation(
+    keys: &[Label],
+    numbers: &[Option<usize>],
+    context: RenderContext,
+    span_attr: &str,
+    options: &HtmlEmitOptions,
+) -> String {
+    let prefix = &options.class_prefix;
+    let items = keys
+        .iter()
+        .zip(numbers)
+        .map(|(key, number)| match (number, context) {
+            (Some(number), RenderContext::Normal | RenderContext::Title) => format!(
+                "<a class=\"{}ref\" href=\"#{}\">{}</a>",
+                prefix,
+                escape_attr(&namespaced_id(
+                    options.id_namespace.as_deref(),
+                    &citation_id(&key.name)
+                )),
+                number
+            ),
+            (Some(number), RenderContext::ReferenceText) => number.to_string(),
+            (None, _) => format!(
+                "<span class=\"{}ref ref-unresolved\" data-ref-label=\"{}\">@{}</span>",
+                prefix,
+                escape_attr(&key.name),
+                escape_text(&key.name)
+            ),
+        })
+        .collect::<Vec<_>>();
+    format!(
+        "<span class=\"{}citation\"{}>[{}]</span>",
+        prefix,
+        span_attr,
+        items.join(", ")
+    )
+}
+
+fn citation_id(key: &str) -> String {
+    format!("cite-{}", key)
+}
+
+/// Appends the numbered references list for the document's resolved citations.
+fn emit_references(writer: &mut HtmlWriter, document: &Document) {
+    let mut cited = CitedKeys::default();
+    walk_document(&mut cited, document);
+    if cited.keys.is_empty() {
+        return;
+    }
+    writer.line(&format!(
+        "<section class=\"{}references\">",
+        writer.options.class_prefix
+    ));
+    writer.indent += 1;
+    writer.line("<ol>");
+    writer.indent += 1;
+    for key in cited.keys {
+        let entry = document.bibliography_entry(&key).unwrap_or_default();
+        let id = namespaced_id(writer.options.id_namespace.as_deref(), &citation_id(&key));
+        writer.line(&format!(
+            "<li id=\"{}\">{}</li>",
+            escape_attr(&id),
+            escape_text(entry)
+        ));
+    }
+    writer.indent -= 1;
+    writer.line("</ol>");
+    writer.indent -= 1;
+    writer.line("</section>");
+}
+
+/// Resolved citation keys, in the order the resolver numbered them.
+#[derive(Default)]
+struct CitedKeys {
+    keys: Vec<String>,
+}
+
+impl Visitor for CitedKeys {
+    fn visit_inline(&mut self, inline: &Inline) {
+        if let InlineKind::Citation { keys, numbers } = &inline.kind {
+            for (key, number) in keys.iter().zip(numbers) {
+                if let Some(number) = number
+                    && *number > self.keys.len()
+                {
+                    self.keys.push(key.name.clone());
+                }
+            }
+        }
+        walk_inline(self, inline);
+    }
+}
+
+/// Splits `type`, `reversed` and `continue` off an ordered list's attributes.
+/// Recognized values become real `<ol>` attributes; anything else stays a `data-*` attribute.
+fn ordered_list_attrs(items: &[AttrItem]) -> (String, Vec<AttrItem>) {
+    let mut out = String::new();
+    let mut rest = Vec::new();
+    for item in items {
+        let value = item.value.raw.trim();
+        match item.key.as_str() {
+            "type" if matches!(value, "1" | "a" | "A" | "i" | "I") => {
+                out.push_str(&format!(" type=\"{}\"", value));
+            }
+            "reversed" if matches!(value, "true" | "false") => {
+                if value == "true" {
+                    out.push_str(" reversed");
+                }
+            }
+            // Applied to the list's start number by the resolver.
+            "continue" if matches!(value, "true" | "false") => {}
+            _ => rest.push(item.clone()),
+        }
+    }
+    (out, rest)
+}
+
+/// Splits `collapsible` and `open` off a box's attributes. Returns whether a
+/// collapsible box starts open, or `None` for a plain box.
+fn collapsible_box_attrs(items: &[AttrItem]) -> (Option<bool>, Vec<AttrItem>) {
+    let mut collapsible = false;
+    let mut open = false;
+    let mut rest = Vec::new();
+    for item in items {
+        let value = item.value.raw.trim();
+        match item.key.as_str() {
+            "collapsible" if matches!(value, "true" | "false") => collapsible = value == "true",
+            "open" if matches!(value, "true" | "false") => open = value == "true",
+            _ => rest.push(item.clone()),
+        }
+    }
+    (collapsible.then_some(open), rest)
+}
+
 fn task_input_html(checked: bool) -> String {
     if checked {
         "<input type=\"checkbox\" disabled=\"\" checked=\"\" /> ".to_string()
@@ -1288,6 +2468,7 @@ fn emit_paragraph_with_prefix(writer: &mut HtmlWriter, content: &[Inline], prefi
         &mut writer.math_counter,
         &writer.math_settings,
         writer.source_map.as_ref(),
+        &writer.options,
     );
     writer.out.push_str(&"  ".repeat(writer.indent));
     writer.out.push_str("<p>");
@@ -1296,9 +2477,29 @@ fn emit_paragraph_with_prefix(writer: &mut HtmlWriter, content: &[Inline], prefi
     writer.out.push_str("</p>\n");
 }
 
-fn emit_table(writer: &mut HtmlWriter, table: &Table, attrs: &str) {
+/// Table styles a target line can pick with `.class`; other class names are dropped.
+const TABLE_CLASSES: &[&str] = &["bordered", "striped", "compact"];
+
+fn table_class_attr(classes: &[AttrItem]) -> String {
+    let mut names: Vec<&str> = Vec::new();
+    for item in classes {
+        let name = item.value.raw.as_str();
+        if TABLE_CLASSES.contains(&name) && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if names.is_empty() {
+        return String::new();
+    }
+    format!(" class=\"{}\"", names.join(" "))
+}
+
+fn emit_table(writer: &mut HtmlWriter, table: &Table, attrs: &str, caption: Option<&str>) {
     writer.line(&format!("<table{}>", attrs));
     writer.indent += 1;
+    if let Some(caption) = caption {
+        writer.line(&format!("<caption>{}</caption>", escape_text(caption)));
+    }
     writer.line("<thead>");
     writer.indent += 1;
     writer.line("<tr>");
@@ -1311,6 +2512,7 @@ fn emit_table(writer: &mut HtmlWriter, table: &Table, attrs: &str) {
             &mut writer.math_counter,
             &writer.math_settings,
             writer.source_map.as_ref(),
+            &writer.options,
         );
         writer.line(&format!("<th{}>{}</th>", align_attr, inline));
     }
@@ -1332,6 +2534,7 @@ fn emit_table(writer: &mut HtmlWriter, table: &Table, attrs: &str) {
                     &mut writer.math_counter,
                     &writer.math_settings,
                     writer.source_map.as_ref(),
+                    &writer.options,
                 );
                 writer.line(&format!("<td{}>{}</td>", align_attr, inline));
             }
@@ -1354,47 +2557,194 @@ fn table_align_attr(align: Option<TableAlign>) -> &'static str {
     }
 }
 
-fn render_inlines_text(inlines: &[Inline]) -> String {
-    let mut out = String::new();
-    for inline in inlines {
-        match &inline.kind {
-            InlineKind::Text(text) => out.push_str(text),
-            InlineKind::CodeSpan(text) => out.push_str(text),
-            InlineKind::MathInline { typst_src } => out.push_str(typst_src),
-            InlineKind::SoftBreak | InlineKind::HardBreak => out.push('\n'),
-            InlineKind::Ref { label, bracket, .. } => {
-                if let Some(bracket) = bracket.as_deref() {
-                    out.push_str(&render_inlines_text(bracket));
-                } else {
-                    out.push_str(&label.name);
-                }
+/// Options for rendering the text of a link, which never gets `<time>` wrappers.
+fn link_text_options(options: &HtmlEmitOptions) -> Cow<'_, HtmlEmitOptions> {
+    if options.wrap_dates {
+        Cow::Owned(HtmlEmitOptions {
+            wrap_dates: false,
+            ..options.clone()
+        })
+    } else {
+        Cow::Borrowed(options)
+    }
+}
+
+/// Escapes `text`, wrapping each date that stands as its own word in `<time>`.
+fn escape_text_wrapping_dates(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let at_boundary = text[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|prev| !is_date_word_char(prev));
+        let Some(len) = at_boundary.then(|| date_len(&text[i..])).flatten() else {
+            i += text[i..].chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+        let date = &text[i..i + len];
+        out.push_str(&escape_text(&text[last..i]));
+        out.push_str(&format!(
+            "<time datetime=\"{}\">{}</time>",
+            escape_attr(date),
+            escape_text(date)
+        ));
+        i += len;
+        last = i;
+    }
+    out.push_str(&escape_text(&text[last..]));
+    out
+}
+
+/// Length of the `YYYY-MM-DD` or `YYYY-MM-DDThh:mm[:ss]` date that starts
+/// `text` and ends at a word boundary.
+fn date_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let number = |start: usize, len: usize, max: u32| {
+        let digits = bytes.get(start..start + len)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let value = digits
+            .iter()
+            .fold(0, |value, digit| value * 10 + u32::from(digit - b'0'));
+        (value <= max).then_some(value)
+    };
+    number(0, 4, 9999)?;
+    let month = (bytes.get(4) == Some(&b'-'))
+        .then(|| number(5, 2, 12))
+        .flatten()?;
+    let day = (bytes.get(7) == Some(&b'-'))
+        .then(|| number(8, 2, 31))
+        .flatten()?;
+    if month == 0 || day == 0 {
+        return None;
+    }
+    let mut len = 10;
+    if bytes.get(10) == Some(&b'T')
+        && number(11, 2, 23).is_some()
+        && bytes.get(13) == Some(&b':')
+        && number(14, 2, 59).is_some()
+    {
+        len = 16;
+        if bytes.get(16) == Some(&b':') && number(17, 2, 59).is_some() {
+            len = 19;
+        }
+    }
+    let ends_word = text[len..]
+        .chars()
+        .next()
+        .is_none_or(|next| !is_date_word_char(next));
+    ends_word.then_some(len)
+}
+
+fn is_date_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '-' | '_')
+}
+
+/// SmartyPants-style punctuation for one text node. `prev` is the character
+/// before the node, if any, so a quote right after an emphasis closes it.
+fn smarten(text: &str, prev: Option<char>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut prev = prev;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' | '\'' => {
+                let opens = prev.is_none_or(|prev| {
+                    prev.is_whitespace() || "([{<-\u{2013}\u{2014}\u{201c}\u{2018}".contains(prev)
+                });
+                let curly = match (ch, opens) {
+                    ('"', true) => '\u{201c}',
+                    ('"', false) => '\u{201d}',
+                    (_, true) => '\u{2018}',
+                    // Closing single quotes double as apostrophes (`don't`).
+                    (_, false) => '\u{2019}',
+                };
+                out.push(curly);
+                prev = Some(curly);
+                continue;
             }
-            InlineKind::Emph(children)
-            | InlineKind::Strong(children)
-            | InlineKind::Strikethrough(children)
-            | InlineKind::Link { children, .. }
-            | InlineKind::LinkRef { children, .. } => {
-                out.push_str(&render_inlines_text(children));
+            '-' => {
+                let mut run = 1;
+                while chars.next_if_eq(&'-').is_some() {
+                    run += 1;
+                }
+                out.push_str(&dashes(run));
             }
-            InlineKind::Image { alt, .. } | InlineKind::ImageRef { alt, .. } => {
-                out.push_str(&render_inlines_text(alt));
+            '.' if chars.peek() == Some(&'.') => {
+                let mut run = 1;
+                while chars.next_if_eq(&'.').is_some() {
+                    run += 1;
+                }
+                out.push_str(&"\u{2026}".repeat(run / 3));
+                out.push_str(&".".repeat(run % 3));
             }
-            InlineKind::HtmlSpan { raw } => out.push_str(raw),
+            _ => out.push(ch),
         }
+        prev = out.chars().next_back();
     }
     out
 }
 
-fn line_in_ranges(line: u32, ranges: &[LineRange]) -> bool {
-    ranges
-        .iter()
-        .any(|range| range.start <= line && line <= range.end)
+/// Splits a run of hyphens the way CommonMark's `smart` extension does:
+/// all em dashes or all en dashes when the length allows, em dashes first otherwise.
+fn dashes(run: usize) -> String {
+    let (em, en) = match run {
+        1 => return "-".to_string(),
+        _ if run % 3 == 0 => (run / 3, 0),
+        _ if run % 2 == 0 => (0, run / 2),
+        _ if run % 3 == 2 => (run / 3, 1),
+        _ => ((run - 4) / 3, 2),
+    };
+    format!("{}{}", "\u{2014}".repeat(em), "\u{2013}".repeat(en))
 }
 
-fn split_lines_preserve(text: &str) -> Vec<String> {
-    if text.is_empty() {
-        return vec![String::new()];
+/// Warns about every directive in `document` that has no handler in `options`.
+pub fn directive_diagnostics(
+    document: &Document,
+    options: &HtmlEmitOptions,
+    source_map: &SourceMap,
+) -> Vec<Diagnostic> {
+    let mut collector = UnknownDirectives {
+        options,
+        source_map,
+        diagnostics: Vec::new(),
+    };
+    walk_document(&mut collector, document);
+    collector.diagnostics
+}
+
+struct UnknownDirectives<'a> {
+    options: &'a HtmlEmitOptions,
+    source_map: &'a SourceMap,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Visitor for UnknownDirectives<'_> {
+    fn visit_inline(&mut self, inline: &Inline) {
+        if let InlineKind::Directive(directive) = &inline.kind
+            && !self
+                .options
+                .directive_handlers
+                .contains_key(&directive.name)
+            && !is_media_directive(directive)
+            && !is_kbd_directive(directive, self.options)
+        {
+            self.diagnostics.push(Diagnostic::new(
+                self.source_map.range(inline.span),
+                DiagnosticSeverity::Warning,
+                W_DIRECTIVE_UNKNOWN,
+                format!("no handler for directive '{}'", directive.name),
+            ));
+        }
+        walk_inline(self, inline);
     }
+}
+
+// A trailing `\n` ends the last line, so `"a\nb\n"` and `"a\nb"` both yield two lines.
+fn split_lines_preserve(text: &str) -> Vec<String> {
     let mut lines = Vec::new();
     let mut start = 0;
     let bytes = text.as_bytes();
@@ -1408,7 +2758,7 @@ fn split_lines_preserve(text: &str) -> Vec<String> {
             start = idx + 1;
         }
     }
-    if start <= text.len() {
+    if start < text.len() {
         let mut line = text[start..].to_string();
         if line.ends_with('\r') {
             line.pop();
@@ -1478,9 +2828,22 @@ fn escape_url_attr(text: &str) -> String {
     escape_attr(&encoded)
 }
 
+fn attr_value<'a>(items: &'a [AttrItem], key: &str) -> Option<&'a str> {
+    items
+        .iter()
+        .find(|item| item.key == key)
+        .map(|item| item.value.raw.as_str())
+}
+
+/// Items become `data-*` attributes, except a valid `dir`, which browsers only
+/// honour as the real attribute when laying out bidirectional text.
 fn data_attrs(items: &[AttrItem]) -> String {
     let mut out = String::new();
     for item in items {
+        if item.key == "dir" && is_text_dir(&item.value.raw) {
+            out.push_str(&format!(" dir=\"{}\"", item.value.raw));
+            continue;
+        }
         out.push_str(&format!(
             " data-{}=\"{}\"",
             escape_attr(&item.key),
@@ -1506,16 +2869,315 @@ fn compose_block_attrs_with_span(
     items: &[AttrItem],
     span: Span,
     source_map: Option<&SourceMap>,
+    id_namespace: Option<&str>,
 ) -> String {
-    let mut out = id_attr(label);
+    let mut out = id_attr(label, id_namespace);
     out.push_str(&span_attr(span, source_map));
     out.push_str(&data_attrs(items));
     out
 }
 
-fn id_attr(label: Option<&Label>) -> String {
+// Sections and boxes turn a valid `lang` item into the HTML `lang` attribute so
+// screen readers switch pronunciation, and a section's `.unnumbered` into
+// `class`; other items stay `data-*`.
+fn compose_wrapper_attrs_with_span(
+    label: Option<&Label>,
+    items: &[AttrItem],
+    span: Span,
+    source_map: Option<&SourceMap>,
+    id_namespace: Option<&str>,
+) -> String {
+    let mut out = id_attr(label, id_namespace);
+    out.push_str(&span_attr(span, source_map));
+    out.push_str(&wrapper_data_attrs(items));
+    out
+}
+
+fn wrapper_data_attrs(items: &[AttrItem]) -> String {
+    let mut out = String::new();
+    for item in items {
+        if item.key == "lang" && is_lang_tag(item.value.raw.trim()) {
+            out.push_str(&format!(" lang=\"{}\"", escape_attr(item.value.raw.trim())));
+        } else if item.key == "class" {
+            out.push_str(&format!(" class=\"{}\"", escape_attr(&item.value.raw)));
+        } else {
+            out.push_str(&data_attrs(std::slice::from_ref(item)));
+        }
+    }
+    out
+}
+
+/// Whether `value` is one of the HTML `dir` values: `rtl`, `ltr`, or `auto`.
+pub fn is_text_dir(value: &str) -> bool {
+    matches!(value, "rtl" | "ltr" | "auto")
+}
+
+/// Loose BCP 47 check: alphabetic primary subtag, then alphanumeric subtags of up to 8 chars.
+fn is_lang_tag(value: &str) -> bool {
+    let mut subtags = value.split('-');
+    let primary_ok = subtags.next().is_some_and(|primary| {
+        (1..=8).contains(&primary.len()) && primary.bytes().all(|b| b.is_ascii_alphabetic())
+    });
+    primary_ok
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+        })
+}
+
+// `.class` tokens become one `class` attribute; other items stay `data-*`.
+fn inline_span_attrs(attrs: &AttrList, id_namespace: Option<&str>) -> String {
+    let mut out = id_attr(attrs.label.as_ref(), id_namespace);
+    let classes: Vec<&str> = attrs
+        .items
+        .iter()
+        .filter(|item| item.key == "class")
+        .map(|item| item.value.raw.as_str())
+        .collect();
+    if !classes.is_empty() {
+        out.push_str(&format!(" class=\"{}\"", escape_attr(&classes.join(" "))));
+    }
+    let others: Vec<AttrItem> = attrs
+        .items
+        .iter()
+        .filter(|item| item.key != "class")
+        .cloned()
+        .collect();
+    out.push_str(&data_attrs(&others));
+    out
+}
+
+/// `::video[..](url)` and `::audio[..](url)` render as media elements unless
+/// a handler is registered under the name.
+fn is_media_directive(directive: &Directive) -> bool {
+    directive.url.is_some() && matches!(directive.name.as_str(), "video" | "audio")
+}
+
+/// `::kbd[keys]` without a destination, when `kbd_directive` is on.
+fn is_kbd_directive(directive: &Directive, options: &HtmlEmitOptions) -> bool {
+    options.kbd_directive && directive.name == "kbd" && directive.url.is_none()
+}
+
+/// Writes the label's plain text as a key combination. Each `+`-separated key
+/// gets its own `<kbd>` inside an outer one; a single key gets just one.
+fn kbd_html(directive: &Directive, span_attr: &str, options: &HtmlEmitOptions) -> String {
+    let text = render_inlines_text(&directive.label);
+    let keys = split_keys(&text);
+    let mut out = String::from("<kbd");
+    out.push_str(&inline_span_attrs(
+        &directive.attrs,
+        options.id_namespace.as_deref(),
+    ));
+    out.push_str(span_attr);
+    out.push('>');
+    if keys.len() < 2 {
+        out.push_str(&escape_text(text.trim()));
+    } else {
+        let keys: Vec<String> = keys
+            .iter()
+            .map(|key| format!("<kbd>{}</kbd>", escape_text(key)))
+            .collect();
+        out.push_str(&keys.join("+"));
+    }
+    out.push_str("</kbd>");
+    out
+}
+
+/// Splits `Ctrl+Shift+P` into keys. A `+` where a key is expected is the plus
+/// key itself, so `Ctrl++` is `Ctrl` and `+`.
+fn split_keys(text: &str) -> Vec<&str> {
+    let mut keys = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let len = if rest.starts_with('+') {
+            1
+        } else {
+            rest.find('+').unwrap_or(rest.len())
+        };
+        keys.push(rest[..len].trim());
+        rest = rest[len..]
+            .strip_prefix('+')
+            .unwrap_or(&rest[len..])
+            .trim_start();
+    }
+    keys
+}
+
+/// Writes `<video controls>` or `<audio controls>` with a single `<source>`.
+/// `type` goes on the source; `poster`, `width` and `height` on a video.
+fn media_html(directive: &Directive, span_attr: &str, options: &HtmlEmitOptions) -> String {
+    let tag = directive.name.as_str();
+    let mut out = format!("<{} controls", tag);
+    let mut source_type = None;
+    let mut rest = AttrList {
+        label: directive.attrs.label.clone(),
+        ..AttrList::default()
+    };
+    for item in &directive.attrs.items {
+        let value = item.value.raw.trim();
+        match item.key.as_str() {
+            "type" => source_type = Some(value),
+            "poster" if tag == "video" => {
+                out.push_str(&format!(" poster=\"{}\"", escape_url_attr(value)));
+            }
+            "width" | "height" if tag == "video" => {
+                out.push_str(&format!(" {}=\"{}\"", item.key, escape_attr(value)));
+            }
+            _ => rest.items.push(item.clone()),
+        }
+    }
+    let label = render_inlines_text(&directive.label);
+    if !label.is_empty() {
+        out.push_str(&format!(" aria-label=\"{}\"", escape_attr(&label)));
+    }
+    out.push_str(&inline_span_attrs(&rest, options.id_namespace.as_deref()));
+    out.push_str(span_attr);
+    out.push_str("><source src=\"");
+    out.push_str(&escape_url_attr(
+        directive.url.as_deref().unwrap_or_default(),
+    ));
+    out.push('"');
+    if let Some(source_type) = source_type {
+        out.push_str(&format!(" type=\"{}\"", escape_attr(source_type)));
+    }
+    out.push_str(&format!(" /></{}>", tag));
+    out
+}
+
+fn render_image(
+    url: &str,
+    title: Option<&str>,
+    alt: &[Inline],
+    attrs: &AttrList,
+    with_align: bool,
+    span_attr: &str,
+    id_namespace: Option<&str>,
+) -> String {
+    let mut out = String::from("<img src=\"");
+    out.push_str(&escape_url_attr(url));
+    out.push_str("\" alt=\"");
+    let alt_text = render_inlines_text(alt);
+    out.push_str(&escape_attr(&alt_text));
+    out.push('"');
+    if alt_text.is_empty() {
+        out.push_str(" aria-hidden=\"true\"");
+    }
+    if let Some(title) = title {
+        out.push_str(" title=\"");
+        out.push_str(&escape_attr(title));
+        out.push('"');
+    }
+    out.push_str(&image_attrs(attrs, with_align, id_namespace));
+    out.push_str(span_attr);
+    out.push_str(" />");
+    out
+}
+
+/// `srcset` and `sizes` become real `<img>` attributes; the rest of the list is
+/// handled like a bracketed span's. `with_align` is false when a figure around
+/// the image takes the `align-*` class instead.
+fn image_attrs(attrs: &AttrList, with_align: bool, id_namespace: Option<&str>) -> String {
+    let mut out = String::new();
+    let mut rest = AttrList {
+        label: attrs.label.clone(),
+        ..AttrList::default()
+    };
+    for item in &attrs.items {
+        match item.key.as_str() {
+            "srcset" | "sizes" => out.push_str(&format!(
+                " {}=\"{}\"",
+                item.key,
+                escape_attr(item.value.raw.trim())
+            )),
+            "align" => {}
+            _ => rest.items.push(item.clone()),
+        }
+    }
+    if with_align && let Some(align) = image_align(attrs) {
+        rest.items.push(AttrItem {
+            key: "class".to_string(),
+            value: AttrValue {
+                raw: align.to_string(),
+                span: Span { start: 0, end: 0 },
+                quoted: false,
+            },
+        });
+    }
+    out.push_str(&inline_span_attrs(&rest, id_namespace));
+    out
+}
+
+/// The `align-*` class for a valid `align` item; other values are ignored.
+fn image_align(attrs: &AttrList) -> Option<&'static str> {
+    let item = attrs.items.iter().find(|item| item.key == "align")?;
+    match item.value.raw.trim() {
+        "left" => Some("align-left"),
+        "right" => Some("align-right"),
+        "center" => Some("align-center"),
+        _ => None,
+    }
+}
+
+/// A paragraph holding only an image with a `caption` item becomes
+/// `<figure>` with the caption, or the alt text for an empty caption, as
+/// `<figcaption>`. The figure takes the paragraph's attributes and the
+/// image's `align-*` class.
+fn captioned_image_html(
+    writer: &mut HtmlWriter,
+    block: &Block,
+    content: &[Inline],
+) -> Option<String> {
+    let [image] = content else {
+        return None;
+    };
+    let InlineKind::Image {
+        url,
+        title,
+        alt,
+        attrs,
+    } = &image.kind
+    else {
+        return None;
+    };
+    let caption = attrs.items.iter().find(|item| item.key == "caption")?;
+    let mut image_attrs = attrs.clone();
+    image_attrs.items.retain(|item| item.key != "caption");
+    let image_html = render_image(
+        url,
+        title.as_deref(),
+        alt,
+        &image_attrs,
+        false,
+        &span_attr(image.span, writer.source_map.as_ref()),
+        writer.options.id_namespace.as_deref(),
+    );
+    let caption_html = match caption.value.raw.trim() {
+        "" => escape_text(&render_inlines_text(alt)),
+        text => escape_text(text),
+    };
+    let mut figure_attrs = compose_block_attrs_with_span(
+        block.attrs.label.as_ref(),
+        &block.attrs.items,
+        block.span,
+        writer.source_map.as_ref(),
+        writer.options.id_namespace.as_deref(),
+    );
+    if let Some(align) = image_align(attrs) {
+        figure_attrs.push_str(&format!(" class=\"{}\"", align));
+    }
+    Some(format!(
+        "<figure{}>{}<figcaption>{}</figcaption></figure>",
+        figure_attrs, image_html, caption_html
+    ))
+}
+
+fn id_attr(label: Option<&Label>, id_namespace: Option<&str>) -> String {
     label
-        .map(|label| format!(" id=\"{}\"", escape_attr(&label.name)))
+        .map(|label| {
+            format!(
+                " id=\"{}\"",
+                escape_attr(&namespaced_id(id_namespace, &label.name))
+            )
+        })
         .unwrap_or_default()
 }
 
@@ -0,0 +1,427 @@
+// Subset of the GitHub gemoji shortcodes (https://github.com/github/gemoji).
+// ASCII-only source; emoji values use Rust \u{...} escapes. Keep sorted by name.
+
+#[cfg(not(feature = "emoji"))]
+pub fn lookup_emoji(_name: &str) -> Option<&'static str> {
+    None
+}
+
+#[cfg(feature = "emoji")]
+pub fn lookup_emoji(name: &str) -> Option<&'static str> {
+    let idx = EMOJI.binary_search_by_key(&name, |(key, _)| *key).ok()?;
+    Some(EMOJI[idx].1)
+}
+
+#[cfg(feature = "emoji")]
+static EMOJI: &[(&str, &str)] = &[
+    ("+1", "\u{1f44d}"),
+    ("-1", "\u{1f44e}"),
+    ("100", "\u{1f4af}"),
+    ("8ball", "\u{1f3b1}"),
+    ("airplane", "\u{2708}\u{fe0f}"),
+    ("alarm_clock", "\u{23f0}"),
+    ("alien", "\u{1f47d}"),
+    ("anchor", "\u{2693}"),
+    ("angel", "\u{1f47c}"),
+    ("angry", "\u{1f620}"),
+    ("apple", "\u{1f34e}"),
+    ("arrow_down", "\u{2b07}\u{fe0f}"),
+    ("arrow_left", "\u{2b05}\u{fe0f}"),
+    ("arrow_right", "\u{27a1}\u{fe0f}"),
+    ("arrow_up", "\u{2b06}\u{fe0f}"),
+    ("arrows_counterclockwise", "\u{1f504}"),
+    ("art", "\u{1f3a8}"),
+    ("astonished", "\u{1f632}"),
+    ("avocado", "\u{1f951}"),
+    ("baby", "\u{1f476}"),
+    ("balloon", "\u{1f388}"),
+    ("ballot_box_with_check", "\u{2611}\u{fe0f}"),
+    ("banana", "\u{1f34c}"),
+    ("bangbang", "\u{203c}\u{fe0f}"),
+    ("bar_chart", "\u{1f4ca}"),
+    ("baseball", "\u{26be}"),
+    ("basketball", "\u{1f3c0}"),
+    ("bath", "\u{1f6c0}"),
+    ("battery", "\u{1f50b}"),
+    ("bear", "\u{1f43b}"),
+    ("bee", "\u{1f41d}"),
+    ("beer", "\u{1f37a}"),
+    ("beers", "\u{1f37b}"),
+    ("bell", "\u{1f514}"),
+    ("bento", "\u{1f371}"),
+    ("bike", "\u{1f6b2}"),
+    ("bikini", "\u{1f459}"),
+    ("bird", "\u{1f426}"),
+    ("birthday", "\u{1f382}"),
+    ("black_circle", "\u{26ab}"),
+    ("black_heart", "\u{1f5a4}"),
+    ("blue_heart", "\u{1f499}"),
+    ("blush", "\u{1f60a}"),
+    ("book", "\u{1f4d6}"),
+    ("bookmark", "\u{1f516}"),
+    ("bookmark_tabs", "\u{1f4d1}"),
+    ("books", "\u{1f4da}"),
+    ("boom", "\u{1f4a5}"),
+    ("bow", "\u{1f647}"),
+    ("boy", "\u{1f466}"),
+    ("brain", "\u{1f9e0}"),
+    ("bread", "\u{1f35e}"),
+    ("broken_heart", "\u{1f494}"),
+    ("bug", "\u{1f41b}"),
+    ("bulb", "\u{1f4a1}"),
+    ("burrito", "\u{1f32f}"),
+    ("bus", "\u{1f68c}"),
+    ("bust_in_silhouette", "\u{1f464}"),
+    ("busts_in_silhouette", "\u{1f465}"),
+    ("cactus", "\u{1f335}"),
+    ("cake", "\u{1f370}"),
+    ("calendar", "\u{1f4c6}"),
+    ("camera", "\u{1f4f7}"),
+    ("candy", "\u{1f36c}"),
+    ("car", "\u{1f697}"),
+    ("carrot", "\u{1f955}"),
+    ("cat", "\u{1f431}"),
+    ("cd", "\u{1f4bf}"),
+    ("chart_with_downwards_trend", "\u{1f4c9}"),
+    ("chart_with_upwards_trend", "\u{1f4c8}"),
+    ("checkered_flag", "\u{1f3c1}"),
+    ("cheese", "\u{1f9c0}"),
+    ("cherries", "\u{1f352}"),
+    ("cherry_blossom", "\u{1f338}"),
+    ("chicken", "\u{1f414}"),
+    ("chocolate_bar", "\u{1f36b}"),
+    ("christmas_tree", "\u{1f384}"),
+    ("clap", "\u{1f44f}"),
+    ("clipboard", "\u{1f4cb}"),
+    ("cloud", "\u{2601}\u{fe0f}"),
+    ("clown_face", "\u{1f921}"),
+    ("cocktail", "\u{1f378}"),
+    ("coffee", "\u{2615}"),
+    ("collision", "\u{1f4a5}"),
+    ("computer", "\u{1f4bb}"),
+    ("confetti_ball", "\u{1f38a}"),
+    ("confounded", "\u{1f616}"),
+    ("confused", "\u{1f615}"),
+    ("construction", "\u{1f6a7}"),
+    ("cookie", "\u{1f36a}"),
+    ("cool", "\u{1f192}"),
+    ("cop", "\u{1f46e}"),
+    ("corn", "\u{1f33d}"),
+    ("cow", "\u{1f42e}"),
+    ("crab", "\u{1f980}"),
+    ("credit_card", "\u{1f4b3}"),
+    ("crescent_moon", "\u{1f319}"),
+    ("crossed_fingers", "\u{1f91e}"),
+    ("crown", "\u{1f451}"),
+    ("cry", "\u{1f622}"),
+    ("curry", "\u{1f35b}"),
+    ("dancer", "\u{1f483}"),
+    ("dart", "\u{1f3af}"),
+    ("deciduous_tree", "\u{1f333}"),
+    ("desktop_computer", "\u{1f5a5}\u{fe0f}"),
+    ("disappointed", "\u{1f61e}"),
+    ("dna", "\u{1f9ec}"),
+    ("dog", "\u{1f436}"),
+    ("dollar", "\u{1f4b5}"),
+    ("dolphin", "\u{1f42c}"),
+    ("door", "\u{1f6aa}"),
+    ("doughnut", "\u{1f369}"),
+    ("dress", "\u{1f457}"),
+    ("droplet", "\u{1f4a7}"),
+    ("e-mail", "\u{1f4e7}"),
+    ("ear", "\u{1f442}"),
+    ("earth_africa", "\u{1f30d}"),
+    ("earth_americas", "\u{1f30e}"),
+    ("earth_asia", "\u{1f30f}"),
+    ("egg", "\u{1f95a}"),
+    ("eggplant", "\u{1f346}"),
+    ("electric_plug", "\u{1f50c}"),
+    ("email", "\u{1f4e7}"),
+    ("envelope", "\u{2709}\u{fe0f}"),
+    ("evergreen_tree", "\u{1f332}"),
+    ("exclamation", "\u{2757}"),
+    ("expressionless", "\u{1f611}"),
+    ("eyeglasses", "\u{1f453}"),
+    ("eyes", "\u{1f440}"),
+    ("facepalm", "\u{1f926}"),
+    ("facepunch", "\u{1f44a}"),
+    ("fallen_leaf", "\u{1f342}"),
+    ("file_folder", "\u{1f4c1}"),
+    ("fire", "\u{1f525}"),
+    ("fish", "\u{1f41f}"),
+    ("fist", "\u{270a}"),
+    ("fist_oncoming", "\u{1f44a}"),
+    ("fist_raised", "\u{270a}"),
+    ("flashlight", "\u{1f526}"),
+    ("flipper", "\u{1f42c}"),
+    ("floppy_disk", "\u{1f4be}"),
+    ("flushed", "\u{1f633}"),
+    ("football", "\u{1f3c8}"),
+    ("footprints", "\u{1f463}"),
+    ("four_leaf_clover", "\u{1f340}"),
+    ("fox_face", "\u{1f98a}"),
+    ("free", "\u{1f193}"),
+    ("fries", "\u{1f35f}"),
+    ("frog", "\u{1f438}"),
+    ("frowning_face", "\u{2639}\u{fe0f}"),
+    ("full_moon", "\u{1f315}"),
+    ("game_die", "\u{1f3b2}"),
+    ("gear", "\u{2699}\u{fe0f}"),
+    ("gem", "\u{1f48e}"),
+    ("ghost", "\u{1f47b}"),
+    ("gift", "\u{1f381}"),
+    ("girl", "\u{1f467}"),
+    ("globe_with_meridians", "\u{1f310}"),
+    ("grapes", "\u{1f347}"),
+    ("green_apple", "\u{1f34f}"),
+    ("green_heart", "\u{1f49a}"),
+    ("grey_question", "\u{2754}"),
+    ("grimacing", "\u{1f62c}"),
+    ("grin", "\u{1f601}"),
+    ("grinning", "\u{1f600}"),
+    ("guitar", "\u{1f3b8}"),
+    ("hamburger", "\u{1f354}"),
+    ("hammer", "\u{1f528}"),
+    ("hand", "\u{270b}"),
+    ("handbag", "\u{1f45c}"),
+    ("handshake", "\u{1f91d}"),
+    ("hankey", "\u{1f4a9}"),
+    ("headphones", "\u{1f3a7}"),
+    ("hear_no_evil", "\u{1f649}"),
+    ("heart", "\u{2764}\u{fe0f}"),
+    ("heart_eyes", "\u{1f60d}"),
+    ("heavy_check_mark", "\u{2714}\u{fe0f}"),
+    ("heavy_exclamation_mark", "\u{2757}"),
+    ("heavy_minus_sign", "\u{2796}"),
+    ("heavy_plus_sign", "\u{2795}"),
+    ("herb", "\u{1f33f}"),
+    ("honeybee", "\u{1f41d}"),
+    ("hospital", "\u{1f3e5}"),
+    ("hot_pepper", "\u{1f336}\u{fe0f}"),
+    ("hotdog", "\u{1f32d}"),
+    ("hourglass", "\u{231b}"),
+    ("house", "\u{1f3e0}"),
+    ("hushed", "\u{1f62f}"),
+    ("icecream", "\u{1f366}"),
+    ("inbox_tray", "\u{1f4e5}"),
+    ("information_desk_person", "\u{1f481}"),
+    ("information_source", "\u{2139}\u{fe0f}"),
+    ("innocent", "\u{1f607}"),
+    ("iphone", "\u{1f4f1}"),
+    ("jeans", "\u{1f456}"),
+    ("joy", "\u{1f602}"),
+    ("key", "\u{1f511}"),
+    ("keyboard", "\u{2328}\u{fe0f}"),
+    ("kimono", "\u{1f458}"),
+    ("kiss", "\u{1f48b}"),
+    ("kissing_heart", "\u{1f618}"),
+    ("koala", "\u{1f428}"),
+    ("large_blue_circle", "\u{1f535}"),
+    ("laughing", "\u{1f606}"),
+    ("lemon", "\u{1f34b}"),
+    ("link", "\u{1f517}"),
+    ("lion", "\u{1f981}"),
+    ("lips", "\u{1f444}"),
+    ("lipstick", "\u{1f484}"),
+    ("lock", "\u{1f512}"),
+    ("lollipop", "\u{1f36d}"),
+    ("mag", "\u{1f50d}"),
+    ("mag_right", "\u{1f50e}"),
+    ("man", "\u{1f468}"),
+    ("mandarin", "\u{1f34a}"),
+    ("maple_leaf", "\u{1f341}"),
+    ("mask", "\u{1f637}"),
+    ("medal_sports", "\u{1f3c5}"),
+    ("memo", "\u{1f4dd}"),
+    ("microphone", "\u{1f3a4}"),
+    ("microscope", "\u{1f52c}"),
+    ("moneybag", "\u{1f4b0}"),
+    ("monkey_face", "\u{1f435}"),
+    ("mountain", "\u{26f0}\u{fe0f}"),
+    ("mouse", "\u{1f42d}"),
+    ("muscle", "\u{1f4aa}"),
+    ("mushroom", "\u{1f344}"),
+    ("musical_note", "\u{1f3b5}"),
+    ("necktie", "\u{1f454}"),
+    ("nerd_face", "\u{1f913}"),
+    ("neutral_face", "\u{1f610}"),
+    ("new", "\u{1f195}"),
+    ("no_bell", "\u{1f515}"),
+    ("no_entry", "\u{26d4}"),
+    ("no_entry_sign", "\u{1f6ab}"),
+    ("no_good", "\u{1f645}"),
+    ("no_mouth", "\u{1f636}"),
+    ("nose", "\u{1f443}"),
+    ("notes", "\u{1f3b6}"),
+    ("nut_and_bolt", "\u{1f529}"),
+    ("ocean", "\u{1f30a}"),
+    ("octopus", "\u{1f419}"),
+    ("office", "\u{1f3e2}"),
+    ("ok", "\u{1f197}"),
+    ("ok_hand", "\u{1f44c}"),
+    ("ok_woman", "\u{1f646}"),
+    ("older_man", "\u{1f474}"),
+    ("older_woman", "\u{1f475}"),
+    ("open_book", "\u{1f4d6}"),
+    ("open_file_folder", "\u{1f4c2}"),
+    ("open_hands", "\u{1f450}"),
+    ("open_mouth", "\u{1f62e}"),
+    ("orange", "\u{1f34a}"),
+    ("orange_heart", "\u{1f9e1}"),
+    ("outbox_tray", "\u{1f4e4}"),
+    ("package", "\u{1f4e6}"),
+    ("palm_tree", "\u{1f334}"),
+    ("panda_face", "\u{1f43c}"),
+    ("paperclip", "\u{1f4ce}"),
+    ("peach", "\u{1f351}"),
+    ("pear", "\u{1f350}"),
+    ("pencil", "\u{1f4dd}"),
+    ("pencil2", "\u{270f}\u{fe0f}"),
+    ("penguin", "\u{1f427}"),
+    ("pensive", "\u{1f614}"),
+    ("phone", "\u{260e}\u{fe0f}"),
+    ("pig", "\u{1f437}"),
+    ("pill", "\u{1f48a}"),
+    ("pineapple", "\u{1f34d}"),
+    ("pizza", "\u{1f355}"),
+    ("point_down", "\u{1f447}"),
+    ("point_left", "\u{1f448}"),
+    ("point_right", "\u{1f449}"),
+    ("point_up", "\u{261d}\u{fe0f}"),
+    ("point_up_2", "\u{1f446}"),
+    ("police_officer", "\u{1f46e}"),
+    ("poop", "\u{1f4a9}"),
+    ("pout", "\u{1f621}"),
+    ("pray", "\u{1f64f}"),
+    ("printer", "\u{1f5a8}\u{fe0f}"),
+    ("punch", "\u{1f44a}"),
+    ("purple_heart", "\u{1f49c}"),
+    ("pushpin", "\u{1f4cc}"),
+    ("question", "\u{2753}"),
+    ("rabbit", "\u{1f430}"),
+    ("rage", "\u{1f621}"),
+    ("rainbow", "\u{1f308}"),
+    ("raised_hand", "\u{270b}"),
+    ("raised_hands", "\u{1f64c}"),
+    ("raising_hand", "\u{1f64b}"),
+    ("ramen", "\u{1f35c}"),
+    ("recycle", "\u{267b}\u{fe0f}"),
+    ("red_car", "\u{1f697}"),
+    ("red_circle", "\u{1f534}"),
+    ("relieved", "\u{1f60c}"),
+    ("repeat", "\u{1f501}"),
+    ("rice", "\u{1f35a}"),
+    ("ring", "\u{1f48d}"),
+    ("robot", "\u{1f916}"),
+    ("rocket", "\u{1f680}"),
+    ("rofl", "\u{1f923}"),
+    ("roll_eyes", "\u{1f644}"),
+    ("rose", "\u{1f339}"),
+    ("rotating_light", "\u{1f6a8}"),
+    ("runner", "\u{1f3c3}"),
+    ("running", "\u{1f3c3}"),
+    ("santa", "\u{1f385}"),
+    ("satellite", "\u{1f4e1}"),
+    ("satisfied", "\u{1f606}"),
+    ("school", "\u{1f3eb}"),
+    ("school_satchel", "\u{1f392}"),
+    ("scissors", "\u{2702}\u{fe0f}"),
+    ("scream", "\u{1f631}"),
+    ("see_no_evil", "\u{1f648}"),
+    ("seedling", "\u{1f331}"),
+    ("shield", "\u{1f6e1}\u{fe0f}"),
+    ("ship", "\u{1f6a2}"),
+    ("shirt", "\u{1f455}"),
+    ("shit", "\u{1f4a9}"),
+    ("shower", "\u{1f6bf}"),
+    ("shrug", "\u{1f937}"),
+    ("skull", "\u{1f480}"),
+    ("sleeping", "\u{1f634}"),
+    ("sleepy", "\u{1f62a}"),
+    ("slightly_smiling_face", "\u{1f642}"),
+    ("smile", "\u{1f604}"),
+    ("smiley", "\u{1f603}"),
+    ("smiley_cat", "\u{1f63a}"),
+    ("smirk", "\u{1f60f}"),
+    ("snail", "\u{1f40c}"),
+    ("snake", "\u{1f40d}"),
+    ("snowflake", "\u{2744}\u{fe0f}"),
+    ("snowman", "\u{26c4}"),
+    ("sob", "\u{1f62d}"),
+    ("soccer", "\u{26bd}"),
+    ("sos", "\u{1f198}"),
+    ("sparkles", "\u{2728}"),
+    ("sparkling_heart", "\u{1f496}"),
+    ("speak_no_evil", "\u{1f64a}"),
+    ("speech_balloon", "\u{1f4ac}"),
+    ("star", "\u{2b50}"),
+    ("star2", "\u{1f31f}"),
+    ("stopwatch", "\u{23f1}\u{fe0f}"),
+    ("strawberry", "\u{1f353}"),
+    ("stuck_out_tongue", "\u{1f61b}"),
+    ("stuck_out_tongue_winking_eye", "\u{1f61c}"),
+    ("sun_with_face", "\u{1f31e}"),
+    ("sunflower", "\u{1f33b}"),
+    ("sunglasses", "\u{1f60e}"),
+    ("sunny", "\u{2600}\u{fe0f}"),
+    ("sushi", "\u{1f363}"),
+    ("sweat", "\u{1f613}"),
+    ("sweat_smile", "\u{1f605}"),
+    ("syringe", "\u{1f489}"),
+    ("taco", "\u{1f32e}"),
+    ("tada", "\u{1f389}"),
+    ("tangerine", "\u{1f34a}"),
+    ("taxi", "\u{1f695}"),
+    ("tea", "\u{1f375}"),
+    ("telephone", "\u{260e}\u{fe0f}"),
+    ("telescope", "\u{1f52d}"),
+    ("tennis", "\u{1f3be}"),
+    ("tent", "\u{26fa}"),
+    ("test_tube", "\u{1f9ea}"),
+    ("thinking", "\u{1f914}"),
+    ("thought_balloon", "\u{1f4ad}"),
+    ("thumbsdown", "\u{1f44e}"),
+    ("thumbsup", "\u{1f44d}"),
+    ("tiger", "\u{1f42f}"),
+    ("tipping_hand_person", "\u{1f481}"),
+    ("tired_face", "\u{1f62b}"),
+    ("toilet", "\u{1f6bd}"),
+    ("tomato", "\u{1f345}"),
+    ("tongue", "\u{1f445}"),
+    ("triangular_flag_on_post", "\u{1f6a9}"),
+    ("triumph", "\u{1f624}"),
+    ("trophy", "\u{1f3c6}"),
+    ("tropical_fish", "\u{1f420}"),
+    ("tshirt", "\u{1f455}"),
+    ("tulip", "\u{1f337}"),
+    ("turtle", "\u{1f422}"),
+    ("tv", "\u{1f4fa}"),
+    ("umbrella", "\u{2614}"),
+    ("unamused", "\u{1f612}"),
+    ("unicorn", "\u{1f984}"),
+    ("unlock", "\u{1f513}"),
+    ("up", "\u{1f199}"),
+    ("upside_down_face", "\u{1f643}"),
+    ("v", "\u{270c}\u{fe0f}"),
+    ("video_game", "\u{1f3ae}"),
+    ("volcano", "\u{1f30b}"),
+    ("walking", "\u{1f6b6}"),
+    ("warning", "\u{26a0}\u{fe0f}"),
+    ("watch", "\u{231a}"),
+    ("watermelon", "\u{1f349}"),
+    ("wave", "\u{1f44b}"),
+    ("weary", "\u{1f629}"),
+    ("whale", "\u{1f433}"),
+    ("white_check_mark", "\u{2705}"),
+    ("white_circle", "\u{26aa}"),
+    ("wine_glass", "\u{1f377}"),
+    ("wink", "\u{1f609}"),
+    ("woman", "\u{1f469}"),
+    ("worried", "\u{1f61f}"),
+    ("wrench", "\u{1f527}"),
+    ("x", "\u{274c}"),
+    ("yellow_heart", "\u{1f49b}"),
+    ("yum", "\u{1f60b}"),
+    ("zap", "\u{26a1}"),
+    ("zzz", "\u{1f4a4}"),
+];
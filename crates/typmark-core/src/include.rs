@@ -0,0 +1,321 @@
+use crate::ast::{AttrList, Block, BlockKind, Inline, InlineKind, Label, LinkDefinition};
+use crate::diagnostic::{Diagnostic, DiagnosticSeverity, E_INCLUDE};
+use crate::parser::{ParseOptions, ParseResult, parse_with_options};
+use crate::source_map::{Range, SourceMap, line_breaks};
+use crate::span::Span;
+use crate::visit::{VisitorMut, walk_blocks_mut};
+
+/// Maximum nesting of `@include` lines; deeper includes are rejected.
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Loads the files named by `@include(path)` lines.
+///
+/// The core parser never touches the filesystem; callers such as the CLI
+/// supply an implementation backed by `std::fs`.
+pub trait IncludeResolver {
+    /// Loads `path` as written in the file named `from` (`None` for the root document).
+    fn load(&self, from: Option<&str>, path: &str) -> Result<IncludedSource, String>;
+}
+
+/// A file returned by an [`IncludeResolver`].
+#[derive(Clone, Debug)]
+pub struct IncludedSource {
+    /// Identifies the file for cycle detection and diagnostics.
+    pub name: String,
+    pub text: String,
+}
+
+/// Result of [`expand_includes`].
+///
+/// Included files are appended to the root text, each starting on a new line,
+/// and every span in the expanded document points into [`IncludeExpansion::source`].
+#[derive(Clone, Debug)]
+pub struct IncludeExpansion {
+    pub source: String,
+    files: Vec<IncludedFile>,
+}
+
+#[derive(Clone, Debug)]
+struct IncludedFile {
+    name: String,
+    start_line: usize,
+    end_line: usize,
+}
+
+impl IncludeExpansion {
+    /// Rewrites the ranges of a diagnostic to lines of the file they fall in
+    /// and records that file's name.
+    pub fn relocate(&self, diagnostic: &mut Diagnostic) {
+        if let Some(name) = self.relocate_range(&mut diagnostic.range) {
+            diagnostic.file = Some(name);
+        }
+        for related in &mut diagnostic.related {
+            if let Some(name) = self.relocate_range(&mut related.range) {
+                related.file = Some(name);
+            }
+        }
+        for fix in &mut diagnostic.fixes {
+            self.relocate_range(&mut fix.range);
+        }
+    }
+
+    fn relocate_range(&self, range: &mut Range) -> Option<String> {
+        let line = range.start.line;
+        let file = self
+            .files
+            .iter()
+            .find(|file| file.start_line <= line && line < file.end_line)?;
+        range.start.line -= file.start_line;
+        range.end.line = range.end.line.saturating_sub(file.start_line);
+        Some(file.name.clone())
+    }
+}
+
+/// Replaces `@include(path)` blocks with the parsed blocks of the files they name.
+///
+/// `root` names the document itself so that it cannot include itself. Failed
+/// loads, cycles, and includes nested deeper than [`MAX_INCLUDE_DEPTH`] are
+/// reported as `E_INCLUDE` and dropped. Pass the returned source to `resolve`
+/// and run [`IncludeExpansion::relocate`] on the resulting diagnostics.
+///
+/// `result` must come from a parse with [`FeatureSet::INCLUDES`] set, and
+/// included files are parsed with `options`, which needs it too for nested includes.
+///
+/// [`FeatureSet::INCLUDES`]: crate::FeatureSet::INCLUDES
+pub fn expand_includes(
+    result: &mut ParseResult,
+    source: &str,
+    root: Option<&str>,
+    resolver: &dyn IncludeResolver,
+    options: &ParseOptions,
+) -> IncludeExpansion {
+    let mut expander = Expander {
+        resolver,
+        options,
+        source: source.to_string(),
+        line_count: line_breaks(source) + 1,
+        files: Vec::new(),
+        stack: root.map(str::to_string).into_iter().collect(),
+        diagnostics: Vec::new(),
+        link_defs: Vec::new(),
+    };
+    expander.expand_blocks(&mut result.document.blocks, root, 0);
+
+    result.diagnostics.append(&mut expander.diagnostics);
+    for (label, def) in expander.link_defs {
+        result.link_defs.entry(label).or_insert(def);
+    }
+    result.source_map = SourceMap::with_encoding(&expander.source, options.position_encoding);
+    IncludeExpansion {
+        source: expander.source,
+        files: expander.files,
+    }
+}
+
+struct Expander<'a> {
+    resolver: &'a dyn IncludeResolver,
+    options: &'a ParseOptions,
+    source: String,
+    /// Lines in `source`, kept up to date as files are appended.
+    line_count: usize,
+    files: Vec<IncludedFile>,
+    stack: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    link_defs: Vec<(String, LinkDefinition)>,
+}
+
+impl Expander<'_> {
+    fn expand_blocks(&mut self, blocks: &mut Vec<Block>, from: Option<&str>, depth: usize) {
+        let mut idx = 0;
+        while idx < blocks.len() {
+            match &mut blocks[idx].kind {
+                BlockKind::Include { path } => {
+                    let path = path.clone();
+                    let included = self.include(&path, blocks[idx].span, from, depth);
+                    let count = included.len();
+                    blocks.splice(idx..=idx, included);
+                    idx += count;
+                    continue;
+                }
+                BlockKind::List(list) => {
+                    for item in &mut list.items {
+                        self.expand_blocks(&mut item.blocks, from, depth);
+                    }
+                }
+                BlockKind::BlockQuote { blocks, .. } => self.expand_blocks(blocks, from, depth),
+                BlockKind::Box(box_block) => {
+                    self.expand_blocks(&mut box_block.blocks, from, depth);
+                }
+                BlockKind::Container { blocks, .. } => self.expand_blocks(blocks, from, depth),
+                _ => {}
+            }
+            idx += 1;
+        }
+    }
+
+    fn include(&mut self, path: &str, span: Span, from: Option<&str>, depth: usize) -> Vec<Block> {
+        if depth >= MAX_INCLUDE_DEPTH {
+            self.error(
+                span,
+                format!("include depth exceeds {}: '{}'", MAX_INCLUDE_DEPTH, path),
+            );
+            return Vec::new();
+        }
+        let loaded = match self.resolver.load(from, path) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                self.error(span, format!("cannot include '{}': {}", path, err));
+                return Vec::new();
+            }
+        };
+        if self.stack.contains(&loaded.name) {
+            let mut chain = self.stack.join(" -> ");
+            chain.push_str(" -> ");
+            chain.push_str(&loaded.name);
+            self.error(span, format!("include cycle: {}", chain));
+            return Vec::new();
+        }
+
+        let text = loaded.text.strip_prefix('\u{feff}').unwrap_or(&loaded.text);
+        if !self.source.ends_with('\n') {
+            self.push_source("\n");
+        }
+        let start_offset = self.source.len();
+        let start_line = self.line_count - 1;
+        self.push_source(text);
+        self.files.push(IncludedFile {
+            name: loaded.name.clone(),
+            start_line,
+            end_line: self.line_count,
+        });
+
+        // The file is parsed on its own, then moved to where it starts in the
+        // combined source.
+        let mut parsed = parse_with_options(text, self.options);
+        walk_blocks_mut(&mut SpanShift(start_offset), &mut parsed.document.blocks);
+        for diag in &mut parsed.diagnostics {
+            shift_lines(&mut diag.range, start_line);
+            for related in &mut diag.related {
+                shift_lines(&mut related.range, start_line);
+            }
+            for fix in &mut diag.fixes {
+                shift_lines(&mut fix.range, start_line);
+            }
+        }
+        self.diagnostics.append(&mut parsed.diagnostics);
+        self.link_defs
+            .extend(parsed.link_defs.into_iter().map(|(label, mut def)| {
+                SpanShift(start_offset).span(&mut def.span);
+                (label, def)
+            }));
+        self.stack.push(loaded.name.clone());
+        self.expand_blocks(&mut parsed.document.blocks, Some(&loaded.name), depth + 1);
+        self.stack.pop();
+        parsed.document.blocks
+    }
+
+    fn push_source(&mut self, text: &str) {
+        // A `\n` right after a lone `\r` turns it into one `\r\n` break.
+        let joins_crlf = self.source.ends_with('\r') && text.starts_with('\n');
+        self.line_count += line_breaks(text) - usize::from(joins_crlf);
+        self.source.push_str(text);
+    }
+
+    fn error(&mut self, span: Span, message: String) {
+        let range =
+            SourceMap::with_encoding(&self.source, self.options.position_encoding).range(span);
+        self.diagnostics.push(Diagnostic::new(
+            range,
+            DiagnosticSeverity::Error,
+            E_INCLUDE,
+            message,
+        ));
+    }
+}
+
+fn shift_lines(range: &mut Range, lines: usize) {
+    range.start.line += lines;
+    range.end.line += lines;
+}
+
+/// Moves every span in an included file's blocks by the byte offset the file
+/// starts at in the combined source.
+struct SpanShift(usize);
+
+impl SpanShift {
+    fn span(&self, span: &mut Span) {
+        span.start += self.0;
+        span.end += self.0;
+    }
+
+    fn label(&self, label: &mut Label) {
+        self.span(&mut label.span);
+    }
+
+    fn attrs(&self, attrs: &mut AttrList) {
+        if let Some(span) = &mut attrs.span {
+            self.span(span);
+        }
+        if let Some(label) = &mut attrs.label {
+            self.label(label);
+        }
+        for item in &mut attrs.items {
+            self.span(&mut item.value.span);
+        }
+    }
+}
+
+impl VisitorMut for SpanShift {
+    fn visit_block_mut(&mut self, block: &mut Block) -> bool {
+        self.span(&mut block.span);
+        self.attrs(&mut block.attrs);
+        match &mut block.kind {
+            BlockKind::Section {
+                label: Some(label), ..
+            } => self.label(label),
+            BlockKind::List(list) => {
+                for item in &mut list.items {
+                    self.span(&mut item.span);
+                }
+            }
+            BlockKind::CodeBlock(code_block) => {
+                self.attrs(&mut code_block.info_attrs);
+                for line_label in &mut code_block.meta.line_labels {
+                    self.label(&mut line_label.label);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn visit_inline_mut(&mut self, inline: &mut Inline) -> bool {
+        self.span(&mut inline.span);
+        match &mut inline.kind {
+            InlineKind::Image { attrs, .. } | InlineKind::Span { attrs, .. } => self.attrs(attrs),
+            InlineKind::Directive(directive) => self.attrs(&mut directive.attrs),
+            InlineKind::LinkRef { meta, .. } | InlineKind::ImageRef { meta, .. } => {
+                self.span(&mut meta.opener_span);
+                self.span(&mut meta.closer_span);
+                for span in [
+                    &mut meta.label_open_span,
+                    &mut meta.label_span,
+                    &mut meta.label_close_span,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    self.span(span);
+                }
+            }
+            InlineKind::Ref { label, .. } => self.label(label),
+            InlineKind::Citation { keys, .. } => {
+                for key in keys {
+                    self.label(key);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+}
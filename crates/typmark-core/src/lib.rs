@@ -1,33 +1,60 @@
 mod ast;
+mod builder;
 mod diagnostic;
 mod emit;
+mod emoji;
 mod entities;
+mod include;
 mod label;
 mod math;
+mod normalize;
+mod outline;
 mod parser;
+mod render_hash;
 mod resolver;
 mod section;
 mod source_map;
 mod span;
+mod text;
+mod visit;
 
 pub use ast::{
-    AttrItem, AttrList, AttrValue, Block, BlockKind, BoxBlock, CodeBlock, CodeMeta, Document,
-    Inline, InlineKind, InlineSeq, Label, LineLabel, LineRange, List, ListItem, NodeId,
-    ResolvedRef,
+    AlertKind, AttrItem, AttrList, AttrValue, Block, BlockKind, BoxBlock, CodeBlock, CodeMeta,
+    Directive, Document, Inline, InlineKind, InlineSeq, Label, LineDiff, LineLabel, LineRange,
+    LineStatus, LinkDefinition, List, ListItem, NodeId, RefCategory, RefNumber, ResolvedRef,
 };
 pub use diagnostic::{
-    Diagnostic, DiagnosticSeverity, E_ATTR_SYNTAX, E_CODE_CONFLICT, E_LABEL_DUP, E_MATH_INLINE_NL,
-    E_REF_BRACKET_NL, E_REF_DEPTH, E_REF_OMIT, E_REF_SELF_TITLE, E_TARGET_ORPHAN,
-    RelatedDiagnostic, W_BOX_STYLE_INVALID, W_CODE_RANGE_OOB, W_REF_MISSING,
+    Diagnostic, DiagnosticSeverity, E_ATTR_SYNTAX, E_CODE_CONFLICT, E_INCLUDE, E_LABEL_DUP,
+    E_MATH_COMPILE, E_MATH_INLINE_NL, E_MATH_PREAMBLE, E_REF_BRACKET_NL, E_REF_DEPTH, E_REF_OMIT,
+    E_REF_SELF_TITLE, E_TARGET_ORPHAN, Fix, RelatedDiagnostic, W_ALERT_UNKNOWN, W_ATTR_UNKNOWN,
+    W_BOX_STYLE_INVALID, W_CITE_MISSING, W_CODE_RANGE_OOB, W_CODE_WRAP_INVALID, W_DIR_INVALID,
+    W_DIRECTIVE_UNKNOWN, W_HEADING_SKIP, W_IMAGE_SRCSET_INVALID, W_LINK_DEF_DUP, W_LINK_DEF_UNUSED,
+    W_REF_CATEGORY, W_REF_MISSING, W_TABLE_RAGGED, W_UNCLOSED_BOX, W_UNCLOSED_FENCE,
+    W_UNCLOSED_MATH,
 };
 pub use emit::{
-    HtmlEmitOptions, emit_html, emit_html_document_sanitized_with_options,
-    emit_html_document_sanitized_with_options_and_source_map, emit_html_document_with_options,
+    DirectiveHandler, HeadingAnchorPosition, HtmlEmitOptions, LineEnding, RawHtmlMode,
+    SanitizeConfig, directive_diagnostics, emit_html, emit_html_document_sanitized_with_config,
+    emit_html_document_sanitized_with_config_and_source_map,
+    emit_html_document_sanitized_with_options,
+    emit_html_document_sanitized_with_options_and_source_map, emit_html_document_to_writer,
+    emit_html_document_to_writer_with_source_map, emit_html_document_with_options,
     emit_html_document_with_options_and_source_map, emit_html_sanitized,
-    emit_html_sanitized_with_options, emit_html_with_options,
+    emit_html_sanitized_with_config, emit_html_sanitized_with_options, emit_html_to_writer,
+    emit_html_with_options, is_text_dir,
 };
-pub use math::add_font_bytes;
-pub use parser::{ParseResult, parse};
-pub use resolver::{ResolveResult, resolve};
-pub use source_map::{Position, Range, SourceMap};
+pub use include::{
+    IncludeExpansion, IncludeResolver, IncludedSource, MAX_INCLUDE_DEPTH, expand_includes,
+};
+pub use math::{MathOutput, MathRenderer, add_font_bytes};
+pub use normalize::normalize_html;
+pub use outline::OutlineEntry;
+pub use parser::{
+    FeatureSet, ParseOptions, ParseResult, SlugWikilinkResolver, WikilinkResolver, parse,
+    parse_with_observer, parse_with_options, parse_with_options_and_observer,
+};
+pub use render_hash::render_hash;
+pub use resolver::{ResolveOptions, ResolveResult, resolve, resolve_with_options};
+pub use source_map::{Position, PositionEncoding, Range, SourceMap};
 pub use span::{Span, SpanError};
+pub use visit::{Visitor, VisitorMut, walk_block, walk_document, walk_document_mut, walk_inline};
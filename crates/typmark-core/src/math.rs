@@ -1,5 +1,9 @@
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use crate::ast::AttrList;
 use lru::LruCache;
 use once_cell::sync::Lazy;
 use typst::diag::{FileError, FileResult};
@@ -10,8 +14,12 @@ use typst::text::{Font, FontBook};
 use typst::utils::LazyHash;
 use typst::{Library, LibraryExt, World};
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::panic::{self, AssertUnwindSafe};
 #[cfg(not(target_arch = "wasm32"))]
 use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
 
 /// The state for a single Typst compilation.
 struct MathWorld {
@@ -140,37 +148,132 @@ fn is_font_file(path: &Path) -> bool {
     matches!(ext, "ttf" | "otf" | "ttc" | "otc")
 }
 
-type CacheKey = (String, bool, Option<String>, Option<String>, Option<String>); // (source, is_display_mode, inline_size, block_size, font)
-type Cache = Mutex<LruCache<CacheKey, String>>;
+/// Everything that changes the compiled SVG or whether compiling gives up.
+/// `max_output_bytes` is left out and checked on every lookup instead.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    source: String,
+    display: bool,
+    inline_size: Option<String>,
+    block_size: Option<String>,
+    font: Option<String>,
+    preamble: Option<String>,
+    timeout: Option<Duration>,
+}
+type Cache = Mutex<LruCache<CacheKey, Result<String, String>>>;
 
 static FONT_SLOT: Lazy<Mutex<FontSlot>> = Lazy::new(|| Mutex::new(load_fonts()));
 static TYPST_LIBRARY: Lazy<LazyHash<Library>> = Lazy::new(|| LazyHash::new(Library::default()));
 static RENDER_CACHE: Lazy<Cache> = Lazy::new(|| Mutex::new(LruCache::new(100.try_into().unwrap())));
+type PreambleCache = Mutex<HashMap<(String, Option<Duration>), Result<(), String>>>;
+static PREAMBLE_CACHE: Lazy<PreambleCache> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Selects the markup emitted for math nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MathOutput {
+    /// Typst-rendered SVG only.
+    #[default]
+    Svg,
+    /// MathML produced by a [`MathRenderer`].
+    /// Falls back to `SvgWithMathMLAnnotation` when no renderer is set or it yields nothing.
+    MathML,
+    /// Typst-rendered SVG wrapped in `<span role="math">` labelled with the Typst source.
+    SvgWithMathMLAnnotation,
+    /// The Typst source in a `data-typst` attribute, left for a client-side renderer.
+    /// Nothing is compiled.
+    Raw,
+}
+
+/// Hook for plugging in a MathML backend.
+pub trait MathRenderer: fmt::Debug + Send + Sync {
+    /// Converts a Typst math snippet to MathML.
+    /// Returns None when the snippet cannot be converted.
+    fn render_mathml(&self, source: &str, display: bool) -> Option<String>;
+}
 
-#[derive(Clone, Debug, Default)]
+/// Default for [`MathSettings::timeout`], overridden by the `math-timeout-ms` setting.
+pub(crate) const DEFAULT_MATH_TIMEOUT: Duration = Duration::from_millis(5000);
+/// Default for [`MathSettings::max_output_bytes`], overridden by the `math-max-bytes` setting.
+pub(crate) const DEFAULT_MATH_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+#[derive(Clone, Debug)]
 pub struct MathSettings {
     pub inline_size: Option<String>,
     pub block_size: Option<String>,
     pub font: Option<String>,
+    /// Typst code (e.g. `#let` definitions) placed before every equation.
+    pub preamble: Option<String>,
+    pub output: MathOutput,
+    pub renderer: Option<Arc<dyn MathRenderer>>,
+    /// Gives up on a compilation that runs longer than this. `None` waits indefinitely.
+    pub timeout: Option<Duration>,
+    /// Largest SVG accepted for one equation, in bytes.
+    pub max_output_bytes: usize,
+}
+
+impl Default for MathSettings {
+    fn default() -> Self {
+        Self {
+            inline_size: None,
+            block_size: None,
+            font: None,
+            preamble: None,
+            output: MathOutput::default(),
+            renderer: None,
+            timeout: Some(DEFAULT_MATH_TIMEOUT),
+            max_output_bytes: DEFAULT_MATH_MAX_OUTPUT_BYTES,
+        }
+    }
 }
 
 /// Renders a Typst math snippet to an SVG string.
 /// Returns Ok(svg_string) on success, or Err(raw_source) on failure.
 pub fn render_math(source: &str, display: bool, settings: &MathSettings) -> Result<String, String> {
-    let cache_key = (
-        source.to_string(),
-        display,
-        settings.inline_size.clone(),
-        settings.block_size.clone(),
-        settings.font.clone(),
-    );
+    compile_math(source, display, settings).map_err(|_| source.to_string())
+}
+
+/// Compiles a math snippet and returns the first Typst error message on failure.
+/// Results are cached, failures and timeouts included, so emitting the snippet
+/// afterwards does not compile it again.
+pub fn check_math(source: &str, display: bool, settings: &MathSettings) -> Result<(), String> {
+    compile_math(source, display, settings).map(|_| ())
+}
 
-    // Check cache first
+fn compile_math(source: &str, display: bool, settings: &MathSettings) -> Result<String, String> {
+    let cache_key = CacheKey {
+        source: source.to_string(),
+        display,
+        inline_size: settings.inline_size.clone(),
+        block_size: settings.block_size.clone(),
+        font: settings.font.clone(),
+        preamble: settings.preamble.clone(),
+        timeout: settings.timeout,
+    };
 
-    if let Some(cached) = RENDER_CACHE.lock().unwrap().get(&cache_key) {
-        return Ok(cached.clone());
+    let cached = RENDER_CACHE.lock().unwrap().get(&cache_key).cloned();
+    let svg = match cached {
+        Some(result) => result?,
+        None => {
+            let result = compile_uncached(source, display, settings);
+            RENDER_CACHE.lock().unwrap().put(cache_key, result.clone());
+            result?
+        }
+    };
+    if svg.len() > settings.max_output_bytes {
+        return Err(format!(
+            "math output is {} bytes, over the limit of {} bytes",
+            svg.len(),
+            settings.max_output_bytes
+        ));
     }
+    Ok(svg)
+}
 
+fn compile_uncached(
+    source: &str,
+    display: bool,
+    settings: &MathSettings,
+) -> Result<String, String> {
     // Create a Typst world for this compilation
 
     let mut preamble = String::from(
@@ -190,11 +293,131 @@ pub fn render_math(source: &str, display: bool, settings: &MathSettings) -> Resu
         preamble.push_str(&format!("#set text(size: {})\n", size));
     };
 
+    if let Some(user_preamble) = &settings.preamble {
+        preamble.push_str(user_preamble);
+        preamble.push('\n');
+    }
+
     let wrapped_source = format!(
         "{}#math.equation(block: {}, $ {} $)",
         preamble, display, source
     );
 
+    let world = math_world(wrapped_source);
+
+    // Compile and render
+
+    run_with_timeout(settings.timeout, move || compile_svg(&world))?
+}
+
+fn compile_svg(world: &MathWorld) -> Result<String, String> {
+    let warned = typst::compile::<PagedDocument>(world);
+    #[cfg(not(target_arch = "wasm32"))]
+    if std::env::var("TYPMARK_DEBUG_MATH").is_ok() {
+        for warning in &warned.warnings {
+            eprintln!(
+                "typst math warning: {:?}: {}",
+                warning.severity, warning.message
+            );
+        }
+    }
+    let document = warned.output.map_err(|errors| {
+        errors
+            .first()
+            .map(|error| error.message.to_string())
+            .unwrap_or_else(|| "invalid math".to_string())
+    })?;
+    let Some(page) = document.pages.first() else {
+        return Err("math produced no output".to_string());
+    };
+    Ok(normalize_svg_ids(&typst_svg::svg(page)))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type MathJob = Box<dyn FnOnce() + Send>;
+
+/// The thread that runs compilations with a timeout, started on first use.
+#[cfg(not(target_arch = "wasm32"))]
+static MATH_WORKER: Lazy<Mutex<Option<Sender<MathJob>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Runs `job` on the math worker thread and stops waiting for it after `timeout`.
+///
+/// Jobs run one at a time. Typst cannot be interrupted, so after a timeout the
+/// worker is abandoned: it finishes the compile in the background, drops the
+/// result and exits, and the next job starts a new worker.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_with_timeout<T, F>(timeout: Option<Duration>, job: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let Some(timeout) = timeout else {
+        return Ok(job());
+    };
+    let mut worker = MATH_WORKER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let (result_sender, result_receiver) = mpsc::channel();
+    let job: MathJob = Box::new(move || {
+        let _ = result_sender.send(job());
+    });
+    let sender = match worker.take() {
+        Some(sender) => sender,
+        None => spawn_math_worker()?,
+    };
+    sender
+        .send(job)
+        .map_err(|_| "cannot start math compilation: worker stopped".to_string())?;
+    match result_receiver.recv_timeout(timeout) {
+        Ok(value) => {
+            *worker = Some(sender);
+            Ok(value)
+        }
+        Err(RecvTimeoutError::Timeout) => Err(timeout_message(timeout)),
+        Err(RecvTimeoutError::Disconnected) => {
+            *worker = Some(sender);
+            Err("math compilation failed".to_string())
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_math_worker() -> Result<Sender<MathJob>, String> {
+    let (sender, receiver) = mpsc::channel::<MathJob>();
+    std::thread::Builder::new()
+        .name("typmark-math".to_string())
+        // Typst recurses deeply on nested content; match the main thread's stack.
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || {
+            while let Ok(job) = receiver.recv() {
+                // A panicking compile drops its result sender, which the caller
+                // sees as a failure; the worker stays up for the next job.
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            }
+        })
+        .map_err(|err| format!("cannot start math compilation: {}", err))?;
+    Ok(sender)
+}
+
+/// wasm32 has no threads to bail out of, so the job runs to completion and
+/// the timeout is not enforced there.
+#[cfg(target_arch = "wasm32")]
+fn run_with_timeout<T, F>(_timeout: Option<Duration>, job: F) -> Result<T, String>
+where
+    F: FnOnce() -> T,
+{
+    Ok(job())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn timeout_message(timeout: Duration) -> String {
+    format!(
+        "math compilation timed out after {} ms",
+        timeout.as_millis()
+    )
+}
+
+fn math_world(text: String) -> MathWorld {
     let main_file_id = FileId::new(None, VirtualPath::new("main.typ"));
 
     let (book, fonts) = {
@@ -202,55 +425,76 @@ pub fn render_math(source: &str, display: bool, settings: &MathSettings) -> Resu
         (slot.book.clone(), slot.fonts.clone())
     };
 
-    let world = MathWorld {
+    MathWorld {
         library: &TYPST_LIBRARY,
         book: LazyHash::new(book),
         fonts,
-        source: Source::new(main_file_id, wrapped_source),
+        source: Source::new(main_file_id, text),
         main_id: main_file_id,
-    };
-
-    // Compile and render
-
-    let result = {
-        let warned = typst::compile::<PagedDocument>(&world);
-        #[cfg(not(target_arch = "wasm32"))]
-        if std::env::var("TYPMARK_DEBUG_MATH").is_ok() {
-            for warning in &warned.warnings {
-                eprintln!(
-                    "typst math warning: {:?}: {}",
-                    warning.severity, warning.message
-                );
-            }
-        }
-        warned.output.ok().and_then(|doc| {
-            if doc.pages.is_empty() {
-                None
-            } else {
-                Some(normalize_svg_ids(&typst_svg::svg(&doc.pages[0])))
-            }
-        })
-    };
-
-    match result {
-        Some(svg) => {
-            RENDER_CACHE.lock().unwrap().put(cache_key, svg.clone());
+    }
+}
 
-            Ok(svg)
+/// Compiles a math preamble on its own, within `settings.timeout`, and returns the
+/// first error message. Results are cached per preamble and timeout, so a
+/// document checks its preamble once.
+pub fn check_math_preamble(preamble: &str, settings: &MathSettings) -> Result<(), String> {
+    let cache_key = (preamble.to_string(), settings.timeout);
+    if let Some(cached) = PREAMBLE_CACHE.lock().unwrap().get(&cache_key) {
+        return cached.clone();
+    }
+    let world = math_world(format!("{}\n", preamble));
+    let result = run_with_timeout(settings.timeout, move || {
+        match typst::compile::<PagedDocument>(&world).output {
+            Ok(_) => Ok(()),
+            Err(errors) => Err(errors
+                .first()
+                .map(|error| error.message.to_string())
+                .unwrap_or_else(|| "invalid math preamble".to_string())),
         }
+    })
+    .and_then(|result| result);
+    PREAMBLE_CACHE
+        .lock()
+        .unwrap()
+        .insert(cache_key, result.clone());
+    result
+}
 
-        None => {
-            if std::env::var("TYPMARK_DEBUG_MATH").is_ok() {
-                let warned = typst::compile::<PagedDocument>(&world);
-                if let Err(errors) = warned.output {
-                    for error in errors {
-                        eprintln!("typst math error: {:?}: {}", error.severity, error.message);
-                    }
+/// Reads the `math-*` document settings.
+pub(crate) fn math_settings_from_attrs(settings: Option<&AttrList>) -> MathSettings {
+    let mut out = MathSettings::default();
+    let Some(settings) = settings else {
+        return out;
+    };
+    let mut preamble = None;
+    for item in &settings.items {
+        match item.key.as_str() {
+            "math-inline-size" => out.inline_size = Some(item.value.raw.clone()),
+            "math-block-size" => out.block_size = Some(item.value.raw.clone()),
+            "math-font" => out.font = Some(item.value.raw.clone()),
+            "math-timeout-ms" => {
+                if let Ok(ms) = item.value.raw.trim().parse::<u64>() {
+                    out.timeout = (ms > 0).then(|| Duration::from_millis(ms));
+                }
+            }
+            "math-max-bytes" => {
+                if let Ok(bytes) = item.value.raw.trim().parse() {
+                    out.max_output_bytes = bytes;
                 }
             }
-            Err(source.to_string())
+            "math-preamble" => preamble = Some(item.value.raw.clone()),
+            _ => {}
         }
     }
+    // A broken preamble is reported once by the resolver and then left out, so
+    // it does not fail every equation. It is checked within the document's own
+    // timeout, which may be set after it.
+    if let Some(preamble) = preamble
+        && check_math_preamble(&preamble, &out).is_ok()
+    {
+        out.preamble = Some(preamble);
+    }
+    out
 }
 
 /// Adds a font from raw bytes to the Typst font book.
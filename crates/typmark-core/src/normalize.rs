@@ -0,0 +1,219 @@
+/// Elements whose content is copied verbatim.
+const RAW_TEXT_ELEMENTS: &[&str] = &["pre", "script", "style", "textarea"];
+
+/// Elements where surrounding whitespace never renders.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "caption",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "link",
+    "main",
+    "meta",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "ul",
+];
+
+enum Token<'a> {
+    Tag { text: &'a str, block: bool },
+    Text(&'a str),
+}
+
+impl Token<'_> {
+    fn is_block_tag(&self) -> bool {
+        matches!(self, Token::Tag { block: true, .. })
+    }
+}
+
+/// Canonicalizes whitespace in emitted HTML for snapshot comparisons.
+///
+/// Whitespace next to a block-level tag is dropped, a tag that follows a
+/// block-level tag or opens a block goes on a new line, and other whitespace runs collapse to one
+/// space. `<pre>`, `<script>`, `<style>` and `<textarea>` elements are kept
+/// verbatim, and line endings become `\n`. Two outputs that normalize to the
+/// same string render the same.
+pub fn normalize_html(html: &str) -> String {
+    let html = html.replace("\r\n", "\n").replace('\r', "\n");
+    let tokens = tokenize(&html);
+    let mut out = String::with_capacity(html.len());
+    let mut last_tag_block: Option<bool> = None;
+    for (idx, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Tag { text, block } => {
+                let opens_block = *block && !text.starts_with("</");
+                if last_tag_block.is_some_and(|last| last || opens_block) {
+                    out.push('\n');
+                }
+                out.push_str(text);
+                last_tag_block = Some(*block);
+            }
+            Token::Text(text) => {
+                let mut text = collapse_whitespace(text);
+                if idx == 0 || tokens[idx - 1].is_block_tag() {
+                    text = text.trim_start().to_string();
+                }
+                if tokens.get(idx + 1).is_none_or(Token::is_block_tag) {
+                    text = text.trim_end().to_string();
+                }
+                if !text.is_empty() {
+                    out.push_str(&text);
+                    last_tag_block = None;
+                }
+            }
+        }
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// Turns every `\n` into `\r\n`, except inside `<pre>`, `<script>`, `<style>`
+/// and `<textarea>` elements. Existing `\r\n` pairs are left alone.
+pub(crate) fn crlf_line_endings(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() + html.len() / 16);
+    for token in tokenize(html) {
+        let text = match token {
+            Token::Tag { text, .. }
+                if !text.starts_with("</")
+                    && RAW_TEXT_ELEMENTS.contains(&tag_name(text).as_str()) =>
+            {
+                out.push_str(text);
+                continue;
+            }
+            Token::Tag { text, .. } | Token::Text(text) => text,
+        };
+        for ch in text.chars() {
+            if ch == '\n' && !out.ends_with('\r') {
+                out.push('\r');
+            }
+            out.push(ch);
+        }
+    }
+    out
+}
+
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = find_tag_start(rest) else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+            rest = &rest[start..];
+        }
+        let end = tag_end(rest);
+        let name = tag_name(&rest[..end]);
+        let block = BLOCK_ELEMENTS.contains(&name.as_str())
+            || rest[..end].to_ascii_lowercase().starts_with("<!doctype");
+        let mut len = end;
+        if !rest.starts_with("</") && RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            let close = format!("</{}>", name);
+            len = rest[end..]
+                .to_ascii_lowercase()
+                .find(&close)
+                .map_or(rest.len(), |idx| end + idx + close.len());
+        }
+        tokens.push(Token::Tag {
+            text: &rest[..len],
+            block,
+        });
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+/// Finds the next `<` that opens a tag, comment or doctype rather than plain text.
+fn find_tag_start(html: &str) -> Option<usize> {
+    html.match_indices('<').map(|(idx, _)| idx).find(|&idx| {
+        html[idx + 1..]
+            .chars()
+            .next()
+            .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '/' || ch == '!')
+    })
+}
+
+/// Length of the tag at the start of `html`, skipping `>` inside quoted attribute values.
+fn tag_end(html: &str) -> usize {
+    if html.starts_with("<!--") {
+        return html.find("-->").map_or(html.len(), |idx| idx + 3);
+    }
+    let mut quote = None;
+    for (idx, ch) in html.char_indices().skip(1) {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) => {}
+            None if ch == '"' || ch == '\'' => quote = Some(ch),
+            None if ch == '>' => return idx + 1,
+            None => {}
+        }
+    }
+    html.len()
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('<')
+        .trim_start_matches('/')
+        .chars()
+        .take_while(|ch| ch.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_space = false;
+    for ch in text.chars() {
+        if ch.is_ascii_whitespace() {
+            if !last_space {
+                out.push(' ');
+            }
+            last_space = true;
+        } else {
+            out.push(ch);
+            last_space = false;
+        }
+    }
+    out
+}
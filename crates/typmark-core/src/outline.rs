@@ -0,0 +1,46 @@
+use crate::ast::BlockKind;
+use crate::emit::HtmlEmitOptions;
+use crate::parser::ParseResult;
+use crate::span::Span;
+use crate::text::{namespaced_id, render_inlines_text, shifted_heading_level};
+
+/// One heading of a document outline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutlineEntry {
+    /// Level as emitted, after `heading_offset`.
+    pub level: u8,
+    /// Plain text of the title, with line breaks as spaces.
+    pub text: String,
+    /// The `id` the heading is emitted with, if it has a label.
+    pub id: Option<String>,
+    /// The heading itself, including a setext underline.
+    pub span: Span,
+}
+
+impl ParseResult {
+    /// The top-level headings, which `resolve` nests into the section tree, in
+    /// document order. Headings inside lists, quotes and boxes are left out, as
+    /// they are from section numbering. Levels and ids follow `options` the way
+    /// emitting does.
+    pub fn outline(&self, options: &HtmlEmitOptions) -> Vec<OutlineEntry> {
+        self.document
+            .blocks
+            .iter()
+            .filter_map(|block| {
+                let BlockKind::Heading { level, title } = &block.kind else {
+                    return None;
+                };
+                Some(OutlineEntry {
+                    level: shifted_heading_level(*level, options.heading_offset),
+                    text: render_inlines_text(title).trim().replace('\n', " "),
+                    id: block
+                        .attrs
+                        .label
+                        .as_ref()
+                        .map(|label| namespaced_id(options.id_namespace.as_deref(), &label.name)),
+                    span: block.span,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,17 +1,24 @@
 use crate::ast::{
-    AttrItem, AttrList, AttrValue, Block, BlockKind, BoxBlock, CodeBlock, CodeBlockKind, CodeMeta,
-    Document, Inline, InlineKind, InlineSeq, Label, LineLabel, LineRange, LinkDefinition,
-    LinkRefMeta, List, ListItem, Table, TableAlign,
+    AlertKind, AttrItem, AttrList, AttrValue, Block, BlockKind, BoxBlock, CodeBlock, CodeBlockKind,
+    CodeMeta, Directive, Document, Inline, InlineKind, InlineSeq, Label, LineLabel, LineRange,
+    LinkDefinition, LinkRefMeta, List, ListItem, RefCategory, Table, TableAlign,
 };
 use crate::diagnostic::{
     Diagnostic, DiagnosticSeverity, E_ATTR_SYNTAX, E_CODE_CONFLICT, E_MATH_INLINE_NL,
-    E_REF_BRACKET_NL, E_TARGET_ORPHAN, W_BOX_STYLE_INVALID, W_CODE_RANGE_OOB,
+    E_REF_BRACKET_NL, E_TARGET_ORPHAN, Fix, RelatedDiagnostic, W_ALERT_UNKNOWN, W_ATTR_UNKNOWN,
+    W_BOX_STYLE_INVALID, W_CODE_RANGE_OOB, W_CODE_WRAP_INVALID, W_DIR_INVALID,
+    W_IMAGE_SRCSET_INVALID, W_LINK_DEF_DUP, W_TABLE_RAGGED, W_UNCLOSED_BOX, W_UNCLOSED_FENCE,
+    W_UNCLOSED_MATH,
 };
+use crate::emit::is_text_dir;
+use crate::emoji::lookup_emoji;
 use crate::entities::lookup_named_entity;
 use crate::label::{is_label_escape, normalize_link_label};
-use crate::source_map::SourceMap;
+use crate::source_map::{PositionEncoding, SourceMap};
 use crate::span::Span;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
 pub struct ParseResult {
     pub document: Document,
@@ -20,11 +27,177 @@ pub struct ParseResult {
     pub link_defs: HashMap<String, LinkDefinition>,
 }
 
+/// Syntax extensions that can be switched off; disabled syntax parses as literal text.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FeatureSet(u16);
+
+impl FeatureSet {
+    /// Raw HTML blocks and inline HTML.
+    pub const RAW_HTML: Self = Self(1 << 0);
+    /// Bare URL and email autolinks. `<url>` autolinks are always on.
+    pub const AUTOLINKS: Self = Self(1 << 1);
+    pub const IMAGES: Self = Self(1 << 2);
+    pub const TABLES: Self = Self(1 << 3);
+    pub const STRIKETHROUGH: Self = Self(1 << 4);
+    /// Inline `$...$` and block `$$` math.
+    pub const MATH: Self = Self(1 << 5);
+    /// `::: box` containers.
+    pub const BOXES: Self = Self(1 << 6);
+    /// `::name[label]{attrs}` inline directives.
+    pub const DIRECTIVES: Self = Self(1 << 7);
+    /// `@include(path)` lines. Opt-in: not part of [`FeatureSet::all`], since
+    /// only callers that run [`crate::expand_includes`] can use the blocks.
+    pub const INCLUDES: Self = Self(1 << 8);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Every feature except the opt-in [`FeatureSet::INCLUDES`].
+    pub const fn all() -> Self {
+        Self(
+            Self::RAW_HTML.0
+                | Self::AUTOLINKS.0
+                | Self::IMAGES.0
+                | Self::TABLES.0
+                | Self::STRIKETHROUGH.0
+                | Self::MATH.0
+                | Self::BOXES.0
+                | Self::DIRECTIVES.0,
+        )
+    }
+
+    /// The CommonMark subset: raw HTML and images.
+    pub const fn commonmark() -> Self {
+        Self(Self::RAW_HTML.0 | Self::IMAGES.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl Default for FeatureSet {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl std::ops::BitOr for FeatureSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    pub features: FeatureSet,
+    /// Reports table rows whose cell count differs from the column count as
+    /// errors instead of warnings.
+    pub strict_tables: bool,
+    /// Warns with `W_LINK_DEF_DUP` when a link reference label is defined more
+    /// than once. The first definition still wins.
+    pub warn_duplicate_link_defs: bool,
+    /// Turns `[[Page Name]]` and `[[Page Name|display]]` into wikilinks whose
+    /// href comes from this resolver. Unset keeps `[[...]]` as ordinary brackets.
+    pub wikilink_resolver: Option<Arc<dyn WikilinkResolver>>,
+    /// Parses plain CommonMark: `features` is replaced by [`FeatureSet::commonmark`],
+    /// and target and settings lines, `@refs`, citations, `[text]{...}` spans,
+    /// image attribute lists, `\!{...}!` raw text, fence attribute lists, alerts,
+    /// task list items, emoji shortcodes and wikilinks are read as plain text.
+    pub strict_commonmark: bool,
+    /// Unit for the columns of the returned source map and of diagnostic ranges.
+    pub position_encoding: PositionEncoding,
+    /// Fewest backticks or tildes that open a code fence; a closing fence needs
+    /// at least as many as its opening fence. Shorter runs are read as text.
+    /// Values below 3 count as 3, and strict CommonMark always uses 3.
+    pub min_fence_len: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            features: FeatureSet::default(),
+            strict_tables: false,
+            warn_duplicate_link_defs: false,
+            wikilink_resolver: None,
+            strict_commonmark: false,
+            position_encoding: PositionEncoding::default(),
+            min_fence_len: 3,
+        }
+    }
+}
+
+/// Hook for turning a wikilink target into a link destination.
+pub trait WikilinkResolver: fmt::Debug + Send + Sync {
+    fn resolve_wikilink(&self, target: &str) -> String;
+}
+
+/// Lowercases the target and replaces each run of whitespace with `-`,
+/// so `[[Page Name]]` links to `page-name`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SlugWikilinkResolver;
+
+impl WikilinkResolver for SlugWikilinkResolver {
+    fn resolve_wikilink(&self, target: &str) -> String {
+        target
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join("-")
+            .to_lowercase()
+    }
+}
+
 pub fn parse(source: &str) -> ParseResult {
-    let mut prepass = Parser::new(source);
+    parse_with_options(source, &ParseOptions::default())
+}
+
+pub fn parse_with_options(source: &str, options: &ParseOptions) -> ParseResult {
+    parse_with_options_and_observer(source, options, &mut |_| {})
+}
+
+/// Like [`parse`], but calls `observer` with each diagnostic as soon as it is
+/// reported, so long-running tools can show them before parsing finishes.
+/// The returned `diagnostics` hold the same diagnostics in the same order.
+pub fn parse_with_observer(source: &str, observer: &mut dyn FnMut(&Diagnostic)) -> ParseResult {
+    parse_with_options_and_observer(source, &ParseOptions::default(), observer)
+}
+
+pub fn parse_with_options_and_observer(
+    source: &str,
+    options: &ParseOptions,
+    observer: &mut dyn FnMut(&Diagnostic),
+) -> ParseResult {
+    let mut prepass = Parser::new(source, options);
+    if options.warn_duplicate_link_defs {
+        prepass.link_def_spans = Some(HashMap::new());
+    }
     let _ = prepass.parse_document_with_mode(false);
-    let mut parser = Parser::new(source);
+    let mut parser = Parser::new(source, options);
     parser.link_defs = prepass.link_defs;
+    // Definitions are collected in the prepass only, so its duplicate warnings
+    // are the only ones kept; the main pass reports everything else again.
+    for diag in prepass.diagnostics {
+        if diag.code == W_LINK_DEF_DUP {
+            observer(&diag);
+            parser.diagnostics.push(diag);
+        }
+    }
+    parser.observer = Some(observer);
     let document = parser.parse_document();
     ParseResult {
         document,
@@ -34,12 +207,45 @@ pub fn parse(source: &str) -> ParseResult {
     }
 }
 
-struct Parser {
+struct Parser<'a> {
     source: String,
     lines: Vec<Line>,
     diagnostics: Vec<Diagnostic>,
+    /// Called with each diagnostic as it is reported; unset on the prepass,
+    /// whose diagnostics are mostly dropped.
+    observer: Option<&'a mut dyn FnMut(&Diagnostic)>,
     source_map: SourceMap,
     link_defs: HashMap<String, LinkDefinition>,
+    /// Where each link definition label was first defined; set on the prepass
+    /// when duplicates are reported.
+    link_def_spans: Option<HashMap<String, Span>>,
+    features: FeatureSet,
+    strict_tables: bool,
+    wikilink_resolver: Option<Arc<dyn WikilinkResolver>>,
+    strict: bool,
+    min_fence_len: usize,
+    /// How many `parse_blocks` calls are on the stack.
+    block_depth: usize,
+    /// How many `parse_inline_range` calls are on the stack, through
+    /// reference brackets and directive labels.
+    inline_range_depth: usize,
+}
+
+/// Quotes, lists and boxes nested deeper than this are read as plain text, so
+/// adversarial input cannot exhaust the stack in the recursive parser, resolver
+/// and emitter.
+const MAX_BLOCK_DEPTH: usize = 32;
+
+/// Emphasis, links and spans that would nest deeper than this keep their
+/// delimiters as text, for the same reason, and reference brackets and
+/// directive labels this deep are read as plain text.
+const MAX_INLINE_DEPTH: usize = 32;
+
+struct FenceInfo {
+    lang: Option<String>,
+    attrs: AttrList,
+    no_highlight: bool,
+    soft_wrap: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -70,16 +276,36 @@ struct BracketEntry {
     active: bool,
 }
 
-impl Parser {
-    fn new(source: &str) -> Self {
-        let source_map = SourceMap::new(source);
+impl Parser<'_> {
+    fn new(source: &str, options: &ParseOptions) -> Self {
+        let source_map = SourceMap::with_encoding(source, options.position_encoding);
         let lines = split_lines(source);
         Self {
             source: source.to_string(),
             lines,
             diagnostics: Vec::new(),
+            observer: None,
             source_map,
             link_defs: HashMap::new(),
+            link_def_spans: None,
+            features: if options.strict_commonmark {
+                FeatureSet::commonmark()
+            } else {
+                options.features
+            },
+            strict_tables: options.strict_tables,
+            wikilink_resolver: options
+                .wikilink_resolver
+                .clone()
+                .filter(|_| !options.strict_commonmark),
+            strict: options.strict_commonmark,
+            min_fence_len: if options.strict_commonmark {
+                3
+            } else {
+                options.min_fence_len.max(3)
+            },
+            block_depth: 0,
+            inline_range_depth: 0,
         }
     }
 
@@ -116,13 +342,22 @@ impl Parser {
             Some(attrs) => attrs,
             None => return (None, 0),
         };
-        if attrs.label.is_some() {
+        // A label or a class means the line targets the block below it.
+        if attrs.label.is_some() || attrs.items.iter().any(|item| item.key == "class") {
             return (None, 0);
         }
         (Some(attrs), idx + 1)
     }
 
     fn parse_blocks(&mut self, lines: &[Line], parse_inlines: bool) -> Vec<Block> {
+        self.block_depth += 1;
+        let blocks = self.parse_blocks_at_depth(lines, parse_inlines);
+        self.block_depth -= 1;
+        blocks
+    }
+
+    fn parse_blocks_at_depth(&mut self, lines: &[Line], parse_inlines: bool) -> Vec<Block> {
+        let nesting_allowed = self.block_depth <= MAX_BLOCK_DEPTH;
         let mut blocks = Vec::new();
         let mut i = 0;
         // Target-line attributes are scoped to the current container only.
@@ -175,7 +410,9 @@ impl Parser {
                 continue;
             }
 
-            if let Some((block, next)) = self.parse_box_block(lines, i, parse_inlines) {
+            if nesting_allowed
+                && let Some((block, next)) = self.parse_container_block(lines, i, parse_inlines)
+            {
                 let mut block = block;
                 self.finalize_block(&mut block, &mut pending_attrs);
                 blocks.push(block);
@@ -191,6 +428,14 @@ impl Parser {
                 continue;
             }
 
+            if let Some((block, next)) = self.parse_include(lines, i) {
+                let mut block = block;
+                self.finalize_block(&mut block, &mut pending_attrs);
+                blocks.push(block);
+                i = next;
+                continue;
+            }
+
             if let Some((block, next)) = self.parse_thematic_break(lines, i) {
                 let mut block = block;
                 self.finalize_block(&mut block, &mut pending_attrs);
@@ -199,7 +444,9 @@ impl Parser {
                 continue;
             }
 
-            if let Some((block, next)) = self.parse_block_quote(lines, i, parse_inlines) {
+            if nesting_allowed
+                && let Some((block, next)) = self.parse_block_quote(lines, i, parse_inlines)
+            {
                 let mut block = block;
                 self.finalize_block(&mut block, &mut pending_attrs);
                 blocks.push(block);
@@ -207,7 +454,8 @@ impl Parser {
                 continue;
             }
 
-            if let Some((block, next)) = self.parse_list(lines, i, parse_inlines) {
+            if nesting_allowed && let Some((block, next)) = self.parse_list(lines, i, parse_inlines)
+            {
                 let mut block = block;
                 self.finalize_block(&mut block, &mut pending_attrs);
                 blocks.push(block);
@@ -278,9 +526,47 @@ impl Parser {
 
     fn finalize_block(&mut self, block: &mut Block, pending: &mut Option<AttrList>) {
         self.apply_pending_attrs(block, pending);
+        match block.kind {
+            BlockKind::Table(_) => {}
+            BlockKind::Heading { .. } => {
+                self.reject_block_classes(&mut block.attrs, &["unnumbered"])
+            }
+            _ => self.reject_block_classes(&mut block.attrs, &[]),
+        }
         if let BlockKind::Box(_) = block.kind {
             self.validate_box_styles(&block.attrs);
         }
+        self.validate_attr_keys(&block.kind, &block.attrs);
+        self.validate_dir(&block.attrs);
+        if let BlockKind::CodeBlock(code_block) = &block.kind {
+            self.validate_attr_keys(&block.kind, &code_block.info_attrs);
+            self.validate_dir(&code_block.info_attrs);
+        }
+    }
+
+    /// Only tables take `.class` items from a target line, and headings take
+    /// `.unnumbered`; elsewhere they stay the syntax error they were before.
+    fn reject_block_classes(&mut self, attrs: &mut AttrList, allowed: &[&str]) {
+        let mut spans = Vec::new();
+        attrs.items.retain(|item| {
+            if item.key == "class" && !allowed.contains(&item.value.raw.as_str()) {
+                spans.push(item.value.span);
+                false
+            } else {
+                true
+            }
+        });
+        for span in spans {
+            self.push_diag(
+                Span {
+                    start: span.start - 1,
+                    end: span.end,
+                },
+                DiagnosticSeverity::Error,
+                E_ATTR_SYNTAX,
+                "invalid attribute item",
+            );
+        }
     }
 
     fn parse_heading(
@@ -317,6 +603,9 @@ impl Parser {
         start: usize,
         parse_inlines: bool,
     ) -> Option<(Block, usize)> {
+        if !self.features.contains(FeatureSet::TABLES) {
+            return None;
+        }
         let line = lines.get(start)?;
         let (header_offset, header_text) = table_line_view(&line.text)?;
         let (header_cells, header_has_pipe) = split_table_cells(header_text, header_offset);
@@ -331,6 +620,7 @@ impl Parser {
             return None;
         }
 
+        self.check_table_row(line, header_cells.len(), aligns.len());
         let headers =
             parse_table_cells(self, line.start, &header_cells, aligns.len(), parse_inlines);
 
@@ -349,6 +639,7 @@ impl Parser {
             if !row_has_pipe {
                 break;
             }
+            self.check_table_row(row_line, row_cells.len(), aligns.len());
             let row = parse_table_cells(
                 self,
                 row_line.start,
@@ -378,6 +669,57 @@ impl Parser {
         ))
     }
 
+    fn check_table_row(&mut self, line: &Line, cells: usize, columns: usize) {
+        if cells == columns {
+            return;
+        }
+        let severity = if self.strict_tables {
+            DiagnosticSeverity::Error
+        } else {
+            DiagnosticSeverity::Warning
+        };
+        let span = Span {
+            start: line.start,
+            end: line.end,
+        };
+        self.push_diag(
+            span,
+            severity,
+            W_TABLE_RAGGED,
+            &format!("table row has {} cells, expected {}", cells, columns),
+        );
+    }
+
+    fn parse_include(&self, lines: &[Line], start: usize) -> Option<(Block, usize)> {
+        if !self.features.contains(FeatureSet::INCLUDES) {
+            return None;
+        }
+        let line = &lines[start];
+        let path = line
+            .text
+            .trim()
+            .strip_prefix("@include(")?
+            .strip_suffix(')')?
+            .trim();
+        if path.is_empty() || path.contains(['(', ')']) {
+            return None;
+        }
+        let span = Span {
+            start: line.start,
+            end: line.end,
+        };
+        Some((
+            Block {
+                span,
+                attrs: AttrList::default(),
+                kind: BlockKind::Include {
+                    path: path.to_string(),
+                },
+            },
+            start + 1,
+        ))
+    }
+
     fn parse_thematic_break(&self, lines: &[Line], start: usize) -> Option<(Block, usize)> {
         let line = &lines[start];
         if !is_thematic_break_line(&line.text) {
@@ -413,21 +755,22 @@ impl Parser {
             if line.text.trim().is_empty() {
                 break;
             }
-            if let Some(kind) = match_html_block_start(&line.text) {
-                if !matches!(kind, HtmlBlockKind::Type7) {
-                    break;
-                }
+            let interrupts = if let Some(kind) = self.match_html_block_start(&line.text) {
+                !matches!(kind, HtmlBlockKind::Type7)
             } else if let Some(marker) = parse_list_marker(&line.text) {
-                if !marker.empty && (!marker.ordered || marker.start == Some(1)) {
-                    break;
-                }
-            } else if self.is_block_start(line) {
+                !marker.empty && (!marker.ordered || marker.start == Some(1))
+            } else {
+                self.is_block_start(line)
+            };
+            // A block start reaches the first line only when its parser declined
+            // it, as quotes and lists do past `MAX_BLOCK_DEPTH`; it is text then.
+            if interrupts && i > start {
                 break;
             }
             if let Some((label, definition, next)) = parse_link_reference_definition_lines(lines, i)
                 && content_lines.is_empty()
             {
-                self.link_defs.entry(label).or_insert(definition);
+                self.add_link_def(label, definition);
                 i = next;
                 continue;
             }
@@ -495,22 +838,46 @@ impl Parser {
 
     fn parse_code_block(&mut self, lines: &[Line], start: usize) -> Option<(Block, usize)> {
         let line = &lines[start];
-        let (indent_len, fence_len, fence_char, info) = parse_fence_open(&line.text)?;
-        let (lang, info_attrs) = self.parse_fence_info(line, fence_len, info);
+        let (indent_len, fence_len, fence_char, info) =
+            parse_fence_open(&line.text, self.min_fence_len)?;
+        let FenceInfo {
+            lang,
+            attrs: info_attrs,
+            no_highlight,
+            soft_wrap,
+        } = self.parse_fence_info(line, fence_len, info);
 
         let mut code_lines: Vec<String> = Vec::new();
         let mut i = start + 1;
+        let mut closed = false;
         while i < lines.len() {
             let candidate = &lines[i];
             if is_fence_close(&candidate.text, fence_len, fence_char) {
+                closed = true;
+                i += 1;
+                break;
+            }
+            // The empty remainder after a final newline is not a code line.
+            if candidate.text.is_empty() && !candidate.has_newline && i + 1 == lines.len() {
                 i += 1;
                 break;
             }
-            let text = strip_leading_spaces(&candidate.text, indent_len);
-            code_lines.push(text.to_string());
+            code_lines.push(strip_indent_columns(&candidate.text, indent_len));
             i += 1;
         }
-        let text = code_lines.join("\n");
+        if !closed {
+            let fence_start = line.start + indent_len;
+            self.warn_unclosed(
+                lines,
+                Span {
+                    start: fence_start,
+                    end: fence_start + fence_len,
+                },
+                W_UNCLOSED_FENCE,
+                "code fence is not closed",
+            );
+        }
+        let text = join_code_lines(&code_lines);
         let meta = self.parse_code_meta(&info_attrs, &text, line.start, line.end);
         let mut block_attrs = AttrList::default();
         if let Some(label) = info_attrs.label.clone() {
@@ -535,6 +902,9 @@ impl Parser {
                     info_attrs,
                     meta,
                     text,
+                    no_highlight,
+                    soft_wrap,
+                    output: None,
                 }),
             },
             i,
@@ -571,7 +941,7 @@ impl Parser {
             i += 1;
         }
 
-        let text = code_lines.join("\n");
+        let text = join_code_lines(&code_lines);
         let span = Span {
             start: line.start,
             end: lines[last_line_idx].end,
@@ -592,6 +962,9 @@ impl Parser {
                     info_attrs: AttrList::default(),
                     meta,
                     text,
+                    no_highlight: false,
+                    soft_wrap: false,
+                    output: None,
                 }),
             },
             i,
@@ -599,6 +972,9 @@ impl Parser {
     }
 
     fn parse_math_block(&mut self, lines: &[Line], start: usize) -> Option<(Block, usize)> {
+        if !self.features.contains(FeatureSet::MATH) {
+            return None;
+        }
         let line = &lines[start];
         let trimmed = line.text.trim();
         if !trimmed.starts_with("$$") {
@@ -623,15 +999,29 @@ impl Parser {
         }
         let mut i = start + 1;
         let mut body_lines = Vec::new();
+        let mut closed = false;
         while i < lines.len() {
             let candidate = &lines[i];
             if candidate.text.trim() == "$$" {
+                closed = true;
                 i += 1;
                 break;
             }
             body_lines.push(candidate.text.clone());
             i += 1;
         }
+        if !closed {
+            let open_start = line.start + (line.text.len() - line.text.trim_start().len());
+            self.warn_unclosed(
+                lines,
+                Span {
+                    start: open_start,
+                    end: open_start + 2,
+                },
+                W_UNCLOSED_MATH,
+                "math block is not closed",
+            );
+        }
         let typst_src = body_lines.join("\n");
         let span = Span {
             start: line.start,
@@ -651,32 +1041,37 @@ impl Parser {
         ))
     }
 
-    fn parse_box_block(
+    /// Parses `:::name [title] {attrs}` up to the matching `:::` fence. `box` is
+    /// the built-in box; any other name becomes a generic container.
+    fn parse_container_block(
         &mut self,
         lines: &[Line],
         start: usize,
         parse_inlines: bool,
     ) -> Option<(Block, usize)> {
         let line = &lines[start];
-        if !line.text.starts_with(":::") {
+        if !self.features.contains(FeatureSet::BOXES) {
             return None;
         }
-        let fence_len = line.text.chars().take_while(|c| *c == ':').count();
-        if fence_len < 3 {
-            return None;
-        }
-        let rest = line.text[fence_len..].trim_start();
-        if !rest.starts_with("box") {
-            return None;
+        let (fence_len, name, rest) = parse_container_open(&line.text)?;
+        let name = name.to_string();
+        // `rest` and every slice below are suffixes of the line, so offsets
+        // come from their lengths.
+        let rest = rest.trim_start();
+        let offset_of = |text: &str| line.start + line.text.len() - text.len();
+        let mut title_text = rest.trim_end();
+        let mut attrs = AttrList::default();
+        if title_text.ends_with('}')
+            && let Some(open_idx) = title_text.rfind('{')
+        {
+            let base_offset = offset_of(&rest[open_idx..]);
+            attrs = self.parse_attr_list_text(&title_text[open_idx..], base_offset);
+            title_text = title_text[..open_idx].trim_end();
         }
-        let title_text = rest.strip_prefix("box").unwrap_or("").trim_start();
         let title = if title_text.is_empty() {
             None
         } else if parse_inlines {
-            Some(self.parse_inline(
-                title_text,
-                line.start + (line.text.len() - title_text.len()),
-            ))
+            Some(self.parse_inline(title_text, offset_of(rest)))
         } else {
             None
         };
@@ -687,7 +1082,9 @@ impl Parser {
         while i < lines.len() {
             let candidate = &lines[i];
             let trimmed = candidate.text.trim();
-            if let Some((_, inner_fence_len, fence_char, _)) = parse_fence_open(&candidate.text) {
+            if let Some((_, inner_fence_len, fence_char, _)) =
+                parse_fence_open(&candidate.text, self.min_fence_len)
+            {
                 inner_lines.push(candidate.clone());
                 i += 1;
                 while i < lines.len() {
@@ -707,7 +1104,7 @@ impl Parser {
                 }
                 continue;
             }
-            if trimmed == "$$" {
+            if trimmed == "$$" && self.features.contains(FeatureSet::MATH) {
                 inner_lines.push(candidate.clone());
                 i += 1;
                 while i < lines.len() {
@@ -720,7 +1117,7 @@ impl Parser {
                 }
                 continue;
             }
-            if self.is_box_open(&candidate.text) {
+            if self.is_container_open(&candidate.text) {
                 let nested_len = candidate.text.chars().take_while(|c| *c == ':').count();
                 fence_stack.push(nested_len);
                 inner_lines.push(candidate.clone());
@@ -745,6 +1142,22 @@ impl Parser {
             inner_lines.push(candidate.clone());
             i += 1;
         }
+        if !fence_stack.is_empty() {
+            let message = if name == "box" {
+                "box is not closed"
+            } else {
+                "container is not closed"
+            };
+            self.warn_unclosed(
+                lines,
+                Span {
+                    start: line.start,
+                    end: line.start + fence_len,
+                },
+                W_UNCLOSED_BOX,
+                message,
+            );
+        }
         let blocks = self.parse_blocks(&inner_lines, parse_inlines);
         let span = Span {
             start: line.start,
@@ -754,19 +1167,21 @@ impl Parser {
                 lines[i.saturating_sub(1)].end
             },
         };
-        Some((
-            Block {
-                span,
-                attrs: AttrList::default(),
-                kind: BlockKind::Box(BoxBlock { title, blocks }),
-            },
-            i,
-        ))
+        let kind = if name == "box" {
+            BlockKind::Box(BoxBlock { title, blocks })
+        } else {
+            BlockKind::Container {
+                name,
+                title,
+                blocks,
+            }
+        };
+        Some((Block { span, attrs, kind }, i))
     }
 
     fn parse_html_block(&mut self, lines: &[Line], start: usize) -> Option<(Block, usize)> {
         let line = &lines[start];
-        let kind = match_html_block_start(&line.text)?;
+        let kind = self.match_html_block_start(&line.text)?;
         let mut raw_lines = vec![line.text.clone()];
         let mut i = start + 1;
 
@@ -841,6 +1256,8 @@ impl Parser {
         let mut i = start;
         let mut quote_lines = Vec::new();
         let mut can_lazy = false;
+        // Fence opened on a quoted line; its content never takes lazy continuation lines.
+        let mut open_fence: Option<(usize, u8)> = None;
         while i < lines.len() {
             let candidate = &lines[i];
             if let Some((prefix_bytes, partially_consumed_tab, remaining_tab_cols, current_col)) =
@@ -886,14 +1303,26 @@ impl Parser {
                     has_newline: candidate.has_newline,
                     lazy_continuation: false,
                 };
-                let list_allows_lazy = parse_list_marker(&line.text).is_some_and(|marker| {
-                    remove_list_indent(&line.text, marker.marker_len, marker.content_indent)
-                        .trim_start()
-                        .starts_with('>')
-                });
-                can_lazy = self.line_can_continue_paragraph(&line)
-                    || line.text.trim_start().starts_with('>')
-                    || list_allows_lazy;
+                if let Some((fence_len, fence_char)) = open_fence {
+                    if is_fence_close(&line.text, fence_len, fence_char) {
+                        open_fence = None;
+                    }
+                    can_lazy = false;
+                } else if let Some((_, fence_len, fence_char, _)) =
+                    parse_fence_open(&line.text, self.min_fence_len)
+                {
+                    open_fence = Some((fence_len, fence_char));
+                    can_lazy = false;
+                } else {
+                    let list_allows_lazy = parse_list_marker(&line.text).is_some_and(|marker| {
+                        remove_list_indent(&line.text, marker.marker_len, marker.content_indent)
+                            .trim_start()
+                            .starts_with('>')
+                    });
+                    can_lazy = self.line_can_continue_paragraph(&line)
+                        || line.text.trim_start().starts_with('>')
+                        || list_allows_lazy;
+                }
                 quote_lines.push(line);
                 i += 1;
                 continue;
@@ -928,6 +1357,11 @@ impl Parser {
             }
             break;
         }
+        let kind = if self.strict {
+            None
+        } else {
+            self.take_alert_marker(&mut quote_lines)
+        };
         let blocks = self.parse_blocks(&quote_lines, parse_inlines);
         let span = Span {
             start: line.start,
@@ -941,12 +1375,44 @@ impl Parser {
             Block {
                 span,
                 attrs: AttrList::default(),
-                kind: BlockKind::BlockQuote { blocks },
+                kind: BlockKind::BlockQuote { blocks, kind },
             },
             i,
         ))
     }
 
+    /// Consumes a leading `[!TYPE]` line of a block quote.
+    /// Unknown types keep the line as content and report a warning.
+    fn take_alert_marker(&mut self, quote_lines: &mut Vec<Line>) -> Option<AlertKind> {
+        let first = quote_lines.first()?;
+        let trimmed = first.text.trim();
+        let name = trimmed.strip_prefix("[!")?.strip_suffix(']')?;
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphabetic()) {
+            return None;
+        }
+        match AlertKind::parse(name) {
+            Some(kind) => {
+                quote_lines.remove(0);
+                Some(kind)
+            }
+            None => {
+                let leading = first.text.len() - first.text.trim_start().len();
+                let start = (first.start + leading).min(first.end);
+                let span = Span {
+                    start,
+                    end: (start + trimmed.len()).min(first.end),
+                };
+                self.push_diag(
+                    span,
+                    DiagnosticSeverity::Warning,
+                    W_ALERT_UNKNOWN,
+                    "unknown alert type",
+                );
+                None
+            }
+        }
+    }
+
     fn parse_list(
         &mut self,
         lines: &[Line],
@@ -1082,7 +1548,7 @@ impl Parser {
                 start: current.start,
                 end: lines[last_line_idx].end,
             };
-            let task = if parse_inlines {
+            let task = if parse_inlines && !self.strict {
                 detect_task_marker(&mut blocks)
             } else {
                 None
@@ -1124,8 +1590,8 @@ impl Parser {
 
     fn is_block_start(&self, line: &Line) -> bool {
         self.is_code_fence_line(&line.text)
-            || line.text.trim() == "$$"
-            || self.is_box_open(&line.text)
+            || (self.features.contains(FeatureSet::MATH) && line.text.trim() == "$$")
+            || self.is_container_open(&line.text)
             || self.is_html_block_start(&line.text)
             || blockquote_prefix_len(&line.text).is_some()
             || is_thematic_break_line(&line.text)
@@ -1135,7 +1601,14 @@ impl Parser {
     }
 
     fn is_html_block_start(&self, text: &str) -> bool {
-        match_html_block_start(text).is_some()
+        self.match_html_block_start(text).is_some()
+    }
+
+    fn match_html_block_start(&self, text: &str) -> Option<HtmlBlockKind> {
+        if !self.features.contains(FeatureSet::RAW_HTML) {
+            return None;
+        }
+        match_html_block_start(text)
     }
 
     fn line_can_continue_paragraph(&self, line: &Line) -> bool {
@@ -1145,7 +1618,7 @@ impl Parser {
         if setext_underline_level(&line.text).is_some() {
             return false;
         }
-        if let Some(kind) = match_html_block_start(&line.text) {
+        if let Some(kind) = self.match_html_block_start(&line.text) {
             if !matches!(kind, HtmlBlockKind::Type7) {
                 return false;
             }
@@ -1243,6 +1716,28 @@ impl Parser {
         offsets: &[usize],
         start: usize,
         end: usize,
+    ) -> InlineSeq {
+        if self.inline_range_depth >= MAX_INLINE_DEPTH {
+            if start >= end {
+                return Vec::new();
+            }
+            return vec![Inline {
+                span: self.span_from_offsets(offsets, start, end),
+                kind: InlineKind::Text(buffer[start..end].to_string()),
+            }];
+        }
+        self.inline_range_depth += 1;
+        let inlines = self.parse_inline_range_at_depth(buffer, offsets, start, end);
+        self.inline_range_depth -= 1;
+        inlines
+    }
+
+    fn parse_inline_range_at_depth(
+        &mut self,
+        buffer: &str,
+        offsets: &[usize],
+        start: usize,
+        end: usize,
     ) -> InlineSeq {
         // Inline parsing uses delimiter and bracket stacks (ASCII-focused CommonMark).
         let bytes = buffer.as_bytes();
@@ -1272,7 +1767,24 @@ impl Parser {
                                 span,
                                 kind: InlineKind::HardBreak,
                             });
-                            i += 2;
+                            i = skip_line_indent(bytes, i + 2, end);
+                            text_start = i;
+                            continue;
+                        }
+                        if next == b'!'
+                            && !self.strict
+                            && let Some((inline, next)) =
+                                self.parse_raw_inline(buffer, offsets, i, end)
+                        {
+                            self.flush_text_buf(
+                                &mut out,
+                                offsets,
+                                &mut text_buf,
+                                &mut text_start,
+                                i,
+                            );
+                            out.push(inline);
+                            i = next;
                             text_start = i;
                             continue;
                         }
@@ -1308,7 +1820,7 @@ impl Parser {
                     i += run_len;
                     continue;
                 }
-                b'$' => {
+                b'$' if self.features.contains(FeatureSet::MATH) => {
                     if let Some((inline, next)) = self.parse_inline_math(buffer, offsets, i, end) {
                         self.flush_text_buf(&mut out, offsets, &mut text_buf, &mut text_start, i);
                         out.push(inline);
@@ -1325,7 +1837,9 @@ impl Parser {
                         text_start = i;
                         continue;
                     }
-                    if let Some((inline, next)) = self.parse_html_span(buffer, offsets, i, end) {
+                    if self.features.contains(FeatureSet::RAW_HTML)
+                        && let Some((inline, next)) = self.parse_html_span(buffer, offsets, i, end)
+                    {
                         self.flush_text_buf(&mut out, offsets, &mut text_buf, &mut text_start, i);
                         out.push(inline);
                         i = next;
@@ -1343,7 +1857,16 @@ impl Parser {
                         continue;
                     }
                 }
-                b'@' => {
+                b':' if self.features.contains(FeatureSet::DIRECTIVES) => {
+                    if let Some((inline, next)) = self.parse_directive(buffer, offsets, i, end) {
+                        self.flush_text_buf(&mut out, offsets, &mut text_buf, &mut text_start, i);
+                        out.push(inline);
+                        i = next;
+                        text_start = i;
+                        continue;
+                    }
+                }
+                b'@' if !self.strict => {
                     if let Some((inline, next)) =
                         self.parse_reference_inline(buffer, offsets, i, end)
                     {
@@ -1355,6 +1878,18 @@ impl Parser {
                     }
                 }
                 b'!' => {
+                    if i + 1 < end
+                        && bytes[i + 1] == b'['
+                        && !self.features.contains(FeatureSet::IMAGES)
+                    {
+                        // Keep the bracket out of link matching so the image stays literal.
+                        if text_buf.is_empty() {
+                            text_start = i;
+                        }
+                        text_buf.extend_from_slice(b"![");
+                        i += 2;
+                        continue;
+                    }
                     if i + 1 < end && bytes[i + 1] == b'[' {
                         self.flush_text_buf(&mut out, offsets, &mut text_buf, &mut text_start, i);
                         self.push_text_node(&mut out, offsets, i, i + 2, "![");
@@ -1372,6 +1907,20 @@ impl Parser {
                 }
                 b'[' => {
                     self.flush_text_buf(&mut out, offsets, &mut text_buf, &mut text_start, i);
+                    if let Some((inline, next)) = self.parse_wikilink(buffer, offsets, i, end) {
+                        out.push(inline);
+                        i = next;
+                        text_start = i;
+                        continue;
+                    }
+                    if !self.strict
+                        && let Some((inline, next)) = self.parse_citation(buffer, offsets, i, end)
+                    {
+                        out.push(inline);
+                        i = next;
+                        text_start = i;
+                        continue;
+                    }
                     self.push_text_node(&mut out, offsets, i, i + 1, "[");
                     let node_index = out.len().saturating_sub(1);
                     brackets.push(BracketEntry {
@@ -1408,12 +1957,14 @@ impl Parser {
                 }
                 b'*' | b'_' | b'~' => {
                     let run_len = count_run(bytes, i, end, b);
-                    if b == b'~' && run_len < 2 {
+                    if b == b'~'
+                        && (run_len < 2 || !self.features.contains(FeatureSet::STRIKETHROUGH))
+                    {
                         if text_buf.is_empty() {
                             text_start = i;
                         }
-                        text_buf.push(b'~');
-                        i += 1;
+                        text_buf.extend(std::iter::repeat_n(b'~', run_len));
+                        i += run_len;
                         continue;
                     }
                     let (can_open, can_close) =
@@ -1441,17 +1992,21 @@ impl Parser {
                     continue;
                 }
                 b'\n' => {
-                    let trailing = text_buf
+                    // Only spaces written in the source count; `&#32;` stays text.
+                    let trailing = bytes[..i]
                         .iter()
                         .rev()
                         .take_while(|byte| **byte == b' ')
-                        .count();
+                        .count()
+                        .min(
+                            text_buf
+                                .iter()
+                                .rev()
+                                .take_while(|byte| **byte == b' ')
+                                .count(),
+                        );
                     let hard_break = trailing >= 2;
-                    if trailing > 0 {
-                        for _ in 0..trailing {
-                            text_buf.pop();
-                        }
-                    }
+                    text_buf.truncate(text_buf.len() - trailing);
                     self.flush_text_buf(&mut out, offsets, &mut text_buf, &mut text_start, i);
                     let span = self.span_from_offsets(offsets, i, i + 1);
                     out.push(Inline {
@@ -1462,7 +2017,7 @@ impl Parser {
                             InlineKind::SoftBreak
                         },
                     });
-                    i += 1;
+                    i = skip_line_indent(bytes, i + 1, end);
                     text_start = i;
                     continue;
                 }
@@ -1477,7 +2032,12 @@ impl Parser {
 
         self.flush_text_buf(&mut out, offsets, &mut text_buf, &mut text_start, end);
         self.process_emphasis(&mut out, &mut delims);
-        autolink_inlines(&mut out);
+        if self.features.contains(FeatureSet::AUTOLINKS) {
+            autolink_inlines(&mut out);
+        }
+        if !self.strict {
+            emoji_inlines(&mut out);
+        }
         out
     }
 
@@ -1559,6 +2119,28 @@ impl Parser {
         None
     }
 
+    /// Parses `\!{...}!`. The content runs to the first `}!` and is kept verbatim.
+    fn parse_raw_inline(
+        &self,
+        buffer: &str,
+        offsets: &[usize],
+        start: usize,
+        end: usize,
+    ) -> Option<(Inline, usize)> {
+        if !buffer[start..end].starts_with("\\!{") {
+            return None;
+        }
+        let content_start = start + 3;
+        let close = content_start + buffer[content_start..end].find("}!")?;
+        Some((
+            Inline {
+                span: self.span_from_offsets(offsets, start, close + 2),
+                kind: InlineKind::Raw(buffer[content_start..close].to_string()),
+            },
+            close + 2,
+        ))
+    }
+
     fn parse_inline_math(
         &mut self,
         buffer: &str,
@@ -1915,8 +2497,10 @@ impl Parser {
                 return None;
             }
             let last_ws = buffer[..start]
-                .rfind(|ch: char| ch.is_whitespace())
-                .map(|idx| idx + 1)
+                .char_indices()
+                .rev()
+                .find(|(_, ch)| ch.is_whitespace())
+                .map(|(idx, ch)| idx + ch.len_utf8())
                 .unwrap_or(0);
             let token = &buffer[last_ws..start];
             if token.contains('/') || token.contains('\\') {
@@ -1924,8 +2508,9 @@ impl Parser {
             }
         }
         let bytes = buffer.as_bytes();
-        let (label, label_end) = parse_label(bytes, start + 1, end)?;
-        let label_span = self.span_from_offsets(offsets, start + 1, label_end);
+        let (numbered, label_start) = parse_ref_category(bytes, start + 1, end);
+        let (label, label_end) = parse_label(bytes, label_start, end)?;
+        let label_span = self.span_from_offsets(offsets, label_start, label_end);
         let mut bracket = None;
         let mut next = label_end;
         if label_end < end
@@ -1958,6 +2543,7 @@ impl Parser {
                         span: label_span,
                     },
                     bracket,
+                    numbered,
                     resolved: None,
                 },
             },
@@ -1965,6 +2551,170 @@ impl Parser {
         ))
     }
 
+    /// Parses `[[target]]` or `[[target|display]]` when a wikilink resolver is set.
+    /// The brackets may not nest or span lines, and the target may not be blank.
+    fn parse_wikilink(
+        &mut self,
+        buffer: &str,
+        offsets: &[usize],
+        start: usize,
+        end: usize,
+    ) -> Option<(Inline, usize)> {
+        let resolver = self.wikilink_resolver.as_ref()?;
+        if !buffer[start..end].starts_with("[[") {
+            return None;
+        }
+        let content_start = start + 2;
+        let content_len = buffer[content_start..end].find("]]")?;
+        let content = &buffer[content_start..content_start + content_len];
+        if content.contains(['[', ']', '\n']) {
+            return None;
+        }
+        let (target, display) = match content.split_once('|') {
+            Some((target, display)) => (target.trim(), display.trim()),
+            None => (content.trim(), ""),
+        };
+        if target.is_empty() {
+            return None;
+        }
+        let display = if display.is_empty() { target } else { display };
+        let next = content_start + content_len + 2;
+        Some((
+            Inline {
+                span: self.span_from_offsets(offsets, start, next),
+                kind: InlineKind::WikiLink {
+                    target: target.to_string(),
+                    display: display.to_string(),
+                    href: resolver.resolve_wikilink(target),
+                },
+            },
+            next,
+        ))
+    }
+
+    /// Parses a citation group `[@key]` or `[@key1; @key2]`. Anything else inside the
+    /// brackets, or a following `(`, `[` or `{`, leaves the brackets to link parsing.
+    fn parse_citation(
+        &mut self,
+        buffer: &str,
+        offsets: &[usize],
+        start: usize,
+        end: usize,
+    ) -> Option<(Inline, usize)> {
+        let bytes = buffer.as_bytes();
+        let mut keys = Vec::new();
+        let mut i = start + 1;
+        loop {
+            if bytes.get(i) != Some(&b'@') {
+                return None;
+            }
+            let (name, label_end) = parse_label(bytes, i + 1, end)?;
+            keys.push(Label {
+                name,
+                span: self.span_from_offsets(offsets, i + 1, label_end),
+            });
+            i = label_end;
+            while i < end && bytes[i] == b' ' {
+                i += 1;
+            }
+            match bytes.get(i) {
+                Some(b']') if i < end => break,
+                Some(b';') if i < end => {
+                    i += 1;
+                    while i < end && bytes[i] == b' ' {
+                        i += 1;
+                    }
+                }
+                _ => return None,
+            }
+        }
+        let next = i + 1;
+        if next < end && matches!(bytes[next], b'(' | b'[' | b'{') {
+            return None;
+        }
+        let numbers = vec![None; keys.len()];
+        Some((
+            Inline {
+                span: self.span_from_offsets(offsets, start, next),
+                kind: InlineKind::Citation { keys, numbers },
+            },
+            next,
+        ))
+    }
+
+    /// Parses `::name[label]` with an optional `{attrs}` suffix, and for `video`
+    /// and `audio` an optional `(url)` before it.
+    /// The name must start with a letter and the label may not span lines.
+    fn parse_directive(
+        &mut self,
+        buffer: &str,
+        offsets: &[usize],
+        start: usize,
+        end: usize,
+    ) -> Option<(Inline, usize)> {
+        let bytes = buffer.as_bytes();
+        if let Some(prev) = buffer[..start].chars().next_back()
+            && (prev.is_alphanumeric() || prev == ':')
+        {
+            return None;
+        }
+        let name_start = start + 2;
+        if bytes.get(start + 1) != Some(&b':')
+            || name_start >= end
+            || !bytes[name_start].is_ascii_alphabetic()
+        {
+            return None;
+        }
+        let name_end = bytes[name_start..end]
+            .iter()
+            .position(|b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_')))
+            .map_or(end, |len| name_start + len);
+        if name_end >= end || bytes[name_end] != b'[' {
+            return None;
+        }
+        let label_start = name_end + 1;
+        let (label_end, had_newline) = find_bracket_end(bytes, label_start, end)?;
+        if had_newline {
+            return None;
+        }
+        let label = self.parse_inline_range(buffer, offsets, label_start, label_end);
+        let name = &buffer[name_start..name_end];
+        let mut next = label_end + 1;
+        // Other directives leave a following `(...)` as text, as before media
+        // directives existed.
+        let url = if matches!(name, "video" | "audio") {
+            parse_inline_link_destination(buffer, next, end).map(|(url, _, close)| {
+                next = close + 1;
+                url
+            })
+        } else {
+            None
+        };
+        let attrs = if let Some(attr_close) = find_inline_attr_end(bytes, next, end) {
+            let base_offset = self.span_from_offsets(offsets, next, next + 1).start;
+            let attrs = self.parse_attr_list_text(&buffer[next..=attr_close], base_offset);
+            self.validate_dir(&attrs);
+            next = attr_close + 1;
+            attrs
+        } else {
+            AttrList::default()
+        };
+        let span = self.span_from_offsets(offsets, start, next);
+        Some((
+            Inline {
+                span,
+                kind: InlineKind::Directive(Directive {
+                    name: name.to_string(),
+                    label,
+                    attrs,
+                    url,
+                    raw: buffer[start..next].to_string(),
+                }),
+            },
+            next,
+        ))
+    }
+
     fn parse_bracket_inlines(
         &mut self,
         buffer: &str,
@@ -1992,9 +2742,16 @@ impl Parser {
         delims: &mut Vec<Delimiter>,
         brackets: &mut Vec<BracketEntry>,
     ) -> Option<usize> {
-        let opener_pos = brackets.iter().rposition(|entry| entry.active)?;
+        // Links deactivate enclosing brackets, but a span may still wrap them.
+        let span_close =
+            find_inline_attr_end(buffer.as_bytes(), current + 1, end).filter(|_| !self.strict);
+        let opener_pos = match brackets.last() {
+            Some(last) if span_close.is_some() && !last.active && !last.image => brackets.len() - 1,
+            _ => brackets.iter().rposition(|entry| entry.active)?,
+        };
         let opener = brackets.get(opener_pos)?.clone();
-        if opener.image
+        if opener.active
+            && opener.image
             && let Some(inactive_pos) = brackets
                 .iter()
                 .rposition(|entry| !entry.active && !entry.image)
@@ -2007,6 +2764,7 @@ impl Parser {
             Inline {
                 url: String,
                 title: Option<String>,
+                attrs: AttrList,
                 close: usize,
             },
             Reference {
@@ -2014,14 +2772,48 @@ impl Parser {
                 meta: LinkRefMeta,
                 close: usize,
             },
+            Span {
+                attrs: AttrList,
+                close: usize,
+            },
         }
-        let parsed = if let Some((inline_url, inline_title, inline_close)) =
-            parse_inline_link_destination(buffer, current + 1, end)
+        let parsed = if opener.active
+            && let Some((inline_url, inline_title, inline_close)) =
+                parse_inline_link_destination(buffer, current + 1, end)
         {
+            // Only images take an attribute list after the destination.
+            let attr_close = find_inline_attr_end(buffer.as_bytes(), inline_close + 1, end)
+                .filter(|_| opener.image && !self.strict);
+            let (attrs, close) = match attr_close {
+                Some(attr_close) => {
+                    let base_offset = self
+                        .span_from_offsets(offsets, inline_close + 1, inline_close + 2)
+                        .start;
+                    let attrs = self
+                        .parse_attr_list_text(&buffer[inline_close + 1..=attr_close], base_offset);
+                    self.validate_image_attrs(&attrs);
+                    self.validate_dir(&attrs);
+                    (attrs, attr_close)
+                }
+                None => (AttrList::default(), inline_close),
+            };
             ParsedLink::Inline {
                 url: inline_url,
                 title: inline_title,
-                close: inline_close,
+                attrs,
+                close,
+            }
+        } else if !opener.image
+            && let Some(attr_close) = span_close
+        {
+            let base_offset = self
+                .span_from_offsets(offsets, current + 1, current + 2)
+                .start;
+            let attrs = self.parse_attr_list_text(&buffer[current + 1..=attr_close], base_offset);
+            self.validate_dir(&attrs);
+            ParsedLink::Span {
+                attrs,
+                close: attr_close,
             }
         } else {
             let bytes = buffer.as_bytes();
@@ -2099,9 +2891,14 @@ impl Parser {
         if opener.node_index >= out.len() {
             return None;
         }
+        if inline_depth(&out[opener.node_index + 1..]) >= MAX_INLINE_DEPTH {
+            brackets.remove(opener_pos);
+            return None;
+        }
         let close = match parsed {
             ParsedLink::Inline { close, .. } => close,
             ParsedLink::Reference { close, .. } => close,
+            ParsedLink::Span { close, .. } => close,
         };
         let span = self.span_from_offsets(offsets, opener.start, close + 1);
 
@@ -2125,12 +2922,15 @@ impl Parser {
         }
 
         let kind = match parsed {
-            ParsedLink::Inline { url, title, .. } => {
+            ParsedLink::Inline {
+                url, title, attrs, ..
+            } => {
                 if opener.image {
                     InlineKind::Image {
                         url,
                         title,
                         alt: children,
+                        attrs,
                     }
                 } else {
                     InlineKind::Link {
@@ -2155,10 +2955,12 @@ impl Parser {
                     }
                 }
             }
+            ParsedLink::Span { attrs, .. } => InlineKind::Span { children, attrs },
         };
+        let is_link = matches!(kind, InlineKind::Link { .. } | InlineKind::LinkRef { .. });
         out.push(Inline { span, kind });
 
-        if !opener.image {
+        if is_link {
             for entry in brackets.iter_mut() {
                 if !entry.image {
                     entry.active = false;
@@ -2224,6 +3026,15 @@ impl Parser {
                     continue;
                 }
             };
+            let inner = out
+                .get(delims[opener_index].node_index + 1..closer.node_index)
+                .unwrap_or_default();
+            if inline_depth(inner) >= MAX_INLINE_DEPTH {
+                if let Some(entry) = delims.get_mut(closer_index) {
+                    entry.can_close = false;
+                }
+                continue;
+            }
             self.apply_emphasis(out, delims, opener_index, closer_index, use_len);
         }
     }
@@ -2408,36 +3219,104 @@ impl Parser {
 
     fn is_target_line_text(&self, text: &str) -> bool {
         let trimmed = text.trim();
-        trimmed.starts_with('{') && trimmed.ends_with('}') && trimmed.len() >= 2
+        !self.strict && trimmed.starts_with('{') && trimmed.ends_with('}') && trimmed.len() >= 2
     }
 
-    fn parse_fence_info(
-        &mut self,
-        line: &Line,
-        _fence_len: usize,
-        info: String,
-    ) -> (Option<String>, AttrList) {
-        if let Some(brace_idx) = info.find('{') {
-            let lang_part = info[..brace_idx].trim();
-            let lang = if lang_part.is_empty() {
-                None
-            } else {
-                Some(lang_part.to_string())
-            };
+    /// Splits the info string into the language and attribute list. A `nohighlight`
+    /// word or a `{.no-highlight}` class opts the block out of syntax highlighting;
+    /// any other class is rejected as on target lines. `wrap=soft|none` picks
+    /// the line wrapping mode.
+    fn parse_fence_info(&mut self, line: &Line, _fence_len: usize, info: String) -> FenceInfo {
+        let (lang_part, mut attrs) = if !self.strict
+            && let Some(brace_idx) = info.find('{')
+        {
             let open_idx = line.text.find('{').unwrap_or(line.text.len());
-            let close_idx = line
-                .text
+            let close_end = line.text[open_idx..]
                 .rfind('}')
-                .unwrap_or(line.text.len().saturating_sub(1));
+                .map_or(line.text.len(), |idx| open_idx + idx + 1);
             let base_offset = line.start + open_idx;
-            let attrs = self.parse_attr_list_text(&line.text[open_idx..=close_idx], base_offset);
-            (lang, attrs)
+            let attrs = self.parse_attr_list_text(&line.text[open_idx..close_end], base_offset);
+            (&info[..brace_idx], attrs)
+        } else {
+            (info.as_str(), AttrList::default())
+        };
+
+        let mut no_highlight = false;
+        let mut classes = Vec::new();
+        attrs.items.retain(|item| {
+            if item.key != "class" {
+                return true;
+            }
+            if item.value.raw == "no-highlight" {
+                no_highlight = true;
+            } else {
+                classes.push(item.value.span);
+            }
+            false
+        });
+        for span in classes {
+            let span = Span {
+                start: span.start - 1,
+                end: span.end,
+            };
+            self.push_diag(
+                span,
+                DiagnosticSeverity::Error,
+                E_ATTR_SYNTAX,
+                "invalid attribute item",
+            );
+        }
+
+        let mut lang_part = lang_part.trim().to_string();
+        if !self.strict
+            && lang_part
+                .split_whitespace()
+                .any(|word| word == "nohighlight")
+        {
+            no_highlight = true;
+            lang_part = lang_part
+                .split_whitespace()
+                .filter(|word| *word != "nohighlight")
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+        if self.strict {
+            // CommonMark takes the first word of the info string as the language.
+            lang_part = lang_part
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+        }
+        let lang = if lang_part.is_empty() {
+            None
         } else {
-            let lang = if info.is_empty() { None } else { Some(info) };
-            (lang, AttrList::default())
+            Some(lang_part)
+        };
+
+        let mut soft_wrap = false;
+        if let Some(item) = attrs.items.iter().find(|item| item.key == "wrap") {
+            match item.value.raw.as_str() {
+                "soft" => soft_wrap = true,
+                "none" => {}
+                _ => self.push_diag(
+                    item.value.span,
+                    DiagnosticSeverity::Warning,
+                    W_CODE_WRAP_INVALID,
+                    "invalid wrap value, expected soft or none",
+                ),
+            }
+        }
+        FenceInfo {
+            lang,
+            attrs,
+            no_highlight,
+            soft_wrap,
         }
     }
 
+    /// Parses `{#label key=value .class}`; a `.class` token is stored as a
+    /// `class` item, which callers keep or reject.
     fn parse_attr_list_text(&mut self, text: &str, base_offset: usize) -> AttrList {
         let mut attrs = AttrList::default();
         let span = Span {
@@ -2476,7 +3355,7 @@ impl Parser {
             tokens.push((start, inner.len()));
         }
 
-        for (start, end) in tokens {
+        for (idx, &(start, end)) in tokens.iter().enumerate() {
             let token = &inner[start..end];
             if let Some(name) = token.strip_prefix('#') {
                 if attrs.label.is_some() {
@@ -2484,11 +3363,18 @@ impl Parser {
                         start: base_offset + 1 + start,
                         end: base_offset + 1 + end,
                     };
-                    self.push_diag(
+                    // The first label is an earlier token; delete back to its end.
+                    let fix_span = Span {
+                        start: base_offset + 1 + tokens[idx - 1].1,
+                        end: span.end,
+                    };
+                    let fix = self.fix(fix_span, "", "Remove the duplicate label");
+                    self.push_diag_with_fix(
                         span,
                         DiagnosticSeverity::Error,
                         E_ATTR_SYNTAX,
                         "duplicate label",
+                        fix,
                     );
                     continue;
                 }
@@ -2502,15 +3388,58 @@ impl Parser {
                         span,
                         DiagnosticSeverity::Error,
                         E_ATTR_SYNTAX,
-                        "invalid label syntax",
+                        "invalid label syntax",
+                    );
+                    continue;
+                }
+                let span = Span {
+                    start: base_offset + 1 + start + 1,
+                    end: base_offset + 1 + end,
+                };
+                attrs.label = Some(Label { name, span });
+                continue;
+            }
+            if let Some(name) = token.strip_prefix('.') {
+                let span = Span {
+                    start: base_offset + 1 + start,
+                    end: base_offset + 1 + end,
+                };
+                if !is_valid_label(name) {
+                    self.push_diag(
+                        span,
+                        DiagnosticSeverity::Error,
+                        E_ATTR_SYNTAX,
+                        "invalid class syntax",
                     );
                     continue;
                 }
+                attrs.items.push(AttrItem {
+                    key: "class".to_string(),
+                    value: AttrValue {
+                        raw: name.to_string(),
+                        span: Span {
+                            start: span.start + 1,
+                            end: span.end,
+                        },
+                        quoted: false,
+                    },
+                });
+                continue;
+            }
+            // A bare key is a flag: `{reversed}` reads as `{reversed=true}`.
+            if is_attr_key(token) {
                 let span = Span {
-                    start: base_offset + 1 + start + 1,
+                    start: base_offset + 1 + start,
                     end: base_offset + 1 + end,
                 };
-                attrs.label = Some(Label { name, span });
+                attrs.items.push(AttrItem {
+                    key: token.to_string(),
+                    value: AttrValue {
+                        raw: "true".to_string(),
+                        span,
+                        quoted: false,
+                    },
+                });
                 continue;
             }
             let mut iter = token.splitn(2, '=');
@@ -2521,12 +3450,25 @@ impl Parser {
                     start: base_offset + 1 + start,
                     end: base_offset + 1 + end,
                 };
-                self.push_diag(
-                    span,
-                    DiagnosticSeverity::Error,
-                    E_ATTR_SYNTAX,
-                    "invalid attribute item",
-                );
+                // `key:value` is a common slip for `key=value`.
+                match token.split_once(':') {
+                    Some((key, value)) if is_attr_key(key) && !value.is_empty() => {
+                        let fix = self.fix(span, format!("{}={}", key, value), "Use `=`");
+                        self.push_diag_with_fix(
+                            span,
+                            DiagnosticSeverity::Error,
+                            E_ATTR_SYNTAX,
+                            "invalid attribute item",
+                            fix,
+                        );
+                    }
+                    _ => self.push_diag(
+                        span,
+                        DiagnosticSeverity::Error,
+                        E_ATTR_SYNTAX,
+                        "invalid attribute item",
+                    ),
+                }
                 continue;
             }
             let value = value.unwrap_or("");
@@ -2573,12 +3515,104 @@ impl Parser {
                 "border-width" => !is_border_width(value),
                 _ => false,
             };
-            if invalid {
-                self.push_diag(
+            if !invalid {
+                continue;
+            }
+            // Offer a fix only when the intended value is clear.
+            let suggestion = match item.key.as_str() {
+                "border-style" => {
+                    let value = value.to_ascii_lowercase();
+                    BORDER_STYLES
+                        .iter()
+                        .find(|style| edit_distance(&value, style) <= 2)
+                        .map(|style| style.to_string())
+                }
+                "border-width" => {
+                    Some(value.replace(' ', "")).filter(|value| is_border_width(value))
+                }
+                _ => Some(format!("#{}", value)).filter(|value| is_hex_color(value)),
+            };
+            match suggestion {
+                Some(suggestion) => {
+                    let title = format!("Use `{}`", suggestion);
+                    let fix = self.fix(item.value.span, suggestion, &title);
+                    self.push_diag_with_fix(
+                        item.value.span,
+                        DiagnosticSeverity::Warning,
+                        W_BOX_STYLE_INVALID,
+                        "invalid box style value",
+                        fix,
+                    );
+                }
+                None => self.push_diag(
                     item.value.span,
                     DiagnosticSeverity::Warning,
                     W_BOX_STYLE_INVALID,
                     "invalid box style value",
+                ),
+            }
+        }
+    }
+
+    /// `dir` becomes the HTML attribute only for `rtl`, `ltr` and `auto`; other
+    /// values stay `data-dir` and are reported.
+    fn validate_dir(&mut self, attrs: &AttrList) {
+        for item in attrs.items.iter().filter(|item| item.key == "dir") {
+            if !is_text_dir(&item.value.raw) {
+                self.push_diag(
+                    item.value.span,
+                    DiagnosticSeverity::Warning,
+                    W_DIR_INVALID,
+                    "invalid dir value, expected rtl, ltr or auto",
+                );
+            }
+        }
+    }
+
+    /// Warns about keys that TypMark reads on other blocks, or that look like a
+    /// misspelling of a key this block reads. Other keys are plain `data-*`
+    /// attributes and pass silently.
+    fn validate_attr_keys(&mut self, kind: &BlockKind, attrs: &AttrList) {
+        let known = block_attr_keys(kind);
+        for item in &attrs.items {
+            let key = item.key.as_str();
+            if matches!(key, "class" | "lang" | "dir") || known.contains(&key) {
+                continue;
+            }
+            let message = if let Some(suggestion) = known
+                .iter()
+                .find(|candidate| is_misspelled_key(key, candidate))
+            {
+                format!(
+                    "unknown attribute '{}', did you mean '{}'?",
+                    key, suggestion
+                )
+            } else if BLOCK_ATTR_KEYS.iter().any(|(_, keys)| keys.contains(&key)) {
+                format!("attribute '{}' has no effect on this block", key)
+            } else {
+                continue;
+            };
+            let start = item.value.span.start - 1 - usize::from(item.value.quoted) - key.len();
+            self.push_diag(
+                Span {
+                    start,
+                    end: start + key.len(),
+                },
+                DiagnosticSeverity::Warning,
+                W_ATTR_UNKNOWN,
+                &message,
+            );
+        }
+    }
+
+    fn validate_image_attrs(&mut self, attrs: &AttrList) {
+        for item in attrs.items.iter().filter(|item| item.key == "srcset") {
+            if !is_srcset(&item.value.raw) {
+                self.push_diag(
+                    item.value.span,
+                    DiagnosticSeverity::Warning,
+                    W_IMAGE_SRCSET_INVALID,
+                    "invalid srcset value",
                 );
             }
         }
@@ -2734,6 +3768,35 @@ impl Parser {
                 continue;
             }
             if let Some((start, end)) = entry.split_once('-') {
+                // An open end (`5-`, `-5`) reaches the edge of the block, so the
+                // range is clamped to the block instead of reported out of bounds.
+                if start.is_empty() != end.is_empty() {
+                    let start = if start.is_empty() {
+                        Ok(1)
+                    } else {
+                        start.parse::<u32>()
+                    };
+                    let end = if end.is_empty() {
+                        Ok(u32::MAX)
+                    } else {
+                        end.parse::<u32>()
+                    };
+                    match (start, end) {
+                        (Ok(start), Ok(end)) if start > 0 && end > 0 => {
+                            let end = end.min(max_lines);
+                            if start <= end {
+                                ranges.push(LineRange { start, end });
+                            }
+                        }
+                        _ => self.push_diag(
+                            item.value.span,
+                            DiagnosticSeverity::Error,
+                            E_ATTR_SYNTAX,
+                            "invalid line range",
+                        ),
+                    }
+                    continue;
+                }
                 if let (Ok(start), Ok(end)) = (start.parse::<u32>(), end.parse::<u32>()) {
                     if start == 0 || end == 0 || end < start {
                         self.push_diag(
@@ -2809,6 +3872,40 @@ impl Parser {
         });
     }
 
+    fn add_link_def(&mut self, label: String, definition: LinkDefinition) {
+        let span = definition.span;
+        if let Some(spans) = &mut self.link_def_spans {
+            if let Some(first) = spans.get(&label) {
+                let mut diag = Diagnostic::new(
+                    self.source_map.range(span),
+                    DiagnosticSeverity::Warning,
+                    W_LINK_DEF_DUP,
+                    "duplicate link reference definition; the first one is used",
+                );
+                diag.related.push(RelatedDiagnostic::new(
+                    self.source_map.range(*first),
+                    Some("first definition".to_string()),
+                ));
+                self.report(diag);
+            } else {
+                spans.insert(label.clone(), span);
+            }
+        }
+        self.link_defs.entry(label).or_insert(definition);
+    }
+
+    /// Warns about a block whose closing delimiter is missing when it runs to
+    /// the end of the document. Blocks cut short by their list item or quote
+    /// follow CommonMark and are not reported.
+    fn warn_unclosed(&mut self, lines: &[Line], span: Span, code: &'static str, message: &str) {
+        if lines
+            .last()
+            .is_none_or(|line| line.end >= self.source.len())
+        {
+            self.push_diag(span, DiagnosticSeverity::Warning, code, message);
+        }
+    }
+
     fn push_diag(
         &mut self,
         span: Span,
@@ -2817,54 +3914,102 @@ impl Parser {
         message: &str,
     ) {
         let range = self.source_map.range(span);
-        self.diagnostics
-            .push(Diagnostic::new(range, severity, code, message));
+        self.report(Diagnostic::new(range, severity, code, message));
+    }
+
+    fn push_diag_with_fix(
+        &mut self,
+        span: Span,
+        severity: DiagnosticSeverity,
+        code: &'static str,
+        message: &str,
+        fix: Fix,
+    ) {
+        let range = self.source_map.range(span);
+        self.report(Diagnostic::new(range, severity, code, message).with_fix(fix));
+    }
+
+    fn report(&mut self, diag: Diagnostic) {
+        if let Some(observer) = &mut self.observer {
+            observer(&diag);
+        }
+        self.diagnostics.push(diag);
+    }
+
+    /// A fix putting `replacement` in place of `span`.
+    fn fix(&self, span: Span, replacement: impl Into<String>, title: &str) -> Fix {
+        Fix::new(self.source_map.range(span), replacement, title)
     }
 
     fn is_heading_line(&self, text: &str) -> bool {
         parse_atx_heading(text).is_some()
     }
 
-    fn is_box_open(&self, text: &str) -> bool {
-        if !text.starts_with(":::") {
-            return false;
-        }
-        let fence_len = text.chars().take_while(|c| *c == ':').count();
-        let rest = text[fence_len..].trim_start();
-        rest.starts_with("box")
+    fn is_container_open(&self, text: &str) -> bool {
+        self.features.contains(FeatureSet::BOXES) && parse_container_open(text).is_some()
     }
 
     fn is_code_fence_line(&self, text: &str) -> bool {
-        parse_fence_open(text).is_some()
+        parse_fence_open(text, self.min_fence_len).is_some()
+    }
+}
+
+/// Splits a `:::name rest` line into the fence length, the container name, and
+/// the rest of the line. Names are ASCII letters, digits, `-` and `_`, starting
+/// with a letter.
+fn parse_container_open(text: &str) -> Option<(usize, &str, &str)> {
+    let fence_len = text.chars().take_while(|c| *c == ':').count();
+    if fence_len < 3 {
+        return None;
     }
+    let rest = text[fence_len..].trim_start();
+    if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name_len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(rest.len());
+    let (name, rest) = rest.split_at(name_len);
+    if !rest.is_empty() && !rest.starts_with([' ', '\t', '{']) {
+        return None;
+    }
+    Some((fence_len, name, rest))
 }
 
+/// Splits the source into lines, accepting `\n`, `\r\n`, and lone `\r` terminators.
+/// A leading UTF-8 BOM is skipped. Line offsets always refer to the original source.
 fn split_lines(source: &str) -> Vec<Line> {
+    let bytes = source.as_bytes();
     let mut lines = Vec::new();
-    let mut start = 0;
-    for (idx, byte) in source.bytes().enumerate() {
-        if byte == b'\n' {
-            let text = source[start..idx].to_string();
-            lines.push(Line {
-                text,
-                start,
-                end: idx,
-                has_newline: true,
-                lazy_continuation: false,
-            });
-            start = idx + 1;
-        }
-    }
-    if start <= source.len() {
-        let text = source[start..].to_string();
+    let mut start = if source.starts_with('\u{feff}') { 3 } else { 0 };
+    let mut idx = start;
+    while idx < bytes.len() {
+        let terminator = match bytes[idx] {
+            b'\n' => 1,
+            b'\r' if bytes.get(idx + 1) == Some(&b'\n') => 2,
+            b'\r' => 1,
+            _ => {
+                idx += 1;
+                continue;
+            }
+        };
         lines.push(Line {
-            text,
+            text: source[start..idx].to_string(),
             start,
-            end: source.len(),
-            has_newline: false,
+            end: idx,
+            has_newline: true,
             lazy_continuation: false,
         });
+        idx += terminator;
+        start = idx;
     }
+    lines.push(Line {
+        text: source[start..].to_string(),
+        start,
+        end: source.len(),
+        has_newline: false,
+        lazy_continuation: false,
+    });
     lines
 }
 
@@ -2972,7 +4117,7 @@ fn strip_indent_up_to(text: &str, max_cols: usize) -> Option<&str> {
     Some(&text[idx..])
 }
 
-fn parse_fence_open(text: &str) -> Option<(usize, usize, u8, String)> {
+fn parse_fence_open(text: &str, min_len: usize) -> Option<(usize, usize, u8, String)> {
     let bytes = text.as_bytes();
     let mut idx = 0;
     while idx < bytes.len() && idx < 3 && bytes[idx] == b' ' {
@@ -2994,7 +4139,7 @@ fn parse_fence_open(text: &str) -> Option<(usize, usize, u8, String)> {
         .iter()
         .take_while(|b| **b == fence_char)
         .count();
-    if fence_len < 3 {
+    if fence_len < min_len {
         return None;
     }
     let info = rest[fence_len..].trim_matches(|ch| ch == ' ' || ch == '\t');
@@ -3102,18 +4247,27 @@ fn is_fence_close(text: &str, fence_len: usize, fence_char: u8) -> bool {
         .all(|b| *b == b' ' || *b == b'\t')
 }
 
-fn strip_leading_spaces(text: &str, max: usize) -> &str {
-    if max == 0 {
-        return text;
-    }
+/// Removes up to `columns` columns of leading spaces and tabs, like
+/// [`remove_indent_columns`], but leaves the rest of the line untouched so tabs
+/// inside code survive. A tab straddling the boundary keeps its remaining
+/// columns as spaces.
+fn strip_indent_columns(text: &str, columns: usize) -> String {
     let bytes = text.as_bytes();
+    let mut col = 0;
     let mut idx = 0;
-    let mut count = 0;
-    while idx < bytes.len() && count < max && bytes[idx] == b' ' {
+    while idx < bytes.len() && col < columns {
+        let next_col = match bytes[idx] {
+            b' ' => col + 1,
+            b'\t' => col + 4 - col % 4,
+            _ => break,
+        };
         idx += 1;
-        count += 1;
+        if next_col > columns {
+            return format!("{}{}", " ".repeat(next_col - columns), &text[idx..]);
+        }
+        col = next_col;
     }
-    &text[idx..]
+    text[idx..].to_string()
 }
 
 fn setext_underline_level(text: &str) -> Option<u8> {
@@ -3682,7 +4836,7 @@ fn parse_table_separator(text: &str, base_offset: usize) -> Option<Vec<TableAlig
 }
 
 fn parse_table_cells(
-    parser: &mut Parser,
+    parser: &mut Parser<'_>,
     line_start: usize,
     cells: &[TableCellRaw],
     expected: usize,
@@ -3703,6 +4857,33 @@ fn parse_table_cells(
     out
 }
 
+/// Nesting depth of `inlines`; recursion stays within `MAX_INLINE_DEPTH`
+/// because deeper trees are never built.
+fn inline_depth(inlines: &[Inline]) -> usize {
+    inlines
+        .iter()
+        .map(|inline| match &inline.kind {
+            InlineKind::Emph(children)
+            | InlineKind::Strong(children)
+            | InlineKind::Strikethrough(children)
+            | InlineKind::Link { children, .. }
+            | InlineKind::LinkRef { children, .. }
+            | InlineKind::Span { children, .. }
+            | InlineKind::Image { alt: children, .. }
+            | InlineKind::ImageRef { alt: children, .. }
+            | InlineKind::Directive(Directive {
+                label: children, ..
+            }) => 1 + inline_depth(children),
+            InlineKind::Ref {
+                bracket: Some(children),
+                ..
+            } => 1 + inline_depth(children),
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
 fn detect_task_marker(blocks: &mut [Block]) -> Option<bool> {
     let first = blocks.first_mut()?;
     let BlockKind::Paragraph { content } = &mut first.kind else {
@@ -3801,14 +4982,33 @@ fn autolink_inlines(inlines: &mut InlineSeq) {
                     kind: InlineKind::Strikethrough(children),
                 });
             }
+            InlineKind::Span { children, attrs } => {
+                let mut children = children;
+                autolink_inlines(&mut children);
+                out.push(Inline {
+                    span: inline.span,
+                    kind: InlineKind::Span { children, attrs },
+                });
+            }
+            InlineKind::Directive(mut directive) => {
+                autolink_inlines(&mut directive.label);
+                out.push(Inline {
+                    span: inline.span,
+                    kind: InlineKind::Directive(directive),
+                });
+            }
             InlineKind::Link { .. }
             | InlineKind::LinkRef { .. }
             | InlineKind::Image { .. }
             | InlineKind::ImageRef { .. }
             | InlineKind::CodeSpan(_)
+            | InlineKind::Raw(_)
             | InlineKind::HtmlSpan { .. }
             | InlineKind::MathInline { .. }
             | InlineKind::Ref { .. }
+            | InlineKind::Citation { .. }
+            | InlineKind::Emoji { .. }
+            | InlineKind::WikiLink { .. }
             | InlineKind::SoftBreak
             | InlineKind::HardBreak => {
                 out.push(inline);
@@ -3818,6 +5018,97 @@ fn autolink_inlines(inlines: &mut InlineSeq) {
     *inlines = out;
 }
 
+fn emoji_inlines(inlines: &mut InlineSeq) {
+    let mut out = Vec::new();
+    for mut inline in inlines.drain(..) {
+        match &mut inline.kind {
+            InlineKind::Text(text) => {
+                out.extend(split_emoji(text, inline.span));
+                continue;
+            }
+            InlineKind::Emph(children)
+            | InlineKind::Strong(children)
+            | InlineKind::Strikethrough(children)
+            | InlineKind::Link { children, .. }
+            | InlineKind::LinkRef { children, .. }
+            | InlineKind::Span { children, .. } => emoji_inlines(children),
+            InlineKind::Directive(directive) => emoji_inlines(&mut directive.label),
+            _ => {}
+        }
+        out.push(inline);
+    }
+    *inlines = out;
+}
+
+/// Splits `:name:` shortcodes with a known emoji out of a text node.
+fn split_emoji(text: &str, span: Span) -> InlineSeq {
+    let bytes = text.as_bytes();
+    let clamp = |offset: usize| (span.start + offset).min(span.end);
+    let mut out = Vec::new();
+    let mut last = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b':' || (i > 0 && bytes[i - 1].is_ascii_alphanumeric()) {
+            i += 1;
+            continue;
+        }
+        let name_len = bytes[i + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'+' | b'-'))
+            .count();
+        let close = i + 1 + name_len;
+        let name = &text[i + 1..close];
+        let emoji = if bytes.get(close) == Some(&b':') {
+            lookup_emoji(name)
+        } else {
+            None
+        };
+        let Some(emoji) = emoji else {
+            i += 1;
+            continue;
+        };
+        if i > last {
+            out.push(Inline {
+                span: Span {
+                    start: clamp(last),
+                    end: clamp(i),
+                },
+                kind: InlineKind::Text(text[last..i].to_string()),
+            });
+        }
+        out.push(Inline {
+            span: Span {
+                start: clamp(i),
+                end: clamp(close + 1),
+            },
+            kind: InlineKind::Emoji {
+                name: name.to_string(),
+                emoji: emoji.to_string(),
+            },
+        });
+        i = close + 1;
+        last = i;
+    }
+    if last < bytes.len() {
+        out.push(Inline {
+            span: Span {
+                start: clamp(last),
+                end: span.end,
+            },
+            kind: InlineKind::Text(text[last..].to_string()),
+        });
+    }
+    out
+}
+
+/// Skips the spaces and tabs that start a paragraph continuation line.
+fn skip_line_indent(bytes: &[u8], mut i: usize, end: usize) -> usize {
+    while i < end && matches!(bytes[i], b' ' | b'\t') {
+        i += 1;
+    }
+    i
+}
+
 fn split_autolinks(text: &str, span: Span) -> InlineSeq {
     let bytes = text.as_bytes();
     let mut out = Vec::new();
@@ -4523,17 +5814,20 @@ fn item_has_blank_between_blocks(lines: &[Line], blocks: &[Block]) -> bool {
     false
 }
 
-fn count_lines(text: &str) -> u32 {
-    if text.is_empty() {
-        return 0;
+/// Joins code lines CommonMark-style: every line, including the last, ends with `\n`.
+fn join_code_lines(lines: &[String]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(line);
+        text.push('\n');
     }
-    let mut count = 1;
-    for byte in text.bytes() {
-        if byte == b'\n' {
-            count += 1;
-        }
-    }
-    count
+    text
+}
+
+/// Counts code lines the same way the emitter splits them: a trailing `\n`
+/// ends the last line instead of starting a new one.
+fn count_lines(text: &str) -> u32 {
+    text.lines().count() as u32
 }
 
 fn is_valid_label(name: &str) -> bool {
@@ -4544,6 +5838,81 @@ fn is_valid_label(name: &str) -> bool {
         .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
 }
 
+/// Attribute keys use the same characters as labels.
+fn is_attr_key(key: &str) -> bool {
+    is_valid_label(key)
+}
+
+/// Attribute keys read from a block's target line, by the blocks that read
+/// them. `class` and `lang` apply everywhere.
+const BLOCK_ATTR_KEYS: &[(&str, &[&str])] = &[
+    (
+        "box",
+        &[
+            "bg",
+            "title-bg",
+            "border-color",
+            "border-style",
+            "border-width",
+            "collapsible",
+            "open",
+        ],
+    ),
+    ("container", &["open"]),
+    (
+        "code",
+        &[
+            "caption", "file", "title", "hl", "diff_add", "diff_del", "wrap", "output",
+        ],
+    ),
+    ("list", &["type", "reversed", "continue"]),
+    ("table", &["caption"]),
+];
+
+fn block_attr_keys(kind: &BlockKind) -> &'static [&'static str] {
+    let name = match kind {
+        BlockKind::Box(_) => "box",
+        BlockKind::Container { .. } => "container",
+        BlockKind::CodeBlock(_) => "code",
+        BlockKind::List(_) => "list",
+        BlockKind::Table(_) => "table",
+        _ => return &[],
+    };
+    BLOCK_ATTR_KEYS
+        .iter()
+        .find(|(block, _)| *block == name)
+        .map_or(&[], |(_, keys)| keys)
+}
+
+/// `bg-color` for `bg`, or a key within a typo or two of `candidate`.
+fn is_misspelled_key(key: &str, candidate: &str) -> bool {
+    if key
+        .strip_prefix(candidate)
+        .is_some_and(|rest| rest.starts_with('-'))
+    {
+        return true;
+    }
+    let limit = if candidate.len() < 5 { 1 } else { 2 };
+    edit_distance(key, candidate) <= limit
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (row[j + 1] + 1)
+                .min(row[j] + 1)
+                .min(diag + usize::from(ca != *cb));
+            diag = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
 fn is_hex_color(value: &str) -> bool {
     let value = value.trim();
     let hex = match value.strip_prefix('#') {
@@ -4557,11 +5926,10 @@ fn is_hex_color(value: &str) -> bool {
     hex.chars().all(|c| c.is_ascii_hexdigit())
 }
 
+const BORDER_STYLES: &[&str] = &["solid", "dashed", "dotted", "double", "none"];
+
 fn is_border_style(value: &str) -> bool {
-    matches!(
-        value.trim(),
-        "solid" | "dashed" | "dotted" | "double" | "none"
-    )
+    BORDER_STYLES.contains(&value.trim())
 }
 
 fn is_border_width(value: &str) -> bool {
@@ -4660,6 +6028,23 @@ fn delimiter_blocked(opener: &Delimiter, closer: &Delimiter) -> bool {
     opener.len % 3 != 0 || closer.len % 3 != 0
 }
 
+/// Reads a `fig:`-style category prefix; returns the label start after it.
+fn parse_ref_category(bytes: &[u8], start: usize, end: usize) -> (Option<RefCategory>, usize) {
+    let mut i = start;
+    while i < end && bytes[i].is_ascii_lowercase() {
+        i += 1;
+    }
+    if i + 1 < end && bytes[i] == b':' {
+        let prefix = std::str::from_utf8(&bytes[start..i]).unwrap_or_default();
+        if let Some(category) = RefCategory::from_prefix(prefix)
+            && parse_label(bytes, i + 1, end).is_some()
+        {
+            return (Some(category), i + 1);
+        }
+    }
+    (None, start)
+}
+
 fn parse_label(bytes: &[u8], start: usize, end: usize) -> Option<(String, usize)> {
     if start >= end {
         return None;
@@ -4681,6 +6066,44 @@ fn parse_label(bytes: &[u8], start: usize, end: usize) -> Option<(String, usize)
     }
 }
 
+/// Loose `srcset` check: comma-separated candidates, each a URL with at most
+/// one width (`480w`) or density (`2x`, `1.5x`) descriptor.
+fn is_srcset(value: &str) -> bool {
+    value.split(',').all(|candidate| {
+        let mut parts = candidate.split_whitespace();
+        let (Some(_url), descriptor, None) = (parts.next(), parts.next(), parts.next()) else {
+            return false;
+        };
+        descriptor.is_none_or(|descriptor| {
+            if let Some(width) = descriptor.strip_suffix('w') {
+                !width.is_empty() && width.bytes().all(|b| b.is_ascii_digit())
+            } else if let Some(density) = descriptor.strip_suffix('x') {
+                density.parse::<f64>().is_ok_and(|density| density > 0.0)
+            } else {
+                false
+            }
+        })
+    })
+}
+
+/// Finds the `}` closing an attribute list that starts at `start`, on the same line.
+fn find_inline_attr_end(bytes: &[u8], start: usize, end: usize) -> Option<usize> {
+    if bytes.get(start) != Some(&b'{') {
+        return None;
+    }
+    let mut in_quotes = false;
+    for (idx, &b) in bytes.iter().enumerate().take(end).skip(start + 1) {
+        match b {
+            b'\n' => return None,
+            b'{' if !in_quotes => return None,
+            b'"' => in_quotes = !in_quotes,
+            b'}' if !in_quotes => return Some(idx),
+            _ => {}
+        }
+    }
+    None
+}
+
 fn find_bracket_end(bytes: &[u8], start: usize, end: usize) -> Option<(usize, bool)> {
     let mut i = start;
     let mut depth = 0usize;
@@ -4995,7 +6418,14 @@ fn parse_link_reference_definition_lines(
                         // Treat as destination-only definition (no title).
                         return Some((
                             label,
-                            LinkDefinition { url, title: None },
+                            LinkDefinition {
+                                url,
+                                title: None,
+                                span: Span {
+                                    start: line.start,
+                                    end: lines[end_line_idx].end,
+                                },
+                            },
                             end_line_idx + 1,
                         ));
                     }
@@ -5014,7 +6444,11 @@ fn parse_link_reference_definition_lines(
         }
     }
 
-    Some((label, LinkDefinition { url, title }, end_line_idx + 1))
+    let span = Span {
+        start: line.start,
+        end: lines[end_line_idx].end,
+    };
+    Some((label, LinkDefinition { url, title, span }, end_line_idx + 1))
 }
 
 fn skip_spaces_tabs(text: &str, mut pos: usize) -> usize {
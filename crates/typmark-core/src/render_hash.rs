@@ -0,0 +1,118 @@
+use crate::emit::HtmlEmitOptions;
+
+/// Bump whenever the HTML emitted for the same input and options changes, so
+/// hashes from older builds stop matching.
+const RENDER_FORMAT_VERSION: u32 = 1;
+
+/// A hash of everything that determines the HTML for `source`, for use as an
+/// ETag or cache key without rendering.
+///
+/// The input is the crate version, an internal output format version, the
+/// source with line endings normalized to `\n`, and every field of `options`.
+/// Math and directive renderers are hashed by their `Debug` output, and
+/// included files and fonts added with `add_font_bytes` are not hashed at all;
+/// change the source or options when those change. The value is stable across
+/// runs and platforms for one crate version, but not across crate versions.
+/// Format it with `{:016x}` for a header value.
+pub fn render_hash(source: &str, options: &HtmlEmitOptions) -> u64 {
+    let HtmlEmitOptions {
+        wrap_sections,
+        simple_code_blocks,
+        math_output,
+        math_renderer,
+        raw_html,
+        strip_comments,
+        heading_offset,
+        class_prefix,
+        directive_handlers,
+        id_namespace,
+        emoji,
+        soft_breaks_as_br,
+        smart_punctuation,
+        wrap_dates,
+        heading_anchors,
+        heading_anchor_position,
+        code_copy_button,
+        number_sections,
+        kbd_directive,
+        line_ending,
+    } = options;
+
+    let mut hasher = Fnv64::new();
+    hasher.write_str(env!("CARGO_PKG_VERSION"));
+    hasher.write(&RENDER_FORMAT_VERSION.to_le_bytes());
+    hasher.write_str(&source.replace("\r\n", "\n").replace('\r', "\n"));
+
+    for flag in [
+        *wrap_sections,
+        *simple_code_blocks,
+        *strip_comments,
+        *emoji,
+        *soft_breaks_as_br,
+        *smart_punctuation,
+        *wrap_dates,
+        *heading_anchors,
+        *code_copy_button,
+        *number_sections,
+        *kbd_directive,
+    ] {
+        hasher.write(&[u8::from(flag)]);
+    }
+    hasher.write(&heading_offset.to_le_bytes());
+    hasher.write_str(&format!("{math_output:?}"));
+    hasher.write_str(&format!("{raw_html:?}"));
+    hasher.write_str(&format!("{heading_anchor_position:?}"));
+    hasher.write_str(&format!("{line_ending:?}"));
+    hasher.write_str(class_prefix);
+    hasher.write_option(id_namespace.as_deref());
+    hasher.write_option(
+        math_renderer
+            .as_ref()
+            .map(|renderer| format!("{renderer:?}"))
+            .as_deref(),
+    );
+    let mut handlers: Vec<_> = directive_handlers.iter().collect();
+    handlers.sort_by(|a, b| a.0.cmp(b.0));
+    hasher.write(&(handlers.len() as u64).to_le_bytes());
+    for (name, handler) in handlers {
+        hasher.write_str(name);
+        hasher.write_str(&format!("{handler:?}"));
+    }
+    hasher.finish()
+}
+
+/// 64-bit FNV-1a. `std`'s default hasher may change between Rust releases.
+struct Fnv64(u64);
+
+impl Fnv64 {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Length-prefixed, so adjacent strings cannot run together.
+    fn write_str(&mut self, text: &str) {
+        self.write(&(text.len() as u64).to_le_bytes());
+        self.write(text.as_bytes());
+    }
+
+    fn write_option(&mut self, text: Option<&str>) {
+        match text {
+            Some(text) => {
+                self.write(&[1]);
+                self.write_str(text);
+            }
+            None => self.write(&[0]),
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -1,17 +1,24 @@
 use std::collections::{HashMap, HashSet};
 
 use crate::ast::{
-    Block, BlockKind, BoxBlock, Document, Inline, InlineKind, InlineSeq, Label, LinkDefinition,
-    LinkRefMeta, List, ResolvedRef,
+    AttrList, Block, BlockKind, BoxBlock, Document, Inline, InlineKind, InlineSeq, Label,
+    LinkDefinition, LinkRefMeta, List, RefCategory, RefNumber, ResolvedRef,
 };
 use crate::diagnostic::{
-    Diagnostic, DiagnosticSeverity, E_LABEL_DUP, E_REF_DEPTH, E_REF_OMIT, E_REF_SELF_TITLE,
-    W_REF_MISSING,
+    Diagnostic, DiagnosticSeverity, E_LABEL_DUP, E_MATH_COMPILE, E_MATH_PREAMBLE, E_REF_DEPTH,
+    E_REF_OMIT, E_REF_SELF_TITLE, Fix, RelatedDiagnostic, W_CITE_MISSING, W_HEADING_SKIP,
+    W_LINK_DEF_UNUSED, W_REF_CATEGORY, W_REF_MISSING,
 };
 use crate::label::{normalize_link_label, unescape_backslash_punct};
-use crate::section::build_sections;
+use crate::math::{
+    MathOutput, MathSettings, check_math, check_math_preamble, math_settings_from_attrs,
+};
+use crate::section::{build_sections, number_sections};
 use crate::source_map::SourceMap;
 use crate::span::Span;
+use crate::visit::{
+    Visitor, VisitorMut, walk_block, walk_document, walk_document_mut, walk_inline,
+};
 
 pub struct ResolveResult {
     pub document: Document,
@@ -23,6 +30,8 @@ struct LabelInfo {
     span: Span,
     kind: LabelKind,
     title: Option<Vec<Inline>>,
+    category: Option<RefCategory>,
+    number: Option<RefNumber>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -32,24 +41,79 @@ enum LabelKind {
     CodeLine,
 }
 
+/// Options for [`resolve_with_options`].
+#[derive(Clone, Debug, Default)]
+pub struct ResolveOptions {
+    /// The math output the document will be emitted with. The math preamble
+    /// and every formula are compiled to report `E_MATH_COMPILE` and
+    /// `E_MATH_PREAMBLE` only when it renders Typst SVG (`Svg`, the default,
+    /// or `SvgWithMathMLAnnotation`).
+    pub math_output: MathOutput,
+    /// Warns with `W_LINK_DEF_UNUSED` about link reference definitions that no
+    /// reference-style link or image uses.
+    pub warn_unused_link_defs: bool,
+    /// Warns with `W_HEADING_SKIP` about headings more than one level deeper
+    /// than the heading before them, such as an `h3` right after an `h1`.
+    pub warn_heading_skips: bool,
+}
+
 pub fn resolve(
+    document: Document,
+    source: &str,
+    source_map: &SourceMap,
+    diagnostics: Vec<Diagnostic>,
+    link_defs: &HashMap<String, LinkDefinition>,
+) -> ResolveResult {
+    resolve_with_options(
+        document,
+        source,
+        source_map,
+        diagnostics,
+        link_defs,
+        &ResolveOptions::default(),
+    )
+}
+
+pub fn resolve_with_options(
     document: Document,
     source: &str,
     source_map: &SourceMap,
     mut diagnostics: Vec<Diagnostic>,
     link_defs: &HashMap<String, LinkDefinition>,
+    options: &ResolveOptions,
 ) -> ResolveResult {
     let mut document = document;
+    if options.warn_unused_link_defs {
+        check_unused_link_defs(&document, link_defs, &mut diagnostics, source_map);
+    }
+    if options.warn_heading_skips {
+        check_heading_skips(&document, &mut diagnostics, source_map);
+    }
     // First, resolve CommonMark-style link references like [text][label].
     resolve_link_refs(&mut document, source, link_defs);
 
     // Then, build the section tree for TypMark-style header/section linking.
     document.blocks = build_sections(document.blocks);
+    number_sections(&mut document.blocks);
+    // Number citations before titles are copied into reference text.
+    resolve_citations(&mut document);
 
     let mut labels = HashMap::new();
     collect_labels(&document.blocks, &mut labels, &mut diagnostics, source_map);
+    assign_ref_numbers(&mut labels);
+    fall_back_citations(&mut document, &labels, &mut diagnostics, source_map);
     check_self_reference_titles(&document.blocks, &mut diagnostics, source_map);
     resolve_refs(&mut document.blocks, &labels, &mut diagnostics, source_map);
+    pair_code_outputs(&mut document.blocks, &mut diagnostics, source_map);
+    continue_list_numbering(&mut document.blocks);
+    if matches!(
+        options.math_output,
+        MathOutput::Svg | MathOutput::SvgWithMathMLAnnotation
+    ) {
+        let settings = math_settings_from_attrs(document.settings.as_ref());
+        check_math_preamble_setting(&document, &settings, &mut diagnostics, source_map);
+        check_math_nodes(&document, settings, &mut diagnostics, source_map);
+    }
 
     ResolveResult {
         document,
@@ -57,6 +121,395 @@ pub fn resolve(
     }
 }
 
+/// Moves the code block named by `output=label` into the code block that names it.
+/// The output must be a later code block in the same container; anything else
+/// is `W_REF_MISSING` and both blocks stay where they are.
+fn pair_code_outputs(
+    blocks: &mut Vec<Block>,
+    diagnostics: &mut Vec<Diagnostic>,
+    source_map: &SourceMap,
+) {
+    let mut idx = 0;
+    while idx < blocks.len() {
+        let target = match &blocks[idx].kind {
+            BlockKind::CodeBlock(code_block) => blocks[idx]
+                .attrs
+                .items
+                .iter()
+                .chain(&code_block.info_attrs.items)
+                .find(|item| item.key == "output")
+                .map(|item| item.value.clone()),
+            _ => None,
+        };
+        if let Some(target) = target {
+            let found = blocks[idx + 1..].iter().position(|block| {
+                matches!(block.kind, BlockKind::CodeBlock(_))
+                    && block
+                        .attrs
+                        .label
+                        .as_ref()
+                        .is_some_and(|label| label.name == target.raw)
+            });
+            match found {
+                Some(offset) => {
+                    let output = blocks.remove(idx + 1 + offset);
+                    if let BlockKind::CodeBlock(code_block) = &mut blocks[idx].kind {
+                        code_block.output = Some(Box::new(output));
+                    }
+                }
+                None => diagnostics.push(Diagnostic::new(
+                    source_map.range(target.span),
+                    DiagnosticSeverity::Warning,
+                    W_REF_MISSING,
+                    format!(
+                        "output label '{}' does not name a following code block",
+                        target.raw
+                    ),
+                )),
+            }
+        }
+        match &mut blocks[idx].kind {
+            BlockKind::List(List { items, .. }) => {
+                for item in items {
+                    pair_code_outputs(&mut item.blocks, diagnostics, source_map);
+                }
+            }
+            BlockKind::BlockQuote { blocks, .. }
+            | BlockKind::Box(BoxBlock { blocks, .. })
+            | BlockKind::Container { blocks, .. }
+            | BlockKind::Section {
+                children: blocks, ..
+            } => pair_code_outputs(blocks, diagnostics, source_map),
+            _ => {}
+        }
+        idx += 1;
+    }
+}
+
+/// Starts a `continue=true` ordered list where the previous ordered list in the
+/// same container left off. Without such a list the written start number stays.
+fn continue_list_numbering(blocks: &mut [Block]) {
+    let mut next_number = None;
+    for block in blocks {
+        let continues = block
+            .attrs
+            .items
+            .iter()
+            .any(|item| item.key == "continue" && item.value.raw.trim() == "true");
+        match &mut block.kind {
+            BlockKind::List(list) => {
+                if list.ordered {
+                    if continues && let Some(number) = next_number {
+                        list.start = Some(number);
+                    }
+                    next_number = Some(list.start.unwrap_or(1) + list.items.len() as u64);
+                }
+                for item in &mut list.items {
+                    continue_list_numbering(&mut item.blocks);
+                }
+            }
+            BlockKind::BlockQuote { blocks, .. }
+            | BlockKind::Box(BoxBlock { blocks, .. })
+            | BlockKind::Container { blocks, .. }
+            | BlockKind::Section {
+                children: blocks, ..
+            } => continue_list_numbering(blocks),
+            _ => {}
+        }
+    }
+}
+
+fn check_math_preamble_setting(
+    document: &Document,
+    math_settings: &MathSettings,
+    diagnostics: &mut Vec<Diagnostic>,
+    source_map: &SourceMap,
+) {
+    let Some(settings) = &document.settings else {
+        return;
+    };
+    for item in settings
+        .items
+        .iter()
+        .filter(|item| item.key == "math-preamble")
+    {
+        if let Err(message) = check_math_preamble(&item.value.raw, math_settings) {
+            diagnostics.push(Diagnostic::new(
+                source_map.range(item.value.span),
+                DiagnosticSeverity::Error,
+                E_MATH_PREAMBLE,
+                format!("math-preamble is ignored: {}", message),
+            ));
+        }
+    }
+}
+
+/// Numbers cited keys in order of first citation and warns about keys
+/// without a `bib-<key>` setting.
+fn resolve_citations(document: &mut Document) {
+    let bibliography = document
+        .settings
+        .iter()
+        .flat_map(|settings| &settings.items)
+        .filter_map(|item| item.key.strip_prefix("bib-"))
+        .map(str::to_string)
+        .collect();
+    let mut citations = CitationNumbers {
+        bibliography,
+        numbers: HashMap::new(),
+    };
+    walk_document_mut(&mut citations, document);
+}
+
+struct CitationNumbers {
+    bibliography: HashSet<String>,
+    numbers: HashMap<String, usize>,
+}
+
+impl VisitorMut for CitationNumbers {
+    fn visit_inline_mut(&mut self, inline: &mut Inline) -> bool {
+        let InlineKind::Citation { keys, numbers } = &mut inline.kind else {
+            return true;
+        };
+        for (key, number) in keys.iter().zip(numbers.iter_mut()) {
+            if self.bibliography.contains(&key.name) {
+                let next = self.numbers.len() + 1;
+                *number = Some(*self.numbers.entry(key.name.clone()).or_insert(next));
+            }
+        }
+        false
+    }
+}
+
+/// Reads a lone `[@key]` without a bibliography entry as the reference `@key`
+/// in brackets when the document has that label, as it was before citations
+/// existed. Other keys without an entry are reported.
+fn fall_back_citations(
+    document: &mut Document,
+    labels: &HashMap<String, LabelInfo>,
+    diagnostics: &mut Vec<Diagnostic>,
+    source_map: &SourceMap,
+) {
+    let mut fallback = CitationFallback {
+        labels,
+        diagnostics,
+        source_map,
+    };
+    walk_document_mut(&mut fallback, document);
+}
+
+struct CitationFallback<'a> {
+    labels: &'a HashMap<String, LabelInfo>,
+    diagnostics: &'a mut Vec<Diagnostic>,
+    source_map: &'a SourceMap,
+}
+
+impl VisitorMut for CitationFallback<'_> {
+    fn visit_inlines_mut(&mut self, inlines: &mut InlineSeq) {
+        let mut idx = 0;
+        while idx < inlines.len() {
+            let span = inlines[idx].span;
+            let label = match &inlines[idx].kind {
+                InlineKind::Citation { keys, numbers }
+                    if keys.len() == 1
+                        && numbers[0].is_none()
+                        && self.labels.contains_key(&keys[0].name) =>
+                {
+                    keys[0].clone()
+                }
+                _ => {
+                    idx += 1;
+                    continue;
+                }
+            };
+            let text = |text: &str, start: usize, end: usize| Inline {
+                span: Span { start, end },
+                kind: InlineKind::Text(text.to_string()),
+            };
+            let reference = Inline {
+                span: Span {
+                    start: label.span.start - 1,
+                    end: label.span.end,
+                },
+                kind: InlineKind::Ref {
+                    label,
+                    bracket: None,
+                    numbered: None,
+                    resolved: None,
+                },
+            };
+            inlines.splice(
+                idx..=idx,
+                [
+                    text("[", span.start, span.start + 1),
+                    reference,
+                    text("]", span.end - 1, span.end),
+                ],
+            );
+            idx += 3;
+        }
+    }
+
+    fn visit_inline_mut(&mut self, inline: &mut Inline) -> bool {
+        let InlineKind::Citation { keys, numbers } = &inline.kind else {
+            return true;
+        };
+        for (key, number) in keys.iter().zip(numbers) {
+            if number.is_none() {
+                self.diagnostics.push(Diagnostic::new(
+                    self.source_map.range(key.span),
+                    DiagnosticSeverity::Warning,
+                    W_CITE_MISSING,
+                    format!("no bibliography entry for '{}'", key.name),
+                ));
+            }
+        }
+        false
+    }
+}
+
+/// Compiles every formula with the document's math settings and reports the
+/// ones that fail, so they are flagged before the error box is emitted.
+fn check_math_nodes(
+    document: &Document,
+    settings: MathSettings,
+    diagnostics: &mut Vec<Diagnostic>,
+    source_map: &SourceMap,
+) {
+    let mut checker = MathChecker {
+        settings,
+        diagnostics,
+        source_map,
+    };
+    walk_document(&mut checker, document);
+}
+
+struct MathChecker<'a> {
+    settings: MathSettings,
+    diagnostics: &'a mut Vec<Diagnostic>,
+    source_map: &'a SourceMap,
+}
+
+impl MathChecker<'_> {
+    fn check(&mut self, typst_src: &str, display: bool, span: Span) {
+        if let Err(message) = check_math(typst_src, display, &self.settings) {
+            self.diagnostics.push(Diagnostic::new(
+                self.source_map.range(span),
+                DiagnosticSeverity::Error,
+                E_MATH_COMPILE,
+                format!("math failed to compile: {}", message),
+            ));
+        }
+    }
+}
+
+impl Visitor for MathChecker<'_> {
+    fn visit_block(&mut self, block: &Block) {
+        if let BlockKind::MathBlock { typst_src } = &block.kind {
+            self.check(typst_src, true, block.span);
+        }
+        walk_block(self, block);
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        if let InlineKind::MathInline { typst_src } = &inline.kind {
+            self.check(typst_src, false, inline.span);
+        }
+        walk_inline(self, inline);
+    }
+}
+
+/// Runs before references are resolved, while every use is still a `LinkRef`
+/// or `ImageRef` node.
+fn check_unused_link_defs(
+    document: &Document,
+    link_defs: &HashMap<String, LinkDefinition>,
+    diagnostics: &mut Vec<Diagnostic>,
+    source_map: &SourceMap,
+) {
+    let mut collector = LinkRefCollector::default();
+    walk_document(&mut collector, document);
+    let mut unused: Vec<_> = link_defs
+        .iter()
+        .filter(|(label, _)| !collector.used.contains(*label))
+        .map(|(_, def)| def.span)
+        .collect();
+    unused.sort_by_key(|span| span.start);
+    for span in unused {
+        diagnostics.push(Diagnostic::new(
+            source_map.range(span),
+            DiagnosticSeverity::Warning,
+            W_LINK_DEF_UNUSED,
+            "unused link reference definition",
+        ));
+    }
+}
+
+/// Runs before sections are built, while every heading still has its own span.
+/// Headings inside quotes, lists and boxes count too, in document order.
+fn check_heading_skips(
+    document: &Document,
+    diagnostics: &mut Vec<Diagnostic>,
+    source_map: &SourceMap,
+) {
+    let mut checker = HeadingSkipChecker {
+        previous: None,
+        diagnostics,
+        source_map,
+    };
+    walk_document(&mut checker, document);
+}
+
+struct HeadingSkipChecker<'a> {
+    previous: Option<(u8, Span)>,
+    diagnostics: &'a mut Vec<Diagnostic>,
+    source_map: &'a SourceMap,
+}
+
+impl Visitor for HeadingSkipChecker<'_> {
+    fn visit_block(&mut self, block: &Block) {
+        if let BlockKind::Heading { level, .. } = block.kind {
+            if let Some((previous, previous_span)) = self.previous
+                && level > previous + 1
+            {
+                let mut diag = Diagnostic::new(
+                    self.source_map.range(block.span),
+                    DiagnosticSeverity::Warning,
+                    W_HEADING_SKIP,
+                    format!(
+                        "heading level {} follows a level {} heading; expected level {} or lower",
+                        level,
+                        previous,
+                        previous + 1
+                    ),
+                );
+                diag.related.push(RelatedDiagnostic::new(
+                    self.source_map.range(previous_span),
+                    Some("previous heading".to_string()),
+                ));
+                self.diagnostics.push(diag);
+            }
+            self.previous = Some((level, block.span));
+        }
+        walk_block(self, block);
+    }
+}
+
+#[derive(Default)]
+struct LinkRefCollector {
+    used: HashSet<String>,
+}
+
+impl Visitor for LinkRefCollector {
+    fn visit_inline(&mut self, inline: &Inline) {
+        if let InlineKind::LinkRef { label, .. } | InlineKind::ImageRef { label, .. } = &inline.kind
+        {
+            self.used.insert(normalize_link_label(label.as_bytes()));
+        }
+        walk_inline(self, inline);
+    }
+}
+
 fn resolve_link_refs(
     document: &mut Document,
     source: &str,
@@ -84,7 +537,7 @@ fn resolve_link_refs_in_blocks(
                 resolve_link_refs_inlines(title, source, link_defs);
                 resolve_link_refs_in_blocks(children, source, link_defs);
             }
-            BlockKind::BlockQuote { blocks } => {
+            BlockKind::BlockQuote { blocks, .. } => {
                 resolve_link_refs_in_blocks(blocks, source, link_defs);
             }
             BlockKind::List(List { items, .. }) => {
@@ -92,7 +545,8 @@ fn resolve_link_refs_in_blocks(
                     resolve_link_refs_in_blocks(&mut item.blocks, source, link_defs);
                 }
             }
-            BlockKind::Box(BoxBlock { title, blocks }) => {
+            BlockKind::Box(BoxBlock { title, blocks })
+            | BlockKind::Container { title, blocks, .. } => {
                 if let Some(title) = title.as_mut() {
                     resolve_link_refs_inlines(title, source, link_defs);
                 }
@@ -142,15 +596,25 @@ fn resolve_link_refs_inlines(
                         let alt = std::mem::take(alt);
                         let url = def.url.clone();
                         let title = def.title.clone();
-                        inline.kind = InlineKind::Image { url, title, alt };
+                        inline.kind = InlineKind::Image {
+                            url,
+                            title,
+                            alt,
+                            attrs: AttrList::default(),
+                        };
                     } else {
                         let alt = std::mem::take(alt);
                         replace = Some(build_link_ref_fallback(meta, alt, true, source));
                     }
                 }
-                InlineKind::Emph(children) | InlineKind::Strong(children) => {
+                InlineKind::Emph(children)
+                | InlineKind::Strong(children)
+                | InlineKind::Span { children, .. } => {
                     resolve_link_refs_inlines(children, source, link_defs);
                 }
+                InlineKind::Directive(directive) => {
+                    resolve_link_refs_inlines(&mut directive.label, source, link_defs);
+                }
                 InlineKind::Link { children, .. } => {
                     resolve_link_refs_inlines(children, source, link_defs);
                 }
@@ -223,12 +687,23 @@ fn collect_labels(
         if let Some(label) = block.attrs.label.as_ref() {
             let (kind, title) = match &block.kind {
                 BlockKind::Section { title, .. } => (LabelKind::Title, Some(title.clone())),
-                BlockKind::Box(BoxBlock { title, .. }) if title.is_some() => {
+                BlockKind::Box(BoxBlock { title, .. }) | BlockKind::Container { title, .. }
+                    if title.is_some() =>
+                {
                     (LabelKind::Title, title.clone())
                 }
                 _ => (LabelKind::Block, None),
             };
-            insert_label(labels, label, kind, title, diagnostics, source_map);
+            let category = ref_category(block);
+            insert_label(
+                labels,
+                label,
+                kind,
+                title,
+                category,
+                diagnostics,
+                source_map,
+            );
         }
 
         if let BlockKind::CodeBlock(code_block) = &block.kind {
@@ -238,6 +713,7 @@ fn collect_labels(
                     &line_label.label,
                     LabelKind::CodeLine,
                     None,
+                    None,
                     diagnostics,
                     source_map,
                 );
@@ -250,10 +726,10 @@ fn collect_labels(
                     collect_labels(&item.blocks, labels, diagnostics, source_map);
                 }
             }
-            BlockKind::BlockQuote { blocks } => {
+            BlockKind::BlockQuote { blocks, .. } => {
                 collect_labels(blocks, labels, diagnostics, source_map);
             }
-            BlockKind::Box(BoxBlock { blocks, .. }) => {
+            BlockKind::Box(BoxBlock { blocks, .. }) | BlockKind::Container { blocks, .. } => {
                 collect_labels(blocks, labels, diagnostics, source_map);
             }
             BlockKind::Section { children, .. } => {
@@ -269,6 +745,7 @@ fn insert_label(
     label: &Label,
     kind: LabelKind,
     title: Option<Vec<Inline>>,
+    category: Option<RefCategory>,
     diagnostics: &mut Vec<Diagnostic>,
     source_map: &SourceMap,
 ) {
@@ -279,7 +756,7 @@ fn insert_label(
             E_LABEL_DUP,
             "duplicate label",
         );
-        diag.related.push(crate::diagnostic::RelatedDiagnostic::new(
+        diag.related.push(RelatedDiagnostic::new(
             source_map.range(existing.span),
             None,
         ));
@@ -292,10 +769,64 @@ fn insert_label(
             span: label.span,
             kind,
             title,
+            category,
+            number: None,
         },
     );
 }
 
+fn ref_category(block: &Block) -> Option<RefCategory> {
+    match &block.kind {
+        BlockKind::Section { .. } | BlockKind::Heading { .. } => Some(RefCategory::Section),
+        BlockKind::Table(_) => Some(RefCategory::Table),
+        BlockKind::MathBlock { .. } => Some(RefCategory::Equation),
+        BlockKind::Box(_) => Some(RefCategory::Figure),
+        BlockKind::Paragraph { content } => match content.as_slice() {
+            [
+                Inline {
+                    kind: InlineKind::Image { .. },
+                    ..
+                },
+            ] => Some(RefCategory::Figure),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Ordinals follow document order, which matches the order of label spans.
+fn assign_ref_numbers(labels: &mut HashMap<String, LabelInfo>) {
+    let mut numbered: Vec<&mut LabelInfo> = labels
+        .values_mut()
+        .filter(|info| info.category.is_some())
+        .collect();
+    numbered.sort_by_key(|info| info.span.start);
+    let mut counters: HashMap<RefCategory, usize> = HashMap::new();
+    for info in numbered {
+        let Some(category) = info.category else {
+            continue;
+        };
+        let ordinal = counters.entry(category).or_insert(0);
+        *ordinal += 1;
+        info.number = Some(RefNumber {
+            category,
+            ordinal: *ordinal,
+        });
+    }
+}
+
+/// The fix deletes the reference, leaving the rest of the title.
+fn self_title_diagnostic(span: Span, source_map: &SourceMap) -> Diagnostic {
+    let range = source_map.range(span);
+    Diagnostic::new(
+        range,
+        DiagnosticSeverity::Error,
+        E_REF_SELF_TITLE,
+        "self-reference in title",
+    )
+    .with_fix(Fix::new(range, "", "Remove the self-reference"))
+}
+
 fn check_self_reference_titles(
     blocks: &[Block],
     diagnostics: &mut Vec<Diagnostic>,
@@ -309,26 +840,19 @@ fn check_self_reference_titles(
                 ..
             } => {
                 if let Some(span) = find_self_ref(title, &label.name) {
-                    diagnostics.push(Diagnostic::new(
-                        source_map.range(span),
-                        DiagnosticSeverity::Error,
-                        E_REF_SELF_TITLE,
-                        "self-reference in title",
-                    ));
+                    diagnostics.push(self_title_diagnostic(span, source_map));
                 }
             }
             BlockKind::Box(BoxBlock {
                 title: Some(title), ..
-            }) => {
+            })
+            | BlockKind::Container {
+                title: Some(title), ..
+            } => {
                 if let Some(label) = block.attrs.label.as_ref()
                     && let Some(span) = find_self_ref(title, &label.name)
                 {
-                    diagnostics.push(Diagnostic::new(
-                        source_map.range(span),
-                        DiagnosticSeverity::Error,
-                        E_REF_SELF_TITLE,
-                        "self-reference in title",
-                    ));
+                    diagnostics.push(self_title_diagnostic(span, source_map));
                 }
             }
             _ => {}
@@ -340,10 +864,10 @@ fn check_self_reference_titles(
                     check_self_reference_titles(&item.blocks, diagnostics, source_map);
                 }
             }
-            BlockKind::BlockQuote { blocks } => {
+            BlockKind::BlockQuote { blocks, .. } => {
                 check_self_reference_titles(blocks, diagnostics, source_map);
             }
-            BlockKind::Box(BoxBlock { blocks, .. }) => {
+            BlockKind::Box(BoxBlock { blocks, .. }) | BlockKind::Container { blocks, .. } => {
                 check_self_reference_titles(blocks, diagnostics, source_map);
             }
             BlockKind::Section { children, .. } => {
@@ -371,11 +895,18 @@ fn find_self_ref(inlines: &[Inline], label: &str) -> Option<Span> {
                     return Some(span);
                 }
             }
-            InlineKind::Emph(children) | InlineKind::Strong(children) => {
+            InlineKind::Emph(children)
+            | InlineKind::Strong(children)
+            | InlineKind::Span { children, .. } => {
                 if let Some(span) = find_self_ref(children, label) {
                     return Some(span);
                 }
             }
+            InlineKind::Directive(directive) => {
+                if let Some(span) = find_self_ref(&directive.label, label) {
+                    return Some(span);
+                }
+            }
             InlineKind::Link { children, .. } | InlineKind::LinkRef { children, .. } => {
                 if let Some(span) = find_self_ref(children, label) {
                     return Some(span);
@@ -412,7 +943,7 @@ fn resolve_refs(
                 resolve_inlines(title, labels, diagnostics, source_map);
                 resolve_refs(children, labels, diagnostics, source_map);
             }
-            BlockKind::BlockQuote { blocks } => {
+            BlockKind::BlockQuote { blocks, .. } => {
                 resolve_refs(blocks, labels, diagnostics, source_map);
             }
             BlockKind::List(List { items, .. }) => {
@@ -420,7 +951,8 @@ fn resolve_refs(
                     resolve_refs(&mut item.blocks, labels, diagnostics, source_map);
                 }
             }
-            BlockKind::Box(BoxBlock { title, blocks }) => {
+            BlockKind::Box(BoxBlock { title, blocks })
+            | BlockKind::Container { title, blocks, .. } => {
                 if let Some(title) = title.as_mut() {
                     resolve_inlines(title, labels, diagnostics, source_map);
                 }
@@ -442,6 +974,7 @@ fn resolve_inlines(
             InlineKind::Ref {
                 label,
                 bracket,
+                numbered,
                 resolved,
             } => {
                 let info = match labels.get(&label.name) {
@@ -457,19 +990,33 @@ fn resolve_inlines(
                     }
                 };
 
-                if bracket.is_none() && info.kind != LabelKind::Title {
+                if let Some(category) = numbered
+                    && info.number.map(|number| number.category) != Some(*category)
+                {
+                    diagnostics.push(Diagnostic::new(
+                        source_map.range(inline.span),
+                        DiagnosticSeverity::Warning,
+                        W_REF_CATEGORY,
+                        "reference category does not match target",
+                    ));
+                }
+
+                // Errors below leave the reference unresolved so it renders as
+                // its label; sibling references are still resolved.
+                if bracket.is_none() && numbered.is_none() && info.kind != LabelKind::Title {
                     diagnostics.push(Diagnostic::new(
                         source_map.range(inline.span),
                         DiagnosticSeverity::Error,
                         E_REF_OMIT,
                         "missing reference text for non-title target",
                     ));
+                    *resolved = None;
+                    continue;
                 }
 
                 let mut display = None;
-                if bracket.is_none() && info.kind == LabelKind::Title {
+                if bracket.is_none() && numbered.is_none() && info.kind == LabelKind::Title {
                     let (text, exceeded) = build_reference_text(&label.name, labels, info.span);
-                    display = Some(text);
                     if exceeded {
                         diagnostics.push(Diagnostic::new(
                             source_map.range(inline.span),
@@ -477,7 +1024,10 @@ fn resolve_inlines(
                             E_REF_DEPTH,
                             "reference display text depth exceeded",
                         ));
+                        *resolved = None;
+                        continue;
                     }
+                    display = Some(text);
                 }
 
                 *resolved = Some(match info.kind {
@@ -487,14 +1037,19 @@ fn resolve_inlines(
                     _ => ResolvedRef::Block {
                         label: label.name.clone(),
                         display,
+                        number: info.number,
                     },
                 });
             }
             InlineKind::Emph(children)
             | InlineKind::Strong(children)
-            | InlineKind::Strikethrough(children) => {
+            | InlineKind::Strikethrough(children)
+            | InlineKind::Span { children, .. } => {
                 resolve_inlines(children, labels, diagnostics, source_map);
             }
+            InlineKind::Directive(directive) => {
+                resolve_inlines(&mut directive.label, labels, diagnostics, source_map);
+            }
             // LinkRef is already resolved, so we only need to recurse.
             InlineKind::Link { children, .. } | InlineKind::LinkRef { children, .. } => {
                 resolve_inlines(children, labels, diagnostics, source_map);
@@ -555,7 +1110,12 @@ fn build_reference_text_from_inlines(
     let mut exceeded = false;
     for inline in inlines {
         match &inline.kind {
-            InlineKind::Text(_) | InlineKind::CodeSpan(_) | InlineKind::MathInline { .. } => {
+            InlineKind::Text(_)
+            | InlineKind::CodeSpan(_)
+            | InlineKind::Raw(_)
+            | InlineKind::MathInline { .. }
+            | InlineKind::Emoji { .. }
+            | InlineKind::WikiLink { .. } => {
                 out.push(inline.clone());
             }
             InlineKind::SoftBreak | InlineKind::HardBreak => {
@@ -594,6 +1154,33 @@ fn build_reference_text_from_inlines(
                 exceeded |= inner_exceeded;
                 out.extend(inner);
             }
+            InlineKind::Span { children, attrs } => {
+                let (inner, inner_exceeded) =
+                    build_reference_text_from_inlines(children, labels, depth, visited);
+                exceeded |= inner_exceeded;
+                // Keep the styling but drop the id so it is not duplicated.
+                let mut attrs = attrs.clone();
+                attrs.label = None;
+                out.push(Inline {
+                    span: inline.span,
+                    kind: InlineKind::Span {
+                        children: inner,
+                        attrs,
+                    },
+                });
+            }
+            InlineKind::Directive(directive) => {
+                let (label, inner_exceeded) =
+                    build_reference_text_from_inlines(&directive.label, labels, depth, visited);
+                exceeded |= inner_exceeded;
+                let mut directive = directive.clone();
+                directive.label = label;
+                directive.attrs.label = None;
+                out.push(Inline {
+                    span: inline.span,
+                    kind: InlineKind::Directive(directive),
+                });
+            }
             InlineKind::Link {
                 url,
                 title,
@@ -617,7 +1204,12 @@ fn build_reference_text_from_inlines(
                 exceeded |= inner_exceeded;
                 out.extend(inner);
             }
-            InlineKind::Ref { label, bracket, .. } => {
+            InlineKind::Ref {
+                label,
+                bracket,
+                numbered,
+                ..
+            } => {
                 let (resolved, display) = match labels.get(&label.name) {
                     Some(info) => {
                         let resolved = match info.kind {
@@ -627,6 +1219,7 @@ fn build_reference_text_from_inlines(
                             _ => ResolvedRef::Block {
                                 label: label.name.clone(),
                                 display: None,
+                                number: info.number,
                             },
                         };
                         (Some(resolved), info.kind)
@@ -641,7 +1234,7 @@ fn build_reference_text_from_inlines(
                         build_reference_text_from_inlines(bracket, labels, depth, visited);
                     exceeded |= inner_exceeded;
                     *bracket = inner;
-                } else if display == LabelKind::Title {
+                } else if display == LabelKind::Title && numbered.is_none() {
                     let (inner, inner_exceeded) = build_reference_text_inner(
                         &label.name,
                         labels,
@@ -662,10 +1255,12 @@ fn build_reference_text_from_inlines(
                     kind: InlineKind::Ref {
                         label: label.clone(),
                         bracket,
+                        numbered: *numbered,
                         resolved,
                     },
                 });
             }
+            InlineKind::Citation { .. } => out.push(inline.clone()),
             InlineKind::HtmlSpan { raw } => {
                 out.push(Inline {
                     span: inline.span,
@@ -37,6 +37,7 @@ pub fn build_sections(blocks: Vec<Block>) -> Vec<Block> {
                     title,
                     label,
                     children,
+                    number: Vec::new(),
                 },
             });
             continue;
@@ -48,6 +49,35 @@ pub fn build_sections(blocks: Vec<Block>) -> Vec<Block> {
     out
 }
 
+/// Numbers sections by their place in the tree built by `build_sections`, so
+/// a skipped heading level does not leave a zero. A `{.unnumbered}` section
+/// does not advance the count, and its subsections stay unnumbered too.
+/// Sections inside lists, quotes and boxes are not part of the outline.
+pub fn number_sections(blocks: &mut [Block]) {
+    number_children(blocks, &[]);
+}
+
+fn number_children(blocks: &mut [Block], parent: &[u32]) {
+    let mut ordinal = 0;
+    for block in blocks {
+        let unnumbered = block
+            .attrs
+            .items
+            .iter()
+            .any(|item| item.key == "class" && item.value.raw == "unnumbered");
+        if let BlockKind::Section {
+            number, children, ..
+        } = &mut block.kind
+            && !unnumbered
+        {
+            ordinal += 1;
+            number.extend(parent);
+            number.push(ordinal);
+            number_children(children, number);
+        }
+    }
+}
+
 fn rewrite_block(mut block: Block) -> Block {
     match &mut block.kind {
         BlockKind::List(List { items, .. }) => {
@@ -55,10 +85,10 @@ fn rewrite_block(mut block: Block) -> Block {
                 item.blocks = build_sections(std::mem::take(&mut item.blocks));
             }
         }
-        BlockKind::BlockQuote { blocks } => {
+        BlockKind::BlockQuote { blocks, .. } => {
             *blocks = build_sections(std::mem::take(blocks));
         }
-        BlockKind::Box(BoxBlock { blocks, .. }) => {
+        BlockKind::Box(BoxBlock { blocks, .. }) | BlockKind::Container { blocks, .. } => {
             *blocks = build_sections(std::mem::take(blocks));
         }
         BlockKind::Section { children, .. } => {
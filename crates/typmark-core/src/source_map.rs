@@ -12,43 +12,169 @@ pub struct Range {
     pub end: Position,
 }
 
+/// Unit that [`Position::character`] counts.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PositionEncoding {
+    /// UTF-8 bytes.
+    #[default]
+    Utf8,
+    /// UTF-16 code units, the LSP default.
+    Utf16,
+    /// Unicode scalar values.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// The name LSP uses for this encoding (`utf-8`, `utf-16` or `utf-32`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16 => "utf-16",
+            Self::Utf32 => "utf-32",
+        }
+    }
+
+    fn width(self, ch: char) -> usize {
+        match self {
+            Self::Utf8 => ch.len_utf8(),
+            Self::Utf16 => ch.len_utf16(),
+            Self::Utf32 => 1,
+        }
+    }
+}
+
+/// Line starts and the non-ASCII characters of a source, which is all that
+/// column arithmetic needs; the source text itself is not kept.
 #[derive(Clone, Debug)]
 pub struct SourceMap {
-    source_len: usize,
+    len: usize,
     line_starts: Vec<usize>,
+    /// End of each line's content, before its line break.
+    line_ends: Vec<usize>,
+    /// Byte offset, byte length, and column width of every non-ASCII character.
+    wide_chars: Vec<(usize, usize, usize)>,
+    encoding: PositionEncoding,
+}
+
+/// Number of line breaks in `text`, counting `\r\n`, `\n` and a lone `\r` once
+/// each, as [`SourceMap`] does.
+pub(crate) fn line_breaks(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|&(idx, byte)| match byte {
+            b'\n' => true,
+            b'\r' => bytes.get(idx + 1) != Some(&b'\n'),
+            _ => false,
+        })
+        .count()
 }
 
 impl SourceMap {
+    /// Counts columns in UTF-8 bytes.
     pub fn new(source: &str) -> Self {
+        Self::with_encoding(source, PositionEncoding::Utf8)
+    }
+
+    pub fn with_encoding(source: &str, encoding: PositionEncoding) -> Self {
+        let bytes = source.as_bytes();
         let mut line_starts = Vec::new();
-        line_starts.push(0);
-        for (idx, byte) in source.bytes().enumerate() {
-            if byte == b'\n' {
+        let mut line_ends = Vec::new();
+        // Columns on the first line do not count a leading UTF-8 BOM.
+        line_starts.push(if source.starts_with('\u{feff}') { 3 } else { 0 });
+        for (idx, byte) in bytes.iter().enumerate() {
+            let ends_line = match byte {
+                b'\n' => true,
+                b'\r' => bytes.get(idx + 1) != Some(&b'\n'),
+                _ => false,
+            };
+            if ends_line {
+                let content_end = if *byte == b'\n' && idx > 0 && bytes[idx - 1] == b'\r' {
+                    idx - 1
+                } else {
+                    idx
+                };
+                line_ends.push(content_end);
                 line_starts.push(idx + 1);
             }
         }
+        line_ends.push(source.len());
+        let wide_chars = source
+            .char_indices()
+            .filter(|(_, ch)| !ch.is_ascii())
+            .map(|(idx, ch)| (idx, ch.len_utf8(), encoding.width(ch)))
+            .filter(|&(idx, _, _)| idx >= line_starts[0])
+            .collect();
         Self {
-            source_len: source.len(),
+            len: source.len(),
             line_starts,
+            line_ends,
+            wide_chars,
+            encoding,
         }
     }
 
+    pub fn encoding(&self) -> PositionEncoding {
+        self.encoding
+    }
+
     pub fn line_count(&self) -> usize {
         self.line_starts.len()
     }
 
     pub fn position(&self, offset: usize) -> Position {
-        let offset = offset.min(self.source_len);
+        let offset = offset.min(self.len);
         let line = match self.line_starts.binary_search(&offset) {
             Ok(index) => index,
             Err(index) => index.saturating_sub(1),
         };
         let line_start = self.line_starts[line];
-        Position {
-            line,
-            // Byte offset from line start (ASCII-safe for now).
-            character: offset.saturating_sub(line_start),
+        let mut character = offset.saturating_sub(line_start);
+        if self.encoding != PositionEncoding::Utf8 {
+            // Each byte counted one column so far; a character that starts
+            // before `offset` counts its full width instead.
+            for &(start, len, width) in self.wide_chars_in(line_start, offset) {
+                character = character + width - len.min(offset - start);
+            }
         }
+        Position { line, character }
+    }
+
+    /// Byte offset of `position`. Columns past the end of the line clamp to the
+    /// line end, and a column inside a character maps to that character's start.
+    pub fn offset(&self, position: Position) -> usize {
+        let (Some(&line_start), Some(&line_end)) = (
+            self.line_starts.get(position.line),
+            self.line_ends.get(position.line),
+        ) else {
+            return self.len;
+        };
+        let target = position.character;
+        let mut cursor = line_start;
+        let mut column = 0;
+        for &(start, len, width) in self.wide_chars_in(line_start, line_end) {
+            if target - column < start - cursor {
+                return cursor + target - column;
+            }
+            column += start - cursor;
+            if column + width > target {
+                return start;
+            }
+            column += width;
+            cursor = start + len;
+        }
+        if target - column < line_end - cursor {
+            cursor + target - column
+        } else {
+            line_end
+        }
+    }
+
+    fn wide_chars_in(&self, start: usize, end: usize) -> &[(usize, usize, usize)] {
+        let from = self.wide_chars.partition_point(|&(idx, _, _)| idx < start);
+        let to = self.wide_chars.partition_point(|&(idx, _, _)| idx < end);
+        &self.wide_chars[from..to]
     }
 
     pub fn range(&self, span: Span) -> Range {
@@ -61,7 +187,7 @@ impl SourceMap {
 
 #[cfg(test)]
 mod tests {
-    use super::{Position, SourceMap};
+    use super::{Position, PositionEncoding, SourceMap};
     use crate::span::Span;
 
     #[test]
@@ -97,4 +223,93 @@ mod tests {
         assert_eq!(range.start.line, 0);
         assert_eq!(range.end.line, 1);
     }
+
+    #[test]
+    fn crlf_cr_and_bom_start_lines() {
+        let map = SourceMap::new("\u{feff}a\r\nb\rc");
+
+        assert_eq!(map.line_count(), 3);
+        assert_eq!(
+            map.position(3),
+            Position {
+                line: 0,
+                character: 0
+            }
+        );
+        assert_eq!(
+            map.position(6),
+            Position {
+                line: 1,
+                character: 0
+            }
+        );
+        assert_eq!(
+            map.position(8),
+            Position {
+                line: 2,
+                character: 0
+            }
+        );
+    }
+
+    #[test]
+    fn columns_follow_the_position_encoding() {
+        // VS Code puts the cursor after "a😀b" at UTF-16 column 4.
+        let source = "x\na😀b = é\n";
+        let after_b = "x\na😀b".len();
+        let expected = [
+            (PositionEncoding::Utf8, 6, 11),
+            (PositionEncoding::Utf16, 4, 8),
+            (PositionEncoding::Utf32, 3, 7),
+        ];
+        for (encoding, column, line_len) in expected {
+            let map = SourceMap::with_encoding(source, encoding);
+            let position = map.position(after_b);
+            assert_eq!(
+                position,
+                Position {
+                    line: 1,
+                    character: column
+                },
+                "{encoding:?}"
+            );
+            assert_eq!(map.offset(position), after_b, "{encoding:?}");
+            let end = map.offset(Position {
+                line: 1,
+                character: 100,
+            });
+            assert_eq!(map.position(end).character, line_len, "{encoding:?}");
+        }
+
+        let map = SourceMap::with_encoding(source, PositionEncoding::Utf16);
+        // Column 2 falls between the surrogates of the emoji.
+        assert_eq!(
+            map.offset(Position {
+                line: 1,
+                character: 2
+            }),
+            3
+        );
+        assert_eq!(SourceMap::new(source).encoding(), PositionEncoding::Utf8);
+    }
+
+    #[test]
+    fn offsets_round_trip_through_positions() {
+        let source = "\u{feff}é😀\r\na\u{1f600}b\rc\n\n";
+        for encoding in [
+            PositionEncoding::Utf8,
+            PositionEncoding::Utf16,
+            PositionEncoding::Utf32,
+        ] {
+            let map = SourceMap::with_encoding(source, encoding);
+            for (offset, _) in source.char_indices().skip(1) {
+                // The `\n` of a `\r\n` has no column of its own.
+                if source[..offset].ends_with('\r') && source[offset..].starts_with('\n') {
+                    continue;
+                }
+                assert_eq!(map.offset(map.position(offset)), offset, "{encoding:?}");
+            }
+            assert_eq!(map.offset(map.position(source.len())), source.len());
+        }
+    }
 }
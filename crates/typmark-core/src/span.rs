@@ -1,10 +1,15 @@
+use std::ops::Range;
+
+/// Half-open byte range `start..end` into the source text.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Span {
     pub start: usize,
     pub end: usize,
 }
 
 impl Span {
+    /// Creates a span, or returns [`SpanError::Inverted`] when `start > end`.
     pub fn new(start: usize, end: usize) -> Result<Self, SpanError> {
         if start <= end {
             Ok(Self { start, end })
@@ -20,9 +25,81 @@ impl Span {
     pub fn is_empty(&self) -> bool {
         self.start == self.end
     }
+
+    /// Whether the byte at `offset` lies inside the span. Empty spans contain no offset.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    /// Whether `other` lies entirely inside this span.
+    pub fn contains_span(&self, other: Span) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// Smallest span covering both spans, including any gap between them.
+    pub fn merge(&self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
 }
 
+/// Converts `start..end`. An inverted range becomes an empty span at `start`;
+/// use [`Span::new`] to reject it instead.
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Span {
+            start: range.start,
+            end: range.end.max(range.start),
+        }
+    }
+}
+
+/// Error from [`Span::new`].
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum SpanError {
+    /// `start` is past `end`. Swap the offsets or build the span from a range
+    /// that was computed in source order.
     Inverted { start: usize, end: usize },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Span, SpanError};
+
+    #[test]
+    fn merge_of_disjoint_spans_covers_both() {
+        let left = Span::from(2..4);
+        let right = Span::from(10..12);
+
+        let merged = left.merge(right);
+        assert_eq!(merged, Span::from(2..12));
+        assert_eq!(right.merge(left), merged);
+        assert!(merged.contains_span(left) && merged.contains_span(right));
+        assert_eq!(merged.len(), 10);
+    }
+
+    #[test]
+    fn contains_is_half_open() {
+        let span = Span::from(3..5);
+        assert!(!span.contains(2));
+        assert!(span.contains(3));
+        assert!(span.contains(4));
+        assert!(!span.contains(5));
+        assert!(!Span::from(3..3).contains(3));
+        assert!(span.contains_span(Span::from(5..5)));
+        assert!(!span.contains_span(Span::from(4..6)));
+    }
+
+    #[test]
+    fn inverted_offsets() {
+        assert_eq!(
+            Span::new(5, 3),
+            Err(SpanError::Inverted { start: 5, end: 3 })
+        );
+        #[allow(clippy::reversed_empty_ranges)]
+        let span = Span::from(5..3);
+        assert_eq!(span, Span { start: 5, end: 5 });
+    }
+}
@@ -0,0 +1,67 @@
+use crate::ast::{Inline, InlineKind};
+
+/// `level` moved by `offset` and kept within `h1`..`h6`.
+pub(crate) fn shifted_heading_level(level: u8, offset: i8) -> u8 {
+    (i16::from(level) + i16::from(offset)).clamp(1, 6) as u8
+}
+
+/// Plain text of `inlines`, with breaks as `\n` and markup dropped.
+pub(crate) fn render_inlines_text(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match &inline.kind {
+            InlineKind::Text(text) => out.push_str(text),
+            InlineKind::CodeSpan(text) | InlineKind::Raw(text) => out.push_str(text),
+            InlineKind::Emoji { name, .. } => {
+                out.push(':');
+                out.push_str(name);
+                out.push(':');
+            }
+            InlineKind::MathInline { typst_src } => out.push_str(typst_src),
+            InlineKind::WikiLink { display, .. } => out.push_str(display),
+            InlineKind::SoftBreak | InlineKind::HardBreak => out.push('\n'),
+            InlineKind::Ref { label, bracket, .. } => {
+                if let Some(bracket) = bracket.as_deref() {
+                    out.push_str(&render_inlines_text(bracket));
+                } else {
+                    out.push_str(&label.name);
+                }
+            }
+            InlineKind::Citation { keys, numbers } => {
+                let items = keys
+                    .iter()
+                    .zip(numbers)
+                    .map(|(key, number)| match number {
+                        Some(number) => number.to_string(),
+                        None => format!("@{}", key.name),
+                    })
+                    .collect::<Vec<_>>();
+                out.push_str(&format!("[{}]", items.join(", ")));
+            }
+            InlineKind::Emph(children)
+            | InlineKind::Strong(children)
+            | InlineKind::Strikethrough(children)
+            | InlineKind::Link { children, .. }
+            | InlineKind::LinkRef { children, .. }
+            | InlineKind::Span { children, .. } => {
+                out.push_str(&render_inlines_text(children));
+            }
+            InlineKind::Image { alt, .. } | InlineKind::ImageRef { alt, .. } => {
+                out.push_str(&render_inlines_text(alt));
+            }
+            InlineKind::Directive(directive) => {
+                out.push_str(&render_inlines_text(&directive.label));
+            }
+            InlineKind::HtmlSpan { raw } => out.push_str(raw),
+        }
+    }
+    out
+}
+
+/// `id` as emitted, behind `id_namespace` and a `-` when one is set.
+pub(crate) fn namespaced_id(id_namespace: Option<&str>, id: &str) -> String {
+    match id_namespace {
+        Some(namespace) => format!("{}-{}", namespace, id),
+        None => id.to_string(),
+    }
+}
@@ -0,0 +1,271 @@
+use crate::ast::{Block, BlockKind, Document, Inline, InlineKind, InlineSeq};
+
+/// Read-only traversal over a document.
+///
+/// The default methods descend into every child node; override one and call
+/// [`walk_block`] or [`walk_inline`] to keep descending past it.
+pub trait Visitor {
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        walk_inline(self, inline);
+    }
+}
+
+/// Visits every top-level block of a document.
+pub fn walk_document<V: Visitor + ?Sized>(visitor: &mut V, document: &Document) {
+    for block in &document.blocks {
+        visitor.visit_block(block);
+    }
+}
+
+/// Visits the inline and block children of a block.
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    match &block.kind {
+        BlockKind::Paragraph { content } => walk_inlines(visitor, content),
+        BlockKind::Heading { title, .. } => walk_inlines(visitor, title),
+        BlockKind::Section {
+            title, children, ..
+        } => {
+            walk_inlines(visitor, title);
+            walk_blocks(visitor, children);
+        }
+        BlockKind::List(list) => {
+            for item in &list.items {
+                walk_blocks(visitor, &item.blocks);
+            }
+        }
+        BlockKind::BlockQuote { blocks, .. } => walk_blocks(visitor, blocks),
+        BlockKind::Box(box_block) => {
+            if let Some(title) = &box_block.title {
+                walk_inlines(visitor, title);
+            }
+            walk_blocks(visitor, &box_block.blocks);
+        }
+        BlockKind::Container { title, blocks, .. } => {
+            if let Some(title) = title {
+                walk_inlines(visitor, title);
+            }
+            walk_blocks(visitor, blocks);
+        }
+        BlockKind::Table(table) => {
+            for cell in table.headers.iter().chain(table.rows.iter().flatten()) {
+                walk_inlines(visitor, cell);
+            }
+        }
+        BlockKind::CodeBlock(code_block) => {
+            if let Some(output) = &code_block.output {
+                visitor.visit_block(output);
+            }
+        }
+        BlockKind::MathBlock { .. }
+        | BlockKind::ThematicBreak
+        | BlockKind::HtmlBlock { .. }
+        | BlockKind::Include { .. } => {}
+    }
+}
+
+/// Visits the inline children of an inline node.
+pub fn walk_inline<V: Visitor + ?Sized>(visitor: &mut V, inline: &Inline) {
+    match &inline.kind {
+        InlineKind::Emph(children)
+        | InlineKind::Strong(children)
+        | InlineKind::Strikethrough(children)
+        | InlineKind::Link { children, .. }
+        | InlineKind::LinkRef { children, .. }
+        | InlineKind::Span { children, .. } => walk_inlines(visitor, children),
+        InlineKind::Directive(directive) => walk_inlines(visitor, &directive.label),
+        InlineKind::Image { alt, .. } | InlineKind::ImageRef { alt, .. } => {
+            walk_inlines(visitor, alt)
+        }
+        InlineKind::Ref {
+            bracket: Some(bracket),
+            ..
+        } => walk_inlines(visitor, bracket),
+        InlineKind::Text(_)
+        | InlineKind::CodeSpan(_)
+        | InlineKind::Raw(_)
+        | InlineKind::SoftBreak
+        | InlineKind::HardBreak
+        | InlineKind::Ref { .. }
+        | InlineKind::Citation { .. }
+        | InlineKind::MathInline { .. }
+        | InlineKind::HtmlSpan { .. }
+        | InlineKind::Emoji { .. }
+        | InlineKind::WikiLink { .. } => {}
+    }
+}
+
+fn walk_blocks<V: Visitor + ?Sized>(visitor: &mut V, blocks: &[Block]) {
+    for block in blocks {
+        visitor.visit_block(block);
+    }
+}
+
+fn walk_inlines<V: Visitor + ?Sized>(visitor: &mut V, inlines: &[Inline]) {
+    for inline in inlines {
+        visitor.visit_inline(inline);
+    }
+}
+
+impl Document {
+    /// Number of blocks and inlines in the document, including all nested children.
+    pub fn node_count(&self) -> usize {
+        self.complexity().nodes
+    }
+
+    /// Deepest nesting of blocks and inlines; a top-level block has depth 1.
+    pub fn max_depth(&self) -> usize {
+        self.complexity().max_depth
+    }
+
+    fn complexity(&self) -> Complexity {
+        let mut complexity = Complexity::default();
+        walk_document(&mut complexity, self);
+        complexity
+    }
+}
+
+#[derive(Default)]
+struct Complexity {
+    nodes: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Complexity {
+    fn enter(&mut self) {
+        self.nodes += 1;
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+    }
+}
+
+impl Visitor for Complexity {
+    fn visit_block(&mut self, block: &Block) {
+        self.enter();
+        walk_block(self, block);
+        self.depth -= 1;
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        self.enter();
+        walk_inline(self, inline);
+        self.depth -= 1;
+    }
+}
+
+/// In-place traversal over a document.
+///
+/// Each method may rewrite the node it receives and returns whether the walk
+/// should descend into that node's children afterwards.
+pub trait VisitorMut {
+    fn visit_block_mut(&mut self, _block: &mut Block) -> bool {
+        true
+    }
+
+    fn visit_inline_mut(&mut self, _inline: &mut Inline) -> bool {
+        true
+    }
+
+    /// Called with each inline sequence before its items are visited, so
+    /// items may be inserted or removed.
+    fn visit_inlines_mut(&mut self, _inlines: &mut InlineSeq) {}
+}
+
+/// Visits every block and inline of a document, outermost first.
+pub fn walk_document_mut<V: VisitorMut + ?Sized>(visitor: &mut V, document: &mut Document) {
+    walk_blocks_mut(visitor, &mut document.blocks);
+}
+
+pub(crate) fn walk_blocks_mut<V: VisitorMut + ?Sized>(visitor: &mut V, blocks: &mut [Block]) {
+    for block in blocks {
+        if !visitor.visit_block_mut(block) {
+            continue;
+        }
+        match &mut block.kind {
+            BlockKind::Paragraph { content } => walk_inlines_mut(visitor, content),
+            BlockKind::Heading { title, .. } => walk_inlines_mut(visitor, title),
+            BlockKind::Section {
+                title, children, ..
+            } => {
+                walk_inlines_mut(visitor, title);
+                walk_blocks_mut(visitor, children);
+            }
+            BlockKind::List(list) => {
+                for item in &mut list.items {
+                    walk_blocks_mut(visitor, &mut item.blocks);
+                }
+            }
+            BlockKind::BlockQuote { blocks, .. } => walk_blocks_mut(visitor, blocks),
+            BlockKind::Box(box_block) => {
+                if let Some(title) = &mut box_block.title {
+                    walk_inlines_mut(visitor, title);
+                }
+                walk_blocks_mut(visitor, &mut box_block.blocks);
+            }
+            BlockKind::Container { title, blocks, .. } => {
+                if let Some(title) = title {
+                    walk_inlines_mut(visitor, title);
+                }
+                walk_blocks_mut(visitor, blocks);
+            }
+            BlockKind::Table(table) => {
+                for cell in table
+                    .headers
+                    .iter_mut()
+                    .chain(table.rows.iter_mut().flatten())
+                {
+                    walk_inlines_mut(visitor, cell);
+                }
+            }
+            BlockKind::CodeBlock(code_block) => {
+                if let Some(output) = &mut code_block.output {
+                    walk_blocks_mut(visitor, std::slice::from_mut(output.as_mut()));
+                }
+            }
+            BlockKind::MathBlock { .. }
+            | BlockKind::ThematicBreak
+            | BlockKind::HtmlBlock { .. }
+            | BlockKind::Include { .. } => {}
+        }
+    }
+}
+
+fn walk_inlines_mut<V: VisitorMut + ?Sized>(visitor: &mut V, inlines: &mut InlineSeq) {
+    visitor.visit_inlines_mut(inlines);
+    for inline in inlines {
+        if !visitor.visit_inline_mut(inline) {
+            continue;
+        }
+        match &mut inline.kind {
+            InlineKind::Emph(children)
+            | InlineKind::Strong(children)
+            | InlineKind::Strikethrough(children)
+            | InlineKind::Link { children, .. }
+            | InlineKind::LinkRef { children, .. }
+            | InlineKind::Span { children, .. } => walk_inlines_mut(visitor, children),
+            InlineKind::Directive(directive) => walk_inlines_mut(visitor, &mut directive.label),
+            InlineKind::Image { alt, .. } | InlineKind::ImageRef { alt, .. } => {
+                walk_inlines_mut(visitor, alt)
+            }
+            InlineKind::Ref {
+                bracket: Some(bracket),
+                ..
+            } => walk_inlines_mut(visitor, bracket),
+            InlineKind::Text(_)
+            | InlineKind::CodeSpan(_)
+            | InlineKind::Raw(_)
+            | InlineKind::SoftBreak
+            | InlineKind::HardBreak
+            | InlineKind::Ref { .. }
+            | InlineKind::Citation { .. }
+            | InlineKind::MathInline { .. }
+            | InlineKind::HtmlSpan { .. }
+            | InlineKind::Emoji { .. }
+            | InlineKind::WikiLink { .. } => {}
+        }
+    }
+}
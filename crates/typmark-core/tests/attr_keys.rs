@@ -0,0 +1,141 @@
+use typmark_core::{W_ATTR_UNKNOWN, W_DIR_INVALID, emit_html, emit_html_sanitized, parse, resolve};
+
+/// `(line, start column, end column, message)` of each unknown-key warning.
+/// A first paragraph keeps the target lines from being read as document settings.
+fn unknown_keys(source: &str) -> Vec<(usize, usize, usize, String)> {
+    parse(&format!("Intro.\n\n{source}"))
+        .diagnostics
+        .into_iter()
+        .filter(|diag| diag.code == W_ATTR_UNKNOWN)
+        .map(|diag| {
+            (
+                diag.range.start.line - 2,
+                diag.range.start.character,
+                diag.range.end.character,
+                diag.message,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn misspelled_box_keys_warn_and_stay_data_attributes() {
+    let source = "{bg-color=#fff bordr-style=\"dashed\" note=keep}\n::: box\nBody\n:::\n";
+    assert_eq!(
+        unknown_keys(source),
+        vec![
+            (
+                0,
+                1,
+                9,
+                "unknown attribute 'bg-color', did you mean 'bg'?".to_string()
+            ),
+            (
+                0,
+                15,
+                26,
+                "unknown attribute 'bordr-style', did you mean 'border-style'?".to_string()
+            ),
+        ]
+    );
+    let parsed = parse(&format!("Intro.\n\n{source}"));
+    let html = emit_html(&parsed.document.blocks);
+    assert!(
+        html.contains("data-bg-color=\"#fff\" data-bordr-style=\"dashed\" data-note=\"keep\""),
+        "{html}"
+    );
+}
+
+#[test]
+fn misspelled_list_keys_warn() {
+    assert_eq!(
+        unknown_keys("{revresed=true}\n1. one\n2. two\n"),
+        vec![(
+            0,
+            1,
+            9,
+            "unknown attribute 'revresed', did you mean 'reversed'?".to_string()
+        )]
+    );
+    assert!(unknown_keys("{type=a reversed continue}\n1. one\n").is_empty());
+}
+
+#[test]
+fn keys_for_other_blocks_warn() {
+    assert_eq!(
+        unknown_keys("{#p caption=Oops}\nA paragraph.\n\n{open=true}\n- item\n"),
+        vec![
+            (
+                0,
+                4,
+                11,
+                "attribute 'caption' has no effect on this block".to_string()
+            ),
+            (
+                3,
+                1,
+                5,
+                "attribute 'open' has no effect on this block".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        unknown_keys("```rs {captoin=\"Main\"}\nfn main() {}\n```\n"),
+        vec![(
+            0,
+            7,
+            14,
+            "unknown attribute 'captoin', did you mean 'caption'?".to_string()
+        )]
+    );
+}
+
+#[test]
+fn plain_data_attributes_do_not_warn() {
+    let source = "{#code note=keep lang=en}\n```rs {hl=1 wrap=soft output=out}\nx\n```\n\n{#out}\n```text\ny\n```\n\n{data=1 author=me}\nText.\n";
+    assert!(unknown_keys(source).is_empty());
+}
+
+#[test]
+fn dir_becomes_the_html_attribute_when_valid() {
+    let source = "Intro.\n\n{dir=rtl}\nمرحبا\n\n{dir=ltr}\n::: box\nBody\n:::\n\nSee [abc]{dir=auto}.\n\n{dir=right}\nText.\n";
+    let parsed = parse(source);
+    let invalid: Vec<_> = parsed
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.code == W_DIR_INVALID)
+        .collect();
+    assert_eq!(invalid.len(), 1, "{:?}", parsed.diagnostics);
+    assert_eq!(invalid[0].range.start.line, 12);
+    assert!(unknown_keys("{dir=rtl}\nText.\n").is_empty());
+
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    for html in [
+        emit_html(&resolved.document.blocks),
+        emit_html_sanitized(&resolved.document.blocks),
+    ] {
+        assert!(html.contains("<p dir=\"rtl\">مرحبا</p>"), "{html}");
+        assert!(html.contains(" dir=\"ltr\">"), "{html}");
+        assert!(html.contains("<span dir=\"auto\">abc</span>"), "{html}");
+        assert!(html.contains("<p data-dir=\"right\">Text.</p>"), "{html}");
+    }
+}
+
+#[test]
+fn invalid_dir_is_reported_on_inline_and_code_fence_attrs() {
+    let source = "Intro.\n\nSee [abc]{dir=up}, ![x](a.png){dir=down} and ::kbd[K]{dir=left}.\n\n```rs {dir=sideways}\nx\n```\n";
+    let parsed = parse(source);
+    let invalid: Vec<_> = parsed
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.code == W_DIR_INVALID)
+        .map(|diag| (diag.range.start.line, diag.range.start.character))
+        .collect();
+    assert_eq!(invalid, vec![(2, 14), (2, 35), (2, 58), (4, 11)]);
+}
@@ -1,7 +1,9 @@
 use serde::Deserialize;
 use std::fs;
 use std::path::Path;
-use typmark_core::{HtmlEmitOptions, emit_html_with_options, parse, resolve};
+use typmark_core::{
+    HtmlEmitOptions, ParseOptions, emit_html_with_options, parse, parse_with_options, resolve,
+};
 
 #[derive(Debug, Deserialize)]
 struct SpecExample {
@@ -59,8 +61,11 @@ fn commonmark_spec() {
         let options = HtmlEmitOptions {
             wrap_sections: false,
             simple_code_blocks: true,
+            ..Default::default()
         };
-        let actual_html = emit_html_with_options(&resolved.document.blocks, &options);
+        let actual_html = emit_html_with_options(&resolved.document.blocks, &options)
+            // TypMark hides decorative images from assistive tech; the spec has no such attribute.
+            .replace("alt=\"\" aria-hidden=\"true\"", "alt=\"\"");
 
         let actual_normalized = normalize_html(&actual_html);
         let expected_normalized = normalize_html(&example.html);
@@ -141,6 +146,162 @@ fn commonmark_spec() {
     );
 }
 
+/// Strict mode turns TypMark's extensions off, so the examples skipped above
+/// for overlapping with them must pass as well.
+#[test]
+fn strict_commonmark_matches_spec() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let Ok(spec_json) = fs::read_to_string(root.join("tests/commonmark/spec.json")) else {
+        eprintln!("Skipping strict CommonMark spec tests.");
+        return;
+    };
+    let examples: Vec<SpecExample> =
+        serde_json::from_str(&spec_json).expect("Failed to parse spec.json");
+    let parse_options = ParseOptions {
+        strict_commonmark: true,
+        ..Default::default()
+    };
+    let options = HtmlEmitOptions {
+        wrap_sections: false,
+        simple_code_blocks: true,
+        ..Default::default()
+    };
+    let mut failures = Vec::new();
+    for example in &examples {
+        let parsed = parse_with_options(&example.markdown, &parse_options);
+        let resolved = resolve(
+            parsed.document,
+            &example.markdown,
+            &parsed.source_map,
+            parsed.diagnostics,
+            &parsed.link_defs,
+        );
+        let actual = emit_html_with_options(&resolved.document.blocks, &options)
+            .replace("alt=\"\" aria-hidden=\"true\"", "alt=\"\"");
+        if normalize_html(&actual) != normalize_html(&example.html) {
+            failures.push(example.example);
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "strict mode fails examples {:?}",
+        failures
+    );
+}
+
+#[test]
+fn strict_commonmark_reads_typmark_syntax_as_text() {
+    let cases = [
+        (
+            "::: box
+hi
+:::
+",
+            "<p>::: box\nhi\n:::</p>",
+        ),
+        (
+            "$$
+x
+$$
+",
+            "<p>$$\nx\n$$</p>",
+        ),
+        ("See @label and [@key].\n", "<p>See @label and [@key].</p>"),
+        ("{#intro}\n# Intro\n", "<p>{#intro}</p>\n<h1>Intro</h1>"),
+        ("[text]{.note} \\!{raw}!\n", "<p>[text]{.note} !{raw}!</p>"),
+        (
+            "> [!NOTE]\n> Hi\n",
+            "<blockquote>\n<p>[!NOTE]\nHi</p>\n</blockquote>",
+        ),
+        ("- [x] done\n", "<ul>\n<li>[x] done</li>\n</ul>"),
+        (
+            "~~no~~ https://example.com\n",
+            "<p>~~no~~ https://example.com</p>",
+        ),
+        (
+            "```rs {#code}\nx\n```\n",
+            "<pre><code class=\"language-rs\">x\n</code></pre>",
+        ),
+    ];
+    let parse_options = ParseOptions {
+        strict_commonmark: true,
+        ..Default::default()
+    };
+    let options = HtmlEmitOptions {
+        wrap_sections: false,
+        simple_code_blocks: true,
+        ..Default::default()
+    };
+    for (markdown, expected) in cases {
+        let parsed = parse_with_options(markdown, &parse_options);
+        let resolved = resolve(
+            parsed.document,
+            markdown,
+            &parsed.source_map,
+            parsed.diagnostics,
+            &parsed.link_defs,
+        );
+        assert!(
+            resolved.diagnostics.is_empty(),
+            "{:?}",
+            resolved.diagnostics
+        );
+        let actual = emit_html_with_options(&resolved.document.blocks, &options);
+        assert_eq!(
+            normalize_html(&actual),
+            normalize_html(expected),
+            "{}",
+            show_whitespace(markdown)
+        );
+    }
+}
+
+/// Fences inside block quotes, checked against the commonmark.js reference output.
+/// A fence's content and closing line never continue a quote lazily.
+#[test]
+fn block_quote_fences_match_reference() {
+    let cases = [
+        (
+            "> ```\n> code\n> ```\n",
+            "<blockquote>\n<pre><code>code\n</code></pre>\n</blockquote>",
+        ),
+        (
+            "> ```\n> code\n```\n\nafter\n",
+            "<blockquote>\n<pre><code>code\n</code></pre>\n</blockquote>\n<pre><code>\nafter\n</code></pre>",
+        ),
+        (
+            "> ```\n> code\nlazy\n> ```\n",
+            "<blockquote>\n<pre><code>code\n</code></pre>\n</blockquote>\n<p>lazy</p>\n<blockquote>\n<pre><code></code></pre>\n</blockquote>",
+        ),
+        (
+            "> text\nlazy\n> ~~~\n> a\n> ~~~\n",
+            "<blockquote>\n<p>text\nlazy</p>\n<pre><code>a\n</code></pre>\n</blockquote>",
+        ),
+    ];
+    let options = HtmlEmitOptions {
+        wrap_sections: false,
+        simple_code_blocks: true,
+        ..Default::default()
+    };
+    for (markdown, expected) in cases {
+        let parsed = parse(markdown);
+        let resolved = resolve(
+            parsed.document,
+            markdown,
+            &parsed.source_map,
+            parsed.diagnostics,
+            &parsed.link_defs,
+        );
+        let actual = emit_html_with_options(&resolved.document.blocks, &options);
+        assert_eq!(
+            normalize_html(&actual),
+            normalize_html(expected),
+            "{}",
+            show_whitespace(markdown)
+        );
+    }
+}
+
 #[derive(Debug)]
 struct Failure {
     example_num: u32,
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+use typmark_core::{
+    HtmlEmitOptions, emit_html, emit_html_document_with_options, emit_html_sanitized,
+    normalize_html, parse, resolve,
+};
+
+fn render(source: &str) -> Vec<String> {
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let blocks = &resolved.document.blocks;
+    let options = HtmlEmitOptions {
+        wrap_sections: true,
+        ..Default::default()
+    };
+    vec![
+        emit_html(blocks),
+        emit_html_sanitized(blocks),
+        emit_html_document_with_options(&resolved.document, &options),
+    ]
+}
+
+#[test]
+fn emit_is_byte_stable_for_every_fixture() -> Result<(), Box<dyn std::error::Error>> {
+    let fixtures_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/fixtures");
+    let mut paths = Vec::new();
+    for dir in [fixtures_dir.clone(), fixtures_dir.join("sani")] {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "tmd") {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let source = fs::read_to_string(&path)?;
+        let first = render(&source);
+        for _ in 0..3 {
+            assert_eq!(
+                render(&source),
+                first,
+                "output changed between runs for {}",
+                path.display()
+            );
+        }
+        for html in &first {
+            let normalized = normalize_html(html);
+            assert_eq!(
+                normalize_html(&normalized),
+                normalized,
+                "normalize_html is not idempotent for {}",
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn normalize_ignores_indentation_between_blocks() {
+    let compact = "<section><h2>Title</h2><ul><li><p>one</p></li></ul></section>";
+    let indented = "<section>\n  <h2>\n    Title\n  </h2>\n  <ul>\r\n    <li>\n      <p>one</p>\n    </li>\n  </ul>\n</section>\n";
+    let expected = "<section>\n<h2>Title</h2>\n<ul>\n<li>\n<p>one</p>\n</li>\n</ul>\n</section>\n";
+    assert_eq!(normalize_html(compact), expected);
+    assert_eq!(normalize_html(indented), expected);
+}
+
+#[test]
+fn normalize_keeps_inline_spacing_and_preformatted_text() {
+    let html = "<p>a  <em>b</em>\n<strong>c</strong>\n</p>\n<pre><code>fn main() {\n    let x = 1;  \n}\n</code></pre>\n";
+    assert_eq!(
+        normalize_html(html),
+        "<p>a <em>b</em> <strong>c</strong></p>\n<pre><code>fn main() {\n    let x = 1;  \n}\n</code></pre>\n"
+    );
+}
+
+#[test]
+fn normalize_pins_a_rendered_document() {
+    let source = "# Intro\n\nSee *this*.\n\n- one\n- two\n\n```rs\nlet  x = 1;\n```\n";
+    let expected = "<section>\n<h1>Intro</h1>\n<p>See <em>this</em>.</p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\" data-lang=\"rs\">\n<pre class=\"TypMark-pre\"><code class=\"language-rs\"><span class=\"line\" data-line=\"1\">let  x = 1;</span></code></pre>\n</figure>\n</section>\n";
+    assert_eq!(normalize_html(&render(source)[0]), expected);
+}
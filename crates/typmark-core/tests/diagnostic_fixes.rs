@@ -0,0 +1,90 @@
+use typmark_core::{
+    Diagnostic, E_ATTR_SYNTAX, E_REF_SELF_TITLE, W_BOX_STYLE_INVALID, parse, resolve,
+};
+
+fn diagnostics(source: &str) -> Vec<Diagnostic> {
+    let parsed = parse(source);
+    resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    )
+    .diagnostics
+}
+
+/// Applies the only fix of the only `code` diagnostic; the fix must stay on one line.
+fn apply_fix(source: &str, code: &str) -> String {
+    let found: Vec<_> = diagnostics(source)
+        .into_iter()
+        .filter(|diag| diag.code == code)
+        .collect();
+    assert_eq!(found.len(), 1, "{found:?}");
+    let [fix] = found[0].fixes.as_slice() else {
+        panic!("expected one fix: {:?}", found[0]);
+    };
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let line_start: usize = lines[..fix.range.start.line].iter().map(|l| l.len()).sum();
+    assert_eq!(fix.range.start.line, fix.range.end.line);
+    let start = line_start + fix.range.start.character;
+    let end = line_start + fix.range.end.character;
+    format!("{}{}{}", &source[..start], fix.replacement, &source[end..])
+}
+
+#[test]
+fn self_title_reference_has_a_deletion_fix() {
+    let source = "{#s}\n# See @s\n";
+    let diag = diagnostics(source)
+        .into_iter()
+        .find(|diag| diag.code == E_REF_SELF_TITLE)
+        .unwrap();
+    assert_eq!(diag.fixes.len(), 1);
+    let fix = &diag.fixes[0];
+    assert_eq!(fix.range, diag.range);
+    assert_eq!((fix.range.start.line, fix.range.start.character), (1, 6));
+    assert_eq!(fix.range.end.character, 8);
+    assert_eq!(fix.replacement, "");
+    assert_eq!(fix.title, "Remove the self-reference");
+    assert_eq!(apply_fix(source, E_REF_SELF_TITLE), "{#s}\n# See \n");
+}
+
+#[test]
+fn box_style_fixes_suggest_a_valid_value() {
+    let fixed = |attrs: &str| {
+        apply_fix(
+            &format!("Intro.\n\n{{{attrs}}}\n::: box\nBody\n:::\n"),
+            W_BOX_STYLE_INVALID,
+        )
+    };
+    assert!(fixed("bg=fafafa").contains("{bg=#fafafa}"));
+    assert!(fixed("border-style=dashd").contains("{border-style=dashed}"));
+    assert!(fixed("border-style=\"Solid\"").contains("{border-style=\"solid\"}"));
+    assert!(fixed("border-width=\"2 px\"").contains("{border-width=\"2px\"}"));
+
+    let source = "Intro.\n\n{bg=red}\n::: box\nBody\n:::\n";
+    let diag = diagnostics(source)
+        .into_iter()
+        .find(|diag| diag.code == W_BOX_STYLE_INVALID)
+        .unwrap();
+    assert!(diag.fixes.is_empty());
+}
+
+#[test]
+fn attr_syntax_fixes_give_the_corrected_form() {
+    assert_eq!(
+        apply_fix("Intro.\n\n{#a note:keep}\nText.\n", E_ATTR_SYNTAX),
+        "Intro.\n\n{#a note=keep}\nText.\n"
+    );
+    assert_eq!(
+        apply_fix("Intro.\n\n{#a  #b x=1}\nText.\n", E_ATTR_SYNTAX),
+        "Intro.\n\n{#a x=1}\nText.\n"
+    );
+    let source = "Intro.\n\n{#a note!}\nText.\n";
+    let found: Vec<_> = diagnostics(source)
+        .into_iter()
+        .filter(|diag| diag.code == E_ATTR_SYNTAX)
+        .collect();
+    assert_eq!(found.len(), 1);
+    assert!(found[0].fixes.is_empty());
+}
@@ -1,4 +1,6 @@
-use typmark_core::{parse, resolve};
+use typmark_core::{
+    HtmlEmitOptions, W_CITE_MISSING, emit_html, emit_html_document_with_options, parse, resolve,
+};
 
 #[test]
 fn document_settings_are_parsed_once() {
@@ -48,3 +50,53 @@ fn labeled_target_line_is_not_document_settings() {
         "intro"
     );
 }
+
+#[test]
+fn bibliography_settings_generate_references_section() {
+    let source = "{ bib-b=\"Beta & co.\" bib-a=\"Alpha.\" unused=1 }\n\nSee [@a; @b] and [@a].";
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    assert!(resolved.diagnostics.is_empty());
+    assert_eq!(resolved.document.bibliography_entry("a"), Some("Alpha."));
+
+    let options = HtmlEmitOptions {
+        id_namespace: Some("doc".to_string()),
+        ..HtmlEmitOptions::default()
+    };
+    let html = emit_html_document_with_options(&resolved.document, &options);
+    assert!(
+        html.contains("[<a class=\"TypMark-ref\" href=\"#doc-cite-a\">1</a>, <a class=\"TypMark-ref\" href=\"#doc-cite-b\">2</a>]"),
+        "{html}"
+    );
+    assert!(html.ends_with(
+        "<section class=\"TypMark-references\">\n  <ol>\n    <li id=\"doc-cite-a\">Alpha.</li>\n    <li id=\"doc-cite-b\">Beta &amp; co.</li>\n  </ol>\n</section>"
+    ));
+}
+
+#[test]
+fn bracketed_label_without_bibliography_entry_stays_a_reference() {
+    let source = "Intro.\n\n{#fig}\n# Figures\n\nSee [@fig] and [@nobody].\n";
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let codes: Vec<_> = resolved.diagnostics.iter().map(|diag| diag.code).collect();
+    assert_eq!(codes, vec![W_CITE_MISSING]);
+    assert_eq!(resolved.diagnostics[0].range.start.character, 17);
+
+    let html = emit_html(&resolved.document.blocks);
+    assert!(
+        html.contains("See [<a class=\"TypMark-ref\" href=\"#fig\">Figures</a>] and "),
+        "{html}"
+    );
+}
@@ -1,16 +1,30 @@
-use typmark_core::{HtmlEmitOptions, emit_html_with_options, parse, resolve};
+use std::sync::Arc;
+use typmark_core::{
+    BlockKind, CodeMeta, Directive, DirectiveHandler, E_ATTR_SYNTAX, E_CODE_CONFLICT,
+    HeadingAnchorPosition, HtmlEmitOptions, LineDiff, LineRange, RawHtmlMode, ResolveResult,
+    SanitizeConfig, SourceMap, W_CODE_RANGE_OOB, W_CODE_WRAP_INVALID, W_DIRECTIVE_UNKNOWN,
+    directive_diagnostics, emit_html, emit_html_document_sanitized_with_config,
+    emit_html_document_sanitized_with_config_and_source_map,
+    emit_html_document_with_options_and_source_map, emit_html_sanitized,
+    emit_html_sanitized_with_config, emit_html_sanitized_with_options, emit_html_to_writer,
+    emit_html_with_options, parse, render_hash, resolve,
+};
 
-#[test]
-fn emit_simple_code_blocks_keep_attrs() {
-    let source = "{#code foo=bar}\n```rs\nlet x = 1;\n```\n";
+fn resolve_source(source: &str) -> ResolveResult {
     let parsed = parse(source);
-    let resolved = resolve(
+    resolve(
         parsed.document,
         source,
         &parsed.source_map,
         parsed.diagnostics,
         &parsed.link_defs,
-    );
+    )
+}
+
+#[test]
+fn emit_simple_code_blocks_keep_attrs() {
+    let source = "{#code foo=bar}\n```rs\nlet x = 1;\n```\n";
+    let resolved = resolve_source(source);
 
     let options = HtmlEmitOptions {
         simple_code_blocks: true,
@@ -22,3 +36,986 @@ fn emit_simple_code_blocks_keep_attrs() {
         "<pre id=\"code\" data-foo=\"bar\"><code class=\"language-rs\">let x = 1;\n</code></pre>";
     assert_eq!(html.trim_end(), expected);
 }
+
+fn render_code_block(source: &str, simple_code_blocks: bool) -> String {
+    let resolved = resolve_source(source);
+    let options = HtmlEmitOptions {
+        simple_code_blocks,
+        ..Default::default()
+    };
+    emit_html_with_options(&resolved.document.blocks, &options)
+}
+
+#[test]
+fn emit_code_block_line_counts_match_across_modes() {
+    let cases = [
+        ("```\na\nb\n```\n", "a\nb\n", 2),
+        ("```\na\n\n```\n", "a\n\n", 2),
+        ("```\n\n```\n", "\n", 1),
+        ("```\n```\n", "", 0),
+    ];
+    for (source, simple_text, lines) in cases {
+        let simple = render_code_block(source, true);
+        assert_eq!(
+            simple.trim_end(),
+            format!("<pre><code>{}</code></pre>", simple_text),
+            "simple output for {:?}",
+            source
+        );
+        let enhanced = render_code_block(source, false);
+        assert_eq!(
+            enhanced.matches("<span class=\"line\"").count(),
+            lines,
+            "enhanced output for {:?}",
+            source
+        );
+    }
+
+    let parsed = parse("```rs {hl=\"2\"}\na\nb\n```\n");
+    assert!(parsed.diagnostics.is_empty());
+    let parsed = parse("```rs {hl=\"3\"}\na\nb\n```\n");
+    assert!(
+        parsed
+            .diagnostics
+            .iter()
+            .any(|diag| diag.code == W_CODE_RANGE_OOB)
+    );
+}
+
+fn code_meta(source: &str) -> (CodeMeta, Vec<&'static str>) {
+    let parsed = parse(source);
+    let codes = parsed.diagnostics.iter().map(|diag| diag.code).collect();
+    let BlockKind::CodeBlock(code) = &parsed.document.blocks[0].kind else {
+        panic!("expected a code block for {source:?}");
+    };
+    (code.meta.clone(), codes)
+}
+
+#[test]
+fn open_ended_line_ranges_clamp_to_the_block() {
+    let range = |start, end| LineRange { start, end };
+
+    let (meta, codes) = code_meta("```rs {hl=\"-2\"}\na\nb\nc\n```\n");
+    assert_eq!(meta.hl, vec![range(1, 2)]);
+    assert!(codes.is_empty());
+
+    let (meta, codes) = code_meta("```rs {hl=\"3-\"}\na\nb\nc\nd\n```\n");
+    assert_eq!(meta.hl, vec![range(3, 4)]);
+    assert!(codes.is_empty());
+
+    let (meta, codes) = code_meta("```rs {hl=\"5-, -9\"}\na\nb\nc\n```\n");
+    assert_eq!(meta.hl, vec![range(1, 3)]);
+    assert!(codes.is_empty(), "{codes:?}");
+
+    let (meta, codes) = code_meta("```rs {hl=\"1:first, 3-\"}\na\nb\nc\n```\n");
+    assert_eq!(meta.hl, vec![range(1, 1), range(3, 3)]);
+    assert_eq!(meta.line_labels[0].label.name, "first");
+    assert!(codes.is_empty());
+
+    let (_, codes) = code_meta("```rs {hl=\"2-\" diff_add=\"3\"}\na\nb\nc\n```\n");
+    assert_eq!(codes, vec![E_CODE_CONFLICT]);
+
+    let (_, codes) = code_meta("```rs {hl=\"-\"}\na\n```\n");
+    assert_eq!(codes, vec![E_ATTR_SYNTAX]);
+    let (_, codes) = code_meta("```rs {hl=\"0-\"}\na\n```\n");
+    assert_eq!(codes, vec![E_ATTR_SYNTAX]);
+}
+
+fn render_with_raw_html(source: &str, raw_html: RawHtmlMode) -> String {
+    let resolved = resolve_source(source);
+    let options = HtmlEmitOptions {
+        raw_html,
+        ..Default::default()
+    };
+    emit_html_with_options(&resolved.document.blocks, &options)
+}
+
+#[test]
+fn emit_strip_raw_html_drops_blocks_and_spans() {
+    let source = "<div>raw</div>\n\nText <b>bold</b> and <https://example.com>.\n";
+    let html = render_with_raw_html(source, RawHtmlMode::Strip);
+    let expected = "<p>Text bold and <a href=\"https://example.com\">https://example.com</a>.</p>";
+    assert_eq!(html.trim_end(), expected);
+}
+
+#[test]
+fn emit_escape_raw_html_renders_text() {
+    let source = "<div>raw</div>\n\nText <b>bold</b>.\n";
+    let html = render_with_raw_html(source, RawHtmlMode::Escape);
+    let expected = "<p>&lt;div&gt;raw&lt;/div&gt;</p>\n<p>Text &lt;b&gt;bold&lt;/b&gt;.</p>";
+    assert_eq!(html.trim_end(), expected);
+}
+
+#[test]
+fn emit_strip_comments_keeps_other_html() {
+    let source = "Intro <!-- note --> text <b>bold</b>.\n\n<!-- draft:\n  fix this\n-->\n<div class=\"keep\">Real</div>\n\n<!--[if IE]><p>Old</p><![endif]-->\n\n<!-- a --> <!-- b -->\n\nOutro <!--\nmulti\n-->.\n";
+    let parsed = parse(source);
+    let blocks = &parsed.document.blocks;
+    let options = HtmlEmitOptions {
+        strip_comments: true,
+        ..Default::default()
+    };
+    let html = emit_html_with_options(blocks, &options);
+    let expected = "<p>Intro  text <b>bold</b>.</p>\n<div class=\"keep\">Real</div>\n<!--[if IE]><p>Old</p><![endif]-->\n<p>Outro .</p>";
+    assert_eq!(html.trim_end(), expected);
+
+    let html = emit_html(blocks);
+    assert!(html.contains("<!-- draft:\n  fix this\n-->"), "{html}");
+    assert!(html.contains("Intro <!-- note --> text"), "{html}");
+}
+
+#[test]
+fn emit_heading_offset_shifts_and_clamps_levels() {
+    let source = "# One\n\n## Two\n\n### Three\n\n#### Four\n\n##### Five\n";
+    let resolved = resolve_source(source);
+
+    for wrap_sections in [true, false] {
+        let options = HtmlEmitOptions {
+            wrap_sections,
+            heading_offset: 2,
+            ..Default::default()
+        };
+        let html = emit_html_with_options(&resolved.document.blocks, &options);
+        assert!(html.contains("<h3>One</h3>"), "{}", html);
+        assert!(html.contains("<h4>Two</h4>"), "{}", html);
+        assert!(html.contains("<h5>Three</h5>"), "{}", html);
+        assert!(html.contains("<h6>Four</h6>"), "{}", html);
+        assert!(html.contains("<h6>Five</h6>"), "{}", html);
+        assert!(!html.contains("<h1>") && !html.contains("<h2>"), "{}", html);
+        assert!(!html.contains("<h7>"), "{}", html);
+    }
+}
+
+#[test]
+fn emit_to_writer_matches_string_output() {
+    let source = "# Title\n\nParagraph with *emph*.\n\n- a\n- b\n\n```rs\nlet x = 1;\n```\n\n    indented\n\n> quote\n";
+    let resolved = resolve_source(source);
+
+    for simple_code_blocks in [false, true] {
+        let options = HtmlEmitOptions {
+            simple_code_blocks,
+            ..Default::default()
+        };
+        let expected = emit_html_with_options(&resolved.document.blocks, &options);
+        let mut streamed = Vec::new();
+        emit_html_to_writer(&resolved.document.blocks, &options, &mut streamed).expect("write");
+        assert_eq!(String::from_utf8(streamed).expect("utf8"), expected);
+    }
+}
+
+#[test]
+fn emit_inline_span_attrs_survive_sanitizing() {
+    let source = "A [word]{.warn #w key=v} here.\n";
+    let resolved = resolve_source(source);
+    let html = emit_html_sanitized(&resolved.document.blocks);
+    let expected = "<p>A <span id=\"w\" class=\"warn\" data-key=\"v\">word</span> here.</p>";
+    assert_eq!(html.trim_end(), expected);
+}
+
+/// Text of every `<pre>` as a browser shows it: tags stripped, entities decoded,
+/// and the newline directly after the start tag dropped.
+fn pre_texts(html: &str) -> Vec<String> {
+    let mut texts = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("<pre") {
+        let open_end = start + rest[start..].find('>').expect("pre start tag") + 1;
+        let close = open_end + rest[open_end..].find("</pre>").expect("pre end tag");
+        let inner = &rest[open_end..close];
+        let mut text = String::new();
+        let mut in_tag = false;
+        for ch in inner.chars() {
+            match ch {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                _ if !in_tag => text.push(ch),
+                _ => {}
+            }
+        }
+        let text = text
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&");
+        texts.push(text.strip_prefix('\n').unwrap_or(&text).to_string());
+        rest = &rest[close..];
+    }
+    texts
+}
+
+#[test]
+fn emit_sanitizing_keeps_code_whitespace() {
+    let cases = [
+        (
+            "fenced",
+            "```rust\nfn main() {\n\tlet  x = \"a  b\";\n\n        deep();   \n}\n```\n",
+        ),
+        ("indented", "    first   line\n\n\n      second\n"),
+        ("leading blank lines", "```\n\n\n  after blanks\n```\n"),
+        (
+            "table",
+            "| a | b |\n| --- | --- |\n| `x   y` | <b>1</b>  <i>2</i> |\n",
+        ),
+        (
+            "nested list",
+            "- one\n  - two\n    ```\n    nested   code\n      keep\n    ```\n  - three\n- four\n",
+        ),
+        ("raw pre", "<pre>\n\n  raw   block\n</pre>\n"),
+    ];
+    for (name, source) in cases {
+        let resolved = resolve_source(source);
+        for simple_code_blocks in [false, true] {
+            let options = HtmlEmitOptions {
+                simple_code_blocks,
+                ..Default::default()
+            };
+            let raw = emit_html_with_options(&resolved.document.blocks, &options);
+            let sanitized = emit_html_sanitized_with_options(&resolved.document.blocks, &options);
+            assert_eq!(
+                pre_texts(&sanitized),
+                pre_texts(&raw),
+                "{name}, simple={simple_code_blocks}\nraw:\n{raw}\nsanitized:\n{sanitized}"
+            );
+            if name == "table" || name == "nested list" {
+                assert_eq!(sanitized, raw, "{name}, simple={simple_code_blocks}");
+            }
+        }
+    }
+}
+
+#[test]
+fn emit_class_prefix_replaces_typmark_classes() {
+    let source = "Intro.\n\n{#top}\n# Title\n\nSee @top and @top[see [docs](https://example.com)], $x$.\n\n> [!NOTE]\n> Heads up.\n\n::: box Boxed\nBody.\n:::\n\n$$\ny\n$$\n\n{#raw}\n<div>raw</div>\n\n```rs {hl=\"1\"}\nlet x = 1;\n```\n";
+    let resolved = resolve_source(source);
+    let options = HtmlEmitOptions {
+        class_prefix: "md-".to_string(),
+        ..Default::default()
+    };
+
+    let html = emit_html_with_options(&resolved.document.blocks, &options);
+    assert!(!html.contains("TypMark-"), "{html}");
+    for class in [
+        "md-ref",
+        "md-delink",
+        "md-alert",
+        "md-box-title",
+        "md-codeblock",
+        "md-pre",
+        "md-html",
+    ] {
+        assert!(html.contains(class), "missing {class}: {html}");
+    }
+}
+
+#[test]
+fn emit_empty_alt_images_are_hidden_from_assistive_tech() {
+    let source = "![](deco.png) ![Chart](chart.png)\n";
+    let resolved = resolve_source(source);
+    let expected = "<p><img src=\"deco.png\" alt=\"\" aria-hidden=\"true\" /> <img src=\"chart.png\" alt=\"Chart\" /></p>";
+    assert_eq!(emit_html(&resolved.document.blocks).trim_end(), expected);
+    assert!(emit_html_sanitized(&resolved.document.blocks).contains("aria-hidden=\"true\""));
+}
+
+#[test]
+fn emit_lang_attribute_on_sections_and_boxes() {
+    let source = "Intro.\n\n{lang=ja}\n# Title\n\n{lang=\"fr-CA\"}\n::: box Note\nBody.\n:::\n\n{lang=\"not a tag\"}\n::: box\nOther.\n:::\n";
+    let resolved = resolve_source(source);
+    let html = emit_html(&resolved.document.blocks);
+    assert!(html.contains("<section lang=\"ja\">"), "{html}");
+    assert!(
+        html.contains("data-typmark=\"box\" lang=\"fr-CA\""),
+        "{html}"
+    );
+    assert!(html.contains("data-lang=\"not a tag\""), "{html}");
+    assert!(emit_html_sanitized(&resolved.document.blocks).contains("lang=\"fr-CA\""));
+}
+
+#[test]
+fn emit_collapsible_boxes_as_details() {
+    let source = "{#outer collapsible=true bg=\"#eee\"}\n:::: box Outer\nIntro.\n\n{collapsible open}\n::: box\nInner.\n:::\n::::\n\n{open}\n::: box Plain\nBody.\n:::\n";
+    let resolved = resolve_source(source);
+    let html = emit_html(&resolved.document.blocks);
+    let expected = "<details class=\"TypMark-box\" data-typmark=\"box\" id=\"outer\" data-bg=\"#eee\">\n  <summary class=\"TypMark-box-title\">Outer</summary>\n  <div class=\"TypMark-box-body\">\n    <p>Intro.</p>\n    <details class=\"TypMark-box\" data-typmark=\"box\" open>\n      <summary class=\"TypMark-box-title\">Details</summary>\n      <div class=\"TypMark-box-body\">\n        <p>Inner.</p>\n      </div>\n    </details>\n  </div>\n</details>\n<div class=\"TypMark-box\" data-typmark=\"box\">\n  <div class=\"TypMark-box-title\">Plain</div>";
+    assert!(html.starts_with(expected), "{html}");
+
+    let sanitized = emit_html_sanitized(&resolved.document.blocks);
+    assert!(sanitized.contains("data-bg=\"#eee\""), "{sanitized}");
+    assert!(
+        sanitized.contains("<details class=\"TypMark-box\" data-typmark=\"box\" open=\"\">"),
+        "{sanitized}"
+    );
+    assert!(sanitized.contains("<summary class=\"TypMark-box-title\">Details</summary>"));
+}
+
+#[test]
+fn emit_captions_survive_sanitizing() {
+    let source = "Intro.\n\n{caption=\"Sizes\"}\n| a |\n| --- |\n| 1 |\n\n```rs {caption=\"Code\"}\nx\n```\n";
+    let resolved = resolve_source(source);
+    let html = emit_html_sanitized(&resolved.document.blocks);
+    assert!(html.contains("<caption>Sizes</caption>"), "{html}");
+    assert!(html.contains("<figcaption>Code</figcaption>"), "{html}");
+}
+
+#[test]
+fn emit_sanitizer_config_extends_the_allow_list() {
+    let source = "Some <mark>marked</mark> text<script>alert(1)</script> and <em>more</em>.\n\n[x](ftp://example.com/x) [y](https://example.com/y)\n";
+    let resolved = resolve_source(source);
+    let blocks = &resolved.document.blocks;
+
+    let default_html = emit_html_sanitized(blocks);
+    assert!(!default_html.contains("<mark>"), "{default_html}");
+    assert!(default_html.contains("<em>more</em>"), "{default_html}");
+
+    let config = SanitizeConfig {
+        add_tags: vec!["mark".to_string()],
+        remove_tags: vec!["em".to_string()],
+        remove_url_schemes: vec!["ftp".to_string()],
+        ..Default::default()
+    };
+    let html = emit_html_sanitized_with_config(blocks, &HtmlEmitOptions::default(), &config);
+    assert!(html.contains("<mark>marked</mark>"), "{html}");
+    assert!(
+        !html.contains("script") && !html.contains("alert"),
+        "{html}"
+    );
+    assert!(html.contains(" and more."), "{html}");
+    assert!(
+        html.contains("<a rel=\"noopener noreferrer\">x</a>"),
+        "{html}"
+    );
+    assert!(html.contains("href=\"https://example.com/y\""), "{html}");
+    assert_eq!(
+        emit_html_sanitized_with_options(blocks, &HtmlEmitOptions::default()),
+        default_html
+    );
+
+    let document_html = emit_html_document_sanitized_with_config(
+        &resolved.document,
+        &HtmlEmitOptions::default(),
+        &config,
+    );
+    assert_eq!(document_html, html);
+    let mapped_html = emit_html_document_sanitized_with_config_and_source_map(
+        &resolved.document,
+        &HtmlEmitOptions::default(),
+        &SourceMap::new(source),
+        &config,
+    );
+    assert!(mapped_html.contains("<mark></mark>"), "{mapped_html}");
+    assert!(!mapped_html.contains("alert"), "{mapped_html}");
+    assert!(!mapped_html.contains("<em>"), "{mapped_html}");
+}
+
+#[derive(Debug)]
+struct Kbd;
+
+impl DirectiveHandler for Kbd {
+    fn render_directive(&self, directive: &Directive, label_html: &str) -> String {
+        let class = directive
+            .attrs
+            .items
+            .iter()
+            .find(|item| item.key == "class")
+            .map_or("", |item| item.value.raw.as_str());
+        format!("<kbd class=\"{class}\">{label_html}</kbd>")
+    }
+}
+
+#[test]
+fn emit_directives_use_registered_handlers() {
+    let source = "Press ::kbd[Ctrl+*C*]{.key} or ::badge[<new>], not std::vec[0].\n";
+    let resolved = resolve_source(source);
+    let mut options = HtmlEmitOptions::default();
+    options
+        .directive_handlers
+        .insert("kbd".to_string(), Arc::new(Kbd));
+
+    let html = emit_html_with_options(&resolved.document.blocks, &options);
+    let expected = "<p>Press <kbd class=\"key\">Ctrl+<em>C</em></kbd> or ::badge[&lt;new&gt;], not std::vec[0].</p>";
+    assert_eq!(html.trim_end(), expected);
+
+    let diagnostics = directive_diagnostics(&resolved.document, &options, &SourceMap::new(source));
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, W_DIRECTIVE_UNKNOWN);
+    assert_eq!(diagnostics[0].range.start.character, 31);
+}
+
+#[test]
+fn non_media_directives_keep_parenthesized_text() {
+    let source = "See ::kbd[x](y) and ::foo[x](y).\n";
+    let resolved = resolve_source(source);
+    let mut options = HtmlEmitOptions::default();
+    options
+        .directive_handlers
+        .insert("kbd".to_string(), Arc::new(Kbd));
+
+    let html = emit_html_with_options(&resolved.document.blocks, &options);
+    let expected = "<p>See <kbd class=\"\">x</kbd>(y) and ::foo[x](y).</p>";
+    assert_eq!(html.trim_end(), expected);
+}
+
+#[test]
+fn kbd_directive_nests_keys_when_enabled() {
+    let source = "Press ::kbd[Ctrl+C], ::kbd[Enter]{.key}, ::kbd[Ctrl + +] or `::kbd[Esc]`.\n";
+    let resolved = resolve_source(source);
+    let blocks = &resolved.document.blocks;
+    let options = HtmlEmitOptions {
+        kbd_directive: true,
+        ..Default::default()
+    };
+
+    let expected = "<p>Press <kbd><kbd>Ctrl</kbd>+<kbd>C</kbd></kbd>, <kbd class=\"key\">Enter</kbd>, <kbd><kbd>Ctrl</kbd>+<kbd>+</kbd></kbd> or <code>::kbd[Esc]</code>.</p>";
+    assert_eq!(
+        emit_html_with_options(blocks, &options).trim_end(),
+        expected
+    );
+    assert_eq!(
+        emit_html_sanitized_with_options(blocks, &options).trim_end(),
+        expected
+    );
+    assert!(
+        directive_diagnostics(&resolved.document, &options, &SourceMap::new(source)).is_empty()
+    );
+
+    let html = emit_html(blocks);
+    assert!(html.contains("Press ::kbd[Ctrl+C], "), "{html}");
+    assert_eq!(
+        directive_diagnostics(
+            &resolved.document,
+            &HtmlEmitOptions::default(),
+            &SourceMap::new(source)
+        )
+        .len(),
+        3
+    );
+}
+
+#[test]
+fn media_directives_render_and_sanitize_on_request() {
+    let source = "::video[Demo run](media/demo.mp4){poster=media/demo.png width=640 type=video/mp4}\n\n::audio[](https://example.com/a.ogg){.clip}\n\n::video[no url]\n";
+    let resolved = resolve_source(source);
+    let blocks = &resolved.document.blocks;
+    let options = HtmlEmitOptions {
+        wrap_sections: false,
+        ..Default::default()
+    };
+
+    let html = emit_html_with_options(blocks, &options);
+    let video = "<video controls poster=\"media/demo.png\" width=\"640\" aria-label=\"Demo run\"><source src=\"media/demo.mp4\" type=\"video/mp4\" /></video>";
+    let audio =
+        "<audio controls class=\"clip\"><source src=\"https://example.com/a.ogg\" /></audio>";
+    assert_eq!(
+        html.trim_end(),
+        format!("<p>{video}</p>\n<p>{audio}</p>\n<p>::video[no url]</p>")
+    );
+    let diagnostics = directive_diagnostics(&resolved.document, &options, &SourceMap::new(source));
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].range.start.line, 4);
+
+    let html = emit_html_sanitized_with_options(blocks, &options);
+    assert!(
+        !html.contains("<video") && !html.contains("<source"),
+        "{html}"
+    );
+
+    let config = SanitizeConfig {
+        allow_media: true,
+        ..Default::default()
+    };
+    let html = emit_html_sanitized_with_config(blocks, &options, &config);
+    assert!(
+        html.contains("<video controls=\"\" poster=\"media/demo.png\" width=\"640\" aria-label=\"Demo run\"><source src=\"media/demo.mp4\" type=\"video/mp4\"></video>"),
+        "{html}"
+    );
+    assert!(
+        html.contains(
+            "<audio controls=\"\" class=\"clip\"><source src=\"https://example.com/a.ogg\"></audio>"
+        ),
+        "{html}"
+    );
+}
+
+#[test]
+fn emit_no_highlight_code_blocks_keep_language() {
+    let source = "```html nohighlight\n<b>x</b>\n```\n\n```rs {.no-highlight #code}\nlet x = 1;\n```\n\n```rs {.other}\ny\n```\n";
+    let resolved = resolve_source(source);
+    assert_eq!(resolved.diagnostics.len(), 1);
+    assert_eq!(resolved.diagnostics[0].message, "invalid attribute item");
+    let html = emit_html(&resolved.document.blocks);
+    assert!(
+        html.contains("data-typmark=\"codeblock\" data-no-highlight data-lang=\"html\">"),
+        "{html}"
+    );
+    assert!(html.contains("<code class=\"language-html\">"), "{html}");
+    assert!(
+        html.contains("id=\"code\" data-no-highlight data-lang=\"rs\">"),
+        "{html}"
+    );
+    assert_eq!(html.matches("data-no-highlight").count(), 2, "{html}");
+    assert_eq!(html.matches("<span class=\"line\"").count(), 3, "{html}");
+    assert!(emit_html_sanitized(&resolved.document.blocks).contains("data-no-highlight"));
+}
+
+#[test]
+fn emit_soft_wrap_code_blocks() {
+    let source = "```rs {wrap=soft}\nlet x = 1;\n```\n\n```rs {wrap=none}\ny\n```\n\n```rs {wrap=auto}\nz\n```\n";
+    let resolved = resolve_source(source);
+    assert_eq!(resolved.diagnostics.len(), 1);
+    assert_eq!(resolved.diagnostics[0].code, W_CODE_WRAP_INVALID);
+    let html = emit_html(&resolved.document.blocks);
+    assert!(
+        html.contains("<figure class=\"TypMark-codeblock TypMark-codeblock--wrap\" data-typmark=\"codeblock\" data-wrap=\"soft\""),
+        "{html}"
+    );
+    assert!(
+        html.contains(
+            "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\" data-wrap=\"none\""
+        ),
+        "{html}"
+    );
+    assert_eq!(html.matches("<span class=\"line\"").count(), 3, "{html}");
+}
+
+#[cfg(feature = "emoji")]
+#[test]
+fn emit_emoji_shortcodes_when_enabled() {
+    let source = "Ship it :rocket::+1: *:tada:* at 10:30:00, `:smile:` and :nope:.\n";
+    let resolved = resolve_source(source);
+    assert_eq!(
+        emit_html(&resolved.document.blocks).trim_end(),
+        "<p>Ship it :rocket::+1: <em>:tada:</em> at 10:30:00, <code>:smile:</code> and :nope:.</p>"
+    );
+
+    let options = HtmlEmitOptions {
+        emoji: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        emit_html_with_options(&resolved.document.blocks, &options).trim_end(),
+        "<p>Ship it <span class=\"emoji\" role=\"img\" aria-label=\"rocket\">\u{1f680}</span><span class=\"emoji\" role=\"img\" aria-label=\"+1\">\u{1f44d}</span> <em><span class=\"emoji\" role=\"img\" aria-label=\"tada\">\u{1f389}</span></em> at 10:30:00, <code>:smile:</code> and :nope:.</p>"
+    );
+}
+
+#[test]
+fn emit_code_block_file_title() {
+    let source = "```rust {file=main.rs hl=\"1\"}\nfn main() {}\n```\n";
+    let resolved = resolve_source(source);
+    let title = "<figcaption class=\"TypMark-code-title\" data-file=\"main.rs\">main.rs</figcaption>\n  <pre class=\"TypMark-pre\">";
+    let html = emit_html(&resolved.document.blocks);
+    assert!(html.contains(title), "{html}");
+    assert!(html.contains("data-lang=\"rust\""), "{html}");
+    assert!(html.contains("data-highlighted-line"), "{html}");
+    let sanitized = emit_html_sanitized(&resolved.document.blocks);
+    assert!(sanitized.contains(title), "{sanitized}");
+
+    let options = HtmlEmitOptions {
+        simple_code_blocks: true,
+        ..Default::default()
+    };
+    assert!(
+        !html.contains("<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\" data-file"),
+        "{html}"
+    );
+    let simple = emit_html_with_options(&resolved.document.blocks, &options);
+    assert!(!simple.contains("figcaption"), "{simple}");
+    assert!(!simple.contains("data-file"), "{simple}");
+
+    let source = "```sh {title=\"Install step\"}\nmake\n```\n";
+    let html = emit_html(&parse(source).document.blocks);
+    assert!(
+        html.contains("<figcaption class=\"TypMark-code-title\">Install step</figcaption>"),
+        "{html}"
+    );
+    assert!(!html.contains("data-title"), "{html}");
+}
+
+#[test]
+fn emit_code_block_title_and_caption_share_one_figcaption() {
+    let source = "Intro.\n\n{caption=\"Entry point\"}\n```rust {file=main.rs}\nfn main() {}\n```\n";
+    let resolved = resolve_source(source);
+    let html = emit_html(&resolved.document.blocks);
+    assert_eq!(html.matches("<figcaption").count(), 1, "{html}");
+    assert!(
+        html.contains("<figcaption class=\"TypMark-code-title\" data-file=\"main.rs\">main.rs <span class=\"TypMark-code-caption\">Entry point</span></figcaption>"),
+        "{html}"
+    );
+    let sanitized = emit_html_sanitized(&resolved.document.blocks);
+    assert!(
+        sanitized.contains(
+            "main.rs <span class=\"TypMark-code-caption\">Entry point</span></figcaption>"
+        ),
+        "{sanitized}"
+    );
+}
+
+#[test]
+fn id_namespace_prefixes_ids_and_ref_links() {
+    let source = "{#intro}\n# Intro\n\nSee @intro and [x]{#mark}.\n\n```rs {hl=\"1:entry\"}\nfn main() {}\n```\n";
+    let resolved = resolve_source(source);
+    let plain = emit_html(&resolved.document.blocks);
+    let options = HtmlEmitOptions {
+        id_namespace: Some("doc1".to_string()),
+        ..Default::default()
+    };
+    let html = emit_html_with_options(&resolved.document.blocks, &options);
+    assert!(html.contains("<section id=\"doc1-intro\""), "{html}");
+    assert!(html.contains("href=\"#doc1-intro\""), "{html}");
+    assert!(html.contains("<span id=\"doc1-mark\">x</span>"), "{html}");
+    assert!(
+        html.contains("id=\"doc1-entry\" data-line-label=\"entry\""),
+        "{html}"
+    );
+    assert_eq!(html.replace("doc1-", ""), plain);
+}
+
+#[test]
+fn soft_breaks_as_br_only_changes_soft_breaks() {
+    let source = "First line\nsecond `a\nb` line  \nthird\n";
+    let resolved = resolve_source(source);
+
+    let html = emit_html(&resolved.document.blocks);
+    assert_eq!(
+        html.trim_end(),
+        "<p>First line\nsecond <code>a b</code> line<br />\nthird</p>"
+    );
+
+    let options = HtmlEmitOptions {
+        soft_breaks_as_br: true,
+        ..Default::default()
+    };
+    let html = emit_html_with_options(&resolved.document.blocks, &options);
+    assert_eq!(
+        html.trim_end(),
+        "<p>First line<br />\nsecond <code>a b</code> line<br />\nthird</p>"
+    );
+
+    let html = emit_html_document_with_options_and_source_map(
+        &resolved.document,
+        &options,
+        &SourceMap::new(source),
+    );
+    assert!(
+        html.contains("First line</span><br data-tm-range=\"0:10-1:0\" />\n"),
+        "{html}"
+    );
+    assert!(
+        html.contains("<br data-tm-range=\"2:9-3:0\" />\n"),
+        "{html}"
+    );
+}
+
+#[test]
+fn smart_punctuation_rewrites_text_only() {
+    let render = |source: &str, smart_punctuation: bool| {
+        let parsed = parse(source);
+        let options = HtmlEmitOptions {
+            smart_punctuation,
+            ..Default::default()
+        };
+        emit_html_with_options(&parsed.document.blocks, &options)
+    };
+
+    let source = "\"She said *'don't'* -- twice...\" and `\"code\" -- ...` 1990--2000 --- end\n";
+    assert_eq!(
+        render(source, false).trim_end(),
+        "<p>&quot;She said <em>'don't'</em> -- twice...&quot; and <code>&quot;code&quot; -- ...</code> 1990--2000 --- end</p>"
+    );
+    assert_eq!(
+        render(source, true).trim_end(),
+        "<p>\u{201c}She said <em>\u{2018}don\u{2019}t\u{2019}</em> \u{2013} twice\u{2026}\u{201d} and <code>&quot;code&quot; -- ...</code> 1990\u{2013}2000 \u{2014} end</p>"
+    );
+
+    let html = render("**\"Bold *quote*\"** it's '90s ---- ----- a-b\n", true);
+    assert_eq!(
+        html.trim_end(),
+        "<p><strong>\u{201c}Bold <em>quote</em>\u{201d}</strong> it\u{2019}s \u{2018}90s \u{2013}\u{2013} \u{2014}\u{2013} a-b</p>"
+    );
+
+    let source = "A \"quote\" -- here\n";
+    let parsed = parse(source);
+    let options = HtmlEmitOptions {
+        smart_punctuation: true,
+        ..Default::default()
+    };
+    let html = emit_html_document_with_options_and_source_map(
+        &parsed.document,
+        &options,
+        &parsed.source_map,
+    );
+    assert!(
+        html.contains("<p data-tm-range=\"0:0-0:17\"><span data-tm-range=\"0:0-0:17\">A \u{201c}quote\u{201d} \u{2013} here</span></p>"),
+        "{html}"
+    );
+}
+
+#[test]
+fn line_status_matches_code_meta() {
+    let source =
+        "```rs {hl=\"2-4,6:done\" diff_add=\"5\" diff_del=\"4-5\"}\na\nb\nc\nd\ne\nf\n```\n";
+    let parsed = parse(source);
+    let BlockKind::CodeBlock(code) = &parsed.document.blocks[0].kind else {
+        panic!("expected code block");
+    };
+
+    let highlighted: Vec<u32> = (1..=6)
+        .filter(|&line| code.line_status(line).highlighted)
+        .collect();
+    assert_eq!(highlighted, [2, 3, 4, 6]);
+    assert_eq!(code.line_status(4).diff, Some(LineDiff::Del));
+    assert_eq!(code.line_status(5).diff, Some(LineDiff::Add));
+    assert_eq!(code.line_status(1).diff, None);
+    assert_eq!(
+        code.line_status(6).label.map(|label| label.name.as_str()),
+        Some("done")
+    );
+    assert_eq!(code.line_status(7).label, None);
+
+    let range = code.meta.hl[0];
+    assert!(!range.contains(1) && range.contains(2) && range.contains(4) && !range.contains(5));
+}
+
+#[test]
+fn wrap_dates_marks_iso_dates_outside_code_and_links() {
+    let render = |source: &str, wrap_dates: bool| {
+        let parsed = parse(source);
+        let options = HtmlEmitOptions {
+            wrap_dates,
+            ..Default::default()
+        };
+        emit_html_with_options(&parsed.document.blocks, &options)
+    };
+
+    let source = "Due 2024-01-15, not 2024-1-5 or `2024-01-15`.\n";
+    assert_eq!(
+        render(source, false).trim_end(),
+        "<p>Due 2024-01-15, not 2024-1-5 or <code>2024-01-15</code>.</p>"
+    );
+    assert_eq!(
+        render(source, true).trim_end(),
+        "<p>Due <time datetime=\"2024-01-15\">2024-01-15</time>, not 2024-1-5 or <code>2024-01-15</code>.</p>"
+    );
+
+    let html = render(
+        "At 2024-01-15T09:30 see [2024-01-15](/log) and $2024-01-15$; id-2024-01-15 2024-13-01 2024-01-150\n",
+        true,
+    );
+    assert!(
+        html.contains("At <time datetime=\"2024-01-15T09:30\">2024-01-15T09:30</time> see"),
+        "{html}"
+    );
+    assert!(html.contains(">2024-01-15</a>"), "{html}");
+    assert_eq!(html.matches("<time").count(), 1, "{html}");
+
+    let parsed = parse("Shipped 2024-01-15.\n");
+    let options = HtmlEmitOptions {
+        wrap_dates: true,
+        ..Default::default()
+    };
+    let html = emit_html_sanitized_with_options(&parsed.document.blocks, &options);
+    assert!(
+        html.contains("<time datetime=\"2024-01-15\">2024-01-15</time>"),
+        "{html}"
+    );
+}
+
+#[test]
+fn heading_anchors_link_to_the_heading_id() {
+    let source = "{#intro}\n# Intro *fast*\n\nBody.\n\n## No label\n";
+    let resolved = resolve_source(source);
+    let render = |heading_anchor_position, wrap_sections| {
+        let options = HtmlEmitOptions {
+            heading_anchors: true,
+            heading_anchor_position,
+            wrap_sections,
+            ..Default::default()
+        };
+        emit_html_with_options(&resolved.document.blocks, &options)
+    };
+
+    let anchor = "<a class=\"anchor\" href=\"#intro\" aria-label=\"Permalink to Intro fast\">";
+    let cases = [
+        (
+            HeadingAnchorPosition::Before,
+            format!("{anchor}#</a> Intro <em>fast</em>"),
+        ),
+        (
+            HeadingAnchorPosition::After,
+            format!("Intro <em>fast</em> {anchor}#</a>"),
+        ),
+        (
+            HeadingAnchorPosition::Wrap,
+            format!("{anchor}Intro <em>fast</em></a>"),
+        ),
+    ];
+    for (position, content) in cases {
+        let html = render(position, true);
+        assert!(
+            html.contains(&format!("<section id=\"intro\">\n  <h1>{content}</h1>")),
+            "{html}"
+        );
+        assert!(html.contains("<h2>No label</h2>"), "{html}");
+        let html = render(position, false);
+        assert!(
+            html.contains(&format!("<h1 id=\"intro\">{content}</h1>")),
+            "{html}"
+        );
+    }
+    assert_eq!(
+        HtmlEmitOptions::default().heading_anchor_position,
+        HeadingAnchorPosition::After
+    );
+
+    let options = HtmlEmitOptions {
+        heading_anchors: true,
+        id_namespace: Some("doc".to_string()),
+        ..Default::default()
+    };
+    let html = emit_html_sanitized_with_options(&resolved.document.blocks, &options);
+    assert!(
+        html.contains(
+            "<a class=\"anchor\" href=\"#doc-intro\" aria-label=\"Permalink to Intro fast\""
+        ),
+        "{html}"
+    );
+    assert!(!emit_html(&resolved.document.blocks).contains("anchor"));
+}
+
+#[test]
+fn code_copy_button_targets_the_input_pre() {
+    let source =
+        "```rs\nlet x = 1;\n```\n\n```py {output=out}\nprint(1)\n```\n\n{#out}\n```text\n1\n```\n";
+    let resolved = resolve_source(source);
+    let options = HtmlEmitOptions {
+        code_copy_button: true,
+        ..Default::default()
+    };
+
+    let html = emit_html_with_options(&resolved.document.blocks, &options);
+    assert!(
+        html.contains(
+            "<button type=\"button\" class=\"TypMark-copy\" data-copy-target=\"TypMark-code-1\" aria-label=\"Copy code\">Copy</button>\n  <pre class=\"TypMark-pre\" id=\"TypMark-code-1\"><code class=\"language-rs\">"
+        ),
+        "{html}"
+    );
+    assert!(
+        html.contains("data-copy-target=\"TypMark-code-2\""),
+        "{html}"
+    );
+    assert_eq!(html.matches("<button").count(), 2, "{html}");
+
+    let html = emit_html_sanitized_with_options(&resolved.document.blocks, &options);
+    assert!(
+        html.contains("<button type=\"button\" class=\"TypMark-copy\" data-copy-target=\"TypMark-code-1\" aria-label=\"Copy code\">"),
+        "{html}"
+    );
+    assert!(html.contains("id=\"TypMark-code-1\""), "{html}");
+    assert!(!emit_html(&resolved.document.blocks).contains("<button"));
+}
+
+#[test]
+fn setext_headings_take_target_line_attrs() {
+    let source = "{#intro}\nTitle\n=====\n\n{#sub note=keep}\nSub\n---\n";
+    let resolved = resolve_source(source);
+    assert!(resolved.diagnostics.is_empty());
+    let options = HtmlEmitOptions {
+        wrap_sections: false,
+        ..Default::default()
+    };
+    let html = emit_html_with_options(&resolved.document.blocks, &options);
+    assert_eq!(
+        html.trim_end(),
+        "<h1 id=\"intro\">Title</h1>\n<h2 id=\"sub\" data-note=\"keep\">Sub</h2>"
+    );
+}
+
+#[test]
+fn number_sections_follows_the_section_tree() {
+    let source = "# Intro\n\n## Scope\n\n### Terms\n\n### Notes\n\n## Goals\n\nBody.\n\n{.unnumbered}\n## Aside\n\n### Inside\n\n# Method\n\n### Deep\n\n> # Quoted\n";
+    let resolved = resolve_source(source);
+    assert!(
+        resolved.diagnostics.is_empty(),
+        "{:?}",
+        resolved.diagnostics
+    );
+    let options = HtmlEmitOptions {
+        wrap_sections: false,
+        number_sections: true,
+        ..Default::default()
+    };
+    let html = emit_html_with_options(&resolved.document.blocks, &options);
+    let headings: Vec<&str> = html
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("<h"))
+        .collect();
+    assert_eq!(
+        headings,
+        vec![
+            "<h1><span class=\"section-number\">1</span> Intro</h1>",
+            "<h2><span class=\"section-number\">1.1</span> Scope</h2>",
+            "<h3><span class=\"section-number\">1.1.1</span> Terms</h3>",
+            "<h3><span class=\"section-number\">1.1.2</span> Notes</h3>",
+            "<h2><span class=\"section-number\">1.2</span> Goals</h2>",
+            "<h2 class=\"unnumbered\">Aside</h2>",
+            "<h3>Inside</h3>",
+            "<h1><span class=\"section-number\">2</span> Method</h1>",
+            "<h3><span class=\"section-number\">2.1</span> Deep</h3>",
+            "<h1>Quoted</h1>",
+        ]
+    );
+
+    let html = emit_html_with_options(&resolved.document.blocks, &HtmlEmitOptions::default());
+    assert!(!html.contains("section-number"));
+    assert!(html.contains("<section class=\"unnumbered\">"));
+}
+
+#[test]
+fn render_hash_follows_source_and_options() {
+    let options = HtmlEmitOptions::default();
+    let hash = render_hash("# Title\n\nBody.\n", &options);
+    assert_eq!(render_hash("# Title\n\nBody.\n", &options), hash);
+    assert_eq!(render_hash("# Title\r\n\r\nBody.\r\n", &options), hash);
+    assert_ne!(render_hash("# Title\n\nBody!\n", &options), hash);
+
+    let changed = [
+        HtmlEmitOptions {
+            wrap_sections: false,
+            ..Default::default()
+        },
+        HtmlEmitOptions {
+            class_prefix: "tm-".to_string(),
+            ..Default::default()
+        },
+        HtmlEmitOptions {
+            id_namespace: Some(String::new()),
+            ..Default::default()
+        },
+        HtmlEmitOptions {
+            heading_anchor_position: HeadingAnchorPosition::Wrap,
+            ..Default::default()
+        },
+        HtmlEmitOptions {
+            raw_html: RawHtmlMode::Escape,
+            ..Default::default()
+        },
+        HtmlEmitOptions {
+            number_sections: true,
+            ..Default::default()
+        },
+        HtmlEmitOptions {
+            kbd_directive: true,
+            ..Default::default()
+        },
+        HtmlEmitOptions {
+            strip_comments: true,
+            ..Default::default()
+        },
+    ];
+    for options in &changed {
+        assert_ne!(
+            render_hash("# Title\n\nBody.\n", options),
+            hash,
+            "{options:?}"
+        );
+    }
+}
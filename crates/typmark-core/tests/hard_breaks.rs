@@ -0,0 +1,48 @@
+use typmark_core::{emit_html, parse, resolve};
+
+fn render(source: &str) -> String {
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    emit_html(&resolved.document.blocks).trim_end().to_string()
+}
+
+#[test]
+fn backslash_and_two_spaces_both_break_lines() {
+    assert_eq!(render("foo\\\nbar"), "<p>foo<br />\nbar</p>");
+    assert_eq!(render("foo  \nbar"), "<p>foo<br />\nbar</p>");
+    assert_eq!(render("foo\\\r\nbar\r\n"), "<p>foo<br />\nbar</p>");
+    assert_eq!(render("foo   \r\nbar\r\n"), "<p>foo<br />\nbar</p>");
+    assert_eq!(
+        render("> foo\\\n> bar\n\n- a  \n  b\n"),
+        "<blockquote>\n  <p>foo<br />\nbar</p>\n</blockquote>\n<ul>\n  <li>a<br />\nb</li>\n</ul>"
+    );
+}
+
+#[test]
+fn breaks_at_the_end_of_a_paragraph_are_dropped() {
+    assert_eq!(render("foo\\"), "<p>foo\\</p>");
+    assert_eq!(render("foo\\\n"), "<p>foo\\</p>");
+    assert_eq!(render("foo\\  \n\nbar\n"), "<p>foo\\</p>\n<p>bar</p>");
+    assert_eq!(render("foo  \n"), "<p>foo</p>");
+    assert_eq!(render("a\\\n\\"), "<p>a<br />\n\\</p>");
+}
+
+#[test]
+fn next_line_indent_is_not_text() {
+    assert_eq!(render("foo\\\n     bar"), "<p>foo<br />\nbar</p>");
+    assert_eq!(render("foo  \n\tbar"), "<p>foo<br />\nbar</p>");
+    assert_eq!(render("foo\n    bar"), "<p>foo\nbar</p>");
+}
+
+#[test]
+fn only_source_spaces_make_a_break() {
+    assert_eq!(render("foo&#32;&#32;\nbar"), "<p>foo  \nbar</p>");
+    assert_eq!(render("foo\\\\\nbar"), "<p>foo\\\nbar</p>");
+    assert_eq!(render("foo \nbar"), "<p>foo\nbar</p>");
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use typmark_core::{
+    BlockKind, Diagnostic, E_ATTR_SYNTAX, E_INCLUDE, E_LABEL_DUP, FeatureSet, IncludeResolver,
+    IncludedSource, Inline, InlineKind, MAX_INCLUDE_DEPTH, ParseOptions, VisitorMut, W_REF_MISSING,
+    emit_html, expand_includes, parse, parse_with_options, resolve, walk_document_mut,
+};
+
+struct MemoryResolver(HashMap<String, String>);
+
+fn include_options() -> ParseOptions {
+    ParseOptions {
+        features: FeatureSet::all() | FeatureSet::INCLUDES,
+        ..Default::default()
+    }
+}
+
+impl IncludeResolver for MemoryResolver {
+    fn load(&self, _from: Option<&str>, path: &str) -> Result<IncludedSource, String> {
+        let text = self.0.get(path).ok_or("not found")?;
+        Ok(IncludedSource {
+            name: path.to_string(),
+            text: text.clone(),
+        })
+    }
+}
+
+fn expand(source: &str, files: &[(&str, &str)]) -> (String, Vec<Diagnostic>) {
+    let resolver = MemoryResolver(
+        files
+            .iter()
+            .map(|(name, text)| (name.to_string(), text.to_string()))
+            .collect(),
+    );
+    let mut parsed = parse_with_options(source, &include_options());
+    let expansion = expand_includes(
+        &mut parsed,
+        source,
+        Some("main.tmd"),
+        &resolver,
+        &include_options(),
+    );
+    let resolved = resolve(
+        parsed.document,
+        &expansion.source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let mut diagnostics = resolved.diagnostics;
+    for diag in &mut diagnostics {
+        expansion.relocate(diag);
+    }
+    (emit_html(&resolved.document.blocks), diagnostics)
+}
+
+#[test]
+fn include_splices_blocks_and_resolves_labels_across_files() {
+    let (html, diagnostics) = expand(
+        "See @intro[intro].\n\n> @include(intro.md)\n\nAfter.\n",
+        &[("intro.md", "{#intro}\nIntro text.\n\nMissing @nope[x].\n")],
+    );
+    assert!(
+        html.contains("<blockquote>\n  <p id=\"intro\">Intro text.</p>"),
+        "{html}"
+    );
+    assert!(html.contains("href=\"#intro\""), "{html}");
+    assert!(html.trim_end().ends_with("<p>After.</p>"), "{html}");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, W_REF_MISSING);
+    assert_eq!(diagnostics[0].file.as_deref(), Some("intro.md"));
+    assert_eq!(diagnostics[0].range.start.line, 3);
+    assert_eq!(diagnostics[0].range.start.character, 8);
+}
+
+#[test]
+fn duplicate_labels_across_files_point_at_both_files() {
+    let (html, diagnostics) = expand(
+        "# Manual\n\nSee @intro and @setup.\n\n@include(intro.md)\n\n@include(setup.md)\n",
+        &[
+            ("intro.md", "{#intro}\n# Intro\n\nRead @setup first.\n"),
+            (
+                "setup.md",
+                "{#setup}\n# Setup\n\nBack to @intro.\n\n{#intro}\n# Intro again\n",
+            ),
+        ],
+    );
+    assert!(
+        html.contains("<a class=\"TypMark-ref\" href=\"#intro\">"),
+        "{html}"
+    );
+    assert!(
+        html.contains("<a class=\"TypMark-ref\" href=\"#setup\">"),
+        "{html}"
+    );
+
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    let dup = &diagnostics[0];
+    assert_eq!(dup.code, E_LABEL_DUP);
+    assert_eq!(dup.file.as_deref(), Some("setup.md"));
+    assert_eq!(dup.range.start.line, 5);
+    assert_eq!(dup.related.len(), 1);
+    assert_eq!(dup.related[0].file.as_deref(), Some("intro.md"));
+    assert_eq!(dup.related[0].range.start.line, 0);
+    assert_eq!(dup.related[0].range.start.character, 2);
+}
+
+#[test]
+fn include_reports_missing_files_and_cycles() {
+    let (html, diagnostics) = expand(
+        "@include(a.md)\n\n@include(gone.md)\n",
+        &[
+            ("a.md", "A.\n\n@include(b.md)\n"),
+            ("b.md", "B.\n\n@include(a.md)\n"),
+        ],
+    );
+    assert_eq!(html.trim_end(), "<p>A.</p>\n<p>B.</p>");
+
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .filter(|diag| diag.code == E_INCLUDE)
+        .collect();
+    assert_eq!(errors.len(), 2, "{diagnostics:?}");
+    assert_eq!(errors[0].file.as_deref(), Some("b.md"));
+    assert_eq!(errors[0].range.start.line, 2);
+    assert_eq!(
+        errors[0].message,
+        "include cycle: main.tmd -> a.md -> b.md -> a.md"
+    );
+    assert_eq!(errors[1].file, None);
+    assert_eq!(errors[1].range.start.line, 2);
+}
+
+#[test]
+fn include_depth_is_bounded() {
+    let (_, diagnostics) = expand("@include(deep.md)\n", &[("deep.md", "@include(deep.md)\n")]);
+    // The file includes itself, which is caught as a cycle before the depth limit.
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].message.starts_with("include cycle"));
+
+    let chain: Vec<(String, String)> = (0..=MAX_INCLUDE_DEPTH)
+        .map(|idx| (format!("{idx}.md"), format!("@include({}.md)\n", idx + 1)))
+        .collect();
+    let chain: Vec<(&str, &str)> = chain
+        .iter()
+        .map(|(name, text)| (name.as_str(), text.as_str()))
+        .collect();
+    let (_, diagnostics) = expand("@include(0.md)\n", &chain);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        format!("include depth exceeds {MAX_INCLUDE_DEPTH}: '{MAX_INCLUDE_DEPTH}.md'")
+    );
+}
+
+#[test]
+fn unexpanded_includes_render_as_text() {
+    let source = "@include(part.md)\n";
+    let parsed = parse_with_options(source, &include_options());
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    assert!(resolved.diagnostics.is_empty());
+    assert_eq!(
+        emit_html(&resolved.document.blocks).trim_end(),
+        "<p>@include(part.md)</p>"
+    );
+
+    // Includes are opt-in, so a plain parse reads the line as a paragraph.
+    let parsed = parse(source);
+    assert!(
+        matches!(parsed.document.blocks[0].kind, BlockKind::Paragraph { .. }),
+        "{:?}",
+        parsed.document.blocks
+    );
+}
+
+#[derive(Default)]
+struct InlineSpans(Vec<(String, typmark_core::Span)>);
+
+impl VisitorMut for InlineSpans {
+    fn visit_inline_mut(&mut self, inline: &mut Inline) -> bool {
+        let name = match &inline.kind {
+            InlineKind::Text(text) => text.clone(),
+            InlineKind::LinkRef { label, .. } => format!("ref:{label}"),
+            _ => return true,
+        };
+        self.0.push((name, inline.span));
+        true
+    }
+}
+
+#[test]
+fn included_spans_and_parse_diagnostics_point_into_the_combined_source() {
+    let resolver = MemoryResolver(HashMap::from([(
+        "part.md".to_string(),
+        "{#a  #b}\nPart [one][x].\n\n[x]: /x\n".to_string(),
+    )]));
+    // A lone `\r` ending the root still starts a new line before the include.
+    let source = "Root.\r\n\r\n@include(part.md)\r";
+    let mut parsed = parse_with_options(source, &include_options());
+    let expansion = expand_includes(&mut parsed, source, None, &resolver, &include_options());
+    let combined = &expansion.source;
+    assert_eq!(
+        combined,
+        "Root.\r\n\r\n@include(part.md)\r\n{#a  #b}\nPart [one][x].\n\n[x]: /x\n"
+    );
+
+    let included = &parsed.document.blocks[1];
+    assert!(matches!(included.kind, BlockKind::Paragraph { .. }));
+    assert_eq!(
+        &combined[included.span.start..included.span.end],
+        "Part [one][x]."
+    );
+    let mut spans = InlineSpans::default();
+    walk_document_mut(&mut spans, &mut parsed.document);
+    for (name, span) in &spans.0 {
+        let text = &combined[span.start..span.end];
+        match name.as_str() {
+            "ref:x" => assert_eq!(text, "[one][x]"),
+            name => assert!(text.contains(name.trim()), "{name:?} vs {text:?}"),
+        }
+    }
+    let def = &parsed.link_defs["x"];
+    assert_eq!(&combined[def.span.start..def.span.end], "[x]: /x");
+
+    let mut syntax = parsed
+        .diagnostics
+        .into_iter()
+        .find(|diag| diag.code == E_ATTR_SYNTAX)
+        .expect("attr syntax error");
+    assert_eq!(syntax.range.start.line, 3);
+    assert_eq!(syntax.fixes[0].range.start.line, 3);
+    expansion.relocate(&mut syntax);
+    assert_eq!(syntax.file.as_deref(), Some("part.md"));
+    assert_eq!(
+        (syntax.range.start.line, syntax.fixes[0].range.start.line),
+        (0, 0)
+    );
+}
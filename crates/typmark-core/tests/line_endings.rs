@@ -0,0 +1,105 @@
+use typmark_core::{
+    HtmlEmitOptions, LineEnding, W_REF_MISSING, emit_html, emit_html_sanitized_with_options,
+    emit_html_to_writer, emit_html_with_options, parse, resolve,
+};
+
+const LF_SOURCE: &str =
+    "# Title\n\nSee @missing here.\n\n```rs\nlet x = 1;\n\nlet y = 2;\n```\n\n- one\n- two\n";
+
+fn render(source: &str) -> (String, Vec<(String, usize, usize)>) {
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let diagnostics = resolved
+        .diagnostics
+        .iter()
+        .map(|diag| {
+            (
+                diag.code.to_string(),
+                diag.range.start.line,
+                diag.range.start.character,
+            )
+        })
+        .collect();
+    (emit_html(&resolved.document.blocks), diagnostics)
+}
+
+#[test]
+fn crlf_source_matches_lf_output() {
+    let crlf = LF_SOURCE.replace('\n', "\r\n");
+    let (lf_html, lf_diags) = render(LF_SOURCE);
+    let (crlf_html, crlf_diags) = render(&crlf);
+    assert!(!crlf_html.contains('\r'));
+    assert_eq!(crlf_html, lf_html);
+    assert_eq!(crlf_diags, lf_diags);
+}
+
+#[test]
+fn lone_cr_terminates_lines() {
+    let cr = LF_SOURCE.replace('\n', "\r");
+    let (lf_html, lf_diags) = render(LF_SOURCE);
+    let (cr_html, cr_diags) = render(&cr);
+    assert_eq!(cr_html, lf_html);
+    assert_eq!(cr_diags, lf_diags);
+}
+
+#[test]
+fn leading_bom_is_ignored() {
+    let with_bom = format!("\u{feff}{}", LF_SOURCE.replace('\n', "\r\n"));
+    let (lf_html, _) = render(LF_SOURCE);
+    let (bom_html, _) = render(&with_bom);
+    assert_eq!(bom_html, lf_html);
+
+    let (_, diags) = render("\u{feff}See @missing.\r\n");
+    assert_eq!(diags, vec![(W_REF_MISSING.to_string(), 0, 4)]);
+}
+
+#[test]
+fn crlf_output_changes_only_terminators_between_tags() {
+    let source = format!("{LF_SOURCE}\nsoft\nbreak\n");
+    let parsed = parse(&source);
+    let resolved = resolve(
+        parsed.document,
+        &source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let blocks = &resolved.document.blocks;
+    let lf = HtmlEmitOptions {
+        simple_code_blocks: true,
+        ..Default::default()
+    };
+    let crlf = HtmlEmitOptions {
+        line_ending: LineEnding::Crlf,
+        ..lf.clone()
+    };
+
+    let lf_html = emit_html_with_options(blocks, &lf);
+    let crlf_html = emit_html_with_options(blocks, &crlf);
+    assert_ne!(crlf_html, lf_html);
+    assert_eq!(crlf_html.replace("\r\n", "\n"), lf_html);
+    assert!(
+        crlf_html.contains(
+            "<pre><code class=\"language-rs\">let x = 1;\n\nlet y = 2;\n</code></pre>\r\n"
+        ),
+        "{crlf_html:?}"
+    );
+    assert!(crlf_html.contains("<p>soft\r\nbreak</p>"), "{crlf_html:?}");
+
+    let mut streamed = Vec::new();
+    emit_html_to_writer(blocks, &crlf, &mut streamed).unwrap();
+    assert_eq!(String::from_utf8(streamed).unwrap(), crlf_html);
+
+    let sanitized = emit_html_sanitized_with_options(blocks, &crlf);
+    assert_eq!(
+        sanitized.replace("\r\n", "\n"),
+        emit_html_sanitized_with_options(blocks, &lf)
+    );
+    assert!(sanitized.contains("</ul>") && sanitized.contains("\r\n"));
+}
@@ -1,4 +1,4 @@
-use typmark_core::{emit_html, parse, resolve};
+use typmark_core::{BlockKind, emit_html, parse, resolve};
 
 #[test]
 fn test_list_item_with_two_tabs() {
@@ -245,3 +245,24 @@ fn test_remove_list_indent_function() {
     // The result should include an indented code block with "  foo".
     assert!(html.contains("<pre><code>  foo"));
 }
+
+fn fenced_code_text(input: &str) -> String {
+    let parsed = parse(input);
+    match &parsed.document.blocks[0].kind {
+        BlockKind::CodeBlock(code) => code.text.clone(),
+        other => panic!("expected a code block, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_indented_fence_strips_tab_indent_by_columns() {
+    // The fence is indented two columns, so a leading tab loses two of its four.
+    let input = "  ```\n\tfoo\tbar\n  \tbaz\n ```\n";
+    assert_eq!(fenced_code_text(input), "  foo\tbar\n\tbaz\n");
+}
+
+#[test]
+fn test_fence_keeps_tabs_past_the_indent() {
+    let input = "   ```\n   \tfoo\n\t\tbar\n```\n";
+    assert_eq!(fenced_code_text(input), "\tfoo\n \tbar\n");
+}
@@ -0,0 +1,289 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use typmark_core::{
+    DiagnosticSeverity, E_MATH_COMPILE, E_MATH_PREAMBLE, HtmlEmitOptions, MathOutput, MathRenderer,
+    ResolveOptions, emit_html_document_sanitized_with_options, emit_html_document_with_options,
+    parse, resolve, resolve_with_options,
+};
+
+#[derive(Debug)]
+struct IdentifierMathML;
+
+impl MathRenderer for IdentifierMathML {
+    fn render_mathml(&self, source: &str, display: bool) -> Option<String> {
+        let display = if display { "block" } else { "inline" };
+        Some(format!(
+            "<math display=\"{}\"><mi>{}</mi></math>",
+            display,
+            source.trim()
+        ))
+    }
+}
+
+fn render(source: &str, options: &HtmlEmitOptions) -> String {
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    emit_html_document_with_options(&resolved.document, options)
+}
+
+#[test]
+fn mathml_output_uses_custom_renderer() {
+    let options = HtmlEmitOptions {
+        math_output: MathOutput::MathML,
+        math_renderer: Some(Arc::new(IdentifierMathML)),
+        ..Default::default()
+    };
+    let html = render("Inline $x$ math.\n\n$$\ny\n$$\n", &options);
+    assert!(html.contains("<math display=\"inline\"><mi>x</mi></math>"));
+    assert!(html.contains(
+        "<div class=\"TypMark-math-block\"><math display=\"block\"><mi>y</mi></math></div>"
+    ));
+    assert!(!html.contains("<svg"));
+}
+
+#[test]
+fn mathml_survives_sanitizer() {
+    let source = "$x$\n";
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let options = HtmlEmitOptions {
+        math_output: MathOutput::MathML,
+        math_renderer: Some(Arc::new(IdentifierMathML)),
+        ..Default::default()
+    };
+    let html = emit_html_document_sanitized_with_options(&resolved.document, &options);
+    assert!(html.contains("<math display=\"inline\"><mi>x</mi></math>"));
+}
+
+#[test]
+fn annotation_output_labels_svg_with_source() {
+    let options = HtmlEmitOptions {
+        math_output: MathOutput::SvgWithMathMLAnnotation,
+        ..Default::default()
+    };
+    let html = render("$x + y$\n", &options);
+    assert!(html.contains("<span role=\"math\" aria-label=\"x + y\"><svg"));
+}
+
+#[test]
+fn default_output_is_plain_svg() {
+    let html = render("$x$\n", &HtmlEmitOptions::default());
+    assert!(html.contains("<svg"));
+    assert!(!html.contains("role=\"math\""));
+}
+
+#[test]
+fn default_svg_is_labelled_image_after_sanitizing() {
+    let source = "$x + y$\n";
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let html =
+        emit_html_document_sanitized_with_options(&resolved.document, &HtmlEmitOptions::default());
+    assert!(html.contains("<svg role=\"img\" aria-label=\"x + y\""));
+    assert!(html.contains("math-inline-strut\" aria-hidden=\"true\""));
+}
+
+#[test]
+fn math_preamble_applies_to_inline_and_block_math() {
+    let html = render(
+        "{ math-preamble=\"#let RR = math.bb[R]\" }\n\nReals $RR$.\n\n$$\nx in RR\n$$\n",
+        &HtmlEmitOptions::default(),
+    );
+    assert_eq!(html.matches("<svg").count(), 2);
+    assert!(!html.contains("--error"));
+}
+
+#[test]
+fn invalid_math_preamble_reports_once_and_is_skipped() {
+    let source = "{ math-preamble=\"#error\" }\n\nOne $x$, two $y$.\n\n$$\nz\n$$\n";
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let preamble_diags: Vec<_> = resolved
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.code == E_MATH_PREAMBLE)
+        .collect();
+    assert_eq!(preamble_diags.len(), 1);
+    assert_eq!(preamble_diags[0].range.start.line, 0);
+    assert_eq!(preamble_diags[0].range.start.character, 17);
+
+    let html = emit_html_document_with_options(&resolved.document, &HtmlEmitOptions::default());
+    assert_eq!(html.matches("<svg").count(), 3);
+}
+
+#[test]
+fn broken_math_reports_compile_errors_at_its_span() {
+    let source = "Fine $x$, broken $a^$.\n\n$$\na^\n$$\n";
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let compile_diags: Vec<_> = resolved
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.code == E_MATH_COMPILE)
+        .collect();
+    assert_eq!(compile_diags.len(), 2, "{:?}", resolved.diagnostics);
+    assert_eq!(compile_diags[0].severity, DiagnosticSeverity::Error);
+    assert_eq!(compile_diags[0].range.start.character, 17);
+    assert_eq!(compile_diags[0].range.end.character, 21);
+    assert_eq!(compile_diags[1].range.start.line, 2);
+    for diag in compile_diags {
+        let message = diag
+            .message
+            .strip_prefix("math failed to compile: ")
+            .expect("prefix");
+        assert!(!message.is_empty());
+    }
+
+    let html = emit_html_document_with_options(&resolved.document, &HtmlEmitOptions::default());
+    assert!(html.contains("math-inline--error"));
+    assert!(html.contains("math-block--error"));
+}
+
+#[test]
+fn math_checks_only_run_for_svg_output() {
+    let source = "{ math-preamble=\"#error\" }\n\nText $x$.\n";
+    let preamble_errors = |math_output| {
+        let parsed = parse(source);
+        let resolved = resolve_with_options(
+            parsed.document,
+            source,
+            &parsed.source_map,
+            parsed.diagnostics,
+            &parsed.link_defs,
+            &ResolveOptions {
+                math_output,
+                ..Default::default()
+            },
+        );
+        resolved
+            .diagnostics
+            .iter()
+            .filter(|diag| diag.code == E_MATH_PREAMBLE)
+            .count()
+    };
+    assert_eq!(preamble_errors(MathOutput::Svg), 1);
+    assert_eq!(preamble_errors(MathOutput::SvgWithMathMLAnnotation), 1);
+    assert_eq!(preamble_errors(MathOutput::MathML), 0);
+    assert_eq!(preamble_errors(MathOutput::Raw), 0);
+}
+
+#[test]
+fn raw_output_keeps_exact_source_without_compiling() {
+    let source = "{ math-preamble=\"#error\" }\n\nBroken $a^ & \"b\" < c$.\n\n$$\nx &= y\n$$\n";
+    let parsed = parse(source);
+    let resolved = resolve_with_options(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+        &ResolveOptions {
+            math_output: MathOutput::Raw,
+            ..Default::default()
+        },
+    );
+    assert!(
+        resolved.diagnostics.is_empty(),
+        "{:?}",
+        resolved.diagnostics
+    );
+
+    let options = HtmlEmitOptions {
+        math_output: MathOutput::Raw,
+        ..Default::default()
+    };
+    let html = emit_html_document_with_options(&resolved.document, &options);
+    assert!(html.contains(
+        "<span class=\"TypMark-math\" data-typst=\"a^ &amp; &quot;b&quot; &lt; c\" data-math-mode=\"inline\"></span>"
+    ));
+    assert!(html.contains(
+        "<div class=\"TypMark-math-block\"><span class=\"TypMark-math\" data-typst=\"x &amp;= y\" data-math-mode=\"display\"></span></div>"
+    ));
+    assert!(!html.contains("<svg"));
+    assert!(!html.contains("--error"));
+}
+
+#[test]
+fn expensive_math_times_out_instead_of_blocking() {
+    let source = "{ math-timeout-ms=200 }\n\n$$\n#{ let n = 0; for i in range(20000) { for j in range(20000) { n += 1 } }; n }\n$$\n";
+    let started = Instant::now();
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let html = emit_html_document_with_options(&resolved.document, &HtmlEmitOptions::default());
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let compile_diags: Vec<_> = resolved
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.code == E_MATH_COMPILE)
+        .collect();
+    assert_eq!(compile_diags.len(), 1, "{:?}", resolved.diagnostics);
+    assert_eq!(
+        compile_diags[0].message,
+        "math failed to compile: math compilation timed out after 200 ms"
+    );
+    assert!(html.contains("math-block--error"));
+}
+
+#[test]
+fn max_bytes_applies_to_already_compiled_math() {
+    let compile_errors = |source: &str| {
+        let parsed = parse(source);
+        resolve(
+            parsed.document,
+            source,
+            &parsed.source_map,
+            parsed.diagnostics,
+            &parsed.link_defs,
+        )
+        .diagnostics
+        .into_iter()
+        .filter(|diag| diag.code == E_MATH_COMPILE)
+        .map(|diag| diag.message)
+        .collect::<Vec<_>>()
+    };
+    assert!(compile_errors("Area $a^2 + b$.\n").is_empty());
+    let errors = compile_errors("{ math-max-bytes=10 }\n\nArea $a^2 + b$.\n");
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(
+        errors[0].ends_with("over the limit of 10 bytes"),
+        "{errors:?}"
+    );
+}
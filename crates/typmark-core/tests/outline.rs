@@ -0,0 +1,39 @@
+use typmark_core::{HtmlEmitOptions, OutlineEntry, parse};
+
+#[test]
+fn outline_lists_atx_and_setext_headings_in_order() {
+    let source = "{#guide}\nUser *guide*\n============\n\nIntro.\n\n## Install `cli`\n\n{#usage}\nUsage\n-----\n\n> # Quoted\n\n### Flags ###\n";
+    let parsed = parse(source);
+    let outline = parsed.outline(&HtmlEmitOptions::default());
+    let entries: Vec<(u8, &str, Option<&str>)> = outline
+        .iter()
+        .map(|entry| (entry.level, entry.text.as_str(), entry.id.as_deref()))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            (1, "User guide", Some("guide")),
+            (2, "Install cli", None),
+            (2, "Usage", Some("usage")),
+            (3, "Flags", None),
+        ]
+    );
+    let spans: Vec<&str> = outline
+        .iter()
+        .map(|OutlineEntry { span, .. }| &source[span.start..span.end])
+        .collect();
+    assert_eq!(spans[0].trim_end(), "User *guide*\n============");
+    assert_eq!(spans[1].trim_end(), "## Install `cli`");
+    assert_eq!(spans[3].trim_end(), "### Flags ###");
+
+    let options = HtmlEmitOptions {
+        heading_offset: 1,
+        id_namespace: Some("doc".to_string()),
+        ..Default::default()
+    };
+    let shifted = parsed.outline(&options);
+    assert_eq!(shifted[0].level, 2);
+    assert_eq!(shifted[0].id.as_deref(), Some("doc-guide"));
+    assert_eq!(shifted[3].level, 4);
+    assert_eq!(shifted[3].span, outline[3].span);
+}
@@ -0,0 +1,354 @@
+use std::sync::Arc;
+use typmark_core::{
+    DiagnosticSeverity, FeatureSet, ParseOptions, PositionEncoding, ResolveOptions,
+    SlugWikilinkResolver, W_HEADING_SKIP, W_LINK_DEF_DUP, W_LINK_DEF_UNUSED, W_TABLE_RAGGED,
+    WikilinkResolver, emit_html, parse, parse_with_observer, parse_with_options,
+    parse_with_options_and_observer, resolve, resolve_with_options,
+};
+
+fn render_without(source: &str, feature: FeatureSet) -> String {
+    let options = ParseOptions {
+        features: FeatureSet::all().without(feature),
+        ..Default::default()
+    };
+    let parsed = parse_with_options(source, &options);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    emit_html(&resolved.document.blocks).trim_end().to_string()
+}
+
+#[test]
+fn default_features_enable_everything() {
+    assert_eq!(ParseOptions::default().features, FeatureSet::all());
+    assert!(FeatureSet::all().contains(FeatureSet::RAW_HTML | FeatureSet::BOXES));
+    assert!(!FeatureSet::empty().contains(FeatureSet::MATH));
+}
+
+#[test]
+fn raw_html_off_escapes_blocks_and_spans() {
+    let html = render_without(
+        "<div>\nhi\n</div>\n\nText <b>bold</b>.\n",
+        FeatureSet::RAW_HTML,
+    );
+    assert_eq!(
+        html,
+        "<p>&lt;div&gt;\nhi\n&lt;/div&gt;</p>\n<p>Text &lt;b&gt;bold&lt;/b&gt;.</p>"
+    );
+}
+
+#[test]
+fn autolinks_off_keeps_bare_urls_as_text() {
+    let html = render_without(
+        "Visit https://example.com or <https://example.org>.\n",
+        FeatureSet::AUTOLINKS,
+    );
+    assert_eq!(
+        html,
+        "<p>Visit https://example.com or <a href=\"https://example.org\">https://example.org</a>.</p>"
+    );
+}
+
+#[test]
+fn images_off_keeps_syntax_literal() {
+    let html = render_without("![alt](img.png)\n", FeatureSet::IMAGES);
+    assert_eq!(html, "<p>![alt](img.png)</p>");
+}
+
+#[test]
+fn tables_off_keeps_pipes_as_text() {
+    let html = render_without("| a | b |\n| --- | --- |\n| 1 | 2 |\n", FeatureSet::TABLES);
+    assert_eq!(html, "<p>| a | b |\n| --- | --- |\n| 1 | 2 |</p>");
+}
+
+#[test]
+fn strikethrough_off_keeps_tildes() {
+    let html = render_without("~~gone~~\n", FeatureSet::STRIKETHROUGH);
+    assert_eq!(html, "<p>~~gone~~</p>");
+}
+
+#[test]
+fn math_off_keeps_dollars() {
+    let html = render_without("Cost $x$.\n\n$$\ny\n$$\n", FeatureSet::MATH);
+    assert_eq!(html, "<p>Cost $x$.</p>\n<p>$$\ny\n$$</p>");
+}
+
+#[test]
+fn boxes_off_keeps_fences_as_text() {
+    let html = render_without("::: box Note\nBody.\n:::\n", FeatureSet::BOXES);
+    assert_eq!(html, "<p>::: box Note\nBody.\n:::</p>");
+}
+
+#[test]
+fn directives_off_keep_syntax_literal() {
+    let html = render_without("Press ::kbd[Ctrl].\n", FeatureSet::DIRECTIVES);
+    assert_eq!(html, "<p>Press ::kbd[Ctrl].</p>");
+}
+
+#[test]
+fn ragged_table_rows_warn_and_keep_alignment() {
+    let source =
+        "| a | b | c |\n| :--- | :---: | ---: |\n| 1 | 2 |\n| 1 | 2 | 3 | 4 |\n| 1 | 2 | 3 |\n";
+    let parsed = parse(source);
+    let ragged: Vec<_> = parsed
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.code == W_TABLE_RAGGED)
+        .collect();
+    assert_eq!(ragged.len(), 2, "{:?}", parsed.diagnostics);
+    assert_eq!(ragged[0].severity, DiagnosticSeverity::Warning);
+    assert_eq!(ragged[0].message, "table row has 2 cells, expected 3");
+    assert_eq!(ragged[0].range.start.line, 2);
+    assert_eq!(ragged[1].message, "table row has 4 cells, expected 3");
+    assert_eq!(ragged[1].range.start.line, 3);
+
+    let html = emit_html(&parsed.document.blocks);
+    assert!(html.contains("<td align=\"right\"></td>"), "{html}");
+    assert!(!html.contains(">4<"), "{html}");
+}
+
+#[test]
+fn strict_tables_promote_ragged_rows_to_errors() {
+    let options = ParseOptions {
+        strict_tables: true,
+        ..Default::default()
+    };
+    let parsed = parse_with_options("| a | b |\n| --- | --- |\n| 1 |\n", &options);
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.diagnostics[0].code, W_TABLE_RAGGED);
+    assert_eq!(parsed.diagnostics[0].severity, DiagnosticSeverity::Error);
+
+    let valid = parse_with_options("| a | b |\n| --- | --- |\n| 1 | 2 |\n", &options);
+    assert!(valid.diagnostics.is_empty());
+}
+
+#[test]
+fn duplicate_link_defs_warn_when_enabled() {
+    let source = "[Docs]: /first\n\n> [  docs\n> ]: /second\n\nSee [DOCS].\n";
+    assert!(parse(source).diagnostics.is_empty());
+
+    let options = ParseOptions {
+        warn_duplicate_link_defs: true,
+        ..Default::default()
+    };
+    let parsed = parse_with_options(source, &options);
+    assert_eq!(parsed.diagnostics.len(), 1, "{:?}", parsed.diagnostics);
+    let dup = &parsed.diagnostics[0];
+    assert_eq!(dup.code, W_LINK_DEF_DUP);
+    assert_eq!(dup.severity, DiagnosticSeverity::Warning);
+    assert_eq!(dup.range.start.line, 2);
+    assert_eq!(dup.range.end.line, 3);
+    assert_eq!(dup.related.len(), 1);
+    assert_eq!(dup.related[0].range.start.line, 0);
+
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let html = emit_html(&resolved.document.blocks);
+    assert!(html.contains("<a href=\"/first\">DOCS</a>"), "{html}");
+}
+
+#[test]
+fn observer_sees_every_diagnostic_in_order() {
+    let source =
+        "[a]: /one\n[a]: /two\n\n{#x y:z}\nText.\n\n| a | b |\n|---|---|\n| 1 |\n\n```\nopen\n";
+    let options = ParseOptions {
+        warn_duplicate_link_defs: true,
+        ..Default::default()
+    };
+    let mut seen = Vec::new();
+    let parsed =
+        parse_with_options_and_observer(source, &options, &mut |diag| seen.push(diag.clone()));
+    assert!(parsed.diagnostics.len() >= 4, "{:?}", parsed.diagnostics);
+    assert_eq!(parsed.diagnostics[0].code, W_LINK_DEF_DUP);
+    assert_eq!(seen, parsed.diagnostics);
+
+    let mut calls = 0;
+    let parsed = parse_with_observer(source, &mut |_| calls += 1);
+    assert_eq!(calls, parsed.diagnostics.len());
+    assert_eq!(parsed.diagnostics, parse(source).diagnostics);
+}
+
+#[test]
+fn unused_link_defs_warn_only_when_enabled() {
+    let source = "See [used] and ![Used][].\n\n[used]: /used\n[foo]: /bar\n";
+    let resolve_source = |options: &ResolveOptions| {
+        let parsed = parse(source);
+        assert_eq!(parsed.link_defs["foo"].url, "/bar");
+        resolve_with_options(
+            parsed.document,
+            source,
+            &parsed.source_map,
+            parsed.diagnostics,
+            &parsed.link_defs,
+            options,
+        )
+    };
+
+    let resolved = resolve_source(&ResolveOptions::default());
+    assert!(
+        resolved.diagnostics.is_empty(),
+        "{:?}",
+        resolved.diagnostics
+    );
+
+    let resolved = resolve_source(&ResolveOptions {
+        warn_unused_link_defs: true,
+        ..Default::default()
+    });
+    assert_eq!(resolved.diagnostics.len(), 1, "{:?}", resolved.diagnostics);
+    let unused = &resolved.diagnostics[0];
+    assert_eq!(unused.code, W_LINK_DEF_UNUSED);
+    assert_eq!(unused.severity, DiagnosticSeverity::Warning);
+    assert_eq!(unused.range.start.line, 3);
+    assert_eq!(unused.range.start.character, 0);
+    assert_eq!(unused.range.end.line, 3);
+    assert_eq!(unused.range.end.character, 11);
+}
+
+#[test]
+fn heading_skips_warn_only_when_enabled() {
+    let skips = |source: &str, warn_heading_skips: bool| {
+        let parsed = parse(source);
+        resolve_with_options(
+            parsed.document,
+            source,
+            &parsed.source_map,
+            parsed.diagnostics,
+            &parsed.link_defs,
+            &ResolveOptions {
+                warn_heading_skips,
+                ..Default::default()
+            },
+        )
+        .diagnostics
+    };
+
+    let found = skips("# A\n\nText.\n\n### C\n", true);
+    assert_eq!(found.len(), 1, "{found:?}");
+    assert_eq!(found[0].code, W_HEADING_SKIP);
+    assert_eq!(found[0].severity, DiagnosticSeverity::Warning);
+    assert_eq!(found[0].range.start.line, 4);
+    assert_eq!(found[0].range.start.character, 0);
+    assert_eq!(found[0].related[0].range.start.line, 0);
+
+    assert!(skips("# A\n\nText.\n\n### C\n", false).is_empty());
+    assert!(skips("# A\n\n## B\n", true).is_empty());
+    assert!(skips("### C\n\n# A\n\n## B\n", true).is_empty());
+}
+
+fn render_with(source: &str, options: &ParseOptions) -> String {
+    let parsed = parse_with_options(source, options);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    emit_html(&resolved.document.blocks).trim_end().to_string()
+}
+
+#[test]
+fn wikilinks_render_plain_and_piped_forms() {
+    let source = "See [[Page Name]], [[Other Page | the other one]] and [link](/x).\n";
+    let options = ParseOptions {
+        wikilink_resolver: Some(Arc::new(SlugWikilinkResolver)),
+        ..Default::default()
+    };
+    assert_eq!(
+        render_with(source, &options),
+        "<p>See <a href=\"page-name\">Page Name</a>, <a href=\"other-page\">the other one</a> and <a href=\"/x\">link</a>.</p>"
+    );
+
+    // Without a resolver, `[[...]]` stays ordinary brackets.
+    assert_eq!(
+        render_with(source, &ParseOptions::default()),
+        "<p>See [[Page Name]], [[Other Page | the other one]] and <a href=\"/x\">link</a>.</p>"
+    );
+}
+
+#[test]
+fn wikilinks_use_the_configured_resolver() {
+    #[derive(Debug)]
+    struct WikiPrefix;
+
+    impl WikilinkResolver for WikiPrefix {
+        fn resolve_wikilink(&self, target: &str) -> String {
+            format!(
+                "/wiki/{}.html",
+                SlugWikilinkResolver.resolve_wikilink(target)
+            )
+        }
+    }
+
+    let options = ParseOptions {
+        wikilink_resolver: Some(Arc::new(WikiPrefix)),
+        ..Default::default()
+    };
+    assert_eq!(
+        render_with("[[Home]] [[]] [[a [b] c]] [x] [[y]]\n\n[x]: /x\n", &options),
+        "<p><a href=\"/wiki/home.html\">Home</a> [[]] [[a [b] c]] <a href=\"/x\">x</a> <a href=\"/wiki/y.html\">y</a></p>"
+    );
+}
+
+#[test]
+fn position_encoding_sets_diagnostic_columns() {
+    let source = "Hi 😀 see @missing.\n";
+    let columns = |position_encoding| {
+        let options = ParseOptions {
+            position_encoding,
+            ..Default::default()
+        };
+        let parsed = parse_with_options(source, &options);
+        let resolved = resolve(
+            parsed.document,
+            source,
+            &parsed.source_map,
+            parsed.diagnostics,
+            &parsed.link_defs,
+        );
+        assert_eq!(resolved.diagnostics.len(), 1, "{:?}", resolved.diagnostics);
+        let range = resolved.diagnostics[0].range;
+        (range.start.character, range.end.character)
+    };
+    assert_eq!(columns(PositionEncoding::default()), (12, 20));
+    assert_eq!(columns(PositionEncoding::Utf16), (10, 18));
+    assert_eq!(columns(PositionEncoding::Utf32), (9, 17));
+}
+
+#[test]
+fn min_fence_len_keeps_shorter_fences_as_text() {
+    let source = "Intro.\n\n~~~\nstill text\n~~~\n\n````md\n```rust\nfn main() {}\n```\n````\n";
+    let options = ParseOptions {
+        min_fence_len: 4,
+        ..Default::default()
+    };
+    let html = render_with(source, &options);
+    assert!(html.contains("<p>~~~\nstill text\n~~~</p>"), "{html}");
+    assert_eq!(html.matches("<figure").count(), 1, "{html}");
+    assert!(
+        html.contains("<span class=\"line\" data-line=\"1\">```rust</span><span class=\"line\" data-line=\"2\">fn main() {}</span><span class=\"line\" data-line=\"3\">```</span>"),
+        "{html}"
+    );
+    assert!(!html.contains("language-rust"), "{html}");
+
+    let html = render_with(source, &ParseOptions::default());
+    assert!(!html.contains("still text</p>"), "{html}");
+
+    // Strict CommonMark ignores the option.
+    let strict = ParseOptions {
+        min_fence_len: 4,
+        strict_commonmark: true,
+        ..Default::default()
+    };
+    assert_eq!(render_with(source, &strict).matches("<figure").count(), 2);
+}
@@ -1,14 +1,108 @@
 use std::panic;
 
 use typmark_core::{
-    AttrList, Block, BlockKind, CodeBlock, Document, Inline, InlineKind, Label, List, Span, parse,
-    resolve,
+    AttrList, Block, BlockKind, CodeBlock, Document, FeatureSet, HtmlEmitOptions, Inline,
+    InlineKind, Label, LineEnding, List, ParseOptions, Span,
+    emit_html_document_with_options_and_source_map, emit_html_sanitized, emit_html_to_writer,
+    parse, parse_with_options, resolve,
 };
 
 const CASES: usize = 200;
 const MAX_LEN: usize = 512;
 const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 \
 \n\t#@*`$[](){}!<>:+-_=./\\\\\"";
+/// Multi-byte and otherwise awkward characters, so byte offsets land inside
+/// code points if any slicing is off by one.
+const WIDE_CHARS: &[&str] = &[
+    "é",
+    "日本",
+    "🎉",
+    "👩‍💻",
+    "\u{301}",
+    "\u{200b}",
+    "\u{feff}",
+    "\u{fffd}",
+    "\r\n",
+    "\r",
+    "\0",
+    "\u{a0}",
+    "ß",
+    "İ",
+    "\u{2028}",
+];
+/// Syntax pieces that open, close or nest constructs, for structured inputs.
+const FRAGMENTS: &[&str] = &[
+    "# ",
+    "## ",
+    "###### ",
+    "====\n",
+    "----\n",
+    "> ",
+    ">> ",
+    "- ",
+    "* ",
+    "1. ",
+    "10) ",
+    "- [ ] ",
+    "    ",
+    "\t",
+    "```",
+    "```rs {hl=\"1-\"}\n",
+    "~~~\n",
+    "$$\n",
+    "$",
+    "`",
+    "``",
+    "::: box\n",
+    "::: details Title\n",
+    ":::\n",
+    ":::: container\n",
+    "{#a}\n",
+    "{#a .c k=v}\n",
+    "{",
+    "}",
+    "{.unnumbered}\n",
+    "@a",
+    "@fig:a",
+    "[@a]",
+    "[x](y)",
+    "[x][y]",
+    "[y]: /url \"t\"\n",
+    "![a](b){align=center caption=\"c\"}",
+    "![",
+    "](",
+    "[[wiki]]",
+    "<div>\n",
+    "</div>\n",
+    "<b>",
+    "\\!{raw}!",
+    "::video[v](v.mp4)",
+    "::x[y]{z=1}",
+    ":smile:",
+    "| a | b |\n",
+    "| --- | :-: |\n",
+    "***",
+    "___",
+    "~~",
+    "\\",
+    "&amp;",
+    "&#x1F600;",
+    "<https://a.b>",
+    "www.example.com",
+    "2024-01-02",
+    "--",
+    "...",
+    "\"",
+    "'",
+    "\n",
+    "\n\n",
+    "  \n",
+    "@include(x.tmd)\n",
+    "> [!NOTE]\n",
+    "[^1]",
+    "[^1]: note\n",
+    "{bg=#fff border-style=dashd}\n",
+];
 
 #[test]
 fn parser_never_panics_on_random_input() -> Result<(), Box<dyn std::error::Error>> {
@@ -49,6 +143,117 @@ fn spans_are_in_bounds_on_random_input() -> Result<(), Box<dyn std::error::Error
     Ok(())
 }
 
+#[test]
+fn pipeline_never_panics_on_structured_input() -> Result<(), Box<dyn std::error::Error>> {
+    let mut rng = Lcg::new(0x2c5e_7a10_94db_f36b);
+    for case in 0..CASES {
+        let source = match case % 3 {
+            0 => structured_string(&mut rng, 64),
+            1 => wide_string(&mut rng, MAX_LEN / 4),
+            _ => lossy_string(&mut rng, MAX_LEN),
+        };
+        let result = panic::catch_unwind(|| render_everything(&source));
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(message)) => {
+                return Err(format!("case {}: {}: {:?}", case, message, source).into());
+            }
+            Err(_) => {
+                return Err(format!("pipeline panicked for case {}: {:?}", case, source).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn pipeline_never_panics_on_deep_nesting() -> Result<(), Box<dyn std::error::Error>> {
+    let depth = 200;
+    let sources = [
+        "> ".repeat(depth) + "text",
+        "- ".repeat(depth) + "item",
+        "::: box\n".repeat(depth),
+        "[".repeat(depth) + "x" + &"](u)".repeat(depth),
+        "*".repeat(depth) + "x" + &"_".repeat(depth),
+        "{".repeat(depth) + "\ntext",
+        "1. ".repeat(depth) + "\n",
+        (1..=depth)
+            .map(|i| format!("{} h\n", "#".repeat(i % 7 + 1)))
+            .collect(),
+        "\\!{".repeat(depth),
+        "`".repeat(depth) + "x",
+    ];
+    for source in &sources {
+        let result = panic::catch_unwind(|| render_everything(source));
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(message)) => return Err(format!("{}: {:?}", message, source).into()),
+            Err(_) => return Err(format!("pipeline panicked for {:?}", source).into()),
+        }
+    }
+    Ok(())
+}
+
+/// Runs `parse`, `resolve` and emitting with most options on, and checks the
+/// streamed output is UTF-8. Then does the same for strict CommonMark.
+fn render_everything(source: &str) -> Result<(), String> {
+    let parsed = parse_with_options(
+        source,
+        &ParseOptions {
+            features: FeatureSet::all() | FeatureSet::INCLUDES,
+            ..Default::default()
+        },
+    );
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    let options = HtmlEmitOptions {
+        emoji: true,
+        smart_punctuation: true,
+        wrap_dates: true,
+        heading_anchors: true,
+        code_copy_button: true,
+        number_sections: true,
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    emit_html_to_writer(&resolved.document.blocks, &options, &mut out)
+        .map_err(|err| format!("write failed: {}", err))?;
+    String::from_utf8(out).map_err(|err| format!("output is not UTF-8: {}", err))?;
+    emit_html_sanitized(&resolved.document.blocks);
+    emit_html_document_with_options_and_source_map(
+        &resolved.document,
+        &HtmlEmitOptions {
+            line_ending: LineEnding::Crlf,
+            soft_breaks_as_br: true,
+            ..Default::default()
+        },
+        &parsed.source_map,
+    );
+
+    let parsed = parse_with_options(
+        source,
+        &ParseOptions {
+            strict_commonmark: true,
+            ..Default::default()
+        },
+    );
+    let options = HtmlEmitOptions {
+        simple_code_blocks: true,
+        wrap_sections: false,
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    emit_html_to_writer(&parsed.document.blocks, &options, &mut out)
+        .map_err(|err| format!("write failed: {}", err))?;
+    String::from_utf8(out).map_err(|err| format!("strict output is not UTF-8: {}", err))?;
+    Ok(())
+}
+
 fn check_document_spans(document: &Document, source_len: usize) -> Result<(), String> {
     check_span(document.span, source_len, "document")?;
     check_block_seq(
@@ -137,7 +342,7 @@ fn check_block(block: &Block, source_len: usize, context: &str) -> Result<(), St
                 )?;
             }
         }
-        BlockKind::BlockQuote { blocks } => {
+        BlockKind::BlockQuote { blocks, .. } => {
             check_block_seq(
                 blocks,
                 block.span,
@@ -161,6 +366,22 @@ fn check_block(block: &Block, source_len: usize, context: &str) -> Result<(), St
                 &format!("{}.box.blocks", context),
             )?;
         }
+        BlockKind::Container { title, blocks, .. } => {
+            if let Some(title) = title {
+                check_inline_seq(
+                    title,
+                    block.span,
+                    source_len,
+                    &format!("{}.container.title", context),
+                )?;
+            }
+            check_block_seq(
+                blocks,
+                block.span,
+                source_len,
+                &format!("{}.container.blocks", context),
+            )?;
+        }
         BlockKind::CodeBlock(CodeBlock {
             info_attrs, meta, ..
         }) => {
@@ -197,7 +418,10 @@ fn check_block(block: &Block, source_len: usize, context: &str) -> Result<(), St
                 }
             }
         }
-        BlockKind::MathBlock { .. } | BlockKind::ThematicBreak | BlockKind::HtmlBlock { .. } => {}
+        BlockKind::MathBlock { .. }
+        | BlockKind::ThematicBreak
+        | BlockKind::HtmlBlock { .. }
+        | BlockKind::Include { .. } => {}
     }
     Ok(())
 }
@@ -234,7 +458,8 @@ fn check_inline(inline: &Inline, source_len: usize, context: &str) -> Result<(),
     match &inline.kind {
         InlineKind::Emph(children)
         | InlineKind::Strong(children)
-        | InlineKind::Strikethrough(children) => check_inline_seq(
+        | InlineKind::Strikethrough(children)
+        | InlineKind::Span { children, .. } => check_inline_seq(
             children,
             inline.span,
             source_len,
@@ -265,12 +490,33 @@ fn check_inline(inline: &Inline, source_len: usize, context: &str) -> Result<(),
                 )?;
             }
         }
+        InlineKind::Citation { keys, .. } => {
+            for key in keys {
+                check_label(key, source_len, &format!("{}.citation.key", context))?;
+            }
+        }
+        InlineKind::Directive(directive) => {
+            check_inline_seq(
+                &directive.label,
+                inline.span,
+                source_len,
+                &format!("{}.directive.label", context),
+            )?;
+            check_attr_list(
+                &directive.attrs,
+                source_len,
+                &format!("{}.directive.attrs", context),
+            )?;
+        }
         InlineKind::Text(_)
         | InlineKind::CodeSpan(_)
+        | InlineKind::Raw(_)
         | InlineKind::SoftBreak
         | InlineKind::HardBreak
         | InlineKind::MathInline { .. }
-        | InlineKind::HtmlSpan { .. } => {}
+        | InlineKind::HtmlSpan { .. }
+        | InlineKind::Emoji { .. }
+        | InlineKind::WikiLink { .. } => {}
     }
     Ok(())
 }
@@ -316,6 +562,45 @@ fn random_string(rng: &mut Lcg, len: usize) -> String {
     out
 }
 
+fn structured_string(rng: &mut Lcg, pieces: usize) -> String {
+    let mut out = String::new();
+    for _ in 0..rng.gen_range(0, pieces + 1) {
+        match rng.gen_range(0, 4) {
+            0 => {
+                let len = rng.gen_range(1, 8);
+                out.push_str(&random_string(rng, len));
+            }
+            1 => out.push_str(WIDE_CHARS[rng.gen_range(0, WIDE_CHARS.len())]),
+            _ => out.push_str(FRAGMENTS[rng.gen_range(0, FRAGMENTS.len())]),
+        }
+    }
+    out
+}
+
+fn wide_string(rng: &mut Lcg, len: usize) -> String {
+    let mut out = String::new();
+    for _ in 0..rng.gen_range(0, len + 1) {
+        if rng.gen_range(0, 3) == 0 {
+            out.push_str(WIDE_CHARS[rng.gen_range(0, WIDE_CHARS.len())]);
+        } else {
+            out.push(CHARSET[rng.gen_range(0, CHARSET.len())] as char);
+        }
+    }
+    out
+}
+
+/// Arbitrary bytes read the way a caller with untrusted input would, with
+/// invalid sequences replaced.
+fn lossy_string(rng: &mut Lcg, len: usize) -> String {
+    let bytes: Vec<u8> = (0..rng.gen_range(0, len + 1))
+        .map(|_| match rng.gen_range(0, 4) {
+            0 => rng.next() as u8,
+            _ => CHARSET[rng.gen_range(0, CHARSET.len())],
+        })
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
 struct Lcg {
     state: u64,
 }
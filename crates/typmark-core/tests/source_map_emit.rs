@@ -73,3 +73,36 @@ fn emit_source_map_attributes_in_sanitized_html() {
         "expected data-tm-range in sanitized HTML"
     );
 }
+
+#[test]
+fn emit_source_map_attributes_on_inline_elements() {
+    let source = "*em* **strong** ~~del~~ [link](u) ![img](i.png)\n";
+    let ParseResult {
+        document,
+        diagnostics,
+        source_map,
+        link_defs,
+    } = parse(source);
+    let resolved = resolve(document, source, &source_map, diagnostics, &link_defs);
+
+    let html = emit_html_document_with_options_and_source_map(
+        &resolved.document,
+        &HtmlEmitOptions::default(),
+        &source_map,
+    );
+
+    for expected in [
+        "<em data-tm-range=\"0:0-0:4\">em</em>",
+        "<strong data-tm-range=\"0:5-0:15\">strong</strong>",
+        "<del data-tm-range=\"0:16-0:23\">del</del>",
+        "<a href=\"u\" data-tm-range=\"0:24-0:33\">link</a>",
+        "<img src=\"i.png\" alt=\"img\" data-tm-range=\"0:34-0:47\" />",
+    ] {
+        assert!(
+            html.contains(expected),
+            "expected {}, got: {}",
+            expected,
+            html
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use typmark_core::{
+    Diagnostic, W_UNCLOSED_BOX, W_UNCLOSED_FENCE, W_UNCLOSED_MATH, emit_html, parse, resolve,
+};
+
+fn render(source: &str) -> (String, Vec<Diagnostic>) {
+    let parsed = parse(source);
+    let resolved = resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+    (emit_html(&resolved.document.blocks), resolved.diagnostics)
+}
+
+/// `(code, line, start column, end column)` of each unclosed-block warning.
+fn unclosed(diagnostics: &[Diagnostic]) -> Vec<(&'static str, usize, usize, usize)> {
+    diagnostics
+        .iter()
+        .filter(|diag| diag.code.starts_with("W_UNCLOSED_"))
+        .map(|diag| {
+            (
+                diag.code,
+                diag.range.start.line,
+                diag.range.start.character,
+                diag.range.end.character,
+            )
+        })
+        .collect()
+}
+
+/// Renders `source` unclosed and with `close` appended, checking that the
+/// output is the same and only the unclosed version warns.
+fn check(source: &str, close: &str) -> Vec<(&'static str, usize, usize, usize)> {
+    let (html, diagnostics) = render(source);
+    let (closed_html, closed_diagnostics) = render(&format!("{source}{close}"));
+    assert_eq!(html, closed_html);
+    assert!(
+        unclosed(&closed_diagnostics).is_empty(),
+        "{closed_diagnostics:?}"
+    );
+    unclosed(&diagnostics)
+}
+
+#[test]
+fn unclosed_code_fence_warns_at_the_opening_fence() {
+    let source = "Intro.\n\n  ````rs\nlet x = 1;\n\n# not a heading\n";
+    assert_eq!(check(source, "````\n"), vec![(W_UNCLOSED_FENCE, 2, 2, 6)]);
+    assert_eq!(
+        check("~~~\nno newline", "\n~~~"),
+        vec![(W_UNCLOSED_FENCE, 0, 0, 3)]
+    );
+}
+
+#[test]
+fn unclosed_box_warns_at_the_opening_fence() {
+    let source = "::: box Title\nBody.\n\n:::: note\nInner.\n::::\n";
+    assert_eq!(check(source, ":::\n"), vec![(W_UNCLOSED_BOX, 0, 0, 3)]);
+    // An unclosed code fence inside swallows the closing `:::` too.
+    let (_, diagnostics) = render("::: box\n```\ncode\n:::\n");
+    assert_eq!(
+        unclosed(&diagnostics),
+        vec![(W_UNCLOSED_BOX, 0, 0, 3), (W_UNCLOSED_FENCE, 1, 0, 3)]
+    );
+}
+
+#[test]
+fn unclosed_math_block_warns_at_the_opening_delimiter() {
+    let source = "Text.\n\n $$\nx^2\n";
+    assert_eq!(check(source, "$$\n"), vec![(W_UNCLOSED_MATH, 2, 1, 3)]);
+}
+
+#[test]
+fn blocks_closed_by_their_container_do_not_warn() {
+    let (_, diagnostics) = render("> ```\n> code\n\nAfter.\n\n- $$\n  x\n\nEnd.\n");
+    assert!(unclosed(&diagnostics).is_empty(), "{diagnostics:?}");
+}
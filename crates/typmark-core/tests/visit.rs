@@ -0,0 +1,150 @@
+use typmark_core::{
+    Block, BlockKind, Document, Inline, InlineKind, Visitor, VisitorMut, parse, resolve,
+    walk_block, walk_document, walk_document_mut, walk_inline,
+};
+
+fn resolved_document(source: &str) -> Document {
+    let parsed = parse(source);
+    resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    )
+    .document
+}
+
+#[derive(Default)]
+struct HeadingCounter {
+    headings: usize,
+    texts: usize,
+}
+
+impl Visitor for HeadingCounter {
+    fn visit_block(&mut self, block: &Block) {
+        if matches!(
+            block.kind,
+            BlockKind::Heading { .. } | BlockKind::Section { .. }
+        ) {
+            self.headings += 1;
+        }
+        walk_block(self, block);
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        if matches!(inline.kind, InlineKind::Text(_)) {
+            self.texts += 1;
+        }
+        walk_inline(self, inline);
+    }
+}
+
+#[test]
+fn visitor_counts_nested_headings() {
+    let source = "# One\n\n## Two *em*\n\n> ### Quoted\n\n::: box Title\n#### Boxed\n:::\n\n- item\n\n  ##### Listed\n";
+    let document = resolved_document(source);
+
+    let mut counter = HeadingCounter::default();
+    walk_document(&mut counter, &document);
+    assert_eq!(counter.headings, 5);
+    assert!(counter.texts >= 7);
+}
+
+struct Shout;
+
+impl VisitorMut for Shout {
+    fn visit_inline_mut(&mut self, inline: &mut Inline) -> bool {
+        match &mut inline.kind {
+            InlineKind::Text(text) => *text = text.to_uppercase(),
+            InlineKind::Strong(_) => return false,
+            _ => {}
+        }
+        true
+    }
+}
+
+#[test]
+fn visitor_mut_rewrites_and_can_skip_children() {
+    let mut document = resolved_document("quiet *soft* **loud**\n");
+    walk_document_mut(&mut Shout, &mut document);
+
+    let BlockKind::Paragraph { content } = &document.blocks[0].kind else {
+        panic!("expected paragraph");
+    };
+    assert_eq!(content[0].kind, InlineKind::Text("QUIET ".to_string()));
+    let InlineKind::Emph(children) = &content[1].kind else {
+        panic!("expected emphasis");
+    };
+    assert_eq!(children[0].kind, InlineKind::Text("SOFT".to_string()));
+    let InlineKind::Strong(children) = &content[3].kind else {
+        panic!("expected strong");
+    };
+    assert_eq!(children[0].kind, InlineKind::Text("loud".to_string()));
+}
+
+#[test]
+fn node_count_and_depth_cover_nested_children() {
+    assert_eq!(resolved_document("").node_count(), 0);
+    assert_eq!(resolved_document("").max_depth(), 0);
+
+    // paragraph > text
+    let flat = resolved_document("plain\n");
+    assert_eq!(flat.node_count(), 2);
+    assert_eq!(flat.max_depth(), 2);
+
+    // list > paragraph > link > emph > text
+    let nested = resolved_document("- [*deep*](https://example.com) tail\n");
+    assert_eq!(nested.max_depth(), 5);
+    // list, paragraph, link, emph, text, trailing text
+    assert_eq!(nested.node_count(), 6);
+
+    // box > blockquote > paragraph > text, plus the box title text
+    let boxed = resolved_document("::: box Title\n> quoted\n:::\n");
+    assert_eq!(boxed.node_count(), 5);
+    assert_eq!(boxed.max_depth(), 4);
+}
+
+#[derive(Default)]
+struct CodeTexts(Vec<String>);
+
+impl Visitor for CodeTexts {
+    fn visit_block(&mut self, block: &Block) {
+        if let BlockKind::CodeBlock(code_block) = &block.kind {
+            self.0.push(code_block.text.clone());
+        }
+        walk_block(self, block);
+    }
+}
+
+struct ClearCode;
+
+impl VisitorMut for ClearCode {
+    fn visit_block_mut(&mut self, block: &mut Block) -> bool {
+        if let BlockKind::CodeBlock(code_block) = &mut block.kind {
+            code_block.text.clear();
+        }
+        true
+    }
+}
+
+#[test]
+fn walkers_descend_into_paired_code_output() {
+    let source = "```py {output=out}\nprint(1)\n```\n\n{#out}\n```text\n1\n```\n";
+    let mut document = resolved_document(source);
+    assert_eq!(document.blocks.len(), 1, "output should be paired");
+
+    let mut texts = CodeTexts::default();
+    walk_document(&mut texts, &document);
+    assert_eq!(texts.0, ["print(1)\n", "1\n"]);
+    assert_eq!(document.node_count(), 2);
+
+    walk_document_mut(&mut ClearCode, &mut document);
+    let BlockKind::CodeBlock(code_block) = &document.blocks[0].kind else {
+        panic!("expected code block");
+    };
+    let BlockKind::CodeBlock(output) = &code_block.output.as_ref().unwrap().kind else {
+        panic!("expected output code block");
+    };
+    assert!(code_block.text.is_empty() && output.text.is_empty());
+}
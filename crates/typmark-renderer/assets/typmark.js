@@ -1,6 +1,40 @@
 (function () {
   "use strict";
 
+  var THEME_KEY = "typmark-theme";
+  var THEMES = ["light", "dark", "auto"];
+
+  // Forces the page theme regardless of the OS setting and remembers it.
+  // "auto" follows prefers-color-scheme again.
+  function setTheme(theme) {
+    if (THEMES.indexOf(theme) < 0) {
+      return false;
+    }
+    document.documentElement.setAttribute("data-typmark-theme", theme);
+    try {
+      window.localStorage.setItem(THEME_KEY, theme);
+    } catch (e) {
+      // Storage can be unavailable, e.g. for file:// pages in some browsers.
+    }
+    return true;
+  }
+
+  function restoreTheme() {
+    var stored = null;
+    try {
+      stored = window.localStorage.getItem(THEME_KEY);
+    } catch (e) {
+      return;
+    }
+    if (THEMES.indexOf(stored) >= 0) {
+      document.documentElement.setAttribute("data-typmark-theme", stored);
+    }
+  }
+
+  window.typmark = window.typmark || {};
+  window.typmark.setTheme = setTheme;
+  restoreTheme();
+
   function applyBoxAttributes() {
     var boxes = document.querySelectorAll(".TypMark-box");
     boxes.forEach(function (box) {
@@ -36,6 +70,38 @@
     });
   }
 
+  // A copy button names its code by id in data-copy-target. The text is read
+  // from that <pre>'s line spans, so highlighting markup and the button itself
+  // never reach the clipboard.
+  function setupCopyButtons() {
+    var buttons = document.querySelectorAll("button.TypMark-copy");
+    buttons.forEach(function (button) {
+      button.addEventListener("click", function () {
+        var target = document.getElementById(
+          button.getAttribute("data-copy-target") || "",
+        );
+        if (!target || !navigator.clipboard) {
+          return;
+        }
+        var lines = target.querySelectorAll(".line");
+        var text = lines.length
+          ? Array.prototype.map
+              .call(lines, function (line) {
+                return line.textContent;
+              })
+              .join("\n")
+          : target.textContent;
+        navigator.clipboard.writeText(text).then(function () {
+          var label = button.textContent;
+          button.textContent = "Copied";
+          setTimeout(function () {
+            button.textContent = label;
+          }, 1200);
+        });
+      });
+    });
+  }
+
   function setupRefScroll() {
     var refs = document.querySelectorAll("a.TypMark-ref");
     refs.forEach(function (link) {
@@ -141,6 +207,7 @@
   function init() {
     applyBoxAttributes();
     wireLineAnchors();
+    setupCopyButtons();
     setupRefScroll();
     setupMathScrollShadows();
   }
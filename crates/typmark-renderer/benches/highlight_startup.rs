@@ -0,0 +1,35 @@
+//! Compares the latency of highlighting a small document with syntect and with
+//! the built-in fast highlighter. Each run starts cold, so the numbers include
+//! loading syntect's syntax and theme sets.
+//!
+//! Run with `cargo bench -p typmark-renderer --bench highlight_startup`.
+
+use std::time::{Duration, Instant};
+use typmark_renderer::{Renderer, Theme};
+
+const RUNS: u32 = 10;
+
+const HTML: &str = concat!(
+    "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\"><pre class=\"TypMark-pre\">",
+    "<code class=\"language-rust\">",
+    "<span class=\"line\" data-line=\"1\">fn main() {</span>",
+    "<span class=\"line\" data-line=\"2\">    let greeting = &quot;hello&quot;; // comment</span>",
+    "<span class=\"line\" data-line=\"3\">    println!(&quot;{greeting} {}&quot;, 42);</span>",
+    "<span class=\"line\" data-line=\"4\">}</span>",
+    "</code></pre></figure>",
+);
+
+fn measure(renderer: &Renderer) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        std::hint::black_box(renderer.highlight_html(std::hint::black_box(HTML)));
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let syntect = measure(&Renderer::new(Theme::Dark));
+    let fast = measure(&Renderer::new(Theme::Dark).with_fast_highlighter());
+    println!("syntect: {:>10.3?} per document", syntect);
+    println!("fast:    {:>10.3?} per document", fast);
+}
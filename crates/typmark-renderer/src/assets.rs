@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::Path;
+
+/// Rewrites `<img src="relative/path">` to `data:` URIs for files under `max_bytes`.
+///
+/// Relative paths are resolved against `base_dir`. Remote and absolute URLs,
+/// files larger than `max_bytes`, and unknown image types are left untouched.
+/// Files that cannot be read are left untouched as well and reported in the
+/// returned warnings.
+pub fn inline_local_assets(html: &str, base_dir: &Path, max_bytes: u64) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(html.len());
+    let mut warnings = Vec::new();
+    let mut rest = html;
+    while let Some(tag_start) = rest.find("<img ") {
+        let Some(tag_len) = rest[tag_start..].find('>') else {
+            break;
+        };
+        let tag = &rest[tag_start..tag_start + tag_len];
+        out.push_str(&rest[..tag_start]);
+        match tag.find(" src=\"").map(|idx| idx + 6) {
+            Some(value_start) => {
+                let value_len = tag[value_start..].find('"').unwrap_or(0);
+                let src = &tag[value_start..value_start + value_len];
+                out.push_str(&tag[..value_start]);
+                match inline_src(src, base_dir, max_bytes) {
+                    Ok(Some(data_uri)) => out.push_str(&data_uri),
+                    Ok(None) => out.push_str(src),
+                    Err(warning) => {
+                        warnings.push(warning);
+                        out.push_str(src);
+                    }
+                }
+                out.push_str(&tag[value_start + value_len..]);
+            }
+            None => out.push_str(tag),
+        }
+        rest = &rest[tag_start + tag_len..];
+    }
+    out.push_str(rest);
+    (out, warnings)
+}
+
+fn inline_src(src: &str, base_dir: &Path, max_bytes: u64) -> Result<Option<String>, String> {
+    if src.is_empty() || src.starts_with('/') || src.starts_with('#') || has_url_scheme(src) {
+        return Ok(None);
+    }
+    let path = percent_decode(src.split(['?', '#']).next().unwrap_or_default());
+    let Some(mime) = image_mime(&path) else {
+        return Ok(None);
+    };
+    let path = base_dir.join(path);
+    let len = fs::metadata(&path)
+        .map_err(|err| format!("cannot inline {}: {}", path.display(), err))?
+        .len();
+    if len > max_bytes {
+        return Ok(None);
+    }
+    let bytes =
+        fs::read(&path).map_err(|err| format!("cannot inline {}: {}", path.display(), err))?;
+    Ok(Some(format!(
+        "data:{};base64,{}",
+        mime,
+        base64_encode(&bytes)
+    )))
+}
+
+/// An `@font-face` rule for `family` with the font file as a `data:` URI source.
+pub(crate) fn font_face_css(family: &str, bytes: &[u8]) -> String {
+    let (mime, format) = match bytes.get(..4) {
+        Some(b"wOF2") => ("font/woff2", "woff2"),
+        Some(b"wOFF") => ("font/woff", "woff"),
+        Some(b"OTTO") => ("font/otf", "opentype"),
+        _ => ("font/ttf", "truetype"),
+    };
+    let family = family
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('<', "\\3c ");
+    format!(
+        "@font-face {{\n  font-family: \"{}\";\n  src: url(\"data:{};base64,{}\") format(\"{}\");\n}}\n",
+        family,
+        mime,
+        base64_encode(bytes),
+        format
+    )
+}
+
+fn has_url_scheme(src: &str) -> bool {
+    match src.find(':') {
+        Some(colon) => src[..colon]
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.')),
+        None => false,
+    }
+}
+
+fn image_mime(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    let mime = match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Undoes the percent-encoding and `&amp;` escaping the emitter applies to URLs.
+fn percent_decode(value: &str) -> String {
+    let value = value.replace("&amp;", "&");
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (idx, byte)| {
+            n | u32::from(*byte) << (16 - 8 * idx)
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * idx) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_encode, inline_local_assets};
+    use std::fs;
+
+    #[test]
+    fn base64_matches_rfc_4648_vectors() {
+        let cases = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(base64_encode(input.as_bytes()), expected);
+        }
+    }
+
+    #[test]
+    fn inlines_small_local_images_only() {
+        let dir = std::env::temp_dir().join(format!("typmark-assets-{}", std::process::id()));
+        fs::create_dir_all(dir.join("img")).unwrap();
+        fs::write(dir.join("img/a b.png"), b"foo").unwrap();
+        fs::write(dir.join("big.gif"), b"foobar").unwrap();
+
+        let html = "<p><img src=\"img/a%20b.png\" alt=\"a\" /> <img src=\"big.gif\" alt=\"\" /> <img src=\"https://example.com/x.png\" alt=\"\" /> <img src=\"missing.png\" alt=\"\" /></p>";
+        let (out, warnings) = inline_local_assets(html, &dir, 4);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            out,
+            "<p><img src=\"data:image/png;base64,Zm9v\" alt=\"a\" /> <img src=\"big.gif\" alt=\"\" /> <img src=\"https://example.com/x.png\" alt=\"\" /> <img src=\"missing.png\" alt=\"\" /></p>"
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("missing.png"), "{warnings:?}");
+    }
+}
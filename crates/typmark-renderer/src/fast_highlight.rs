@@ -0,0 +1,240 @@
+use crate::{Theme, escape_html_code};
+
+/// Languages covered by the built-in tokenizer used by `Renderer::with_fast_highlighter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FastLanguage {
+    Rust,
+    JavaScript,
+    Json,
+    Bash,
+    Python,
+}
+
+impl FastLanguage {
+    /// Matches a code block token after alias resolution.
+    pub(crate) fn from_token(token: &str) -> Option<Self> {
+        let language = match token.to_ascii_lowercase().as_str() {
+            "rust" | "rs" => Self::Rust,
+            "js" | "javascript" | "mjs" | "cjs" => Self::JavaScript,
+            "json" => Self::Json,
+            "bash" | "sh" | "shell" => Self::Bash,
+            "python" | "py" => Self::Python,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    /// Space-separated keywords.
+    fn keywords(self) -> &'static str {
+        match self {
+            Self::Rust => concat!(
+                "as async await break const continue crate dyn else enum extern false fn for if ",
+                "impl in let loop match mod move mut pub ref return self Self static struct super ",
+                "trait true type unsafe use where while",
+            ),
+            Self::JavaScript => concat!(
+                "async await break case catch class const continue default delete do else export ",
+                "extends false finally for from function if import in instanceof let new null of ",
+                "return static super switch this throw true try typeof undefined var void while ",
+                "yield",
+            ),
+            Self::Json => "true false null",
+            Self::Bash => concat!(
+                "case do done elif else esac export fi for function if in local return then until ",
+                "while",
+            ),
+            Self::Python => concat!(
+                "False None True and as assert async await break class continue def del elif else ",
+                "except finally for from global if import in is lambda nonlocal not or pass raise ",
+                "return try while with yield",
+            ),
+        }
+    }
+
+    fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Self::Rust | Self::JavaScript => Some("//"),
+            Self::Bash | Self::Python => Some("#"),
+            Self::Json => None,
+        }
+    }
+
+    fn has_block_comments(self) -> bool {
+        matches!(self, Self::Rust | Self::JavaScript)
+    }
+
+    fn is_quote(self, ch: char) -> bool {
+        match self {
+            // Single quotes in Rust are mostly lifetimes, not strings.
+            Self::Rust | Self::Json => ch == '"',
+            Self::JavaScript => matches!(ch, '"' | '\'' | '`'),
+            Self::Bash | Self::Python => matches!(ch, '"' | '\''),
+        }
+    }
+}
+
+struct Palette {
+    foreground: &'static str,
+    keyword: &'static str,
+    string: &'static str,
+    comment: &'static str,
+    number: &'static str,
+}
+
+// Approximates the syntect themes picked by `pick_theme`.
+const LIGHT_PALETTE: Palette = Palette {
+    foreground: "#323232",
+    keyword: "#a71d5d",
+    string: "#183691",
+    comment: "#969896",
+    number: "#0086b3",
+};
+
+const DARK_PALETTE: Palette = Palette {
+    foreground: "#f8f8f2",
+    keyword: "#f92672",
+    string: "#e6db74",
+    comment: "#75715e",
+    number: "#ae81ff",
+};
+
+/// Highlights one line at a time, carrying block comments across lines.
+pub(crate) struct FastHighlighter {
+    language: Option<FastLanguage>,
+    palette: &'static Palette,
+    in_block_comment: bool,
+}
+
+impl FastHighlighter {
+    /// `None` renders plain text in the theme's foreground color.
+    pub(crate) fn new(language: Option<FastLanguage>, theme: Theme) -> Self {
+        let palette = match theme {
+            Theme::Dark => &DARK_PALETTE,
+            Theme::Light | Theme::Auto => &LIGHT_PALETTE,
+        };
+        Self {
+            language,
+            palette,
+            in_block_comment: false,
+        }
+    }
+
+    /// Returns `<span style="color:…;">` runs in the same shape as syntect's HTML output.
+    pub(crate) fn highlight_line(&mut self, line: &str) -> String {
+        let mut out = LineWriter::default();
+        let palette = self.palette;
+        let Some(language) = self.language else {
+            out.push(palette.foreground, line);
+            return out.finish();
+        };
+
+        let mut rest = line;
+        while !rest.is_empty() {
+            if self.in_block_comment {
+                let Some(idx) = rest.find("*/") else {
+                    out.push(palette.comment, rest);
+                    break;
+                };
+                self.in_block_comment = false;
+                out.push(palette.comment, &rest[..idx + 2]);
+                rest = &rest[idx + 2..];
+                continue;
+            }
+            if language.has_block_comments() && rest.starts_with("/*") {
+                self.in_block_comment = true;
+                out.push(palette.comment, "/*");
+                rest = &rest[2..];
+                continue;
+            }
+            if language
+                .line_comment()
+                .is_some_and(|prefix| rest.starts_with(prefix))
+            {
+                out.push(palette.comment, rest);
+                break;
+            }
+
+            let ch = rest.chars().next().unwrap_or_default();
+            let len = if language.is_quote(ch) {
+                string_len(rest, ch)
+            } else if ch.is_ascii_digit() {
+                rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                    .unwrap_or(rest.len())
+            } else if ch.is_alphabetic() || ch == '_' {
+                rest.find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len())
+            } else {
+                ch.len_utf8()
+            };
+            let token = &rest[..len];
+            let color = if language.is_quote(ch) {
+                palette.string
+            } else if ch.is_ascii_digit() {
+                palette.number
+            } else if language
+                .keywords()
+                .split(' ')
+                .any(|keyword| keyword == token)
+            {
+                palette.keyword
+            } else {
+                palette.foreground
+            };
+            out.push(color, token);
+            rest = &rest[len..];
+        }
+        out.finish()
+    }
+}
+
+/// Length of a quoted string including both quotes, or the rest of the line when unterminated.
+fn string_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (idx, ch) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == quote {
+            return idx + ch.len_utf8();
+        }
+    }
+    text.len()
+}
+
+/// Merges adjacent runs of the same color into one span.
+#[derive(Default)]
+struct LineWriter {
+    out: String,
+    color: Option<&'static str>,
+    run: String,
+}
+
+impl LineWriter {
+    fn push(&mut self, color: &'static str, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if self.color != Some(color) {
+            self.flush();
+            self.color = Some(color);
+        }
+        self.run.push_str(text);
+    }
+
+    fn flush(&mut self) {
+        if let Some(color) = self.color.take() {
+            self.out.push_str(&format!(
+                "<span style=\"color:{};\">{}</span>",
+                color,
+                escape_html_code(&self.run)
+            ));
+            self.run.clear();
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush();
+        self.out
+    }
+}
@@ -2,7 +2,9 @@ use std::collections::BTreeMap;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{Theme as SyntectTheme, ThemeSet};
 use syntect::html::{IncludeBackground, styled_line_to_highlighted_html};
-use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::parsing::SyntaxSet;
+
+use fast_highlight::{FastHighlighter, FastLanguage};
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::fs;
@@ -11,33 +13,141 @@ use std::io;
 #[cfg(not(target_arch = "wasm32"))]
 use std::path::Path;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod assets;
+mod fast_highlight;
 #[cfg(not(target_arch = "wasm32"))]
 mod pdf;
+mod toml_subset;
 
 #[cfg(not(target_arch = "wasm32"))]
-pub use pdf::{PdfBackend, PdfMargin, PdfOptions};
+pub use assets::inline_local_assets;
+#[cfg(not(target_arch = "wasm32"))]
+pub use pdf::{ImageOptions, PdfBackend, PdfMargin, PdfOptions};
+pub use toml_subset::{TomlDocument, TomlEntry, TomlError, TomlValue, parse_toml_subset};
 
 const BASE_CSS: &str = include_str!("../assets/typmark.css");
 const BASE_JS: &str = include_str!("../assets/typmark.js");
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Theme {
     Auto,
     Light,
     Dark,
 }
 
+/// A user palette layered over the built-in theme variables with
+/// [`Renderer::with_theme_overrides`]. `light` applies wherever the light
+/// palette does and `dark` wherever the dark one does; variables a map leaves
+/// out keep their built-in values. `syntax` names the syntect theme for code
+/// blocks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThemeOverrides {
+    pub light: BTreeMap<String, String>,
+    pub dark: BTreeMap<String, String>,
+    pub syntax: Option<String>,
+}
+
+impl ThemeOverrides {
+    /// Reads a palette from a TOML file:
+    ///
+    /// ```toml
+    /// syntax = "Solarized (light)"
+    ///
+    /// [light]
+    /// bg = "#ffffff"
+    /// "--typmark-accent" = "#c2410c"
+    ///
+    /// [dark]
+    /// bg = "#101010"
+    /// ```
+    ///
+    /// Keys without a leading `--` get the `--typmark-` prefix. Values must be
+    /// quoted strings and may not contain `;`, `{`, `}`, or `<`.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let document = parse_toml_subset(text).map_err(|err| err.to_string())?;
+        if let Some((line, table)) = document
+            .tables
+            .iter()
+            .find(|(_, table)| !matches!(table.as_str(), "light" | "dark"))
+        {
+            return Err(format!("line {}: unknown table [{}]", line, table));
+        }
+        let mut overrides = ThemeOverrides::default();
+        for entry in document.entries {
+            let line = entry.line;
+            let TomlValue::String(value) = entry.value else {
+                return Err(format!("line {}: expected a quoted string", line));
+            };
+            if value.contains([';', '{', '}', '<']) {
+                return Err(format!("line {}: invalid CSS value {:?}", line, value));
+            }
+            let vars = match entry.table.as_str() {
+                "" if entry.key == "syntax" => {
+                    overrides.syntax = Some(value);
+                    continue;
+                }
+                "" => return Err(format!("line {}: unknown key {}", line, entry.key)),
+                "light" => &mut overrides.light,
+                _ => &mut overrides.dark,
+            };
+            let name = if entry.key.starts_with("--") {
+                entry.key
+            } else {
+                format!("--typmark-{}", entry.key)
+            };
+            vars.insert(name, value);
+        }
+        Ok(overrides)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Renderer {
     theme: Theme,
+    theme_overrides: ThemeOverrides,
     custom_vars: BTreeMap<String, String>,
+    minify: bool,
+    language_aliases: BTreeMap<String, String>,
+    language_labels: BTreeMap<String, String>,
+    class_prefix: String,
+    lang: String,
+    dir: Option<String>,
+    asset_href: Option<String>,
+    fast_highlighter: bool,
+    /// Font families and their file bytes, for `@font-face` rules in standalone output.
+    #[cfg(not(target_arch = "wasm32"))]
+    fonts: Vec<(String, Vec<u8>)>,
+}
+
+const DEFAULT_CLASS_PREFIX: &str = "TypMark-";
+
+/// Rendered pieces for embedding into a page the caller owns.
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    /// Highlighted body HTML, without `<html>`, `<head>`, or `<body>`.
+    pub html: String,
+    /// Theme custom properties followed by the base stylesheet.
+    pub css: String,
+    pub js: String,
 }
 
 impl Renderer {
     pub fn new(theme: Theme) -> Self {
         Self {
             theme,
+            theme_overrides: ThemeOverrides::default(),
             custom_vars: BTreeMap::new(),
+            minify: false,
+            language_aliases: default_language_aliases(),
+            language_labels: default_language_labels(),
+            class_prefix: DEFAULT_CLASS_PREFIX.to_string(),
+            lang: "en".to_string(),
+            dir: None,
+            asset_href: None,
+            fast_highlighter: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            fonts: Vec::new(),
         }
     }
 
@@ -46,9 +156,100 @@ impl Renderer {
         self
     }
 
+    /// Layers a custom palette and syntax theme over the built-in ones.
+    pub fn with_theme_overrides(mut self, overrides: ThemeOverrides) -> Self {
+        self.theme_overrides = overrides;
+        self
+    }
+
+    /// Maps a code block language to another syntax name before lookup.
+    pub fn with_language_alias(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.language_aliases
+            .insert(from.into().to_ascii_lowercase(), to.into());
+        self
+    }
+
+    /// Sets the name shown for a code block language, emitted as
+    /// `data-lang-label`. Languages without a label show the token as written.
+    pub fn with_language_label(
+        mut self,
+        token: impl Into<String>,
+        display: impl Into<String>,
+    ) -> Self {
+        self.language_labels
+            .insert(token.into().to_ascii_lowercase(), display.into());
+        self
+    }
+
+    /// Replaces the `TypMark-` class prefix in the stylesheet, script, and highlighter.
+    /// Must match `HtmlEmitOptions::class_prefix` of the emitted HTML.
+    pub fn with_class_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.class_prefix = prefix.into();
+        self
+    }
+
+    pub fn class_prefix(&self) -> &str {
+        &self.class_prefix
+    }
+
+    /// Sets the `lang` attribute of the embedded document. Values that do not look
+    /// like a BCP 47 tag are ignored and the default `en` is kept.
+    pub fn with_lang(mut self, lang: &str) -> Self {
+        let lang = lang.trim();
+        if is_lang_tag(lang) {
+            self.lang = lang.to_string();
+        }
+        self
+    }
+
+    /// Sets the `dir` attribute of the embedded document to `rtl`, `ltr`, or
+    /// `auto`. Other values are ignored, and without one the attribute is left
+    /// out so the browser default applies.
+    pub fn with_dir(mut self, dir: &str) -> Self {
+        let dir = dir.trim();
+        if matches!(dir, "rtl" | "ltr" | "auto") {
+            self.dir = Some(dir.to_string());
+        }
+        self
+    }
+
+    /// Links `typmark.css` and `typmark.js` under `href` from the embedded document
+    /// instead of inlining them. `href` is used as a prefix, so it should be empty
+    /// or end with `/`. Pair with [`Renderer::generate_files`] to write the files.
+    pub fn with_linked_assets(mut self, href: impl Into<String>) -> Self {
+        self.asset_href = Some(href.into());
+        self
+    }
+
+    /// Highlights Rust, JavaScript, JSON, Bash, Python, and unlabelled code blocks
+    /// with a small built-in tokenizer instead of syntect. Other languages still
+    /// use syntect, whose syntax and theme sets are only loaded when such a block
+    /// is present.
+    pub fn with_fast_highlighter(mut self) -> Self {
+        self.fast_highlighter = true;
+        self
+    }
+
+    /// Adds a TrueType, OpenType, WOFF, or WOFF2 font under `family`. Standalone
+    /// output embeds it as an `@font-face` rule with a `data:` URI source; refer to
+    /// it by `family` in `--typmark-font` or `--typmark-code-font`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_font_bytes(mut self, family: impl Into<String>, bytes: Vec<u8>) -> Self {
+        self.fonts.push((family.into(), bytes));
+        self
+    }
+
+    /// Strips comments and redundant whitespace from the generated stylesheet.
+    pub fn minify(mut self, enabled: bool) -> Self {
+        self.minify = enabled;
+        self
+    }
+
     pub fn stylesheet(&self) -> String {
         let mut out = String::new();
-        let (light_vars, dark_vars) = default_theme_vars();
+        let (mut light_vars, mut dark_vars) = default_theme_vars();
+        light_vars.extend(self.theme_overrides.light.clone());
+        dark_vars.extend(self.theme_overrides.dark.clone());
 
         match self.theme {
             Theme::Auto => {
@@ -64,13 +265,14 @@ impl Renderer {
                 out.push_str(&root_block(&dark_vars, true));
             }
         }
+        out.push_str(&theme_override_blocks(&light_vars, &dark_vars));
 
         if !self.custom_vars.is_empty() {
             out.push_str(&root_block(&self.custom_vars, false));
         }
 
-        out.push_str(BASE_CSS);
-        out
+        out.push_str(&self.with_prefix(BASE_CSS));
+        if self.minify { minify_css(&out) } else { out }
     }
 
     pub fn embed_html(&self, html: &str, with_inline_css: bool, with_inline_js: bool) -> String {
@@ -94,10 +296,68 @@ impl Renderer {
         with_inline_js: bool,
         base_url: Option<&str>,
         extra_css: Option<&str>,
+    ) -> String {
+        self.embed_document(
+            html,
+            with_inline_css,
+            with_inline_js,
+            base_url,
+            extra_css,
+            self.asset_href.as_deref(),
+        )
+    }
+
+    /// Renders `html` as one self-contained page for offline use. Code blocks are
+    /// highlighted, the stylesheet and script are inlined even with
+    /// [`Renderer::with_linked_assets`], fonts from [`Renderer::with_font_bytes`]
+    /// become `@font-face` rules, and local images are embedded as `data:` URIs,
+    /// resolved against `base_dir` as in [`inline_local_assets`]. Remote images and
+    /// links are left as they are.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_standalone(&self, html: &str, base_dir: &Path) -> String {
+        self.render_standalone_with_warnings(html, base_dir).0
+    }
+
+    /// Like `render_standalone`, but also returns a warning for each local image
+    /// that could not be read and was left as a link.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_standalone_with_warnings(
+        &self,
+        html: &str,
+        base_dir: &Path,
+    ) -> (String, Vec<String>) {
+        let highlighted = self.highlight_html(html);
+        let (inlined, warnings) = inline_local_assets(&highlighted, base_dir, u64::MAX);
+        let font_faces: String = self
+            .fonts
+            .iter()
+            .map(|(family, bytes)| assets::font_face_css(family, bytes))
+            .collect();
+        let extra_css = (!font_faces.is_empty()).then_some(font_faces.trim_end());
+        let out = self.embed_document(&inlined, true, true, None, extra_css, None);
+        (out, warnings)
+    }
+
+    fn embed_document(
+        &self,
+        html: &str,
+        with_inline_css: bool,
+        with_inline_js: bool,
+        base_url: Option<&str>,
+        extra_css: Option<&str>,
+        asset_href: Option<&str>,
     ) -> String {
         let mut out = String::new();
         out.push_str("<!DOCTYPE html>\n");
-        out.push_str("<html lang=\"en\">\n");
+        out.push_str("<html lang=\"");
+        out.push_str(&escape_html_attr(&self.lang));
+        out.push('"');
+        if let Some(dir) = &self.dir {
+            out.push_str(" dir=\"");
+            out.push_str(dir);
+            out.push('"');
+        }
+        out.push_str(">\n");
         out.push_str("<head>\n");
         out.push_str("  <meta charset=\"utf-8\" />\n");
         out.push_str(
@@ -109,13 +369,24 @@ impl Renderer {
             out.push_str("\" />\n");
         }
         if with_inline_css {
-            out.push_str("  <style>\n");
-            out.push_str(&self.stylesheet());
-            if let Some(extra_css) = extra_css {
-                out.push('\n');
-                out.push_str(extra_css);
+            if let Some(href) = asset_href {
+                out.push_str("  <link rel=\"stylesheet\" href=\"");
+                out.push_str(&escape_html_attr(&format!("{}typmark.css", href)));
+                out.push_str("\" />\n");
+                if let Some(extra_css) = extra_css {
+                    out.push_str("  <style>\n");
+                    out.push_str(extra_css);
+                    out.push_str("\n  </style>\n");
+                }
+            } else {
+                out.push_str("  <style>\n");
+                out.push_str(&self.stylesheet());
+                if let Some(extra_css) = extra_css {
+                    out.push('\n');
+                    out.push_str(extra_css);
+                }
+                out.push_str("\n  </style>\n");
             }
-            out.push_str("\n  </style>\n");
         }
         out.push_str("</head>\n");
         out.push_str("<body>\n");
@@ -124,15 +395,31 @@ impl Renderer {
             out.push('\n');
         }
         if with_inline_js {
-            out.push_str("  <script>\n");
-            out.push_str(BASE_JS);
-            out.push_str("\n  </script>\n");
+            if let Some(href) = asset_href {
+                out.push_str("  <script src=\"");
+                out.push_str(&escape_html_attr(&format!("{}typmark.js", href)));
+                out.push_str("\"></script>\n");
+            } else {
+                out.push_str("  <script>\n");
+                out.push_str(&self.script());
+                out.push_str("\n  </script>\n");
+            }
         }
         out.push_str("</body>\n");
         out.push_str("</html>\n");
         out
     }
 
+    /// Returns the highlighted HTML, stylesheet, and script separately, for
+    /// templates that manage `<head>` themselves.
+    pub fn render_fragment(&self, html: &str) -> Fragment {
+        Fragment {
+            html: self.highlight_html(html),
+            css: self.stylesheet(),
+            js: self.script(),
+        }
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     pub fn export_pdf(
         &self,
@@ -140,25 +427,148 @@ impl Renderer {
         options: &PdfOptions,
         output_path: &Path,
     ) -> Result<(), String> {
+        self.export_pdf_with_warnings(html, options, output_path)
+            .map(|_| ())
+    }
+
+    /// Writes the print-ready page that `export_pdf` would print, to be printed to
+    /// PDF from a browser when no PDF backend is installed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_print_html(
+        &self,
+        html: &str,
+        options: &PdfOptions,
+        output_path: &Path,
+    ) -> Result<(), String> {
+        pdf::export_print_html(self, html, options, output_path)
+    }
+
+    /// Like `export_pdf`, but also returns a warning for each option the chosen
+    /// backend ignored.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_pdf_with_warnings(
+        &self,
+        html: &str,
+        options: &PdfOptions,
+        output_path: &Path,
+    ) -> Result<Vec<String>, String> {
         pdf::export_pdf(self, html, options, output_path)
     }
 
+    /// Renders `html` and captures it as a PNG, for social cards and thumbnails.
+    /// Uses chromium's headless screenshot, or wkhtmltoimage, and returns an error
+    /// naming what was searched for when neither is available.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_png(
+        &self,
+        html: &str,
+        options: &ImageOptions,
+        output_path: &Path,
+    ) -> Result<(), String> {
+        pdf::export_png(self, html, options, output_path)
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     pub fn generate_files(&self, out_dir: &Path) -> io::Result<()> {
         fs::create_dir_all(out_dir)?;
         fs::write(out_dir.join("typmark.css"), self.stylesheet())?;
-        fs::write(out_dir.join("typmark.js"), BASE_JS)?;
+        fs::write(out_dir.join("typmark.js"), self.script())?;
         Ok(())
     }
 
     pub fn highlight_html(&self, html: &str) -> String {
-        let syntax_set = SyntaxSet::load_defaults_newlines();
-        let theme_set = ThemeSet::load_defaults();
-        let theme = pick_theme(self.theme, &theme_set);
-        highlight_html_inner(html, &syntax_set, theme)
+        self.highlight_html_with_unresolved(html).0
+    }
+
+    /// Like `highlight_html`, but also returns the code block languages that
+    /// matched no syntax and were rendered as plain text.
+    pub fn highlight_html_with_unresolved(&self, html: &str) -> (String, Vec<String>) {
+        let mut syntect = None;
+        let mut unresolved = Vec::new();
+        let out = highlight_html_inner(html, self, &mut syntect, &mut unresolved);
+        (out, unresolved)
+    }
+
+    fn script(&self) -> String {
+        self.with_prefix(BASE_JS)
+    }
+
+    fn with_prefix(&self, asset: &str) -> String {
+        if self.class_prefix == DEFAULT_CLASS_PREFIX {
+            asset.to_string()
+        } else {
+            asset.replace(DEFAULT_CLASS_PREFIX, &self.class_prefix)
+        }
     }
 }
 
+/// Loose BCP 47 check: alphabetic primary subtag, then alphanumeric subtags of up to 8 chars.
+fn is_lang_tag(value: &str) -> bool {
+    let mut subtags = value.split('-');
+    let primary_ok = subtags.next().is_some_and(|primary| {
+        (1..=8).contains(&primary.len()) && primary.bytes().all(|b| b.is_ascii_alphabetic())
+    });
+    primary_ok
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+        })
+}
+
+fn default_language_aliases() -> BTreeMap<String, String> {
+    [
+        ("sh", "bash"),
+        ("shell", "bash"),
+        ("yml", "yaml"),
+        ("ts", "typescript"),
+        ("rs", "rust"),
+    ]
+    .into_iter()
+    .map(|(from, to)| (from.to_string(), to.to_string()))
+    .collect()
+}
+
+/// Display names for common tokens, following syntect's syntax names.
+/// Aliases are looked up too, so `rs` shows as "Rust".
+fn default_language_labels() -> BTreeMap<String, String> {
+    [
+        ("bash", "Bash"),
+        ("c", "C"),
+        ("cpp", "C++"),
+        ("c++", "C++"),
+        ("cs", "C#"),
+        ("csharp", "C#"),
+        ("css", "CSS"),
+        ("diff", "Diff"),
+        ("go", "Go"),
+        ("haskell", "Haskell"),
+        ("html", "HTML"),
+        ("java", "Java"),
+        ("javascript", "JavaScript"),
+        ("js", "JavaScript"),
+        ("json", "JSON"),
+        ("latex", "LaTeX"),
+        ("lua", "Lua"),
+        ("makefile", "Makefile"),
+        ("markdown", "Markdown"),
+        ("md", "Markdown"),
+        ("php", "PHP"),
+        ("python", "Python"),
+        ("py", "Python"),
+        ("ruby", "Ruby"),
+        ("rb", "Ruby"),
+        ("rust", "Rust"),
+        ("scala", "Scala"),
+        ("sql", "SQL"),
+        ("toml", "TOML"),
+        ("typescript", "TypeScript"),
+        ("xml", "XML"),
+        ("yaml", "YAML"),
+    ]
+    .into_iter()
+    .map(|(token, label)| (token.to_string(), label.to_string()))
+    .collect()
+}
+
 fn default_theme_vars() -> (BTreeMap<String, String>, BTreeMap<String, String>) {
     let light = BTreeMap::from([
         ("--typmark-bg".to_string(), "#fbfbf8".to_string()),
@@ -225,6 +635,118 @@ fn indent_root_block(vars: &BTreeMap<String, String>) -> String {
     out
 }
 
+/// Rules for `<html data-typmark-theme="light|dark|auto">`, set by `typmark.setTheme`.
+/// They win over the renderer's own theme so the page can be switched at runtime.
+fn theme_override_blocks(
+    light_vars: &BTreeMap<String, String>,
+    dark_vars: &BTreeMap<String, String>,
+) -> String {
+    let mut out = String::new();
+    for (theme, vars) in [
+        ("light", light_vars),
+        ("dark", dark_vars),
+        ("auto", light_vars),
+    ] {
+        out.push_str(&format!(":root[data-typmark-theme=\"{}\"] {{\n", theme));
+        out.push_str(&format_vars(vars));
+        out.push_str("}\n");
+    }
+    out.push_str("@media (prefers-color-scheme: dark) {\n");
+    out.push_str("  :root[data-typmark-theme=\"auto\"] {\n");
+    for line in format_vars(dark_vars).lines() {
+        out.push_str("  ");
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str("  }\n");
+    out.push_str("}\n");
+    out
+}
+
+fn minify_css(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut chars = css.chars().peekable();
+    let mut pending_space = false;
+    // One entry per open block: true when the block holds declarations, false for nested rules.
+    let mut blocks: Vec<bool> = Vec::new();
+    let mut prelude_start = 0;
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for next in chars.by_ref() {
+                    if prev == '*' && next == '/' {
+                        break;
+                    }
+                    prev = next;
+                }
+                pending_space = true;
+            }
+            '{' | '}' | ';' | ',' | '>' => {
+                pending_space = false;
+                match ch {
+                    '{' => {
+                        let prelude = out[prelude_start..].trim();
+                        blocks.push(!is_rule_list_prelude(prelude));
+                    }
+                    '}' => {
+                        if out.ends_with(';') {
+                            out.pop();
+                        }
+                        blocks.pop();
+                    }
+                    _ => {}
+                }
+                out.push(ch);
+                if matches!(ch, '{' | '}' | ';') {
+                    prelude_start = out.len();
+                }
+            }
+            _ if ch.is_whitespace() => pending_space = true,
+            _ => {
+                if pending_space
+                    && ch != ')'
+                    && needs_space_before(&out, blocks.last() == Some(&true))
+                {
+                    out.push(' ');
+                }
+                pending_space = false;
+                out.push(ch);
+                if ch == '"' || ch == '\'' {
+                    let mut escaped = false;
+                    for next in chars.by_ref() {
+                        out.push(next);
+                        if escaped {
+                            escaped = false;
+                        } else if next == '\\' {
+                            escaped = true;
+                        } else if next == ch {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+fn is_rule_list_prelude(prelude: &str) -> bool {
+    ["@media", "@supports", "@container", "@layer", "@document"]
+        .iter()
+        .any(|keyword| prelude.starts_with(keyword))
+}
+
+fn needs_space_before(out: &str, in_declarations: bool) -> bool {
+    match out.chars().last() {
+        None | Some('{' | '}' | ';' | ',' | '>' | '(') => false,
+        Some(':') => !in_declarations,
+        Some(_) => true,
+    }
+}
+
 fn escape_html_attr(value: &str) -> String {
     let mut out = String::new();
     for ch in value.chars() {
@@ -239,7 +761,18 @@ fn escape_html_attr(value: &str) -> String {
     out
 }
 
-fn pick_theme(theme: Theme, theme_set: &ThemeSet) -> &SyntectTheme {
+fn pick_theme<'a>(
+    theme: Theme,
+    overrides: &ThemeOverrides,
+    theme_set: &'a ThemeSet,
+) -> &'a SyntectTheme {
+    if let Some(found) = overrides
+        .syntax
+        .as_ref()
+        .and_then(|name| theme_set.themes.get(name))
+    {
+        return found;
+    }
     let candidates = match theme {
         Theme::Dark => [
             "Monokai Extended Bright",
@@ -261,31 +794,141 @@ fn pick_theme(theme: Theme, theme_set: &ThemeSet) -> &SyntectTheme {
         .expect("theme set has at least one theme")
 }
 
-fn highlight_html_inner(html: &str, syntax_set: &SyntaxSet, theme: &SyntectTheme) -> String {
+/// Syntect's default syntax and theme sets, loaded on first use.
+struct Syntect {
+    syntax_set: SyntaxSet,
+    theme_set: ThemeSet,
+}
+
+impl Syntect {
+    fn load() -> Self {
+        Self {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            theme_set: ThemeSet::load_defaults(),
+        }
+    }
+}
+
+fn highlight_html_inner(
+    html: &str,
+    renderer: &Renderer,
+    syntect: &mut Option<Syntect>,
+    unresolved: &mut Vec<String>,
+) -> String {
     let mut out = String::with_capacity(html.len());
     let mut rest = html;
-    let figure_tag = "<figure class=\"TypMark-codeblock\"";
 
-    while let Some(start) = rest.find(figure_tag) {
+    while let Some((start, end)) = find_codeblock_figure(rest, &renderer.class_prefix) {
         out.push_str(&rest[..start]);
-        let after_start = &rest[start..];
-        let end = match after_start.find("</figure>") {
-            Some(index) => index + "</figure>".len(),
-            None => {
-                out.push_str(after_start);
-                return out;
-            }
-        };
-        let figure = &after_start[..end];
-        out.push_str(&highlight_figure(figure, syntax_set, theme));
-        rest = &after_start[end..];
+        let figure = highlight_figure(&rest[start..end], renderer, syntect, unresolved);
+        out.push_str(&add_language_label(&figure, renderer));
+        rest = &rest[end..];
     }
 
     out.push_str(rest);
     out
 }
 
-fn highlight_figure(figure: &str, syntax_set: &SyntaxSet, theme: &SyntectTheme) -> String {
+/// Finds the next code block figure and returns its byte range, closing tag included.
+/// The opening tag must carry both the `codeblock` class and the `data-typmark`
+/// marker in any attribute order, and nested figures are skipped when pairing the
+/// closing tag.
+fn find_codeblock_figure(html: &str, class_prefix: &str) -> Option<(usize, usize)> {
+    let class_name = format!("{}codeblock", class_prefix);
+    let mut search = 0;
+    while let Some(offset) = find_tag(&html[search..], "<figure") {
+        let start = search + offset;
+        let tag_end = start + html[start..].find('>')?;
+        let tag = &html[start..=tag_end];
+        let is_codeblock = tag.contains("data-typmark=\"codeblock\"")
+            && extract_attr(tag, "class")
+                .is_some_and(|class| class.split_whitespace().any(|name| name == class_name));
+        if !is_codeblock {
+            search = tag_end + 1;
+            continue;
+        }
+
+        let mut depth = 1usize;
+        let mut cursor = tag_end + 1;
+        loop {
+            let rest = &html[cursor..];
+            let close = rest.find("</figure>")?;
+            match find_tag(rest, "<figure") {
+                Some(open) if open < close => {
+                    depth += 1;
+                    cursor += open + "<figure".len();
+                }
+                _ => {
+                    depth -= 1;
+                    cursor += close + "</figure>".len();
+                    if depth == 0 {
+                        return Some((start, cursor));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Finds `name` followed by whitespace, `/`, or `>` so `<figure` does not match `<figures`.
+fn find_tag(html: &str, name: &str) -> Option<usize> {
+    let mut search = 0;
+    while let Some(offset) = html[search..].find(name) {
+        let start = search + offset;
+        match html[start + name.len()..].chars().next() {
+            Some(ch) if ch.is_ascii_whitespace() || ch == '>' || ch == '/' => return Some(start),
+            _ => search = start + name.len(),
+        }
+    }
+    None
+}
+
+/// Adds `data-lang-label` after the figure's `data-lang`, unless the figure
+/// has no language or already carries a label.
+fn add_language_label(figure: &str, renderer: &Renderer) -> String {
+    let open_tag = &figure[..figure.find('>').unwrap_or(figure.len())];
+    let token = match extract_attr(open_tag, "data-lang") {
+        Some(token) if !token.is_empty() && !open_tag.contains("data-lang-label=") => token,
+        _ => return figure.to_string(),
+    };
+    let token = unescape_html_code(&token);
+    let key = token.to_ascii_lowercase();
+    let label = renderer
+        .language_labels
+        .get(&key)
+        .or_else(|| {
+            let alias = renderer.language_aliases.get(&key)?;
+            renderer.language_labels.get(&alias.to_ascii_lowercase())
+        })
+        .map(String::as_str)
+        .unwrap_or(&token);
+    let insert_at =
+        open_tag.find("data-lang=\"").expect("data-lang was found") + "data-lang=\"".len();
+    let insert_at = insert_at
+        + open_tag[insert_at..]
+            .find('"')
+            .expect("attribute is closed")
+        + 1;
+    format!(
+        "{} data-lang-label=\"{}\"{}",
+        &figure[..insert_at],
+        escape_html_attr(label),
+        &figure[insert_at..]
+    )
+}
+
+fn highlight_figure(
+    figure: &str,
+    renderer: &Renderer,
+    syntect: &mut Option<Syntect>,
+    unresolved: &mut Vec<String>,
+) -> String {
+    // Opted-out blocks keep the emitter's line spans untouched.
+    let open_tag = &figure[..figure.find('>').unwrap_or(figure.len())];
+    if has_bool_attr(open_tag, "data-no-highlight") {
+        return figure.to_string();
+    }
     let code_start = match figure.find("<code") {
         Some(index) => index,
         None => return figure.to_string(),
@@ -302,11 +945,42 @@ fn highlight_figure(figure: &str, syntax_set: &SyntaxSet, theme: &SyntectTheme)
     let code_inner = &figure[code_tag_end + 1..code_close];
 
     let language = extract_language(code_tag);
-    let syntax = language
-        .as_deref()
-        .and_then(|token| syntax_set.find_syntax_by_token(token))
-        .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
-    let highlighted = highlight_code_lines(code_inner, syntax_set, syntax, theme);
+    let resolved = language.as_deref().map(|token| {
+        renderer
+            .language_aliases
+            .get(&token.to_ascii_lowercase())
+            .map(String::as_str)
+            .unwrap_or(token)
+    });
+    let fast_language = resolved.map(FastLanguage::from_token);
+    let highlighted = if renderer.fast_highlighter && fast_language != Some(None) {
+        let mut highlighter = FastHighlighter::new(fast_language.flatten(), renderer.theme);
+        map_code_lines(code_inner, |line| highlighter.highlight_line(line))
+    } else {
+        let Syntect {
+            syntax_set,
+            theme_set,
+        } = syntect.get_or_insert_with(Syntect::load);
+        let syntax = match (language.as_deref(), resolved) {
+            (Some(token), Some(resolved)) => match syntax_set.find_syntax_by_token(resolved) {
+                Some(syntax) => syntax,
+                None => {
+                    if !unresolved.iter().any(|name| name == token) {
+                        unresolved.push(token.to_string());
+                    }
+                    syntax_set.find_syntax_plain_text()
+                }
+            },
+            _ => syntax_set.find_syntax_plain_text(),
+        };
+        let mut highlighter = HighlightLines::new(
+            syntax,
+            pick_theme(renderer.theme, &renderer.theme_overrides, theme_set),
+        );
+        map_code_lines(code_inner, |line| {
+            highlight_line(line, syntax_set, &mut highlighter)
+        })
+    };
 
     let mut out = String::with_capacity(figure.len() + highlighted.len());
     out.push_str(&figure[..code_tag_end + 1]);
@@ -315,15 +989,11 @@ fn highlight_figure(figure: &str, syntax_set: &SyntaxSet, theme: &SyntectTheme)
     out
 }
 
-fn highlight_code_lines(
-    code_html: &str,
-    syntax_set: &SyntaxSet,
-    syntax: &SyntaxReference,
-    theme: &SyntectTheme,
-) -> String {
+/// Replaces the content of each `<span class="line">` with `highlight` applied
+/// to its unescaped text, leaving the wrappers and everything else intact.
+fn map_code_lines(code_html: &str, mut highlight: impl FnMut(&str) -> String) -> String {
     let mut out = String::with_capacity(code_html.len());
     let mut rest = code_html;
-    let mut highlighter = HighlightLines::new(syntax, theme);
 
     while let Some(span_start) = rest.find("<span ") {
         out.push_str(&rest[..span_start]);
@@ -336,6 +1006,13 @@ fn highlight_code_lines(
         };
         let span_open = &rest[span_start..=span_open_end];
         let content_start = span_open_end + 1;
+        let is_line = extract_attr(span_open, "class")
+            .is_some_and(|class| class.split_whitespace().any(|name| name == "line"));
+        if !is_line {
+            out.push_str(span_open);
+            rest = &rest[content_start..];
+            continue;
+        }
         let close_tag = "</span>";
         let content_end = match rest[content_start..].find(close_tag) {
             Some(index) => content_start + index,
@@ -346,7 +1023,7 @@ fn highlight_code_lines(
         };
         let content = &rest[content_start..content_end];
         let line = unescape_html_code(content);
-        let highlighted = highlight_line(&line, syntax_set, &mut highlighter);
+        let highlighted = highlight(&line);
 
         out.push_str(span_open);
         out.push_str(&highlighted);
@@ -380,6 +1057,11 @@ fn extract_language(code_tag: &str) -> Option<String> {
     None
 }
 
+fn has_bool_attr(tag: &str, name: &str) -> bool {
+    tag.split(|ch: char| ch.is_whitespace() || ch == '>' || ch == '/')
+        .any(|part| part == name || part.starts_with(&format!("{}=", name)))
+}
+
 fn extract_attr(tag: &str, name: &str) -> Option<String> {
     let needle = format!("{}=\"", name);
     let start = tag.find(&needle)? + needle.len();
@@ -449,7 +1131,22 @@ fn strip_font_weight(html: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{Renderer, Theme};
+    use super::{Renderer, Theme, ThemeOverrides, pick_theme};
+    use syntect::highlighting::ThemeSet;
+
+    fn custom_properties(css: &str) -> Vec<(String, String)> {
+        css.split(['{', '}', ';'])
+            .filter_map(|decl| {
+                let (name, value) = decl.split_once(':')?;
+                let name = name.trim();
+                if !name.starts_with("--") {
+                    return None;
+                }
+                let value: String = value.split_whitespace().collect();
+                Some((name.to_string(), value.replace(", ", ",")))
+            })
+            .collect()
+    }
 
     #[test]
     fn embed_html_includes_css_and_js() {
@@ -460,6 +1157,97 @@ mod tests {
         assert!(html.contains("<p>Hi</p>"));
     }
 
+    #[test]
+    fn render_standalone_needs_no_external_files() {
+        let dir = std::env::temp_dir().join(format!("typmark-standalone-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("logo.png"), b"png").unwrap();
+
+        let renderer = Renderer::new(Theme::Light)
+            .with_linked_assets("static/")
+            .with_font_bytes("Body \"Sans\"", b"wOF2data".to_vec());
+        let html = "<p><img src=\"logo.png\" alt=\"\" /> <a href=\"#top\">Top</a></p>\n<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\" data-lang=\"rust\"><pre class=\"TypMark-pre\"><code class=\"language-rust\"><span class=\"line\" data-line=\"1\">fn main() {}</span></code></pre></figure>\n";
+        let out = renderer.render_standalone(html, &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(
+            out.contains("<img src=\"data:image/png;base64,cG5n\""),
+            "{out}"
+        );
+        assert!(out.contains("font-family: \"Body \\\"Sans\\\"\";"), "{out}");
+        assert!(
+            out.contains("src: url(\"data:font/woff2;base64,d09GMmRhdGE=\") format(\"woff2\");")
+        );
+        assert!(out.contains("data-lang-label=\"Rust\""), "{out}");
+        assert!(out.contains("<style>") && out.contains("<script>"));
+        assert!(!out.contains("src=\"http"), "{out}");
+        for (idx, _) in out.match_indices("href=\"") {
+            let value = &out[idx + 6..];
+            assert!(
+                value.starts_with('#') || value.starts_with("data:"),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn theme_overrides_match_script_hooks() {
+        let renderer = Renderer::new(Theme::Dark);
+        let css = renderer.stylesheet();
+        let script = renderer.script();
+        for theme in ["light", "dark", "auto"] {
+            let selector = format!(":root[data-typmark-theme=\"{theme}\"]");
+            assert!(script.contains(&format!("\"{theme}\"")), "{theme}");
+            let block = &css[css.find(&selector).expect("override block")..];
+            let block = &block[..block.find('}').expect("block end")];
+            assert!(block.contains("--typmark-bg:"), "{block}");
+            assert!(block.contains("--typmark-fg:"), "{block}");
+        }
+        let auto_dark = &css[css
+            .find("@media (prefers-color-scheme: dark) {\n  :root[data-typmark-theme=\"auto\"]")
+            .expect("auto dark block")..];
+        assert!(
+            auto_dark[..auto_dark.find('}').expect("block end")].contains("--typmark-bg: #0e1116;")
+        );
+        assert!(css.contains("@media (prefers-reduced-motion: reduce)"));
+        assert!(script.contains("window.typmark.setTheme = setTheme;"));
+        assert!(script.contains("setAttribute(\"data-typmark-theme\""));
+        assert!(script.contains("localStorage"));
+    }
+
+    #[test]
+    fn custom_theme_replaces_palette_and_syntax_theme() {
+        let overrides = ThemeOverrides::from_toml(
+            "syntax = \"Solarized (dark)\"\n\n[light]\nbg = \"#fffdf5\" # brand cream\n\"--typmark-accent\" = \"#c2410c\"\n\n[dark]\nbg = \"#1c1917\"\n",
+        )
+        .expect("valid theme");
+        let renderer = Renderer::new(Theme::Auto).with_theme_overrides(overrides.clone());
+        let css = renderer.stylesheet();
+        let root = &css[..css.find('}').expect("root block end")];
+        assert!(root.contains("--typmark-bg: #fffdf5;"), "{root}");
+        assert!(root.contains("--typmark-accent: #c2410c;"), "{root}");
+        assert!(root.contains("--typmark-fg: #1f2328;"), "{root}");
+        assert!(css.contains("@media (prefers-color-scheme: dark) {\n  :root {"));
+        assert!(css.contains("--typmark-bg: #1c1917;"));
+
+        let theme_set = ThemeSet::load_defaults();
+        let picked = pick_theme(Theme::Light, &overrides, &theme_set);
+        assert!(std::ptr::eq(picked, &theme_set.themes["Solarized (dark)"]));
+
+        assert_eq!(
+            ThemeOverrides::from_toml("[light]\nbg = \"red; }\"\n"),
+            Err("line 2: invalid CSS value \"red; }\"".to_string())
+        );
+        assert!(ThemeOverrides::from_toml("[sepia]\n").is_err());
+        assert!(ThemeOverrides::from_toml("bg = \"#fff\"\n").is_err());
+
+        let light_only = Renderer::new(Theme::Light)
+            .with_theme_overrides(overrides)
+            .stylesheet();
+        let root = &light_only[..light_only.find('}').expect("root block end")];
+        assert!(root.contains("--typmark-bg: #fffdf5;"), "{root}");
+    }
+
     #[test]
     fn embed_html_can_skip_assets() {
         let renderer = Renderer::new(Theme::Light);
@@ -469,6 +1257,36 @@ mod tests {
         assert!(html.contains("<p>Hi</p>"));
     }
 
+    #[test]
+    fn minified_stylesheet_keeps_custom_properties() {
+        let renderer =
+            Renderer::new(Theme::Auto).with_var("--typmark-font", "\"Noto Serif\", serif");
+        let full = renderer.stylesheet();
+        let minified = renderer.clone().minify(true).stylesheet();
+
+        assert!(minified.len() < full.len());
+        assert!(!minified.contains("\n"));
+        assert!(!minified.contains(";}"));
+        assert!(minified.contains("@media (prefers-color-scheme: dark){:root{"));
+        assert!(minified.contains("--typmark-font:\"Noto Serif\",serif"));
+        assert!(minified.contains("section + section{"));
+        assert_eq!(custom_properties(&minified), custom_properties(&full));
+    }
+
+    #[test]
+    fn render_fragment_splits_html_css_and_js() {
+        let renderer = Renderer::new(Theme::Dark).with_var("--typmark-font", "serif");
+        let html = "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\"><pre class=\"TypMark-pre\"><code class=\"language-rust\"><span class=\"line\" data-line=\"1\">let x = 1;</span></code></pre></figure>";
+        let fragment = renderer.render_fragment(html);
+        assert_eq!(fragment.html, renderer.highlight_html(html));
+        assert!(!fragment.html.contains("<head>"));
+        assert_eq!(fragment.css, renderer.stylesheet());
+        assert!(fragment.css.starts_with(":root"));
+        assert!(fragment.css.contains("--typmark-font: serif"));
+        assert!(fragment.js.contains("TypMark-"));
+        assert!(!fragment.js.contains("<script>"));
+    }
+
     #[test]
     fn highlight_preserves_line_wrappers() {
         let renderer = Renderer::new(Theme::Light);
@@ -477,4 +1295,186 @@ mod tests {
         assert!(highlighted.contains("class=\"line\""));
         assert!(highlighted.contains("style=\""));
     }
+
+    #[test]
+    fn highlight_keeps_copy_buttons_and_script_wires_them() {
+        let renderer = Renderer::new(Theme::Light);
+        let html = "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\"><button type=\"button\" class=\"TypMark-copy\" data-copy-target=\"TypMark-code-1\" aria-label=\"Copy code\">Copy</button><pre class=\"TypMark-pre\" id=\"TypMark-code-1\"><code class=\"language-rust\"><span class=\"line\" data-line=\"1\">let x = 1;</span></code></pre></figure>";
+        let highlighted = renderer.highlight_html(html);
+        assert!(highlighted.contains(
+            "<button type=\"button\" class=\"TypMark-copy\" data-copy-target=\"TypMark-code-1\" aria-label=\"Copy code\">Copy</button><pre class=\"TypMark-pre\" id=\"TypMark-code-1\">"
+        ));
+        assert!(highlighted.contains("style=\""));
+        let js = renderer.render_fragment(html).js;
+        assert!(js.contains("button.TypMark-copy"));
+        assert!(js.contains("data-copy-target"));
+    }
+
+    #[test]
+    fn fast_highlighter_colors_rust_without_syntect() {
+        let renderer = Renderer::new(Theme::Light).with_fast_highlighter();
+        let html = "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\"><pre class=\"TypMark-pre\"><code class=\"language-rs\"><span class=\"line\" data-line=\"1\">let s = &quot;a&lt;b&quot;; // 42</span><span class=\"line\" data-line=\"2\">/* open</span><span class=\"line\" data-line=\"3\">closed */ fn f() -&gt; u8 { 7 }</span></code></pre></figure>";
+        let (highlighted, unresolved) = renderer.highlight_html_with_unresolved(html);
+        assert!(unresolved.is_empty());
+        assert!(highlighted.contains(
+            "<span class=\"line\" data-line=\"1\"><span style=\"color:#a71d5d;\">let</span><span style=\"color:#323232;\"> s = </span><span style=\"color:#183691;\">&quot;a&lt;b&quot;</span><span style=\"color:#323232;\">; </span><span style=\"color:#969896;\">// 42</span></span>"
+        ), "{highlighted}");
+        assert!(highlighted.contains(
+            "<span class=\"line\" data-line=\"3\"><span style=\"color:#969896;\">closed */</span><span style=\"color:#323232;\"> </span><span style=\"color:#a71d5d;\">fn</span>"
+        ), "{highlighted}");
+        assert!(highlighted.contains("<span style=\"color:#0086b3;\">7</span>"));
+
+        // Languages the tokenizer does not know still go through syntect.
+        let html = html.replace("language-rs", "language-c");
+        let highlighted = renderer.highlight_html(&html);
+        assert_ne!(
+            highlighted,
+            renderer.highlight_html(&html.replace("language-c", "language-rs"))
+        );
+    }
+
+    #[test]
+    fn language_aliases_resolve_before_lookup() {
+        let block = |lang: &str| {
+            format!(
+                "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\"><pre class=\"TypMark-pre\"><code class=\"language-{lang}\"><span class=\"line\" data-line=\"1\">key: [1, 2]</span></code></pre></figure>"
+            )
+        };
+        let renderer = Renderer::new(Theme::Light);
+        let (yml, unresolved) = renderer.highlight_html_with_unresolved(&block("yml"));
+        let (yaml, _) = renderer.highlight_html_with_unresolved(&block("yaml"));
+        assert!(unresolved.is_empty());
+        assert_eq!(yml.replace("language-yml", "language-yaml"), yaml);
+
+        let (plain, unresolved) = renderer.highlight_html_with_unresolved(&block("nope"));
+        assert_eq!(unresolved, vec!["nope".to_string()]);
+        assert_ne!(plain.replace("language-nope", "language-yaml"), yaml);
+
+        let aliased = Renderer::new(Theme::Light).with_language_alias("nope", "yaml");
+        let (aliased, unresolved) = aliased.highlight_html_with_unresolved(&block("nope"));
+        assert!(unresolved.is_empty());
+        assert_eq!(aliased.replace("language-nope", "language-yaml"), yaml);
+    }
+
+    #[test]
+    fn class_prefix_applies_to_assets_and_highlighting() {
+        let renderer = Renderer::new(Theme::Auto).with_class_prefix("md-");
+        let html = "<figure class=\"md-codeblock\" data-typmark=\"codeblock\"><pre class=\"md-pre\"><code class=\"language-rust\"><span class=\"line\" data-line=\"1\">let x = 1;</span></code></pre></figure>";
+        let page = renderer.embed_html(&renderer.highlight_html(html), true, true);
+        assert!(!page.contains("TypMark-"));
+        assert!(page.contains(".md-codeblock"));
+        assert!(page.contains("style=\""));
+    }
+
+    #[test]
+    fn highlight_handles_escaped_markup_and_nested_figures() {
+        let renderer = Renderer::new(Theme::Light);
+        let escaped = "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\"><pre class=\"TypMark-pre\"><code class=\"language-html\"><span class=\"line\" data-line=\"1\">&lt;/figure&gt;</span><span class=\"line\" data-line=\"2\">&lt;span class=&quot;line&quot;&gt;x&lt;/span&gt;</span></code></pre></figure><p>after</p>";
+        let highlighted = renderer.highlight_html(escaped);
+        assert_eq!(highlighted.matches("<span class=\"line\"").count(), 2);
+        assert!(highlighted.contains("data-line=\"2\""));
+        assert!(highlighted.ends_with("</code></pre></figure><p>after</p>"));
+        assert_eq!(highlighted.matches("</figure>").count(), 1);
+
+        let nested = "<figure data-typmark=\"codeblock\" class=\"TypMark-codeblock\"><figure><figcaption>cap</figcaption></figure><pre class=\"TypMark-pre\"><code class=\"language-rust\"><span class=\"line\" data-line=\"1\">let x = 1;</span></code></pre></figure><figure class=\"TypMark-codeblock-note\">plain</figure>";
+        let highlighted = renderer.highlight_html(nested);
+        assert!(highlighted.contains("<figcaption>cap</figcaption></figure><pre"));
+        assert!(highlighted.contains("style=\""));
+        assert!(
+            highlighted
+                .ends_with("</figure><figure class=\"TypMark-codeblock-note\">plain</figure>")
+        );
+    }
+
+    #[test]
+    fn highlight_skips_no_highlight_figures() {
+        let renderer = Renderer::new(Theme::Light);
+        let figure = "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\" data-no-highlight data-lang=\"rust\"><pre class=\"TypMark-pre\"><code class=\"language-rust\"><span class=\"line\" data-line=\"1\">let x = 1;</span></code></pre></figure>";
+        let figure = figure.replace("rust", "no-such-lang");
+        let (highlighted, unresolved) = renderer.highlight_html_with_unresolved(&figure);
+        assert_eq!(
+            highlighted,
+            figure.replace(
+                "data-lang=\"no-such-lang\"",
+                "data-lang=\"no-such-lang\" data-lang-label=\"no-such-lang\""
+            )
+        );
+        assert!(unresolved.is_empty());
+    }
+
+    #[test]
+    fn highlight_adds_language_labels() {
+        let figure = |lang: &str| {
+            format!(
+                "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\" data-lang=\"{lang}\"><pre class=\"TypMark-pre\"><code class=\"language-{lang}\"><span class=\"line\" data-line=\"1\">x</span></code></pre></figure>"
+            )
+        };
+        let renderer = Renderer::new(Theme::Light).with_fast_highlighter();
+        let html = renderer.highlight_html(&figure("rust"));
+        assert!(
+            html.starts_with("<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\" data-lang=\"rust\" data-lang-label=\"Rust\">"),
+            "{html}"
+        );
+        assert!(
+            renderer
+                .highlight_html(&figure("rs"))
+                .contains("data-lang-label=\"Rust\"")
+        );
+        assert!(
+            renderer
+                .highlight_html(&figure("CPP"))
+                .contains("data-lang-label=\"C++\"")
+        );
+        assert!(
+            renderer
+                .highlight_html(&figure("zig"))
+                .contains("data-lang=\"zig\" data-lang-label=\"zig\"")
+        );
+
+        let renderer = renderer
+            .with_language_label("zig", "Zig <0.12>")
+            .with_language_label("Rust", "Rust 2024");
+        assert!(
+            renderer
+                .highlight_html(&figure("zig"))
+                .contains("data-lang-label=\"Zig &lt;0.12&gt;\"")
+        );
+        assert!(
+            renderer
+                .highlight_html(&figure("rust"))
+                .contains("data-lang-label=\"Rust 2024\"")
+        );
+
+        let plain = "<figure class=\"TypMark-codeblock\" data-typmark=\"codeblock\"><pre class=\"TypMark-pre\"><code><span class=\"line\" data-line=\"1\">x</span></code></pre></figure>";
+        assert!(!renderer.highlight_html(plain).contains("data-lang-label"));
+    }
+
+    #[test]
+    fn lang_sets_html_attribute_when_valid() {
+        let page = Renderer::new(Theme::Light).embed_html("<p>x</p>", false, false);
+        assert!(page.contains("<html lang=\"en\">"));
+
+        let page = Renderer::new(Theme::Light)
+            .with_lang("ja-JP")
+            .embed_html("<p>x</p>", false, false);
+        assert!(page.contains("<html lang=\"ja-JP\">"));
+
+        let page = Renderer::new(Theme::Light)
+            .with_lang("ja\" onload=\"x")
+            .embed_html("<p>x</p>", false, false);
+        assert!(page.contains("<html lang=\"en\">"));
+    }
+
+    #[test]
+    fn dir_sets_html_attribute_when_valid() {
+        let page = Renderer::new(Theme::Light)
+            .with_dir("rtl")
+            .embed_html("<p>x</p>", false, false);
+        assert!(page.contains("<html lang=\"en\" dir=\"rtl\">"));
+
+        let page = Renderer::new(Theme::Light)
+            .with_dir("sideways")
+            .embed_html("<p>x</p>", false, false);
+        assert!(page.contains("<html lang=\"en\">"));
+    }
 }
@@ -2,15 +2,20 @@ use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::Renderer;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PdfBackend {
+    /// Chromium if found in `PATH`, then wkhtmltopdf.
     Auto,
     Chromium,
     Wkhtmltopdf,
+    /// Needs no external tool, but cannot write a PDF: `export_pdf` returns an
+    /// error for it. Use [`Renderer::export_print_html`] to write the print-ready
+    /// page that the other backends would print, to be printed from a browser.
+    Native,
 }
 
 #[derive(Debug, Clone)]
@@ -44,8 +49,25 @@ pub struct PdfOptions {
     pub margin: Option<PdfMargin>,
     pub scale: Option<String>,
     pub base_url: Option<String>,
+    /// Virtual-time budget the Chromium backend gives the page before printing, so
+    /// web fonts and images can load. Nothing confirms that they did.
+    pub font_timeout: Duration,
 }
 
+const DEFAULT_FONT_TIMEOUT: Duration = Duration::from_secs(3);
+
+const CHROMIUM_EXECUTABLES: &[&str] = &[
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "chrome",
+    "msedge",
+    "microsoft-edge",
+];
+const WKHTMLTOPDF_EXECUTABLES: &[&str] = &["wkhtmltopdf"];
+const WKHTMLTOIMAGE_EXECUTABLES: &[&str] = &["wkhtmltoimage"];
+
 impl PdfOptions {
     pub fn new(backend: PdfBackend) -> Self {
         Self {
@@ -54,6 +76,7 @@ impl PdfOptions {
             margin: None,
             scale: None,
             base_url: None,
+            font_timeout: DEFAULT_FONT_TIMEOUT,
         }
     }
 
@@ -76,6 +99,72 @@ impl PdfOptions {
         self.base_url = Some(base_url.into());
         self
     }
+
+    pub fn with_font_timeout(mut self, timeout: Duration) -> Self {
+        self.font_timeout = timeout;
+        self
+    }
+}
+
+/// Options for [`Renderer::export_png`]. The backend is chosen as for PDF export,
+/// except that `Wkhtmltopdf` runs `wkhtmltoimage` from the same package and
+/// `Native` cannot take screenshots.
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    pub backend: PdfBackend,
+    /// Viewport width in CSS pixels.
+    pub width: u32,
+    /// Device scale factor; `2.0` gives an image twice as wide as `width`.
+    pub scale: f64,
+    /// Viewport height in CSS pixels, for a card or thumbnail. The image is the
+    /// viewport, not the whole page: content below it is cut off. Defaults to
+    /// 800 pixels.
+    pub clip: Option<u32>,
+    pub base_url: Option<String>,
+    /// Virtual-time budget the Chromium backend gives the page before capturing, as
+    /// in [`PdfOptions::font_timeout`].
+    pub font_timeout: Duration,
+}
+
+const DEFAULT_IMAGE_WIDTH: u32 = 1200;
+const DEFAULT_IMAGE_HEIGHT: u32 = 800;
+
+impl ImageOptions {
+    pub fn new(backend: PdfBackend) -> Self {
+        Self {
+            backend,
+            width: DEFAULT_IMAGE_WIDTH,
+            scale: 1.0,
+            clip: None,
+            base_url: None,
+            font_timeout: DEFAULT_FONT_TIMEOUT,
+        }
+    }
+
+    pub fn with_width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_clip(mut self, height: u32) -> Self {
+        self.clip = Some(height);
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn with_font_timeout(mut self, timeout: Duration) -> Self {
+        self.font_timeout = timeout;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -84,45 +173,114 @@ enum ResolvedBackend {
     Wkhtmltopdf(PathBuf),
 }
 
+#[derive(Debug, Clone)]
+enum ResolvedImageBackend {
+    Chromium(PathBuf),
+    Wkhtmltoimage(PathBuf),
+}
+
+/// Returns a warning for each option the chosen backend ignored.
 pub fn export_pdf(
     renderer: &Renderer,
     html: &str,
     options: &PdfOptions,
     output_path: &Path,
+) -> Result<Vec<String>, String> {
+    // Resolve first so a missing tool fails before anything is written.
+    let backend = resolve_backend(options.backend)?;
+    let wrapped = print_page(renderer, html, options);
+    create_parent_dir(output_path)?;
+
+    let mut warnings = Vec::new();
+    match backend {
+        ResolvedBackend::Chromium(path) => {
+            let temp = write_temp_html(&wrapped)?;
+            export_with_chromium(&path, &temp.path, output_path, options, &mut warnings)?
+        }
+        ResolvedBackend::Wkhtmltopdf(path) => {
+            let temp = write_temp_html(&wrapped)?;
+            export_with_wkhtmltopdf(&path, &temp.path, output_path, options)?
+        }
+    }
+    Ok(warnings)
+}
+
+pub fn export_png(
+    renderer: &Renderer,
+    html: &str,
+    options: &ImageOptions,
+    output_path: &Path,
 ) -> Result<(), String> {
+    if !(options.width > 0 && options.scale.is_finite() && options.scale > 0.0) {
+        return Err("image width and scale must be greater than zero".to_string());
+    }
+    // Resolve first so a missing tool fails before anything is written.
+    let backend = resolve_image_backend(options.backend)?;
     let highlighted = renderer.highlight_html(html);
-    let extra_css = pdf_extra_css(options.margin.as_ref());
     let wrapped = renderer.embed_html_with_base_and_css(
         &highlighted,
         true,
         false,
         options.base_url.as_deref(),
-        Some(&extra_css),
+        None,
     );
-    let temp = TempFile::new("typmark_pdf", "html")
-        .map_err(|err| format!("failed to create temp file: {}", err))?;
-    fs::write(&temp.path, wrapped).map_err(|err| format!("failed to write temp html: {}", err))?;
 
+    create_parent_dir(output_path)?;
+
+    let temp = write_temp_html(&wrapped)?;
+    match backend {
+        ResolvedImageBackend::Chromium(path) => {
+            screenshot_with_chromium(&path, &temp.path, output_path, options)
+        }
+        ResolvedImageBackend::Wkhtmltoimage(path) => {
+            screenshot_with_wkhtmltoimage(&path, &temp.path, output_path, options)
+        }
+    }
+}
+
+/// Writes the page `export_pdf` would print to `output_path`, without printing it.
+pub fn export_print_html(
+    renderer: &Renderer,
+    html: &str,
+    options: &PdfOptions,
+    output_path: &Path,
+) -> Result<(), String> {
+    let wrapped = print_page(renderer, html, options);
+    create_parent_dir(output_path)?;
+    fs::write(output_path, wrapped)
+        .map_err(|err| format!("failed to write {}: {}", output_path.display(), err))
+}
+
+fn print_page(renderer: &Renderer, html: &str, options: &PdfOptions) -> String {
+    let highlighted = renderer.highlight_html(html);
+    let extra_css = pdf_extra_css(options.margin.as_ref(), renderer.class_prefix());
+    renderer.embed_html_with_base_and_css(
+        &highlighted,
+        true,
+        false,
+        options.base_url.as_deref(),
+        Some(&extra_css),
+    )
+}
+
+fn create_parent_dir(output_path: &Path) -> Result<(), String> {
     if let Some(parent) = output_path.parent()
         && !parent.as_os_str().is_empty()
     {
         fs::create_dir_all(parent)
             .map_err(|err| format!("failed to create output directory: {}", err))?;
     }
-
-    let backend = resolve_backend(options.backend)?;
-    match backend {
-        ResolvedBackend::Chromium(path) => {
-            export_with_chromium(&path, &temp.path, output_path, options)?
-        }
-        ResolvedBackend::Wkhtmltopdf(path) => {
-            export_with_wkhtmltopdf(&path, &temp.path, output_path, options)?
-        }
-    }
     Ok(())
 }
 
-fn pdf_extra_css(margin: Option<&PdfMargin>) -> String {
+fn write_temp_html(html: &str) -> Result<TempFile, String> {
+    let temp = TempFile::new("typmark_pdf", "html")
+        .map_err(|err| format!("failed to create temp file: {}", err))?;
+    fs::write(&temp.path, html).map_err(|err| format!("failed to write temp html: {}", err))?;
+    Ok(temp)
+}
+
+fn pdf_extra_css(margin: Option<&PdfMargin>, prefix: &str) -> String {
     let page_margin = margin
         .map(|value| {
             format!(
@@ -156,10 +314,10 @@ fn pdf_extra_css(margin: Option<&PdfMargin>) -> String {
     max-width: none;\n\
     padding: 0;\n\
   }}\n\
-  .TypMark-math-block {{\n\
+  .{prefix}math-block {{\n\
     overflow: visible;\n\
   }}\n\
-  .TypMark-math-block .typst-doc {{\n\
+  .{prefix}math-block .typst-doc {{\n\
     display: block;\n\
     max-width: 100% !important;\n\
     width: auto !important;\n\
@@ -174,47 +332,94 @@ fn pdf_extra_css(margin: Option<&PdfMargin>) -> String {
 }
 
 fn resolve_backend(backend: PdfBackend) -> Result<ResolvedBackend, String> {
-    let chromium = resolve_executable(&[
-        "chromium",
-        "chromium-browser",
-        "google-chrome",
-        "google-chrome-stable",
-        "chrome",
-        "msedge",
-        "microsoft-edge",
-    ]);
-    let wkhtml = resolve_executable(&["wkhtmltopdf"]);
+    let chromium = || resolve_executable(CHROMIUM_EXECUTABLES);
+    let wkhtml = || resolve_executable(WKHTMLTOPDF_EXECUTABLES);
 
     match backend {
-        PdfBackend::Chromium => chromium
+        PdfBackend::Native => Err(
+            "native backend cannot write a PDF: it has no PDF engine. Install Chromium, Chrome, \
+Edge, or wkhtmltopdf, or write the print-ready page with export_print_html and print it from a browser"
+                .to_string(),
+        ),
+        PdfBackend::Chromium => chromium()
             .map(ResolvedBackend::Chromium)
-            .ok_or_else(|| "chromium backend not found in PATH".to_string()),
-        PdfBackend::Wkhtmltopdf => wkhtml
+            .ok_or_else(|| missing_backend_message("chromium backend", CHROMIUM_EXECUTABLES)),
+        PdfBackend::Wkhtmltopdf => wkhtml()
             .map(ResolvedBackend::Wkhtmltopdf)
-            .ok_or_else(|| "wkhtmltopdf backend not found in PATH".to_string()),
+            .ok_or_else(|| missing_backend_message("wkhtmltopdf backend", WKHTMLTOPDF_EXECUTABLES)),
         PdfBackend::Auto => {
-            if let Some(path) = chromium {
+            if let Some(path) = chromium() {
                 Ok(ResolvedBackend::Chromium(path))
-            } else if let Some(path) = wkhtml {
+            } else if let Some(path) = wkhtml() {
                 Ok(ResolvedBackend::Wkhtmltopdf(path))
             } else {
-                Err(
-                    "no PDF backend found in PATH (chromium or wkhtmltopdf). Install one and retry."
-                        .to_string(),
-                )
+                let searched = [CHROMIUM_EXECUTABLES, WKHTMLTOPDF_EXECUTABLES].concat();
+                Err(missing_backend_message("no PDF backend", &searched))
+            }
+        }
+    }
+}
+
+fn resolve_image_backend(backend: PdfBackend) -> Result<ResolvedImageBackend, String> {
+    let chromium = || resolve_executable(CHROMIUM_EXECUTABLES);
+    let wkhtml = || resolve_executable(WKHTMLTOIMAGE_EXECUTABLES);
+
+    match backend {
+        PdfBackend::Native => {
+            Err("native backend cannot take screenshots: use chromium or wkhtmltoimage".to_string())
+        }
+        PdfBackend::Chromium => chromium()
+            .map(ResolvedImageBackend::Chromium)
+            .ok_or_else(|| missing_image_backend_message("chromium backend", CHROMIUM_EXECUTABLES)),
+        PdfBackend::Wkhtmltopdf => wkhtml()
+            .map(ResolvedImageBackend::Wkhtmltoimage)
+            .ok_or_else(|| {
+                missing_image_backend_message("wkhtmltoimage backend", WKHTMLTOIMAGE_EXECUTABLES)
+            }),
+        PdfBackend::Auto => {
+            if let Some(path) = chromium() {
+                Ok(ResolvedImageBackend::Chromium(path))
+            } else if let Some(path) = wkhtml() {
+                Ok(ResolvedImageBackend::Wkhtmltoimage(path))
+            } else {
+                let searched = [CHROMIUM_EXECUTABLES, WKHTMLTOIMAGE_EXECUTABLES].concat();
+                Err(missing_image_backend_message(
+                    "no screenshot backend",
+                    &searched,
+                ))
             }
         }
     }
 }
 
+fn missing_image_backend_message(what: &str, searched: &[&str]) -> String {
+    format!(
+        "{} found: searched PATH for {}. Install Chromium, Chrome, Edge, or wkhtmltopdf, \
+which provides wkhtmltoimage",
+        what,
+        searched.join(", ")
+    )
+}
+
+/// Names every executable that was looked for and how to get past the error.
+fn missing_backend_message(what: &str, searched: &[&str]) -> String {
+    format!(
+        "{} found: searched PATH for {}. Install Chromium, Chrome, Edge, or wkhtmltopdf, \
+or set pdf-backend to native to write print-ready HTML instead",
+        what,
+        searched.join(", ")
+    )
+}
+
 fn export_with_chromium(
     chromium: &Path,
     html_path: &Path,
     output_path: &Path,
     options: &PdfOptions,
+    warnings: &mut Vec<String>,
 ) -> Result<(), String> {
     if options.page.is_some() || options.margin.is_some() || options.scale.is_some() {
-        eprintln!("note: chromium backend ignores pdf-page, pdf-margin, and pdf-scale");
+        warnings.push("chromium backend ignores pdf-page, pdf-margin, and pdf-scale".to_string());
     }
 
     let html_url = path_to_file_url(html_path)?;
@@ -224,11 +429,81 @@ fn export_with_chromium(
     cmd.arg("--allow-file-access-from-files");
     cmd.arg("--print-to-pdf-no-header");
     cmd.arg("--no-pdf-header-footer");
+    cmd.args(chromium_readiness_args(options.font_timeout));
     cmd.arg(format!("--print-to-pdf={}", output_path.display()));
     cmd.arg(html_url);
     run_command(cmd, "chromium")
 }
 
+// Chromium prints once the page has used up the virtual-time budget. Virtual time
+// is paused while font or image fetches are pending, so they usually land first,
+// but nothing waits on `document.fonts.ready` and a fetch that never finishes is
+// not reported.
+fn chromium_readiness_args(timeout: Duration) -> Vec<String> {
+    vec![
+        "--run-all-compositor-stages-before-draw".to_string(),
+        format!("--virtual-time-budget={}", timeout.as_millis().max(1)),
+    ]
+}
+
+fn screenshot_with_chromium(
+    chromium: &Path,
+    html_path: &Path,
+    output_path: &Path,
+    options: &ImageOptions,
+) -> Result<(), String> {
+    let html_url = path_to_file_url(html_path)?;
+    let mut cmd = Command::new(chromium);
+    cmd.arg("--headless");
+    cmd.arg("--disable-gpu");
+    cmd.arg("--allow-file-access-from-files");
+    cmd.args(chromium_screenshot_args(options));
+    cmd.args(chromium_readiness_args(options.font_timeout));
+    cmd.arg(format!("--screenshot={}", output_path.display()));
+    cmd.arg(html_url);
+    run_command(cmd, "chromium")
+}
+
+fn chromium_screenshot_args(options: &ImageOptions) -> Vec<String> {
+    let height = options.clip.unwrap_or(DEFAULT_IMAGE_HEIGHT);
+    vec![
+        "--hide-scrollbars".to_string(),
+        format!("--window-size={},{}", options.width, height),
+        format!("--force-device-scale-factor={}", options.scale),
+    ]
+}
+
+fn screenshot_with_wkhtmltoimage(
+    wkhtmltoimage: &Path,
+    html_path: &Path,
+    output_path: &Path,
+    options: &ImageOptions,
+) -> Result<(), String> {
+    let mut cmd = Command::new(wkhtmltoimage);
+    cmd.arg("--quiet");
+    cmd.arg("--enable-local-file-access");
+    cmd.args(wkhtmltoimage_args(options));
+    cmd.arg(html_path);
+    cmd.arg(output_path);
+    run_command(cmd, "wkhtmltoimage")
+}
+
+// wkhtmltoimage captures the whole page, so it is cropped to the same viewport
+// chromium captures. Width and crop are in output pixels, after the zoom.
+fn wkhtmltoimage_args(options: &ImageOptions) -> Vec<String> {
+    let scaled = |pixels: u32| ((pixels as f64 * options.scale).round() as u32).to_string();
+    vec![
+        "--format".to_string(),
+        "png".to_string(),
+        "--width".to_string(),
+        scaled(options.width),
+        "--zoom".to_string(),
+        options.scale.to_string(),
+        "--crop-h".to_string(),
+        scaled(options.clip.unwrap_or(DEFAULT_IMAGE_HEIGHT)),
+    ]
+}
+
 fn export_with_wkhtmltopdf(
     wkhtmltopdf: &Path,
     html_path: &Path,
@@ -366,3 +641,107 @@ impl Drop for TempFile {
         let _ = fs::remove_file(&self.path);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_backend_lists_searched_executables() {
+        let message = missing_backend_message("no PDF backend", &["chromium", "wkhtmltopdf"]);
+        assert_eq!(
+            message,
+            "no PDF backend found: searched PATH for chromium, wkhtmltopdf. Install Chromium, Chrome, Edge, or wkhtmltopdf, or set pdf-backend to native to write print-ready HTML instead"
+        );
+    }
+
+    #[test]
+    fn native_backend_refuses_to_write_a_pdf() {
+        let dir = env::temp_dir().join(format!("typmark_native_pdf_{}", std::process::id()));
+        let renderer = Renderer::new(crate::Theme::Light);
+        let options = PdfOptions::new(PdfBackend::Native);
+        let err = renderer
+            .export_pdf("<p>x</p>", &options, &dir.join("out.pdf"))
+            .unwrap_err();
+        assert!(
+            err.starts_with("native backend cannot write a PDF"),
+            "{err}"
+        );
+        assert!(!dir.exists());
+
+        let page = dir.join("out.html");
+        renderer
+            .export_print_html("<p>x</p>", &options, &page)
+            .unwrap();
+        let written = fs::read_to_string(&page).unwrap();
+        assert!(written.contains("@page { margin: 0; }"));
+        assert!(written.contains("<p>x</p>"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn image_backends_that_cannot_capture_fail_clearly() {
+        let err = resolve_image_backend(PdfBackend::Native).unwrap_err();
+        assert_eq!(
+            err,
+            "native backend cannot take screenshots: use chromium or wkhtmltoimage"
+        );
+
+        let dir = env::temp_dir().join("typmark_no_image_backend");
+        let renderer = Renderer::new(crate::Theme::Light);
+        let options = ImageOptions::new(PdfBackend::Native);
+        let output = dir.join("card.png");
+        assert!(renderer.export_png("<p>x</p>", &options, &output).is_err());
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn image_options_feed_backend_arguments() {
+        let options = ImageOptions::new(PdfBackend::Auto);
+        assert_eq!(
+            (options.width, options.scale, options.clip),
+            (1200, 1.0, None)
+        );
+        assert_eq!(
+            chromium_screenshot_args(&options),
+            [
+                "--hide-scrollbars",
+                "--window-size=1200,800",
+                "--force-device-scale-factor=1"
+            ]
+        );
+        assert_eq!(
+            wkhtmltoimage_args(&options),
+            [
+                "--format", "png", "--width", "1200", "--zoom", "1", "--crop-h", "800"
+            ]
+        );
+
+        let options = options.with_width(600).with_scale(2.0).with_clip(315);
+        assert_eq!(
+            chromium_screenshot_args(&options),
+            [
+                "--hide-scrollbars",
+                "--window-size=600,315",
+                "--force-device-scale-factor=2"
+            ]
+        );
+        assert_eq!(
+            wkhtmltoimage_args(&options),
+            [
+                "--format", "png", "--width", "1200", "--zoom", "2", "--crop-h", "630"
+            ]
+        );
+    }
+
+    #[test]
+    fn font_timeout_defaults_and_feeds_chromium_budget() {
+        let options = PdfOptions::new(PdfBackend::Chromium);
+        assert_eq!(options.font_timeout, Duration::from_secs(3));
+
+        let options = options.with_font_timeout(Duration::from_millis(750));
+        let args = chromium_readiness_args(options.font_timeout);
+        assert!(args.contains(&"--virtual-time-budget=750".to_string()));
+        assert!(args.contains(&"--run-all-compositor-stages-before-draw".to_string()));
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+
+/// A parsed file, in source order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TomlDocument {
+    /// `[name]` headers with their 1-based line numbers.
+    pub tables: Vec<(usize, String)>,
+    pub entries: Vec<TomlEntry>,
+}
+
+/// One `key = value` line. `table` is empty for top-level keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TomlEntry {
+    pub line: usize,
+    pub table: String,
+    pub key: String,
+    pub value: TomlValue,
+}
+
+/// A syntax error at a 1-based line. Displays as `line <n>: <message>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TomlError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TomlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TomlValue {
+    String(String),
+    Bool(bool),
+    /// Kept as written so callers can pick the numeric type.
+    Number(String),
+}
+
+impl TomlValue {
+    pub fn as_string(&self) -> String {
+        match self {
+            TomlValue::String(value) | TomlValue::Number(value) => value.clone(),
+            TomlValue::Bool(value) => value.to_string(),
+        }
+    }
+
+    pub fn as_bool(&self, key: &str) -> Result<bool, String> {
+        match self {
+            TomlValue::Bool(value) => Ok(*value),
+            _ => Err(format!("{} expects true or false", key)),
+        }
+    }
+}
+
+/// Reads the TOML subset used by `typmark.toml` and theme files: top-level keys
+/// and `[table]` headers holding strings, booleans, and numbers. Quoted keys
+/// lose their quotes.
+pub fn parse_toml_subset(text: &str) -> Result<TomlDocument, TomlError> {
+    let mut document = TomlDocument {
+        tables: Vec::new(),
+        entries: Vec::new(),
+    };
+    let mut table = String::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let error = |message: &str| TomlError {
+            line: line_no,
+            message: message.to_string(),
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix('[') {
+            let Some((name, tail)) = rest.split_once(']') else {
+                return Err(error("unterminated table header"));
+            };
+            if !tail.trim().is_empty() && !tail.trim_start().starts_with('#') {
+                return Err(error("unexpected text after table header"));
+            }
+            table = name.trim().to_string();
+            document.tables.push((line_no, table.clone()));
+            continue;
+        }
+        let Some((key, raw)) = trimmed.split_once('=') else {
+            return Err(error("expected key = value"));
+        };
+        let key = key.trim();
+        let key = key
+            .strip_prefix('"')
+            .and_then(|key| key.strip_suffix('"'))
+            .unwrap_or(key);
+        let value = parse_value(raw).map_err(|err| error(&err))?;
+        document.entries.push(TomlEntry {
+            line: line_no,
+            table: table.clone(),
+            key: key.to_string(),
+            value,
+        });
+    }
+    Ok(document)
+}
+
+fn parse_value(raw: &str) -> Result<TomlValue, String> {
+    let raw = raw.trim();
+    let (value, rest) = if let Some(body) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = body.char_indices();
+        let mut end = None;
+        while let Some((idx, ch)) = chars.next() {
+            match ch {
+                '"' => {
+                    end = Some(idx + 1);
+                    break;
+                }
+                '\\' => match chars.next().map(|(_, ch)| ch) {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    _ => return Err("unsupported escape in string".to_string()),
+                },
+                _ => value.push(ch),
+            }
+        }
+        let end = end.ok_or_else(|| "unterminated string".to_string())?;
+        (TomlValue::String(value), &body[end..])
+    } else if let Some(body) = raw.strip_prefix('\'') {
+        let end = body
+            .find('\'')
+            .ok_or_else(|| "unterminated string".to_string())?;
+        (TomlValue::String(body[..end].to_string()), &body[end + 1..])
+    } else {
+        let (token, rest) = match raw.find('#') {
+            Some(idx) => (raw[..idx].trim(), &raw[idx..]),
+            None => (raw, ""),
+        };
+        let value = match token {
+            "true" => TomlValue::Bool(true),
+            "false" => TomlValue::Bool(false),
+            _ if token.parse::<f64>().is_ok() => TomlValue::Number(token.to_string()),
+            _ => return Err(format!("invalid value: {}", token)),
+        };
+        (value, rest)
+    };
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("unexpected text after value: {}", rest));
+    }
+    Ok(value)
+}
@@ -1,11 +1,28 @@
 use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
-#[derive(Deserialize)]
+thread_local! {
+    /// Resolved documents kept by `parse_and_diagnose` until `free_document`.
+    static DOCUMENTS: RefCell<HashMap<u32, CachedDocument>> = RefCell::new(HashMap::new());
+    static NEXT_HANDLE: Cell<u32> = const { Cell::new(1) };
+}
+
+struct CachedDocument {
+    document: typmark_core::Document,
+    source_map: typmark_core::SourceMap,
+}
+
+#[derive(Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct RenderOptions {
     wrap_sections: Option<bool>,
     simple_code_blocks: Option<bool>,
+    raw_math: Option<bool>,
+    number_sections: Option<bool>,
+    /// `utf-8` (the default), `utf-16`, or `utf-32`.
+    position_encoding: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -16,6 +33,14 @@ struct RenderResult {
     source_map: Vec<JsRange>,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiagnoseResult {
+    handle: u32,
+    diagnostics: Vec<JsDiagnostic>,
+    source_map: Vec<JsRange>,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct JsDiagnostic {
@@ -23,6 +48,15 @@ struct JsDiagnostic {
     message: String,
     severity: String,
     range: JsRange,
+    fixes: Vec<JsFix>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsFix {
+    range: JsRange,
+    replacement: String,
+    title: String,
 }
 
 #[derive(Serialize)]
@@ -32,6 +66,20 @@ struct JsRange {
     start_col: usize,
     end_line: usize,
     end_col: usize,
+    /// Unit of the columns: `utf-8`, `utf-16` or `utf-32`.
+    encoding: &'static str,
+}
+
+impl JsRange {
+    fn new(range: typmark_core::Range, encoding: typmark_core::PositionEncoding) -> Self {
+        Self {
+            start_line: range.start.line,
+            start_col: range.start.character,
+            end_line: range.end.line,
+            end_col: range.end.character,
+            encoding: encoding.as_str(),
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -41,40 +89,29 @@ pub fn render_html(source: &str) -> Result<JsValue, JsValue> {
 
 #[wasm_bindgen]
 pub fn render_html_with_options(source: &str, options: JsValue) -> Result<JsValue, JsValue> {
-    let parsed = typmark_core::parse(source);
-    let resolved = typmark_core::resolve(
+    let options = render_options_from_js(options)?;
+    let parsed = typmark_core::parse_with_options(source, &parse_options(&options)?);
+    let emit_options = emit_options(&options);
+    let resolve_options = typmark_core::ResolveOptions {
+        math_output: emit_options.math_output,
+        ..Default::default()
+    };
+    let resolved = typmark_core::resolve_with_options(
         parsed.document,
         source,
         &parsed.source_map,
         parsed.diagnostics,
         &parsed.link_defs,
+        &resolve_options,
     );
 
-    let emit_options = options_from_js(options)?;
     let html = typmark_core::emit_html_document_with_options_and_source_map(
         &resolved.document,
         &emit_options,
         &parsed.source_map,
     );
 
-    let diagnostics = resolved
-        .diagnostics
-        .into_iter()
-        .map(|diag| JsDiagnostic {
-            code: diag.code.to_string(),
-            message: diag.message,
-            severity: match diag.severity {
-                typmark_core::DiagnosticSeverity::Error => "error".to_string(),
-                typmark_core::DiagnosticSeverity::Warning => "warning".to_string(),
-            },
-            range: JsRange {
-                start_line: diag.range.start.line,
-                start_col: diag.range.start.character,
-                end_line: diag.range.end.line,
-                end_col: diag.range.end.character,
-            },
-        })
-        .collect();
+    let diagnostics = diagnostics_to_js(resolved.diagnostics, parsed.source_map.encoding());
 
     let mut source_map = Vec::new();
     collect_block_ranges(
@@ -91,25 +128,157 @@ pub fn render_html_with_options(source: &str, options: JsValue) -> Result<JsValu
     serde_wasm_bindgen::to_value(&result).map_err(|err| JsValue::from_str(&err.to_string()))
 }
 
+/// Parses and resolves `source` without emitting HTML, so no math is rendered.
+///
+/// The resolved document stays cached under the returned `handle` until it is
+/// passed to `free_document`; every handle must be freed exactly once.
+#[wasm_bindgen]
+pub fn parse_and_diagnose(source: &str) -> Result<JsValue, JsValue> {
+    parse_and_diagnose_with_options(source, JsValue::UNDEFINED)
+}
+
+/// Like `parse_and_diagnose`; only `positionEncoding` is read from `options`.
+#[wasm_bindgen]
+pub fn parse_and_diagnose_with_options(source: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    let options = render_options_from_js(options)?;
+    let parsed = typmark_core::parse_with_options(source, &parse_options(&options)?);
+    let resolved = typmark_core::resolve(
+        parsed.document,
+        source,
+        &parsed.source_map,
+        parsed.diagnostics,
+        &parsed.link_defs,
+    );
+
+    let mut source_map = Vec::new();
+    collect_block_ranges(
+        &resolved.document.blocks,
+        &parsed.source_map,
+        &mut source_map,
+    );
+    let encoding = parsed.source_map.encoding();
+    let handle = NEXT_HANDLE.with(|next| {
+        let handle = next.get();
+        next.set(handle.wrapping_add(1).max(1));
+        handle
+    });
+    DOCUMENTS.with(|documents| {
+        documents.borrow_mut().insert(
+            handle,
+            CachedDocument {
+                document: resolved.document,
+                source_map: parsed.source_map,
+            },
+        )
+    });
+
+    let result = DiagnoseResult {
+        handle,
+        diagnostics: diagnostics_to_js(resolved.diagnostics, encoding),
+        source_map,
+    };
+    serde_wasm_bindgen::to_value(&result).map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// Emits HTML for a document cached by `parse_and_diagnose`. The handle stays valid.
+#[wasm_bindgen]
+pub fn emit_from_cached(handle: u32, options: JsValue) -> Result<String, JsValue> {
+    let emit_options = emit_options(&render_options_from_js(options)?);
+    DOCUMENTS.with(|documents| {
+        let documents = documents.borrow();
+        let cached = documents
+            .get(&handle)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown document handle {}", handle)))?;
+        Ok(
+            typmark_core::emit_html_document_with_options_and_source_map(
+                &cached.document,
+                &emit_options,
+                &cached.source_map,
+            ),
+        )
+    })
+}
+
+/// Drops a cached document. Returns false if the handle was unknown or already freed.
+#[wasm_bindgen]
+pub fn free_document(handle: u32) -> bool {
+    DOCUMENTS.with(|documents| documents.borrow_mut().remove(&handle).is_some())
+}
+
 #[wasm_bindgen]
 pub fn add_font(bytes: Vec<u8>) {
     typmark_core::add_font_bytes(bytes);
 }
 
-fn options_from_js(value: JsValue) -> Result<typmark_core::HtmlEmitOptions, JsValue> {
+fn diagnostics_to_js(
+    diagnostics: Vec<typmark_core::Diagnostic>,
+    encoding: typmark_core::PositionEncoding,
+) -> Vec<JsDiagnostic> {
+    diagnostics
+        .into_iter()
+        .map(|diag| JsDiagnostic {
+            code: diag.code.to_string(),
+            message: diag.message,
+            severity: match diag.severity {
+                typmark_core::DiagnosticSeverity::Error => "error".to_string(),
+                typmark_core::DiagnosticSeverity::Warning => "warning".to_string(),
+                typmark_core::DiagnosticSeverity::Info => "info".to_string(),
+                typmark_core::DiagnosticSeverity::Hint => "hint".to_string(),
+            },
+            range: JsRange::new(diag.range, encoding),
+            fixes: diag
+                .fixes
+                .into_iter()
+                .map(|fix| JsFix {
+                    range: JsRange::new(fix.range, encoding),
+                    replacement: fix.replacement,
+                    title: fix.title,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn render_options_from_js(value: JsValue) -> Result<RenderOptions, JsValue> {
     if value.is_null() || value.is_undefined() {
-        return Ok(typmark_core::HtmlEmitOptions::default());
+        return Ok(RenderOptions::default());
     }
-    let parsed: RenderOptions =
-        serde_wasm_bindgen::from_value(value).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    serde_wasm_bindgen::from_value(value).map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+fn parse_options(options: &RenderOptions) -> Result<typmark_core::ParseOptions, JsValue> {
+    let position_encoding = match options.position_encoding.as_deref() {
+        None | Some("utf-8") => typmark_core::PositionEncoding::Utf8,
+        Some("utf-16") => typmark_core::PositionEncoding::Utf16,
+        Some("utf-32") => typmark_core::PositionEncoding::Utf32,
+        Some(other) => {
+            return Err(JsValue::from_str(&format!(
+                "unsupported positionEncoding: {} (expected utf-8|utf-16|utf-32)",
+                other
+            )));
+        }
+    };
+    Ok(typmark_core::ParseOptions {
+        position_encoding,
+        ..Default::default()
+    })
+}
+
+fn emit_options(options: &RenderOptions) -> typmark_core::HtmlEmitOptions {
     let mut out = typmark_core::HtmlEmitOptions::default();
-    if let Some(wrap_sections) = parsed.wrap_sections {
+    if let Some(wrap_sections) = options.wrap_sections {
         out.wrap_sections = wrap_sections;
     }
-    if let Some(simple_code_blocks) = parsed.simple_code_blocks {
+    if let Some(simple_code_blocks) = options.simple_code_blocks {
         out.simple_code_blocks = simple_code_blocks;
     }
-    Ok(out)
+    if let Some(number_sections) = options.number_sections {
+        out.number_sections = number_sections;
+    }
+    if options.raw_math == Some(true) {
+        out.math_output = typmark_core::MathOutput::Raw;
+    }
+    out
 }
 
 fn collect_block_ranges(
@@ -119,17 +288,12 @@ fn collect_block_ranges(
 ) {
     for block in blocks {
         let range = source_map.range(block.span);
-        out.push(JsRange {
-            start_line: range.start.line,
-            start_col: range.start.character,
-            end_line: range.end.line,
-            end_col: range.end.character,
-        });
+        out.push(JsRange::new(range, source_map.encoding()));
         match &block.kind {
             typmark_core::BlockKind::Section { children, .. } => {
                 collect_block_ranges(children, source_map, out);
             }
-            typmark_core::BlockKind::BlockQuote { blocks } => {
+            typmark_core::BlockKind::BlockQuote { blocks, .. } => {
                 collect_block_ranges(blocks, source_map, out);
             }
             typmark_core::BlockKind::List(list) => {
@@ -140,6 +304,9 @@ fn collect_block_ranges(
             typmark_core::BlockKind::Box(box_block) => {
                 collect_block_ranges(&box_block.blocks, source_map, out);
             }
+            typmark_core::BlockKind::Container { blocks, .. } => {
+                collect_block_ranges(blocks, source_map, out);
+            }
             _ => {}
         }
     }